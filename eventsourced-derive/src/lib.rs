@@ -0,0 +1,177 @@
+//! Derive macros for [eventsourced](https://docs.rs/eventsourced/latest/eventsourced), re-exported
+//! from there behind the `derive` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident};
+
+/// Derive command dispatch for a command enum, replacing a large `match` in the command handler
+/// of an `EventSourced` implementation.
+///
+/// For an enum `Cmd` this generates a trait `CmdHandler<Id>` with an associated `Output` type and
+/// one method per variant, named `handle_` followed by the variant name in snake case, taking the
+/// entity ID and the fields of the variant, as well as a method `Cmd::dispatch` which matches on
+/// the command and calls the respective method. As each variant requires its method, missing
+/// handlers are compile time errors.
+///
+/// ```ignore
+/// #[derive(CmdDispatch)]
+/// pub enum Cmd {
+///     Inc(u64),
+///     Dec(u64),
+/// }
+///
+/// impl CmdHandler<Uuid> for Counter {
+///     type Output = Result<Evt, Error>;
+///
+///     fn handle_inc(&self, _id: Uuid, inc: u64) -> Self::Output { ... }
+///
+///     fn handle_dec(&self, _id: Uuid, dec: u64) -> Self::Output { ... }
+/// }
+///
+/// // In `EventSourced::handle_cmd`:
+/// cmd.dispatch(self, id)
+/// ```
+#[proc_macro_derive(CmdDispatch)]
+pub fn derive_cmd_dispatch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    cmd_dispatch(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn cmd_dispatch(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "CmdDispatch can only be derived for enums",
+        ));
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "CmdDispatch cannot be derived for generic enums",
+        ));
+    }
+
+    let vis = &input.vis;
+    let cmd = &input.ident;
+    let handler = format_ident!("{cmd}Handler");
+
+    let mut methods = Vec::with_capacity(data.variants.len());
+    let mut arms = Vec::with_capacity(data.variants.len());
+    for variant in &data.variants {
+        let name = &variant.ident;
+        let method = Ident::new(
+            &format!("handle_{}", snake_case(&name.to_string())),
+            name.span(),
+        );
+
+        let (params, pattern) = match &variant.fields {
+            Fields::Named(fields) => {
+                let params = fields
+                    .named
+                    .iter()
+                    .map(|field| (field.ident.clone().expect("named field"), &field.ty))
+                    .collect::<Vec<_>>();
+                let idents = params.iter().map(|(ident, _)| ident);
+                (params.clone(), quote!({ #(#idents),* }))
+            }
+            Fields::Unnamed(fields) => {
+                let params = fields
+                    .unnamed
+                    .iter()
+                    .enumerate()
+                    .map(|(n, field)| {
+                        (
+                            Ident::new(&format!("field{n}"), Span::call_site()),
+                            &field.ty,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let idents = params.iter().map(|(ident, _)| ident);
+                (params.clone(), quote!(( #(#idents),* )))
+            }
+            Fields::Unit => (vec![], quote!()),
+        };
+
+        let idents = params.iter().map(|(ident, _)| ident).collect::<Vec<_>>();
+        let types = params.iter().map(|(_, ty)| ty);
+        let doc = format!("Handle the [{cmd}::{name}] command.");
+        methods.push(quote! {
+            #[doc = #doc]
+            fn #method(&self, id: Id, #(#idents: #types),*) -> Self::Output;
+        });
+        arms.push(quote! {
+            #cmd::#name #pattern => handler.#method(id, #(#idents),*)
+        });
+    }
+
+    let handler_doc = format!(
+        "Handler for the variants of [{cmd}], see [dispatch]({cmd}::dispatch), generated via \
+         `#[derive(CmdDispatch)]`."
+    );
+    let dispatch_doc = format!(
+        "Dispatch this command to the respective method of the given [{handler}] with the given \
+         entity ID."
+    );
+
+    Ok(quote! {
+        #[doc = #handler_doc]
+        #vis trait #handler<Id> {
+            /// The common result of the handler methods, e.g. the result of the command handler.
+            type Output;
+
+            #(#methods)*
+        }
+
+        impl #cmd {
+            #[doc = #dispatch_doc]
+            #vis fn dispatch<H, Id>(self, handler: &H, id: Id) -> H::Output
+            where
+                H: #handler<Id> + ?Sized,
+            {
+                match self {
+                    #(#arms),*
+                }
+            }
+        }
+    })
+}
+
+/// Convert the given camel case name to snake case, e.g. `IncBy` to `inc_by`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (n, c) in name.char_indices() {
+        if c.is_uppercase() {
+            if n > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snake_case() {
+        assert_eq!(snake_case("Inc"), "inc");
+        assert_eq!(snake_case("IncBy"), "inc_by");
+        assert_eq!(snake_case("inc"), "inc");
+    }
+
+    #[test]
+    fn test_cmd_dispatch_non_enum() {
+        let input = syn::parse_quote! {
+            struct Cmd;
+        };
+        assert!(cmd_dispatch(input).is_err());
+    }
+}
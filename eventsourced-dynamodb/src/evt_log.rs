@@ -0,0 +1,969 @@
+//! An [EvtLog] implementation based on [Amazon DynamoDB](https://aws.amazon.com/dynamodb/).
+
+use crate::{b, client, n, s, Error, Item};
+use async_stream::stream;
+use aws_sdk_dynamodb::{
+    error::{ProvideErrorMetadata, SdkError},
+    operation::{
+        get_item::GetItemError, transact_write_items::TransactWriteItemsError,
+        update_item::UpdateItemError,
+    },
+    primitives::Blob,
+    types::{
+        AttributeDefinition, AttributeValue, BillingMode, GlobalSecondaryIndex, KeySchemaElement,
+        KeyType, Projection, ProjectionType, Put, ReturnValue, ScalarAttributeType,
+        TransactWriteItem, Update,
+    },
+    Client,
+};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use eventsourced::{Clock, EvtEnvelope, EvtLog, GlobalSeqNo, SeqNo, SystemClock};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+    num::NonZeroI32,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::sleep;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Name of the global secondary index for querying events by global sequence number.
+const GLOBAL_INDEX: &str = "global";
+
+/// Value of the partition key of the global secondary index, shared by all events.
+const GLOBAL_PARTITION: &str = "evts";
+
+/// Partition key of the item holding the last global sequence number.
+const GLOBAL_SEQ_NO_ID: &str = "global_seq_no";
+
+/// An [EvtLog] implementation based on [Amazon DynamoDB](https://aws.amazon.com/dynamodb/).
+///
+/// Events are stored as items with the entity ID as partition key and the sequence number as sort
+/// key. The last sequence number of each entity is kept in a head item with sort key `0`, which is
+/// updated along with putting the event item in a transaction, conditionally on the given last
+/// sequence number. Marking an entity ID as deleted sets the `deleted` attribute of its head item.
+///
+/// Global sequence numbers are taken from a counter item before the transaction, hence they are
+/// increasing, but may have gaps. Events are queried by global sequence number via a global
+/// secondary index with a single partition, hence the throughput of a single partition applies and
+/// these queries are eventually consistent.
+#[derive(Clone)]
+pub struct DynamoDbEvtLog {
+    evts_table: String,
+    poll_interval: Duration,
+    read_batch_size: NonZeroI32,
+    client: Client,
+    clock: Arc<dyn Clock>,
+}
+
+impl DynamoDbEvtLog {
+    /// Create a [DynamoDbEvtLog] using the default AWS configuration, e.g. from the environment.
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        let client = client(config.region.as_deref(), config.endpoint_url.as_deref()).await;
+        Self::from_client(client, config).await
+    }
+
+    /// Create a [DynamoDbEvtLog] using the given client, e.g. with custom credentials. The `region`
+    /// and `endpoint_url` of the given config are ignored.
+    pub async fn from_client(client: Client, config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating DynamoDbEvtLog");
+
+        // Setup table.
+        if config.setup {
+            create_evts_table(&client, &config.evts_table).await?;
+        }
+
+        Ok(Self {
+            clock: Arc::new(SystemClock),
+            evts_table: config.evts_table,
+            poll_interval: config.poll_interval,
+            read_batch_size: config.read_batch_size,
+            client,
+        })
+    }
+
+    /// Use the given [Clock] instead of the [SystemClock] to timestamp persisted events.
+    pub fn with_clock(self, clock: impl Clock) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    async fn next_global_seq_no(&self) -> Result<u64, Error> {
+        let output = self
+            .client
+            .update_item()
+            .table_name(&self.evts_table)
+            .key("id", AttributeValue::S(GLOBAL_SEQ_NO_ID.to_string()))
+            .key("seq_no", AttributeValue::N("0".to_string()))
+            .update_expression("ADD last_global_seq_no :one")
+            .expression_attribute_values(":one", AttributeValue::N("1".to_string()))
+            .return_values(ReturnValue::UpdatedNew)
+            .send()
+            .await
+            .map_err(|error| {
+                Error::DynamoDb("cannot get next global seq_no".to_string(), error.into())
+            })?;
+
+        output
+            .attributes()
+            .ok_or_else(|| Error::InvalidItem("missing attributes".to_string()))
+            .and_then(|attributes| n(attributes, "last_global_seq_no"))
+    }
+
+    /// Query the events for the given entity ID or, if none, all events, starting with the given
+    /// (global) sequence number, in batches, paginating over `LastEvaluatedKey`, and keep polling
+    /// for new events.
+    fn query_evts<E, F, FromBytes, FromBytesError>(
+        &self,
+        id: Option<Uuid>,
+        from: u64,
+        filter: F,
+        from_bytes: FromBytes,
+    ) -> impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send
+    where
+        E: Send,
+        F: Fn(&EvtEnvelope<Bytes>) -> bool + Send + 'static,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let this = self.clone();
+        let mut from = from;
+
+        stream! {
+            'outer: loop {
+                let mut next_from = from;
+                let mut exclusive_start_key = None;
+
+                loop {
+                    let query = this
+                        .client
+                        .query()
+                        .table_name(&this.evts_table)
+                        .limit(this.read_batch_size.get())
+                        .expression_attribute_values(":from", AttributeValue::N(from.to_string()))
+                        .set_exclusive_start_key(exclusive_start_key.take());
+                    let query = match id {
+                        Some(id) => query
+                            .key_condition_expression("id = :id AND seq_no >= :from")
+                            .expression_attribute_values(":id", AttributeValue::S(id.to_string()))
+                            .consistent_read(true),
+
+                        None => query
+                            .index_name(GLOBAL_INDEX)
+                            .key_condition_expression(
+                                "evt_partition = :partition AND global_seq_no >= :from",
+                            )
+                            .expression_attribute_values(
+                                ":partition",
+                                AttributeValue::S(GLOBAL_PARTITION.to_string()),
+                            ),
+                    };
+
+                    let output = match query.send().await {
+                        Ok(output) => output,
+
+                        Err(error) => {
+                            yield Err(Error::DynamoDb(
+                                "cannot query events".to_string(),
+                                error.into(),
+                            ));
+                            break 'outer;
+                        }
+                    };
+
+                    for item in output.items() {
+                        let evt = evt_envelope(item);
+                        let evt = match evt {
+                            Ok(evt) => evt,
+
+                            Err(error) => {
+                                yield Err(error);
+                                break 'outer;
+                            }
+                        };
+
+                        next_from = match id {
+                            Some(_) => evt.seq_no.as_u64() + 1,
+                            None => evt.global_seq_no.as_u64() + 1,
+                        };
+
+                        if filter(&evt) {
+                            let evt = from_bytes(evt.evt.clone())
+                                .map_err(|error| Error::FromBytes(Box::new(error)))
+                                .map(|payload| EvtEnvelope {
+                                    id: evt.id,
+                                    entity_type: evt.entity_type,
+                                    seq_no: evt.seq_no,
+                                    global_seq_no: evt.global_seq_no,
+                                    version: evt.version,
+                                    timestamp: evt.timestamp,
+                                    tags: evt.tags,
+                                    evt: payload,
+                                });
+                            let is_err = evt.is_err();
+                            yield evt;
+                            if is_err {
+                                break 'outer;
+                            }
+                        }
+                    }
+
+                    match output.last_evaluated_key() {
+                        Some(key) => exclusive_start_key = Some(key.clone()),
+                        None => break,
+                    }
+                }
+
+                from = next_from;
+                sleep(this.poll_interval).await;
+            }
+        }
+    }
+}
+
+impl Debug for DynamoDbEvtLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamoDbEvtLog")
+            .field("evts_table", &self.evts_table)
+            .finish()
+    }
+}
+
+impl EvtLog for DynamoDbEvtLog {
+    type Error = Error;
+
+    async fn persist<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Uuid,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> Result<SeqNo, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, "persisting event");
+
+        let bytes = to_bytes(evt).map_err(|error| Error::ToBytes(Box::new(error)))?;
+        let seq_no = last_seq_no
+            .map(|seq_no| seq_no.succ())
+            .unwrap_or(SeqNo::MIN);
+        let global_seq_no = self.next_global_seq_no().await?;
+
+        // Only update the head item if the given last sequence number is the actual one.
+        let head = Update::builder()
+            .table_name(&self.evts_table)
+            .key("id", AttributeValue::S(id.to_string()))
+            .key("seq_no", AttributeValue::N("0".to_string()))
+            .update_expression("SET last_seq_no = :seq_no")
+            .expression_attribute_values(":seq_no", AttributeValue::N(seq_no.to_string()));
+        let head = match last_seq_no {
+            Some(last_seq_no) => head
+                .condition_expression("last_seq_no = :last_seq_no")
+                .expression_attribute_values(
+                    ":last_seq_no",
+                    AttributeValue::N(last_seq_no.to_string()),
+                ),
+            None => head.condition_expression("attribute_not_exists(last_seq_no)"),
+        };
+        let head = head
+            .build()
+            .map_err(|error| Error::DynamoDb("cannot build update".to_string(), error.into()))?;
+
+        let tags = tags
+            .iter()
+            .map(|tag| AttributeValue::S(tag.to_owned()))
+            .collect();
+        let evt = Put::builder()
+            .table_name(&self.evts_table)
+            .item("id", AttributeValue::S(id.to_string()))
+            .item("seq_no", AttributeValue::N(seq_no.to_string()))
+            .item(
+                "evt_partition",
+                AttributeValue::S(GLOBAL_PARTITION.to_string()),
+            )
+            .item(
+                "global_seq_no",
+                AttributeValue::N(global_seq_no.to_string()),
+            )
+            .item("version", AttributeValue::N(version.to_string()))
+            .item(
+                "timestamp",
+                AttributeValue::S(self.clock.now().to_rfc3339()),
+            )
+            .item("tags", AttributeValue::L(tags))
+            .item("evt", AttributeValue::B(Blob::new(bytes.as_ref())));
+        let evt = match entity_type {
+            Some(entity_type) => evt.item("entity_type", AttributeValue::S(entity_type.to_owned())),
+            None => evt,
+        };
+        let evt = evt
+            .condition_expression("attribute_not_exists(seq_no)")
+            .build()
+            .map_err(|error| Error::DynamoDb("cannot build put".to_string(), error.into()))?;
+
+        let result = self
+            .client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().update(head).build())
+            .transact_items(TransactWriteItem::builder().put(evt).build())
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(seq_no),
+
+            Err(SdkError::ServiceError(error))
+                if matches!(
+                    error.err(),
+                    TransactWriteItemsError::TransactionCanceledException(_)
+                ) =>
+            {
+                let actual = self.last_seq_no(id).await?;
+                Err(Error::SeqNoConflict {
+                    expected: last_seq_no,
+                    actual,
+                })
+            }
+
+            Err(error) => Err(Error::DynamoDb(
+                "cannot persist event".to_string(),
+                error.into(),
+            )),
+        }
+    }
+
+    async fn delete_to(&self, id: Uuid, to_seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %to_seq_no, "deleting events");
+
+        // Always keep the last event.
+        let Some(last_seq_no) = self.last_seq_no(id).await? else {
+            return Ok(());
+        };
+        let to_seq_no = to_seq_no.as_u64().min(last_seq_no.as_u64() - 1);
+        if to_seq_no == 0 {
+            return Ok(());
+        }
+
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(&self.evts_table)
+                .key_condition_expression("id = :id AND seq_no BETWEEN :from AND :to")
+                .expression_attribute_values(":id", AttributeValue::S(id.to_string()))
+                .expression_attribute_values(":from", AttributeValue::N("1".to_string()))
+                .expression_attribute_values(":to", AttributeValue::N(to_seq_no.to_string()))
+                .projection_expression("seq_no")
+                .set_exclusive_start_key(exclusive_start_key.take())
+                .send()
+                .await
+                .map_err(|error| {
+                    Error::DynamoDb("cannot query events".to_string(), error.into())
+                })?;
+
+            for item in output.items() {
+                let seq_no = n::<u64>(item, "seq_no")?;
+                self.client
+                    .delete_item()
+                    .table_name(&self.evts_table)
+                    .key("id", AttributeValue::S(id.to_string()))
+                    .key("seq_no", AttributeValue::N(seq_no.to_string()))
+                    .send()
+                    .await
+                    .map_err(|error| {
+                        Error::DynamoDb("cannot delete event".to_string(), error.into())
+                    })?;
+            }
+
+            match output.last_evaluated_key() {
+                Some(key) => exclusive_start_key = Some(key.clone()),
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn mark_deleted(&self, id: Uuid) -> Result<(), Self::Error> {
+        debug!(%id, "marking entity as deleted");
+
+        self.client
+            .update_item()
+            .table_name(&self.evts_table)
+            .key("id", AttributeValue::S(id.to_string()))
+            .key("seq_no", AttributeValue::N("0".to_string()))
+            .update_expression("SET #deleted = :deleted")
+            .expression_attribute_names("#deleted", "deleted")
+            .expression_attribute_values(":deleted", AttributeValue::Bool(true))
+            .send()
+            .await
+            .map_err(|error| {
+                Error::DynamoDb("cannot mark entity as deleted".to_string(), error.into())
+            })
+            .map(|_| ())
+    }
+
+    async fn is_deleted(&self, id: Uuid) -> Result<bool, Self::Error> {
+        debug!(%id, "checking whether entity is marked as deleted");
+
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.evts_table)
+            .key("id", AttributeValue::S(id.to_string()))
+            .key("seq_no", AttributeValue::N("0".to_string()))
+            .projection_expression("#deleted")
+            .expression_attribute_names("#deleted", "deleted")
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|error| {
+                Error::DynamoDb("cannot get deleted marker".to_string(), error.into())
+            })?;
+
+        let deleted = output
+            .item()
+            .and_then(|item| item.get("deleted"))
+            .and_then(|value| value.as_bool().ok())
+            .copied()
+            .unwrap_or_default();
+        Ok(deleted)
+    }
+
+    async fn last_seq_no(&self, id: Uuid) -> Result<Option<SeqNo>, Self::Error> {
+        debug!(%id, "getting last seq_no");
+
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.evts_table)
+            .key("id", AttributeValue::S(id.to_string()))
+            .key("seq_no", AttributeValue::N("0".to_string()))
+            .projection_expression("last_seq_no")
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|error| Error::DynamoDb("cannot get last seq_no".to_string(), error.into()))?;
+
+        // The head item might only hold the deleted marker.
+        output
+            .item()
+            .filter(|item| item.contains_key("last_seq_no"))
+            .map(|item| {
+                n::<u64>(item, "last_seq_no")
+                    .and_then(|seq_no| seq_no.try_into().map_err(|_| Error::ZeroSeqNo))
+            })
+            .transpose()
+    }
+
+    async fn evts_by_id<E, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %from_seq_no, "building events by ID stream");
+        Ok(self.query_evts(Some(id), from_seq_no.as_u64(), |_| true, from_bytes))
+    }
+
+    async fn evts_by_ids<E, FromBytes, FromBytesError>(
+        &self,
+        ids: Vec<Uuid>,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(?ids, %from_global_seq_no, "building events by IDs stream");
+        Ok(self.query_evts(
+            None,
+            from_global_seq_no.as_u64(),
+            move |evt| ids.contains(&evt.id),
+            from_bytes,
+        ))
+    }
+
+    async fn evts<E, FromBytes, FromBytesError>(
+        &self,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%from_global_seq_no, "building events stream");
+        Ok(self.query_evts(None, from_global_seq_no.as_u64(), |_| true, from_bytes))
+    }
+
+    async fn evts_by_type<E, FromBytes, FromBytesError>(
+        &self,
+        entity_type: String,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(entity_type, %from_global_seq_no, "building events by type stream");
+        Ok(self.query_evts(
+            None,
+            from_global_seq_no.as_u64(),
+            move |evt| evt.entity_type.as_deref() == Some(entity_type.as_str()),
+            from_bytes,
+        ))
+    }
+
+    /// The given sequence number is used as global sequence number, like for the NATS
+    /// implementation.
+    async fn evts_by_tag<E, FromBytes, FromBytesError>(
+        &self,
+        tag: String,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(tag, %from_seq_no, "building events by tag stream");
+        Ok(self.query_evts(
+            None,
+            from_seq_no.as_u64(),
+            move |evt| evt.tags.contains(&tag),
+            from_bytes,
+        ))
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        self.client
+            .describe_table()
+            .table_name(&self.evts_table)
+            .send()
+            .await
+            .map_err(|error| Error::DynamoDb("cannot describe table".to_string(), error.into()))
+            .map(|_| ())
+    }
+
+    fn is_retryable(error: &Self::Error) -> bool {
+        // Persisting calls UpdateItem for the global sequence number, TransactWriteItems for the
+        // event and GetItem for the last sequence number on a conflict.
+        match error {
+            Error::DynamoDb(_, error) => error
+                .downcast_ref::<SdkError<TransactWriteItemsError>>()
+                .map(is_transient)
+                .or_else(|| {
+                    error
+                        .downcast_ref::<SdkError<UpdateItemError>>()
+                        .map(is_transient)
+                })
+                .or_else(|| {
+                    error
+                        .downcast_ref::<SdkError<GetItemError>>()
+                        .map(is_transient)
+                })
+                .unwrap_or_default(),
+
+            _ => false,
+        }
+    }
+}
+
+/// Configuration for the [DynamoDbEvtLog].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    region: Option<String>,
+
+    #[serde(default)]
+    endpoint_url: Option<String>,
+
+    #[serde(default = "evts_table_default")]
+    evts_table: String,
+
+    #[serde(default = "poll_interval_default", with = "humantime_serde")]
+    poll_interval: Duration,
+
+    #[serde(default = "read_batch_size_default")]
+    read_batch_size: NonZeroI32,
+
+    #[serde(default)]
+    setup: bool,
+}
+
+impl Config {
+    /// Change the `region`.
+    pub fn with_region<T>(self, region: T) -> Self
+    where
+        T: ToString,
+    {
+        let region = Some(region.to_string());
+        Self { region, ..self }
+    }
+
+    /// Change the `endpoint_url`, e.g. for DynamoDB local.
+    pub fn with_endpoint_url<T>(self, endpoint_url: T) -> Self
+    where
+        T: ToString,
+    {
+        let endpoint_url = Some(endpoint_url.to_string());
+        Self {
+            endpoint_url,
+            ..self
+        }
+    }
+
+    /// Change the `evts_table`.
+    pub fn with_evts_table<T>(self, evts_table: T) -> Self
+    where
+        T: ToString,
+    {
+        let evts_table = evts_table.to_string();
+        Self { evts_table, ..self }
+    }
+
+    /// Change the `poll_interval`.
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            ..self
+        }
+    }
+
+    /// Change the `read_batch_size`, i.e. the maximum number of items queried at once.
+    pub fn with_read_batch_size(self, read_batch_size: NonZeroI32) -> Self {
+        Self {
+            read_batch_size,
+            ..self
+        }
+    }
+
+    /// Change the `setup` flag.
+    pub fn with_setup(self, setup: bool) -> Self {
+        Self { setup, ..self }
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            region: None,
+            endpoint_url: None,
+            evts_table: evts_table_default(),
+            poll_interval: poll_interval_default(),
+            read_batch_size: read_batch_size_default(),
+            setup: false,
+        }
+    }
+}
+
+async fn create_evts_table(client: &Client, evts_table: &str) -> Result<(), Error> {
+    let attribute_definition = |name: &str, attribute_type| {
+        AttributeDefinition::builder()
+            .attribute_name(name)
+            .attribute_type(attribute_type)
+            .build()
+            .map_err(|error| {
+                Error::DynamoDb(
+                    "cannot build attribute definition".to_string(),
+                    error.into(),
+                )
+            })
+    };
+    let key_schema_element = |name: &str, key_type| {
+        KeySchemaElement::builder()
+            .attribute_name(name)
+            .key_type(key_type)
+            .build()
+            .map_err(|error| {
+                Error::DynamoDb("cannot build key schema element".to_string(), error.into())
+            })
+    };
+
+    let global_index = GlobalSecondaryIndex::builder()
+        .index_name(GLOBAL_INDEX)
+        .key_schema(key_schema_element("evt_partition", KeyType::Hash)?)
+        .key_schema(key_schema_element("global_seq_no", KeyType::Range)?)
+        .projection(
+            Projection::builder()
+                .projection_type(ProjectionType::All)
+                .build(),
+        )
+        .build()
+        .map_err(|error| {
+            Error::DynamoDb(
+                "cannot build global secondary index".to_string(),
+                error.into(),
+            )
+        })?;
+
+    let result = client
+        .create_table()
+        .table_name(evts_table)
+        .attribute_definitions(attribute_definition("id", ScalarAttributeType::S)?)
+        .attribute_definitions(attribute_definition("seq_no", ScalarAttributeType::N)?)
+        .attribute_definitions(attribute_definition(
+            "evt_partition",
+            ScalarAttributeType::S,
+        )?)
+        .attribute_definitions(attribute_definition(
+            "global_seq_no",
+            ScalarAttributeType::N,
+        )?)
+        .key_schema(key_schema_element("id", KeyType::Hash)?)
+        .key_schema(key_schema_element("seq_no", KeyType::Range)?)
+        .global_secondary_indexes(global_index)
+        .billing_mode(BillingMode::PayPerRequest)
+        .send()
+        .await;
+
+    match result {
+        Ok(_) => Ok(()),
+
+        Err(SdkError::ServiceError(error)) if error.err().is_resource_in_use_exception() => {
+            debug!(evts_table, "events table already exists");
+            Ok(())
+        }
+
+        Err(error) => Err(Error::DynamoDb(
+            format!("cannot create table {evts_table}"),
+            error.into(),
+        )),
+    }
+}
+
+fn evt_envelope(item: &Item) -> Result<EvtEnvelope<Bytes>, Error> {
+    let id = s(item, "id")?
+        .parse::<Uuid>()
+        .map_err(|error| Error::InvalidItem(format!("invalid attribute id: {error}")))?;
+    let seq_no = n::<u64>(item, "seq_no")?
+        .try_into()
+        .map_err(|_| Error::ZeroSeqNo)?;
+    let global_seq_no = n::<u64>(item, "global_seq_no")?
+        .try_into()
+        .map_err(|_| Error::ZeroSeqNo)?;
+    let version = n::<u32>(item, "version")?;
+    let timestamp = s(item, "timestamp")?
+        .parse::<DateTime<Utc>>()
+        .map_err(|error| Error::InvalidItem(format!("invalid attribute timestamp: {error}")))?;
+    let tags = item
+        .get("tags")
+        .and_then(|tags| tags.as_l().ok())
+        .ok_or_else(|| Error::InvalidItem("missing or invalid list attribute tags".to_string()))?
+        .iter()
+        .map(|tag| {
+            tag.as_s()
+                .cloned()
+                .map_err(|_| Error::InvalidItem("invalid tag".to_string()))
+        })
+        .collect::<Result<_, _>>()?;
+    let evt = Bytes::copy_from_slice(b(item, "evt")?);
+    let entity_type = item
+        .get("entity_type")
+        .map(|entity_type| {
+            entity_type
+                .as_s()
+                .cloned()
+                .map_err(|_| Error::InvalidItem("invalid attribute entity_type".to_string()))
+        })
+        .transpose()?;
+
+    Ok(EvtEnvelope {
+        id,
+        entity_type,
+        seq_no,
+        global_seq_no,
+        version,
+        timestamp,
+        tags,
+        evt,
+    })
+}
+
+/// Whether the given error is transient, i.e. a timeout, a failure to dispatch the request or to
+/// receive a response, throttling or an internal server error.
+fn is_transient<E>(error: &SdkError<E>) -> bool
+where
+    E: ProvideErrorMetadata,
+{
+    match error {
+        SdkError::TimeoutError(_) | SdkError::ResponseError(_) => true,
+        SdkError::DispatchFailure(failure) => failure.is_io() || failure.is_timeout(),
+        SdkError::ServiceError(_) => matches!(
+            error.code(),
+            Some(
+                "InternalServerError"
+                    | "ProvisionedThroughputExceededException"
+                    | "RequestLimitExceeded"
+                    | "ThrottlingException"
+            )
+        ),
+        _ => false,
+    }
+}
+
+fn evts_table_default() -> String {
+    "evts".to_string()
+}
+
+const fn poll_interval_default() -> Duration {
+    Duration::from_secs(2)
+}
+
+const fn read_batch_size_default() -> NonZeroI32 {
+    unsafe { NonZeroI32::new_unchecked(1_000) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::client;
+    use eventsourced::convert;
+    use futures::{StreamExt, TryStreamExt};
+    use testcontainers::clients::Cli;
+    use testcontainers_modules::dynamodb_local::DynamoDb;
+
+    #[tokio::test]
+    async fn test_evt_log() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let docker = Cli::default();
+        let container = docker.run(DynamoDb);
+        let client = client(container.get_host_port_ipv4(8000));
+
+        let config = Config::default()
+            .with_read_batch_size(2.try_into()?)
+            .with_setup(true);
+        let evt_log = DynamoDbEvtLog::from_client(client, config).await?;
+
+        let id = Uuid::now_v7();
+
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, None);
+
+        let last_seq_no = evt_log
+            .persist(
+                &1,
+                1,
+                &["tag".to_string()],
+                id,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+        assert_eq!(last_seq_no, SeqNo::MIN);
+
+        let result = evt_log
+            .persist(&2, 1, &[], id, None, None, &convert::prost::to_bytes)
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::SeqNoConflict {
+                expected: None,
+                actual: Some(_)
+            })
+        ));
+
+        let mut last_seq_no = Some(last_seq_no);
+        for n in 2..=5 {
+            let seq_no = evt_log
+                .persist(&n, 1, &[], id, None, last_seq_no, &convert::prost::to_bytes)
+                .await?;
+            last_seq_no = Some(seq_no);
+        }
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+
+        // Items are queried in batches of two, i.e. paginating over `LastEvaluatedKey`.
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(5)
+            .map_ok(|evt| (evt.seq_no.as_u64(), evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(1, 1), (2, 2), (3, 3), (4, 4), (5, 5)]);
+
+        let id_2 = Uuid::now_v7();
+        evt_log
+            .persist(
+                &6,
+                2,
+                &["tag".to_string()],
+                id_2,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let evts_by_tag = evt_log
+            .evts_by_tag::<i32, _, _>("tag".to_string(), SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(2)
+            .map_ok(|evt| (evt.id, evt.version, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_tag, vec![(id, 1, 1), (id_2, 2, 6)]);
+
+        let evts_by_ids = evt_log
+            .evts_by_ids::<i32, _, _>(vec![id_2], GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(1)
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_ids, vec![6]);
+
+        evt_log.delete_to(id, 10.try_into()?).await?;
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(1)
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![5]);
+
+        assert!(!evt_log.is_deleted(id).await?);
+        evt_log.mark_deleted(id).await?;
+        evt_log.mark_deleted(id).await?;
+        assert!(evt_log.is_deleted(id).await?);
+        assert!(!evt_log.is_deleted(id_2).await?);
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+
+        // Marking an entity ID without events as deleted must not break getting its last seq_no.
+        let id_3 = Uuid::now_v7();
+        evt_log.mark_deleted(id_3).await?;
+        assert!(evt_log.is_deleted(id_3).await?);
+        assert_eq!(evt_log.last_seq_no(id_3).await?, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+//! [EvtLog](eventsourced::EvtLog) and [SnapshotStore](eventsourced::SnapshotStore) implementations
+//! based upon [Amazon DynamoDB](https://aws.amazon.com/dynamodb/).
+
+mod evt_log;
+mod snapshot_store;
+
+pub use evt_log::{Config as DynamoDbEvtLogConfig, DynamoDbEvtLog};
+pub use snapshot_store::{Config as DynamoDbSnapshotStoreConfig, DynamoDbSnapshotStore};
+
+use aws_config::BehaviorVersion;
+use aws_sdk_dynamodb::{config::Region, types::AttributeValue, Client};
+use eventsourced::SeqNo;
+use std::{collections::HashMap, error::Error as StdError, str::FromStr};
+use thiserror::Error;
+
+type Item = HashMap<String, AttributeValue>;
+
+/// Errors from the [DynamoDbEvtLog] or [DynamoDbSnapshotStore].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// DynamoDB error.
+    #[error("DynamoDB error: {0}")]
+    DynamoDb(String, #[source] Box<dyn StdError + Send + Sync>),
+
+    /// Cannot convert an event to bytes.
+    #[error("cannot convert an event to bytes")]
+    ToBytes(#[source] Box<dyn StdError + Send + Sync + 'static>),
+
+    /// Cannot convert bytes to an event.
+    #[error("cannot convert bytes to an event")]
+    FromBytes(#[source] Box<dyn StdError + Send + Sync + 'static>),
+
+    /// Sequence number must not be zero.
+    #[error("sequence number must not be zero")]
+    ZeroSeqNo,
+
+    /// The given last sequence number does not match the actual one, e.g. because of a concurrent
+    /// writer for the same entity ID.
+    #[error("expected last sequence number {expected:?}, but was {actual:?}")]
+    SeqNoConflict {
+        expected: Option<SeqNo>,
+        actual: Option<SeqNo>,
+    },
+
+    /// Invalid item, e.g. with a missing or malformed attribute.
+    #[error("invalid item: {0}")]
+    InvalidItem(String),
+}
+
+/// Create a client from the default AWS configuration, e.g. from the environment, with the given
+/// region and endpoint URL, if any.
+async fn client(region: Option<&str>, endpoint_url: Option<&str>) -> Client {
+    let loader = aws_config::defaults(BehaviorVersion::latest());
+    let loader = match region {
+        Some(region) => loader.region(Region::new(region.to_string())),
+        None => loader,
+    };
+    let loader = match endpoint_url {
+        Some(endpoint_url) => loader.endpoint_url(endpoint_url),
+        None => loader,
+    };
+    Client::new(&loader.load().await)
+}
+
+fn n<T>(item: &Item, name: &str) -> Result<T, Error>
+where
+    T: FromStr,
+{
+    item.get(name)
+        .and_then(|value| value.as_n().ok())
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| Error::InvalidItem(format!("missing or invalid number attribute {name}")))
+}
+
+fn s<'a>(item: &'a Item, name: &str) -> Result<&'a str, Error> {
+    item.get(name)
+        .and_then(|value| value.as_s().ok())
+        .map(|value| value.as_str())
+        .ok_or_else(|| Error::InvalidItem(format!("missing or invalid string attribute {name}")))
+}
+
+fn b<'a>(item: &'a Item, name: &str) -> Result<&'a [u8], Error> {
+    item.get(name)
+        .and_then(|value| value.as_b().ok())
+        .map(|value| value.as_ref())
+        .ok_or_else(|| Error::InvalidItem(format!("missing or invalid binary attribute {name}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_sdk_dynamodb::{
+        config::{BehaviorVersion, Credentials, Region},
+        Client, Config,
+    };
+
+    pub fn client(port: u16) -> Client {
+        let config = Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("eu-central-1"))
+            .endpoint_url(format!("http://localhost:{port}"))
+            .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+            .build();
+        Client::from_conf(config)
+    }
+}
@@ -0,0 +1,364 @@
+//! A [SnapshotStore] implementation based on [Amazon DynamoDB](https://aws.amazon.com/dynamodb/).
+
+use crate::{b, client, n, Error};
+use aws_sdk_dynamodb::{
+    error::SdkError,
+    operation::delete_item::DeleteItemError,
+    primitives::Blob,
+    types::{
+        AttributeDefinition, AttributeValue, BillingMode, KeySchemaElement, KeyType,
+        ScalarAttributeType,
+    },
+    Client,
+};
+use bytes::Bytes;
+use eventsourced::{SeqNo, Snapshot, SnapshotStore};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+};
+use tracing::debug;
+use uuid::Uuid;
+
+/// A [SnapshotStore] implementation based on [Amazon DynamoDB](https://aws.amazon.com/dynamodb/).
+/// Snapshots are stored as items with the entity ID as partition key, hence only the last saved
+/// snapshot per entity ID is kept.
+#[derive(Clone)]
+pub struct DynamoDbSnapshotStore {
+    snapshots_table: String,
+    client: Client,
+}
+
+impl DynamoDbSnapshotStore {
+    /// Create a [DynamoDbSnapshotStore] using the default AWS configuration, e.g. from the
+    /// environment.
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        let client = client(config.region.as_deref(), config.endpoint_url.as_deref()).await;
+        Self::from_client(client, config).await
+    }
+
+    /// Create a [DynamoDbSnapshotStore] using the given client, e.g. with custom credentials. The
+    /// `region` and `endpoint_url` of the given config are ignored.
+    pub async fn from_client(client: Client, config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating DynamoDbSnapshotStore");
+
+        // Setup table.
+        if config.setup {
+            create_snapshots_table(&client, &config.snapshots_table).await?;
+        }
+
+        Ok(Self {
+            snapshots_table: config.snapshots_table,
+            client,
+        })
+    }
+}
+
+impl Debug for DynamoDbSnapshotStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamoDbSnapshotStore")
+            .field("snapshots_table", &self.snapshots_table)
+            .finish()
+    }
+}
+
+impl SnapshotStore for DynamoDbSnapshotStore {
+    type Error = Error;
+
+    async fn save<S, ToBytes, ToBytesError>(
+        &self,
+        id: Uuid,
+        seq_no: SeqNo,
+        state_version: u32,
+        state: S,
+        to_bytes: &ToBytes,
+    ) -> Result<(), Self::Error>
+    where
+        S: Send,
+        ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %seq_no, state_version, "saving snapshot");
+
+        let bytes = to_bytes(&state).map_err(|source| Error::ToBytes(Box::new(source)))?;
+        self.client
+            .put_item()
+            .table_name(&self.snapshots_table)
+            .item("id", AttributeValue::S(id.to_string()))
+            .item("seq_no", AttributeValue::N(seq_no.to_string()))
+            .item("state", AttributeValue::B(Blob::new(bytes.as_ref())))
+            .item(
+                "state_version",
+                AttributeValue::N(state_version.to_string()),
+            )
+            .send()
+            .await
+            .map_err(|error| Error::DynamoDb("cannot save snapshot".to_string(), error.into()))?;
+
+        Ok(())
+    }
+
+    async fn load<S, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> Result<Option<Snapshot<S>>, Self::Error>
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, state_version, "loading snapshot");
+
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.snapshots_table)
+            .key("id", AttributeValue::S(id.to_string()))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|error| Error::DynamoDb("cannot load snapshot".to_string(), error.into()))?;
+
+        let Some(item) = output.item() else {
+            return Ok(None);
+        };
+
+        // Snapshots saved before the state version was added do not have one.
+        let snapshot_state_version = match item.get("state_version") {
+            Some(_) => n::<u32>(item, "state_version")?,
+            None => 1,
+        };
+        if snapshot_state_version != state_version {
+            return Ok(None);
+        }
+
+        let seq_no = n::<u64>(item, "seq_no")?
+            .try_into()
+            .map_err(|_| Error::ZeroSeqNo)?;
+        let bytes = Bytes::copy_from_slice(b(item, "state")?);
+        from_bytes(bytes)
+            .map_err(|source| Error::FromBytes(Box::new(source)))
+            .map(|state| Some(Snapshot::new(seq_no, state)))
+    }
+
+    async fn delete_before(&self, id: Uuid, seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %seq_no, "deleting snapshot");
+
+        let result = self
+            .client
+            .delete_item()
+            .table_name(&self.snapshots_table)
+            .key("id", AttributeValue::S(id.to_string()))
+            .condition_expression("seq_no < :seq_no")
+            .expression_attribute_values(":seq_no", AttributeValue::N(seq_no.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+
+            // Nothing to delete.
+            Err(SdkError::ServiceError(error))
+                if matches!(
+                    error.err(),
+                    DeleteItemError::ConditionalCheckFailedException(_)
+                ) =>
+            {
+                Ok(())
+            }
+
+            Err(error) => Err(Error::DynamoDb(
+                "cannot delete snapshot".to_string(),
+                error.into(),
+            )),
+        }
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        self.client
+            .describe_table()
+            .table_name(&self.snapshots_table)
+            .send()
+            .await
+            .map_err(|error| Error::DynamoDb("cannot describe table".to_string(), error.into()))
+            .map(|_| ())
+    }
+}
+
+/// Configuration for the [DynamoDbSnapshotStore].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    region: Option<String>,
+
+    #[serde(default)]
+    endpoint_url: Option<String>,
+
+    #[serde(default = "snapshots_table_default")]
+    snapshots_table: String,
+
+    #[serde(default)]
+    setup: bool,
+}
+
+impl Config {
+    /// Change the `region`.
+    pub fn with_region<T>(self, region: T) -> Self
+    where
+        T: ToString,
+    {
+        let region = Some(region.to_string());
+        Self { region, ..self }
+    }
+
+    /// Change the `endpoint_url`, e.g. for DynamoDB local.
+    pub fn with_endpoint_url<T>(self, endpoint_url: T) -> Self
+    where
+        T: ToString,
+    {
+        let endpoint_url = Some(endpoint_url.to_string());
+        Self {
+            endpoint_url,
+            ..self
+        }
+    }
+
+    /// Change the `snapshots_table`.
+    pub fn with_snapshots_table<T>(self, snapshots_table: T) -> Self
+    where
+        T: ToString,
+    {
+        let snapshots_table = snapshots_table.to_string();
+        Self {
+            snapshots_table,
+            ..self
+        }
+    }
+
+    /// Change the `setup` flag.
+    pub fn with_setup(self, setup: bool) -> Self {
+        Self { setup, ..self }
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            region: None,
+            endpoint_url: None,
+            snapshots_table: snapshots_table_default(),
+            setup: false,
+        }
+    }
+}
+
+async fn create_snapshots_table(client: &Client, snapshots_table: &str) -> Result<(), Error> {
+    let attribute_definition = AttributeDefinition::builder()
+        .attribute_name("id")
+        .attribute_type(ScalarAttributeType::S)
+        .build()
+        .map_err(|error| {
+            Error::DynamoDb(
+                "cannot build attribute definition".to_string(),
+                error.into(),
+            )
+        })?;
+    let key_schema_element = KeySchemaElement::builder()
+        .attribute_name("id")
+        .key_type(KeyType::Hash)
+        .build()
+        .map_err(|error| {
+            Error::DynamoDb("cannot build key schema element".to_string(), error.into())
+        })?;
+
+    let result = client
+        .create_table()
+        .table_name(snapshots_table)
+        .attribute_definitions(attribute_definition)
+        .key_schema(key_schema_element)
+        .billing_mode(BillingMode::PayPerRequest)
+        .send()
+        .await;
+
+    match result {
+        Ok(_) => Ok(()),
+
+        Err(SdkError::ServiceError(error)) if error.err().is_resource_in_use_exception() => {
+            debug!(snapshots_table, "snapshots table already exists");
+            Ok(())
+        }
+
+        Err(error) => Err(Error::DynamoDb(
+            format!("cannot create table {snapshots_table}"),
+            error.into(),
+        )),
+    }
+}
+
+fn snapshots_table_default() -> String {
+    "snapshots".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::client;
+    use eventsourced::convert;
+    use testcontainers::clients::Cli;
+    use testcontainers_modules::dynamodb_local::DynamoDb;
+
+    #[tokio::test]
+    async fn test_snapshot_store() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let docker = Cli::default();
+        let container = docker.run(DynamoDb);
+        let client = client(container.get_host_port_ipv4(8000));
+
+        let config = Config::default().with_setup(true);
+        let snapshot_store = DynamoDbSnapshotStore::from_client(client, config).await?;
+
+        let id = Uuid::now_v7();
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        let seq_no = 42.try_into().unwrap();
+        let state = 666;
+
+        snapshot_store
+            .save(id, seq_no, 1, state, &convert::prost::to_bytes)
+            .await?;
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+
+        assert!(snapshot.is_some());
+        let snapshot = snapshot.unwrap();
+        assert_eq!(snapshot.seq_no, seq_no);
+        assert_eq!(snapshot.state, state);
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 2, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        snapshot_store.delete_before(id, seq_no).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_some());
+
+        snapshot_store.delete_before(id, seq_no.succ()).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        Ok(())
+    }
+}
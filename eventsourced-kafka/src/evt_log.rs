@@ -0,0 +1,931 @@
+//! An [EvtLog] implementation based on [Apache Kafka](https://kafka.apache.org/).
+
+use crate::Error;
+use async_stream::stream;
+use bytes::Bytes;
+use eventsourced::{Clock, EvtEnvelope, EvtLog, GlobalSeqNo, SeqNo, SystemClock};
+use futures::{stream::select_all, Stream};
+use rskafka::{
+    client::{
+        error::{Error as KafkaError, ProtocolError},
+        partition::{Compression, OffsetAt, PartitionClient, UnknownTopicHandling},
+        Client, ClientBuilder,
+    },
+    record::{Record, RecordAndOffset},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::{Mutex, MutexGuard};
+use tracing::debug;
+use uuid::Uuid;
+
+const SEQ_NO: &str = "seq_no";
+const VERSION: &str = "version";
+const TAGS: &str = "tags";
+const ENTITY_TYPE: &str = "entity_type";
+const DELETED: &str = "deleted";
+
+/// An [EvtLog] implementation based on [Apache Kafka](https://kafka.apache.org/).
+///
+/// All events are produced to a single topic, using the entity ID as record key and choosing the
+/// partition by entity ID, hence the events of an entity are kept in order. The sequence number,
+/// version, tags and optional entity type of an event are stored as record headers. Marking an
+/// entity ID as deleted produces a record without value, but with a `deleted` header, to the same
+/// partition, which is skipped when reading events.
+///
+/// Kafka only orders records within a partition, hence the global sequence number of an event is
+/// the offset of its record within its partition plus one. Therefore global sequence numbers are
+/// only unique and ordered per partition and [evts](EvtLog::evts),
+/// [evts_by_ids](EvtLog::evts_by_ids), [evts_by_type](EvtLog::evts_by_type) and
+/// [evts_by_tag](EvtLog::evts_by_tag) interleave the partitions arbitrarily; use a topic with a
+/// single partition if a total order is needed.
+///
+/// Kafka does not support looking up records by anything but offset, hence a sequence number to
+/// offset index is built in memory for each partition by consuming it when it is first accessed
+/// and kept up to date afterwards. For large topics an external sequence number to offset index or
+/// a compacted snapshot topic should be used instead of replaying partitions from the start.
+///
+/// Kafka does not support conditional writes, hence checking the last sequence number when
+/// persisting an event only protects against concurrent writers using the same [KafkaEvtLog] (or
+/// its clones). If other writers exist for the same entity, records with an already taken
+/// sequence number are ignored when reading.
+#[derive(Clone)]
+pub struct KafkaEvtLog {
+    topic: String,
+    poll_interval: Duration,
+    fetch_max_bytes: i32,
+    client: Arc<Client>,
+    partition_clients: Arc<Vec<PartitionClient>>,
+    indexes: Arc<Vec<Mutex<Index>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl KafkaEvtLog {
+    #[allow(missing_docs)]
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating KafkaEvtLog");
+
+        let client = ClientBuilder::new(config.bootstrap_brokers.clone())
+            .build()
+            .await
+            .map_err(|error| Error::Kafka("cannot create client".to_string(), error))?;
+
+        // Setup topic.
+        if config.setup {
+            create_topic(&client, &config).await?;
+        }
+
+        let partitions = client
+            .list_topics()
+            .await
+            .map_err(|error| Error::Kafka("cannot list topics".to_string(), error))?
+            .into_iter()
+            .find(|topic| topic.name == config.topic)
+            .map(|topic| topic.partitions)
+            .ok_or_else(|| Error::UnknownTopic(config.topic.clone()))?;
+
+        let mut partition_clients = Vec::with_capacity(partitions.len());
+        for partition in partitions {
+            let partition_client = client
+                .partition_client(&config.topic, partition, UnknownTopicHandling::Retry)
+                .await
+                .map_err(|error| {
+                    Error::Kafka(
+                        format!("cannot create client for partition {partition}"),
+                        error,
+                    )
+                })?;
+            partition_clients.push(partition_client);
+        }
+        let indexes = partition_clients
+            .iter()
+            .map(|_| Mutex::new(Index::default()))
+            .collect();
+
+        Ok(Self {
+            clock: Arc::new(SystemClock),
+            topic: config.topic,
+            poll_interval: config.poll_interval,
+            fetch_max_bytes: config.fetch_max_bytes,
+            client: Arc::new(client),
+            partition_clients: Arc::new(partition_clients),
+            indexes: Arc::new(indexes),
+        })
+    }
+
+    /// Use the given [Clock] instead of the [SystemClock] to timestamp persisted events.
+    pub fn with_clock(self, clock: impl Clock) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    fn partition(&self, id: Uuid) -> usize {
+        (id.as_u128() % self.partition_clients.len() as u128) as usize
+    }
+
+    /// Get the index for the given partition after catching up with its records.
+    async fn index(&self, partition: usize) -> Result<MutexGuard<'_, Index>, Error> {
+        let client = &self.partition_clients[partition];
+        let mut index = self.indexes[partition].lock().await;
+
+        if index.next_offset.is_none() {
+            let earliest = client
+                .get_offset(OffsetAt::Earliest)
+                .await
+                .map_err(|error| Error::Kafka("cannot get earliest offset".to_string(), error))?;
+            index.next_offset = Some(earliest);
+        }
+
+        loop {
+            let next_offset = index.next_offset.unwrap_or_default();
+            let (records, high_watermark) = client
+                .fetch_records(next_offset, 1..self.fetch_max_bytes, 0)
+                .await
+                .map_err(|error| Error::Kafka("cannot fetch records".to_string(), error))?;
+            let n = records.len();
+
+            for RecordAndOffset { record, offset } in records {
+                index.next_offset = Some(offset + 1);
+                let id = record_id(&record)?;
+                if is_deleted_marker(&record) {
+                    index.deleted.insert(id);
+                    continue;
+                }
+                let seq_no = record_seq_no(&record)?;
+                index.insert(id, seq_no, offset);
+            }
+
+            if n == 0 || index.next_offset.unwrap_or_default() >= high_watermark {
+                break;
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Get the events of the given partitions starting with the given global sequence number.
+    async fn evts_stream<E, F, FromBytes, FromBytesError>(
+        &self,
+        partitions: Vec<usize>,
+        from_global_seq_no: u64,
+        filter: F,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send, Error>
+    where
+        E: Send,
+        F: Fn(&EvtEnvelope<Bytes>) -> bool + Clone + Send + 'static,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let mut records = Vec::with_capacity(partitions.len());
+        for partition in partitions {
+            // Records before the earliest offset might have been deleted by retention.
+            let earliest = self.partition_clients[partition]
+                .get_offset(OffsetAt::Earliest)
+                .await
+                .map_err(|error| Error::Kafka("cannot get earliest offset".to_string(), error))?;
+            let offset = (from_global_seq_no as i64 - 1).max(earliest);
+            records.push(Box::pin(self.records(
+                partition,
+                offset,
+                filter.clone(),
+                from_bytes,
+            )));
+        }
+
+        Ok(select_all(records))
+    }
+
+    /// Fetch the records of the given partition starting with the given offset and keep polling
+    /// for new records.
+    fn records<E, F, FromBytes, FromBytesError>(
+        &self,
+        partition: usize,
+        offset: i64,
+        filter: F,
+        from_bytes: FromBytes,
+    ) -> impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send
+    where
+        E: Send,
+        F: Fn(&EvtEnvelope<Bytes>) -> bool + Send + 'static,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let partition_clients = self.partition_clients.clone();
+        let max_wait_ms = self.poll_interval.as_millis() as i32;
+        let fetch_max_bytes = self.fetch_max_bytes;
+        let mut offset = offset;
+
+        stream! {
+            let client = &partition_clients[partition];
+
+            // Records with an already taken sequence number, e.g. from concurrent writers, are
+            // ignored.
+            let mut last_seq_nos = HashMap::<Uuid, u64>::new();
+
+            'outer: loop {
+                let records = client
+                    .fetch_records(offset, 1..fetch_max_bytes, max_wait_ms)
+                    .await
+                    .map_err(|error| Error::Kafka("cannot fetch records".to_string(), error));
+                let records = match records {
+                    Ok((records, _)) => records,
+
+                    Err(error) => {
+                        yield Err(error);
+                        break 'outer;
+                    }
+                };
+
+                for RecordAndOffset { record, offset: record_offset } in records {
+                    offset = record_offset + 1;
+
+                    if is_deleted_marker(&record) {
+                        continue;
+                    }
+
+                    let evt = match evt_envelope(record, record_offset) {
+                        Ok(evt) => evt,
+
+                        Err(error) => {
+                            yield Err(error);
+                            break 'outer;
+                        }
+                    };
+
+                    let last_seq_no = last_seq_nos.entry(evt.id).or_default();
+                    if evt.seq_no.as_u64() <= *last_seq_no {
+                        continue;
+                    }
+                    *last_seq_no = evt.seq_no.as_u64();
+
+                    if filter(&evt) {
+                        let evt = from_bytes(evt.evt.clone())
+                            .map_err(|error| Error::FromBytes(Box::new(error)))
+                            .map(|payload| EvtEnvelope {
+                                id: evt.id,
+                                entity_type: evt.entity_type,
+                                seq_no: evt.seq_no,
+                                global_seq_no: evt.global_seq_no,
+                                version: evt.version,
+                                timestamp: evt.timestamp,
+                                tags: evt.tags,
+                                evt: payload,
+                            });
+                        let is_err = evt.is_err();
+                        yield evt;
+                        if is_err {
+                            break 'outer;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Debug for KafkaEvtLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KafkaEvtLog")
+            .field("topic", &self.topic)
+            .field("partitions", &self.partition_clients.len())
+            .finish()
+    }
+}
+
+impl EvtLog for KafkaEvtLog {
+    type Error = Error;
+
+    async fn persist<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Uuid,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> Result<SeqNo, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, "persisting event");
+
+        let bytes = to_bytes(evt).map_err(|error| Error::ToBytes(Box::new(error)))?;
+
+        let partition = self.partition(id);
+        let mut index = self.index(partition).await?;
+
+        let actual = index.last_seq_no(id)?;
+        if actual != last_seq_no {
+            return Err(Error::SeqNoConflict {
+                expected: last_seq_no,
+                actual,
+            });
+        }
+
+        let seq_no = last_seq_no
+            .map(|seq_no| seq_no.succ())
+            .unwrap_or(SeqNo::MIN);
+        let tags = serde_json::to_vec(tags).expect("tags can be serialized");
+        let mut headers = BTreeMap::from([
+            (SEQ_NO.to_string(), seq_no.to_string().into_bytes()),
+            (VERSION.to_string(), version.to_string().into_bytes()),
+            (TAGS.to_string(), tags),
+        ]);
+        if let Some(entity_type) = entity_type {
+            headers.insert(ENTITY_TYPE.to_string(), entity_type.as_bytes().to_vec());
+        }
+        let record = Record {
+            key: Some(id.as_bytes().to_vec()),
+            value: Some(bytes.to_vec()),
+            headers,
+            timestamp: self.clock.now(),
+        };
+
+        let offsets = self.partition_clients[partition]
+            .produce(vec![record], Compression::NoCompression)
+            .await
+            .map_err(|error| Error::Kafka("cannot produce record".to_string(), error))?;
+        if let Some(offset) = offsets.first() {
+            index.insert(id, seq_no.as_u64(), *offset);
+        }
+
+        Ok(seq_no)
+    }
+
+    /// Kafka does not support deleting the records for a key, hence events are not deleted, but
+    /// reclaiming storage is left to the retention policy of the topic.
+    async fn delete_to(&self, id: Uuid, to_seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %to_seq_no, "not deleting events");
+        Ok(())
+    }
+
+    async fn mark_deleted(&self, id: Uuid) -> Result<(), Self::Error> {
+        debug!(%id, "marking entity as deleted");
+
+        let partition = self.partition(id);
+        let mut index = self.index(partition).await?;
+        if index.deleted.contains(&id) {
+            return Ok(());
+        }
+
+        let record = Record {
+            key: Some(id.as_bytes().to_vec()),
+            value: None,
+            headers: BTreeMap::from([(DELETED.to_string(), Vec::new())]),
+            timestamp: self.clock.now(),
+        };
+        self.partition_clients[partition]
+            .produce(vec![record], Compression::NoCompression)
+            .await
+            .map_err(|error| Error::Kafka("cannot produce record".to_string(), error))?;
+        index.deleted.insert(id);
+
+        Ok(())
+    }
+
+    async fn is_deleted(&self, id: Uuid) -> Result<bool, Self::Error> {
+        debug!(%id, "checking whether entity is marked as deleted");
+
+        let index = self.index(self.partition(id)).await?;
+        Ok(index.deleted.contains(&id))
+    }
+
+    async fn last_seq_no(&self, id: Uuid) -> Result<Option<SeqNo>, Self::Error> {
+        debug!(%id, "getting last seq_no");
+
+        self.index(self.partition(id)).await?.last_seq_no(id)
+    }
+
+    async fn evts_by_id<E, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %from_seq_no, "building events by ID stream");
+
+        // Seek to the offset corresponding to the given sequence number.
+        let partition = self.partition(id);
+        let offset = self.index(partition).await?.offset(id, from_seq_no);
+
+        Ok(self.records(
+            partition,
+            offset,
+            move |evt| evt.id == id && evt.seq_no >= from_seq_no,
+            from_bytes,
+        ))
+    }
+
+    async fn evts_by_ids<E, FromBytes, FromBytesError>(
+        &self,
+        ids: Vec<Uuid>,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(?ids, %from_global_seq_no, "building events by IDs stream");
+
+        let mut partitions = ids.iter().map(|id| self.partition(*id)).collect::<Vec<_>>();
+        partitions.sort_unstable();
+        partitions.dedup();
+
+        let ids = Arc::new(ids);
+        self.evts_stream(
+            partitions,
+            from_global_seq_no.as_u64(),
+            move |evt| ids.contains(&evt.id),
+            from_bytes,
+        )
+        .await
+    }
+
+    async fn evts<E, FromBytes, FromBytesError>(
+        &self,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%from_global_seq_no, "building events stream");
+
+        let partitions = (0..self.partition_clients.len()).collect();
+        self.evts_stream(
+            partitions,
+            from_global_seq_no.as_u64(),
+            |_| true,
+            from_bytes,
+        )
+        .await
+    }
+
+    async fn evts_by_type<E, FromBytes, FromBytesError>(
+        &self,
+        entity_type: String,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(entity_type, %from_global_seq_no, "building events by type stream");
+
+        let partitions = (0..self.partition_clients.len()).collect();
+        let entity_type = Arc::new(entity_type);
+        self.evts_stream(
+            partitions,
+            from_global_seq_no.as_u64(),
+            move |evt| evt.entity_type.as_deref() == Some(entity_type.as_str()),
+            from_bytes,
+        )
+        .await
+    }
+
+    /// The given sequence number is used as global sequence number, like for the NATS
+    /// implementation.
+    async fn evts_by_tag<E, FromBytes, FromBytesError>(
+        &self,
+        tag: String,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(tag, %from_seq_no, "building events by tag stream");
+
+        let partitions = (0..self.partition_clients.len()).collect();
+        let tag = Arc::new(tag);
+        self.evts_stream(
+            partitions,
+            from_seq_no.as_u64(),
+            move |evt| evt.tags.contains(&tag),
+            from_bytes,
+        )
+        .await
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        self.client
+            .list_topics()
+            .await
+            .map_err(|error| Error::Kafka("cannot list topics".to_string(), error))?
+            .into_iter()
+            .any(|topic| topic.name == self.topic)
+            .then_some(())
+            .ok_or_else(|| Error::UnknownTopic(self.topic.clone()))
+    }
+
+    /// Producing is not idempotent, hence no error is retried, as that might duplicate events.
+    fn is_retryable(_error: &Self::Error) -> bool {
+        false
+    }
+}
+
+/// Configuration for the [KafkaEvtLog].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    bootstrap_brokers: Vec<String>,
+
+    #[serde(default = "topic_default")]
+    topic: String,
+
+    #[serde(default = "poll_interval_default", with = "humantime_serde")]
+    poll_interval: Duration,
+
+    #[serde(default = "fetch_max_bytes_default")]
+    fetch_max_bytes: i32,
+
+    #[serde(default)]
+    setup: bool,
+
+    #[serde(default = "partitions_default")]
+    partitions: i32,
+
+    #[serde(default = "replication_factor_default")]
+    replication_factor: i16,
+}
+
+impl Config {
+    /// Change the `bootstrap_brokers`.
+    pub fn with_bootstrap_brokers<T>(self, bootstrap_brokers: impl IntoIterator<Item = T>) -> Self
+    where
+        T: ToString,
+    {
+        let bootstrap_brokers = bootstrap_brokers
+            .into_iter()
+            .map(|broker| broker.to_string())
+            .collect();
+        Self {
+            bootstrap_brokers,
+            ..self
+        }
+    }
+
+    /// Change the `topic`.
+    pub fn with_topic<T>(self, topic: T) -> Self
+    where
+        T: ToString,
+    {
+        let topic = topic.to_string();
+        Self { topic, ..self }
+    }
+
+    /// Change the `poll_interval`, i.e. the maximum time to wait for new records.
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            ..self
+        }
+    }
+
+    /// Change the `fetch_max_bytes`, i.e. the maximum number of bytes fetched at once.
+    pub fn with_fetch_max_bytes(self, fetch_max_bytes: i32) -> Self {
+        Self {
+            fetch_max_bytes,
+            ..self
+        }
+    }
+
+    /// Change the `setup` flag.
+    pub fn with_setup(self, setup: bool) -> Self {
+        Self { setup, ..self }
+    }
+
+    /// Change the `partitions`, only used when setting up the topic.
+    pub fn with_partitions(self, partitions: i32) -> Self {
+        Self { partitions, ..self }
+    }
+
+    /// Change the `replication_factor`, only used when setting up the topic.
+    pub fn with_replication_factor(self, replication_factor: i16) -> Self {
+        Self {
+            replication_factor,
+            ..self
+        }
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            bootstrap_brokers: vec!["localhost:9092".to_string()],
+            topic: topic_default(),
+            poll_interval: poll_interval_default(),
+            fetch_max_bytes: fetch_max_bytes_default(),
+            setup: false,
+            partitions: partitions_default(),
+            replication_factor: replication_factor_default(),
+        }
+    }
+}
+
+/// Sequence number to offset index for the entities of a partition, also holding the entity IDs
+/// marked as deleted.
+#[derive(Debug, Default)]
+struct Index {
+    next_offset: Option<i64>,
+    offsets: HashMap<Uuid, BTreeMap<u64, i64>>,
+    deleted: HashSet<Uuid>,
+}
+
+impl Index {
+    /// Only insert the offset if the sequence number is larger than the last one of the entity.
+    fn insert(&mut self, id: Uuid, seq_no: u64, offset: i64) {
+        let offsets = self.offsets.entry(id).or_default();
+        if offsets
+            .last_key_value()
+            .map_or(true, |(last_seq_no, _)| seq_no > *last_seq_no)
+        {
+            offsets.insert(seq_no, offset);
+        }
+    }
+
+    fn last_seq_no(&self, id: Uuid) -> Result<Option<SeqNo>, Error> {
+        self.offsets
+            .get(&id)
+            .and_then(|offsets| offsets.last_key_value())
+            .map(|(seq_no, _)| (*seq_no).try_into().map_err(|_| Error::ZeroSeqNo))
+            .transpose()
+    }
+
+    /// The offset of the first event of the entity with at least the given sequence number or
+    /// else the next offset of the partition.
+    fn offset(&self, id: Uuid, from_seq_no: SeqNo) -> i64 {
+        self.offsets
+            .get(&id)
+            .and_then(|offsets| offsets.range(from_seq_no.as_u64()..).next())
+            .map(|(_, offset)| *offset)
+            .unwrap_or_else(|| self.next_offset.unwrap_or_default())
+    }
+}
+
+async fn create_topic(client: &Client, config: &Config) -> Result<(), Error> {
+    let result = client
+        .controller_client()
+        .map_err(|error| Error::Kafka("cannot create controller client".to_string(), error))?
+        .create_topic(
+            &config.topic,
+            config.partitions,
+            config.replication_factor,
+            5_000,
+        )
+        .await;
+
+    match result {
+        Ok(_) => Ok(()),
+
+        Err(KafkaError::ServerError {
+            protocol_error: ProtocolError::TopicAlreadyExists,
+            ..
+        }) => {
+            debug!(topic = config.topic, "topic already exists");
+            Ok(())
+        }
+
+        Err(error) => Err(Error::Kafka(
+            format!("cannot create topic {}", config.topic),
+            error,
+        )),
+    }
+}
+
+fn record_id(record: &Record) -> Result<Uuid, Error> {
+    record
+        .key
+        .as_deref()
+        .and_then(|key| Uuid::from_slice(key).ok())
+        .ok_or_else(|| Error::InvalidRecord("missing or invalid key".to_string()))
+}
+
+fn is_deleted_marker(record: &Record) -> bool {
+    record.headers.contains_key(DELETED)
+}
+
+fn record_seq_no(record: &Record) -> Result<u64, Error> {
+    header(record, SEQ_NO)
+}
+
+fn header<T>(record: &Record, name: &str) -> Result<T, Error>
+where
+    T: std::str::FromStr,
+{
+    record
+        .headers
+        .get(name)
+        .and_then(|value| std::str::from_utf8(value).ok())
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| Error::InvalidRecord(format!("missing or invalid header {name}")))
+}
+
+fn evt_envelope(record: Record, offset: i64) -> Result<EvtEnvelope<Bytes>, Error> {
+    let id = record_id(&record)?;
+    let seq_no = record_seq_no(&record)?
+        .try_into()
+        .map_err(|_| Error::ZeroSeqNo)?;
+    let global_seq_no = (offset as u64 + 1)
+        .try_into()
+        .map_err(|_| Error::ZeroSeqNo)?;
+    let version = header(&record, VERSION)?;
+    let tags = record
+        .headers
+        .get(TAGS)
+        .and_then(|tags| serde_json::from_slice(tags).ok())
+        .ok_or_else(|| Error::InvalidRecord(format!("missing or invalid header {TAGS}")))?;
+    let entity_type = record
+        .headers
+        .get(ENTITY_TYPE)
+        .map(|entity_type| {
+            String::from_utf8(entity_type.clone())
+                .map_err(|_| Error::InvalidRecord(format!("invalid header {ENTITY_TYPE}")))
+        })
+        .transpose()?;
+    let evt = record.value.map(Bytes::from).unwrap_or_default();
+
+    Ok(EvtEnvelope {
+        id,
+        entity_type,
+        seq_no,
+        global_seq_no,
+        version,
+        timestamp: record.timestamp,
+        tags,
+        evt,
+    })
+}
+
+fn topic_default() -> String {
+    "evts".to_string()
+}
+
+const fn poll_interval_default() -> Duration {
+    Duration::from_secs(2)
+}
+
+const fn fetch_max_bytes_default() -> i32 {
+    1_048_576
+}
+
+const fn partitions_default() -> i32 {
+    1
+}
+
+const fn replication_factor_default() -> i16 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eventsourced::convert;
+    use futures::{StreamExt, TryStreamExt};
+    use testcontainers::clients::Cli;
+    use testcontainers_modules::kafka::{Kafka, KAFKA_PORT};
+
+    #[tokio::test]
+    async fn test_evt_log() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let container = client.run(Kafka::default());
+        let broker = format!("localhost:{}", container.get_host_port_ipv4(KAFKA_PORT));
+
+        let config = Config::default()
+            .with_bootstrap_brokers([broker])
+            .with_poll_interval(Duration::from_millis(100))
+            .with_setup(true);
+        let evt_log = KafkaEvtLog::new(config).await?;
+
+        let id = Uuid::now_v7();
+
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, None);
+
+        let last_seq_no = evt_log
+            .persist(
+                &1,
+                1,
+                &["tag".to_string()],
+                id,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+        assert_eq!(last_seq_no, SeqNo::MIN);
+
+        let result = evt_log
+            .persist(&2, 1, &[], id, None, None, &convert::prost::to_bytes)
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::SeqNoConflict {
+                expected: None,
+                actual: Some(_)
+            })
+        ));
+
+        let mut last_seq_no = Some(last_seq_no);
+        for n in 2..=5 {
+            let seq_no = evt_log
+                .persist(&n, 1, &[], id, None, last_seq_no, &convert::prost::to_bytes)
+                .await?;
+            last_seq_no = Some(seq_no);
+        }
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id, 3.try_into()?, convert::prost::from_bytes)
+            .await?
+            .take(3)
+            .map_ok(|evt| (evt.seq_no.as_u64(), evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(3, 3), (4, 4), (5, 5)]);
+
+        let id_2 = Uuid::now_v7();
+        evt_log
+            .persist(
+                &6,
+                2,
+                &["tag".to_string()],
+                id_2,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let evts_by_tag = evt_log
+            .evts_by_tag::<i32, _, _>("tag".to_string(), SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(2)
+            .map_ok(|evt| (evt.id, evt.version, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_tag, vec![(id, 1, 1), (id_2, 2, 6)]);
+
+        let evts_by_ids = evt_log
+            .evts_by_ids::<i32, _, _>(vec![id_2], GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(1)
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_ids, vec![6]);
+
+        let evts = evt_log
+            .evts::<i32, _, _>(GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(6)
+            .map_ok(|evt| (evt.global_seq_no.as_u64(), evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(1, 1), (2, 2), (3, 3), (4, 4), (5, 5), (6, 6)]);
+
+        assert!(!evt_log.is_deleted(id).await?);
+        evt_log.mark_deleted(id).await?;
+        evt_log.mark_deleted(id).await?;
+        assert!(evt_log.is_deleted(id).await?);
+        assert!(!evt_log.is_deleted(id_2).await?);
+
+        // Deleted markers are skipped when reading events.
+        let evts = evt_log
+            .evts::<i32, _, _>(GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(6)
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+//! An [EvtLog](eventsourced::EvtLog) implementation based upon
+//! [Apache Kafka](https://kafka.apache.org/).
+
+mod evt_log;
+
+pub use evt_log::{Config as KafkaEvtLogConfig, KafkaEvtLog};
+
+use eventsourced::SeqNo;
+use thiserror::Error;
+
+/// Errors from the [KafkaEvtLog].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Kafka error.
+    #[error("Kafka error: {0}")]
+    Kafka(String, #[source] rskafka::client::error::Error),
+
+    /// Cannot convert an event to bytes.
+    #[error("cannot convert an event to bytes")]
+    ToBytes(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Cannot convert bytes to an event.
+    #[error("cannot convert bytes to an event")]
+    FromBytes(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Sequence number must not be zero.
+    #[error("sequence number must not be zero")]
+    ZeroSeqNo,
+
+    /// The given last sequence number does not match the actual one, e.g. because of a concurrent
+    /// writer for the same entity ID.
+    #[error("expected last sequence number {expected:?}, but was {actual:?}")]
+    SeqNoConflict {
+        expected: Option<SeqNo>,
+        actual: Option<SeqNo>,
+    },
+
+    /// Unknown topic, i.e. not existing and not set up.
+    #[error("unknown topic {0}")]
+    UnknownTopic(String),
+
+    /// Invalid record, e.g. with a missing or malformed key or header.
+    #[error("invalid record: {0}")]
+    InvalidRecord(String),
+}
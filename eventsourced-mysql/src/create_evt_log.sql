@@ -0,0 +1,21 @@
+CREATE TABLE
+  IF NOT EXISTS evts (
+    seq_no BIGINT NOT NULL,
+    id BINARY(16) NOT NULL,
+    evt LONGBLOB NOT NULL,
+    tags JSON NOT NULL,
+    version INT NOT NULL DEFAULT 1,
+    timestamp TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+    global_seq_no BIGINT NOT NULL AUTO_INCREMENT,
+    entity_type VARCHAR(255),
+    PRIMARY KEY (id, seq_no),
+    UNIQUE KEY evts_global_seq_no (global_seq_no),
+    KEY evts_entity_type (entity_type, global_seq_no)
+  );
+
+CREATE TABLE
+  IF NOT EXISTS evts_deleted (
+    id BINARY(16) NOT NULL,
+    timestamp TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+    PRIMARY KEY (id)
+  );
\ No newline at end of file
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS snapshots (
+  id BINARY(16) NOT NULL,
+  seq_no BIGINT NOT NULL,
+  state LONGBLOB NOT NULL,
+  state_version INT NOT NULL DEFAULT 1,
+  PRIMARY KEY (id, seq_no)
+);
\ No newline at end of file
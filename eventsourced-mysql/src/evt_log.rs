@@ -0,0 +1,822 @@
+//! An [EvtLog] implementation based on [MySQL](https://www.mysql.com/).
+
+use crate::{cnn_pool, Error};
+use async_stream::stream;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use eventsourced::{Clock, EntityEvts, EvtEnvelope, EvtLog, GlobalSeqNo, SeqNo, SystemClock};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    mysql::{MySqlConnectOptions, MySqlRow},
+    pool::PoolConnection,
+    types::Json,
+    MySql, MySqlConnection, MySqlPool, Row,
+};
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+    num::{NonZeroU64, NonZeroUsize},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::sleep;
+use tracing::debug;
+use uuid::Uuid;
+
+/// An [EvtLog] implementation based on [MySQL](https://www.mysql.com/), which also works with
+/// [MariaDB](https://mariadb.org/).
+#[derive(Clone)]
+pub struct MysqlEvtLog {
+    poll_interval: Duration,
+    replay_batch_size: NonZeroUsize,
+    cnn_pool: MySqlPool,
+    clock: Arc<dyn Clock>,
+}
+
+impl MysqlEvtLog {
+    #[allow(missing_docs)]
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating MysqlEvtLog");
+
+        let setup = config.setup.then_some(include_str!("create_evt_log.sql"));
+        let cnn_pool = cnn_pool(config.cnn_options(), setup).await?;
+
+        Ok(Self {
+            clock: Arc::new(SystemClock),
+            poll_interval: config.poll_interval,
+            replay_batch_size: config.replay_batch_size,
+            cnn_pool,
+        })
+    }
+
+    /// Use the given [Clock] instead of the [SystemClock] to timestamp persisted events.
+    pub fn with_clock(self, clock: impl Clock) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    async fn cnn(&self) -> Result<PoolConnection<MySql>, Error> {
+        self.cnn_pool
+            .acquire()
+            .await
+            .map_err(|error| Error::Mysql("cannot get connection from pool".to_string(), error))
+    }
+
+    async fn seq_no_conflict(&self, id: Uuid, expected: Option<SeqNo>) -> Error {
+        match self.last_seq_no(id).await {
+            Ok(actual) => Error::SeqNoConflict { expected, actual },
+            Err(error) => error,
+        }
+    }
+
+    /// Query the events matching the given filter, starting with the given (global) sequence
+    /// number, in batches using keyset pagination and keep polling for new events.
+    fn evts_by_filter<E, FromBytes, FromBytesError>(
+        &self,
+        filter: Filter,
+        from: u64,
+        from_bytes: FromBytes,
+    ) -> impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let cnn_pool = self.cnn_pool.clone();
+        let poll_interval = self.poll_interval;
+        let replay_batch_size = self.replay_batch_size.get();
+        let mut from = from;
+
+        stream! {
+            'outer: loop {
+                let evts = next_evts(&cnn_pool, &filter, from, replay_batch_size).await;
+                let evts = match evts {
+                    Ok(evts) => evts,
+
+                    Err(error) => {
+                        yield Err(error);
+                        break 'outer;
+                    }
+                };
+
+                let n = evts.len();
+                for evt in evts {
+                    from = filter.key(&evt) + 1;
+
+                    let evt = from_bytes(evt.evt.clone())
+                        .map_err(|error| Error::FromBytes(Box::new(error)))
+                        .map(|payload| EvtEnvelope {
+                            id: evt.id,
+                            entity_type: evt.entity_type.clone(),
+                            seq_no: evt.seq_no,
+                            global_seq_no: evt.global_seq_no,
+                            version: evt.version,
+                            timestamp: evt.timestamp,
+                            tags: evt.tags,
+                            evt: payload,
+                        });
+                    let is_err = evt.is_err();
+                    yield evt;
+                    if is_err {
+                        break 'outer;
+                    }
+                }
+
+                // Only sleep if all current events have been read.
+                if n < replay_batch_size {
+                    sleep(poll_interval).await;
+                }
+            }
+        }
+    }
+}
+
+impl Debug for MysqlEvtLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MysqlEvtLog").finish()
+    }
+}
+
+impl EvtLog for MysqlEvtLog {
+    type Error = Error;
+
+    /// The maximum value for sequence numbers. As signed `BIGINT`s are used, this is `i64::MAX` or
+    /// `9_223_372_036_854_775_807`.
+    const MAX_SEQ_NO: SeqNo = SeqNo::new(unsafe { NonZeroU64::new_unchecked(i64::MAX as u64) });
+
+    async fn persist<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Uuid,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> Result<SeqNo, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, "persisting event");
+
+        let seq_no = insert_evt(
+            &mut *self.cnn().await?,
+            evt,
+            version,
+            tags,
+            id,
+            entity_type,
+            last_seq_no,
+            self.clock.now(),
+            to_bytes,
+        )
+        .await?;
+        match seq_no {
+            Some(seq_no) => Ok(seq_no),
+            None => Err(self.seq_no_conflict(id, last_seq_no).await),
+        }
+    }
+
+    async fn persist_batch<E, ToBytes, ToBytesError>(
+        &self,
+        batch: &[EntityEvts<'_, E>],
+        to_bytes: &ToBytes,
+    ) -> Result<Vec<Option<SeqNo>>, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(len = batch.len(), "persisting batch");
+
+        let mut tx = self
+            .cnn_pool
+            .begin()
+            .await
+            .map_err(|error| Error::Mysql("cannot start transaction".to_string(), error))?;
+
+        // Dropping the transaction early, e.g. on errors, rolls it back. Later entries for the
+        // same entity ID continue after the former ones.
+        let mut last_seq_nos = Vec::with_capacity(batch.len());
+        let mut chained = HashMap::new();
+        for entity_evts in batch {
+            let mut last_seq_no = chained
+                .get(&entity_evts.id)
+                .copied()
+                .unwrap_or(entity_evts.last_seq_no);
+            for evt in entity_evts.evts {
+                let seq_no = insert_evt(
+                    &mut tx,
+                    evt.evt(),
+                    entity_evts.version,
+                    evt.tags(),
+                    entity_evts.id,
+                    entity_evts.entity_type,
+                    last_seq_no,
+                    self.clock.now(),
+                    to_bytes,
+                )
+                .await?;
+                match seq_no {
+                    Some(seq_no) => last_seq_no = Some(seq_no),
+                    None => {
+                        drop(tx);
+                        return Err(self.seq_no_conflict(entity_evts.id, last_seq_no).await);
+                    }
+                }
+            }
+            chained.insert(entity_evts.id, last_seq_no);
+            last_seq_nos.push(last_seq_no);
+        }
+
+        tx.commit()
+            .await
+            .map_err(|error| Error::Mysql("cannot commit transaction".to_string(), error))?;
+
+        Ok(last_seq_nos)
+    }
+
+    async fn delete_to(&self, id: Uuid, to_seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %to_seq_no, "deleting events");
+
+        // Always keep the last event. As MySQL does not support subqueries on the table deleted
+        // from, the last sequence number is queried upfront.
+        let Some(last_seq_no) = self.last_seq_no(id).await? else {
+            return Ok(());
+        };
+        let to_seq_no = to_seq_no.min(last_seq_no).as_u64() as i64;
+
+        sqlx::query("DELETE FROM evts WHERE id = ? AND seq_no <= ? AND seq_no < ?")
+            .bind(id)
+            .bind(to_seq_no)
+            .bind(last_seq_no.as_u64() as i64)
+            .execute(&self.cnn_pool)
+            .await
+            .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
+
+    async fn mark_deleted(&self, id: Uuid) -> Result<(), Self::Error> {
+        debug!(%id, "marking entity as deleted");
+
+        sqlx::query("INSERT IGNORE INTO evts_deleted (id) VALUES (?)")
+            .bind(id)
+            .execute(&self.cnn_pool)
+            .await
+            .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
+
+    async fn is_deleted(&self, id: Uuid) -> Result<bool, Self::Error> {
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM evts_deleted WHERE id = ?)")
+            .bind(id)
+            .fetch_one(&self.cnn_pool)
+            .await
+            .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))
+    }
+
+    async fn last_seq_no(&self, id: Uuid) -> Result<Option<SeqNo>, Self::Error> {
+        sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(seq_no) FROM evts WHERE id = ?")
+            .bind(id)
+            .fetch_one(&self.cnn_pool)
+            .await
+            .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))?
+            .map(|seq_no| (seq_no as u64).try_into().map_err(|_| Error::ZeroSeqNo))
+            .transpose()
+    }
+
+    async fn evts_by_id<E, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %from_seq_no, "building events by ID stream");
+        Ok(self.evts_by_filter(Filter::Id(id), from_seq_no.as_u64(), from_bytes))
+    }
+
+    async fn evts_by_ids<E, FromBytes, FromBytesError>(
+        &self,
+        ids: Vec<Uuid>,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(?ids, %from_global_seq_no, "building events by IDs stream");
+        Ok(self.evts_by_filter(Filter::Ids(ids), from_global_seq_no.as_u64(), from_bytes))
+    }
+
+    async fn evts<E, FromBytes, FromBytesError>(
+        &self,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%from_global_seq_no, "building events stream");
+        Ok(self.evts_by_filter(Filter::All, from_global_seq_no.as_u64(), from_bytes))
+    }
+
+    async fn evts_by_type<E, FromBytes, FromBytesError>(
+        &self,
+        entity_type: String,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(entity_type, %from_global_seq_no, "building events by type stream");
+        Ok(self.evts_by_filter(
+            Filter::EntityType(entity_type),
+            from_global_seq_no.as_u64(),
+            from_bytes,
+        ))
+    }
+
+    /// The given sequence number is used as global sequence number, like for the NATS
+    /// implementation.
+    async fn evts_by_tag<E, FromBytes, FromBytesError>(
+        &self,
+        tag: String,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(tag, %from_seq_no, "building events by tag stream");
+        Ok(self.evts_by_filter(Filter::Tag(tag), from_seq_no.as_u64(), from_bytes))
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        sqlx::query("SELECT 1")
+            .execute(&self.cnn_pool)
+            .await
+            .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
+
+    fn is_retryable(error: &Self::Error) -> bool {
+        error.is_transient()
+    }
+}
+
+/// Configuration for the [MysqlEvtLog].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    host: String,
+
+    port: u16,
+
+    user: String,
+
+    password: String,
+
+    dbname: String,
+
+    #[serde(default = "poll_interval_default", with = "humantime_serde")]
+    poll_interval: Duration,
+
+    #[serde(default = "replay_batch_size_default")]
+    replay_batch_size: NonZeroUsize,
+
+    #[serde(default)]
+    setup: bool,
+}
+
+impl Config {
+    /// Change the `host`.
+    pub fn with_host<T>(self, host: T) -> Self
+    where
+        T: ToString,
+    {
+        let host = host.to_string();
+        Self { host, ..self }
+    }
+
+    /// Change the `port`.
+    pub fn with_port(self, port: u16) -> Self {
+        Self { port, ..self }
+    }
+
+    /// Change the `user`.
+    pub fn with_user<T>(self, user: T) -> Self
+    where
+        T: ToString,
+    {
+        let user = user.to_string();
+        Self { user, ..self }
+    }
+
+    /// Change the `password`.
+    pub fn with_password<T>(self, password: T) -> Self
+    where
+        T: ToString,
+    {
+        let password = password.to_string();
+        Self { password, ..self }
+    }
+
+    /// Change the `dbname`.
+    pub fn with_dbname<T>(self, dbname: T) -> Self
+    where
+        T: ToString,
+    {
+        let dbname = dbname.to_string();
+        Self { dbname, ..self }
+    }
+
+    /// Change the `poll_interval`.
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            ..self
+        }
+    }
+
+    /// Change the `replay_batch_size`, i.e. the maximum number of events fetched at once.
+    pub fn with_replay_batch_size(self, replay_batch_size: NonZeroUsize) -> Self {
+        Self {
+            replay_batch_size,
+            ..self
+        }
+    }
+
+    /// Change the `setup` flag.
+    pub fn with_setup(self, setup: bool) -> Self {
+        Self { setup, ..self }
+    }
+
+    fn cnn_options(&self) -> MySqlConnectOptions {
+        MySqlConnectOptions::new()
+            .host(&self.host)
+            .port(self.port)
+            .username(&self.user)
+            .password(&self.password)
+            .database(&self.dbname)
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 3306,
+            user: "root".to_string(),
+            password: "".to_string(),
+            dbname: "test".to_string(),
+            poll_interval: poll_interval_default(),
+            replay_batch_size: replay_batch_size_default(),
+            setup: false,
+        }
+    }
+}
+
+/// Filter for querying events, determining whether the sequence number or the global sequence
+/// number is used for keyset pagination.
+enum Filter {
+    Id(Uuid),
+    Ids(Vec<Uuid>),
+    All,
+    EntityType(String),
+    Tag(String),
+}
+
+impl Filter {
+    fn key(&self, evt: &EvtEnvelope<Bytes>) -> u64 {
+        match self {
+            Filter::Id(_) => evt.seq_no.as_u64(),
+            _ => evt.global_seq_no.as_u64(),
+        }
+    }
+}
+
+/// Query the next batch of events matching the given filter.
+async fn next_evts(
+    cnn_pool: &MySqlPool,
+    filter: &Filter,
+    from: u64,
+    replay_batch_size: usize,
+) -> Result<Vec<EvtEnvelope<Bytes>>, Error> {
+    debug!(from, "querying events");
+
+    let condition = match filter {
+        Filter::Id(_) => "id = ? AND seq_no >= ? ORDER BY seq_no".to_string(),
+        Filter::Ids(ids) if ids.is_empty() => "FALSE AND global_seq_no >= ?".to_string(),
+        Filter::Ids(ids) => {
+            let placeholders = vec!["?"; ids.len()].join(", ");
+            format!("id IN ({placeholders}) AND global_seq_no >= ? ORDER BY global_seq_no")
+        }
+        Filter::All => "global_seq_no >= ? ORDER BY global_seq_no".to_string(),
+        Filter::EntityType(_) => {
+            "entity_type = ? AND global_seq_no >= ? ORDER BY global_seq_no".to_string()
+        }
+        Filter::Tag(_) => {
+            "JSON_CONTAINS(tags, JSON_QUOTE(?)) AND global_seq_no >= ? ORDER BY global_seq_no"
+                .to_string()
+        }
+    };
+    let query = format!(
+        "SELECT id, seq_no, global_seq_no, version, timestamp, tags, evt, entity_type FROM evts
+         WHERE {condition}
+         LIMIT ?"
+    );
+
+    let mut query = sqlx::query(&query);
+    match filter {
+        Filter::Id(id) => query = query.bind(id),
+        Filter::Ids(ids) => {
+            for id in ids {
+                query = query.bind(id);
+            }
+        }
+        Filter::All => {}
+        Filter::EntityType(entity_type) => query = query.bind(entity_type),
+        Filter::Tag(tag) => query = query.bind(tag),
+    }
+
+    query
+        .bind(from as i64)
+        .bind(replay_batch_size as i64)
+        .fetch_all(cnn_pool)
+        .await
+        .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))?
+        .into_iter()
+        .map(evt_envelope)
+        .collect()
+}
+
+fn evt_envelope(row: MySqlRow) -> Result<EvtEnvelope<Bytes>, Error> {
+    let get_error = |error| Error::Mysql("cannot get column".to_string(), error);
+
+    let id = row.try_get::<Uuid, _>(0).map_err(get_error)?;
+    let seq_no = (row.try_get::<i64, _>(1).map_err(get_error)? as u64)
+        .try_into()
+        .map_err(|_| Error::ZeroSeqNo)?;
+    let global_seq_no = (row.try_get::<i64, _>(2).map_err(get_error)? as u64)
+        .try_into()
+        .map_err(|_| Error::ZeroSeqNo)?;
+    let version = row.try_get::<i32, _>(3).map_err(get_error)? as u32;
+    let timestamp = row.try_get::<DateTime<Utc>, _>(4).map_err(get_error)?;
+    let Json(tags) = row.try_get::<Json<Vec<String>>, _>(5).map_err(get_error)?;
+    let evt = row.try_get::<Vec<u8>, _>(6).map_err(get_error)?;
+    let entity_type = row.try_get::<Option<String>, _>(7).map_err(get_error)?;
+
+    Ok(EvtEnvelope {
+        id,
+        entity_type,
+        seq_no,
+        global_seq_no,
+        version,
+        timestamp,
+        tags,
+        evt: evt.into(),
+    })
+}
+
+/// Insert the given event, returning `None` if the given last sequence number is not the actual
+/// one.
+#[allow(clippy::too_many_arguments)]
+async fn insert_evt<E, ToBytes, ToBytesError>(
+    cnn: &mut MySqlConnection,
+    evt: &E,
+    version: u32,
+    tags: &[String],
+    id: Uuid,
+    entity_type: Option<&str>,
+    last_seq_no: Option<SeqNo>,
+    timestamp: DateTime<Utc>,
+    to_bytes: &ToBytes,
+) -> Result<Option<SeqNo>, Error>
+where
+    ToBytes: Fn(&E) -> Result<Bytes, ToBytesError>,
+    ToBytesError: StdError + Send + Sync + 'static,
+{
+    let seq_no = last_seq_no
+        .map(|seq_no| seq_no.succ())
+        .unwrap_or(SeqNo::MIN);
+
+    let bytes = to_bytes(evt).map_err(|error| Error::ToBytes(Box::new(error)))?;
+
+    // Only insert if the given last sequence number is the actual one; a concurrent insert of the
+    // same sequence number is rejected by the primary key.
+    let expected = last_seq_no.map(|seq_no| seq_no.as_u64() as i64);
+    let result = sqlx::query(
+        "INSERT INTO evts (seq_no, id, evt, tags, timestamp, version, entity_type)
+         SELECT ?, ?, ?, ?, ?, ?, ? FROM DUAL
+         WHERE (SELECT MAX(seq_no) FROM evts WHERE id = ?) <=> ?",
+    )
+    .bind(seq_no.as_u64() as i64)
+    .bind(id)
+    .bind(bytes.as_ref())
+    .bind(Json(tags))
+    .bind(timestamp)
+    .bind(version as i32)
+    .bind(entity_type)
+    .bind(id)
+    .bind(expected)
+    .execute(cnn)
+    .await;
+
+    match result {
+        Ok(result) if result.rows_affected() == 1 => Ok(Some(seq_no)),
+
+        Ok(_) => Ok(None),
+
+        Err(sqlx::Error::Database(error)) if error.is_unique_violation() => Ok(None),
+
+        Err(error) => Err(Error::Mysql("cannot execute query".to_string(), error)),
+    }
+}
+
+const fn poll_interval_default() -> Duration {
+    Duration::from_secs(2)
+}
+
+const fn replay_batch_size_default() -> NonZeroUsize {
+    unsafe { NonZeroUsize::new_unchecked(1_000) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eventsourced::{convert, IntoTaggedEvt};
+    use futures::{StreamExt, TryStreamExt};
+    use testcontainers::clients::Cli;
+    use testcontainers_modules::mysql::Mysql;
+
+    #[tokio::test]
+    async fn test_evt_log() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let container = client.run(Mysql::default());
+        let port = container.get_host_port_ipv4(3306);
+
+        let config = Config::default()
+            .with_port(port)
+            .with_replay_batch_size(2.try_into()?)
+            .with_setup(true);
+        let evt_log = MysqlEvtLog::new(config).await?;
+
+        let id = Uuid::now_v7();
+
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, None);
+
+        let last_seq_no = evt_log
+            .persist(
+                &1,
+                1,
+                &["tag".to_string()],
+                id,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+        assert_eq!(last_seq_no, SeqNo::MIN);
+
+        let result = evt_log
+            .persist(&2, 1, &[], id, None, None, &convert::prost::to_bytes)
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::SeqNoConflict {
+                expected: None,
+                actual: Some(_)
+            })
+        ));
+
+        let result = evt_log
+            .persist(
+                &2,
+                1,
+                &[],
+                id,
+                None,
+                Some(10.try_into()?),
+                &convert::prost::to_bytes,
+            )
+            .await;
+        assert!(matches!(result, Err(Error::SeqNoConflict { .. })));
+        assert!(!MysqlEvtLog::is_retryable(&result.unwrap_err()));
+
+        let mut last_seq_no = Some(last_seq_no);
+        for n in 2..=5 {
+            let seq_no = evt_log
+                .persist(&n, 1, &[], id, None, last_seq_no, &convert::prost::to_bytes)
+                .await?;
+            last_seq_no = Some(seq_no);
+        }
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+
+        // Events are fetched in batches of two, i.e. across three pages.
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(5)
+            .map_ok(|evt| (evt.seq_no.as_u64(), evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(1, 1), (2, 2), (3, 3), (4, 4), (5, 5)]);
+
+        let id_2 = Uuid::now_v7();
+        evt_log
+            .persist(
+                &6,
+                2,
+                &["tag".to_string()],
+                id_2,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let evts_by_tag = evt_log
+            .evts_by_tag::<i32, _, _>("tag".to_string(), SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(2)
+            .map_ok(|evt| (evt.id, evt.version, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_tag, vec![(id, 1, 1), (id_2, 2, 6)]);
+
+        let evts_by_ids = evt_log
+            .evts_by_ids::<i32, _, _>(vec![id_2], GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(1)
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_ids, vec![6]);
+
+        evt_log.delete_to(id, 10.try_into()?).await?;
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+        let evts = evt_log
+            .evts::<i32, _, _>(GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(2)
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![5, 6]);
+
+        assert!(!evt_log.is_deleted(id).await?);
+        evt_log.mark_deleted(id).await?;
+        evt_log.mark_deleted(id).await?;
+        assert!(evt_log.is_deleted(id).await?);
+        assert!(!evt_log.is_deleted(id_2).await?);
+
+        // A conflict for one entity must roll back the whole batch.
+        let id_3 = Uuid::now_v7();
+        let batch_evts = [7.into_tagged_evt(), 8.into_tagged_evt()];
+        let batch = [
+            EntityEvts {
+                id: id_3,
+                entity_type: None,
+                last_seq_no: None,
+                version: 1,
+                evts: &batch_evts,
+            },
+            EntityEvts {
+                id: id_2,
+                entity_type: None,
+                last_seq_no: None,
+                version: 1,
+                evts: &batch_evts,
+            },
+        ];
+        let result = evt_log
+            .persist_batch(&batch, &convert::prost::to_bytes)
+            .await;
+        assert!(matches!(result, Err(Error::SeqNoConflict { .. })));
+        assert_eq!(evt_log.last_seq_no(id_3).await?, None);
+
+        Ok(())
+    }
+}
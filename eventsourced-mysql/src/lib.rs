@@ -0,0 +1,93 @@
+//! [EvtLog](eventsourced::EvtLog) and [SnapshotStore](eventsourced::SnapshotStore) implementations
+//! based upon [MySQL](https://www.mysql.com/), which also work with
+//! [MariaDB](https://mariadb.org/).
+
+mod evt_log;
+mod snapshot_store;
+
+pub use evt_log::{Config as MysqlEvtLogConfig, MysqlEvtLog};
+pub use snapshot_store::{Config as MysqlSnapshotStoreConfig, MysqlSnapshotStore};
+
+use eventsourced::SeqNo;
+use sqlx::{
+    mysql::{MySqlConnectOptions, MySqlDatabaseError, MySqlPoolOptions},
+    MySqlPool,
+};
+use thiserror::Error;
+
+/// Errors from the [MysqlEvtLog] or [MysqlSnapshotStore].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// MySQL error.
+    #[error("MySQL error: {0}")]
+    Mysql(String, #[source] sqlx::Error),
+
+    /// Cannot convert an event to bytes.
+    #[error("cannot convert an event to bytes")]
+    ToBytes(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Cannot convert bytes to an event.
+    #[error("cannot convert bytes to an event")]
+    FromBytes(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Sequence number must not be zero.
+    #[error("sequence number must not be zero")]
+    ZeroSeqNo,
+
+    /// The given last sequence number does not match the actual one, e.g. because of a concurrent
+    /// writer for the same entity ID.
+    #[error("expected last sequence number {expected:?}, but was {actual:?}")]
+    SeqNoConflict {
+        expected: Option<SeqNo>,
+        actual: Option<SeqNo>,
+    },
+}
+
+impl Error {
+    /// Whether this error is transient, i.e. caused by a lost or refused connection, a lock wait
+    /// timeout or a deadlock, such that the failed operation can be retried.
+    fn is_transient(&self) -> bool {
+        match self {
+            Error::Mysql(_, sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut) => true,
+
+            Error::Mysql(_, sqlx::Error::Database(error)) => error
+                .try_downcast_ref::<MySqlDatabaseError>()
+                .is_some_and(|error| {
+                    matches!(
+                        error.number(),
+                        ER_CON_COUNT_ERROR
+                            | ER_SERVER_SHUTDOWN
+                            | ER_LOCK_WAIT_TIMEOUT
+                            | ER_LOCK_DEADLOCK
+                    )
+                }),
+
+            _ => false,
+        }
+    }
+}
+
+const ER_CON_COUNT_ERROR: u16 = 1040;
+const ER_SERVER_SHUTDOWN: u16 = 1053;
+const ER_LOCK_WAIT_TIMEOUT: u16 = 1205;
+const ER_LOCK_DEADLOCK: u16 = 1213;
+
+/// Create a connection pool and, if `setup` is enabled, execute the given setup script.
+async fn cnn_pool(
+    cnn_options: MySqlConnectOptions,
+    setup: Option<&str>,
+) -> Result<MySqlPool, Error> {
+    let cnn_pool = MySqlPoolOptions::new()
+        .connect_with(cnn_options)
+        .await
+        .map_err(|error| Error::Mysql("cannot create connection pool".to_string(), error))?;
+
+    if let Some(setup) = setup {
+        sqlx::raw_sql(setup)
+            .execute(&cnn_pool)
+            .await
+            .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))?;
+    }
+
+    Ok(cnn_pool)
+}
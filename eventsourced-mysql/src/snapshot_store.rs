@@ -0,0 +1,341 @@
+//! A [SnapshotStore] implementation based on [MySQL](https://www.mysql.com/).
+
+use crate::{cnn_pool, Error};
+use bytes::Bytes;
+use eventsourced::{SeqNo, Snapshot, SnapshotStore};
+use serde::{Deserialize, Serialize};
+use sqlx::{mysql::MySqlConnectOptions, MySqlPool};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+};
+use tracing::debug;
+use uuid::Uuid;
+
+/// A [SnapshotStore] implementation based on [MySQL](https://www.mysql.com/), which also works
+/// with [MariaDB](https://mariadb.org/).
+#[derive(Clone)]
+pub struct MysqlSnapshotStore {
+    cnn_pool: MySqlPool,
+}
+
+impl MysqlSnapshotStore {
+    #[allow(missing_docs)]
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating MysqlSnapshotStore");
+
+        let setup = config
+            .setup
+            .then_some(include_str!("create_snapshot_store.sql"));
+        let cnn_pool = cnn_pool(config.cnn_options(), setup).await?;
+
+        Ok(Self { cnn_pool })
+    }
+}
+
+impl Debug for MysqlSnapshotStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MysqlSnapshotStore").finish()
+    }
+}
+
+impl SnapshotStore for MysqlSnapshotStore {
+    type Error = Error;
+
+    async fn save<S, ToBytes, ToBytesError>(
+        &self,
+        id: Uuid,
+        seq_no: SeqNo,
+        state_version: u32,
+        state: S,
+        to_bytes: &ToBytes,
+    ) -> Result<(), Self::Error>
+    where
+        S: Send,
+        ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %seq_no, state_version, "saving snapshot");
+
+        let bytes = to_bytes(&state).map_err(|source| Error::ToBytes(Box::new(source)))?;
+        sqlx::query("INSERT INTO snapshots (id, seq_no, state, state_version) VALUES (?, ?, ?, ?)")
+            .bind(id)
+            .bind(seq_no.as_u64() as i64)
+            .bind(bytes.as_ref())
+            .bind(state_version)
+            .execute(&self.cnn_pool)
+            .await
+            .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
+
+    async fn load<S, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> Result<Option<Snapshot<S>>, Self::Error>
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, state_version, "loading snapshot");
+
+        sqlx::query_as::<_, (i64, Vec<u8>)>(
+            "SELECT seq_no, state FROM snapshots
+             WHERE id = ? AND state_version = ?
+             ORDER BY seq_no DESC
+             LIMIT 1",
+        )
+        .bind(id)
+        .bind(state_version)
+        .fetch_optional(&self.cnn_pool)
+        .await
+        .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))?
+        .map(move |(seq_no, bytes)| {
+            let seq_no = (seq_no as u64).try_into().map_err(|_| Error::ZeroSeqNo)?;
+            from_bytes(bytes.into())
+                .map_err(|source| Error::FromBytes(Box::new(source)))
+                .map(|state| Snapshot::new(seq_no, state))
+        })
+        .transpose()
+    }
+
+    async fn load_at<S, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        max_seq_no: SeqNo,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> Result<Option<Snapshot<S>>, Self::Error>
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %max_seq_no, state_version, "loading snapshot");
+
+        sqlx::query_as::<_, (i64, Vec<u8>)>(
+            "SELECT seq_no, state FROM snapshots
+             WHERE id = ? AND state_version = ? AND seq_no <= ?
+             ORDER BY seq_no DESC
+             LIMIT 1",
+        )
+        .bind(id)
+        .bind(state_version)
+        .bind(max_seq_no.as_u64() as i64)
+        .fetch_optional(&self.cnn_pool)
+        .await
+        .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))?
+        .map(move |(seq_no, bytes)| {
+            let seq_no = (seq_no as u64).try_into().map_err(|_| Error::ZeroSeqNo)?;
+            from_bytes(bytes.into())
+                .map_err(|source| Error::FromBytes(Box::new(source)))
+                .map(|state| Snapshot::new(seq_no, state))
+        })
+        .transpose()
+    }
+
+    async fn delete_before(&self, id: Uuid, seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %seq_no, "deleting snapshots");
+
+        sqlx::query("DELETE FROM snapshots WHERE id = ? AND seq_no < ?")
+            .bind(id)
+            .bind(seq_no.as_u64() as i64)
+            .execute(&self.cnn_pool)
+            .await
+            .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        sqlx::query("SELECT 1")
+            .execute(&self.cnn_pool)
+            .await
+            .map_err(|error| Error::Mysql("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
+}
+
+/// Configuration for the [MysqlSnapshotStore].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    host: String,
+
+    port: u16,
+
+    user: String,
+
+    password: String,
+
+    dbname: String,
+
+    #[serde(default)]
+    setup: bool,
+}
+
+impl Config {
+    /// Change the `host`.
+    pub fn with_host<T>(self, host: T) -> Self
+    where
+        T: ToString,
+    {
+        let host = host.to_string();
+        Self { host, ..self }
+    }
+
+    /// Change the `port`.
+    pub fn with_port(self, port: u16) -> Self {
+        Self { port, ..self }
+    }
+
+    /// Change the `user`.
+    pub fn with_user<T>(self, user: T) -> Self
+    where
+        T: ToString,
+    {
+        let user = user.to_string();
+        Self { user, ..self }
+    }
+
+    /// Change the `password`.
+    pub fn with_password<T>(self, password: T) -> Self
+    where
+        T: ToString,
+    {
+        let password = password.to_string();
+        Self { password, ..self }
+    }
+
+    /// Change the `dbname`.
+    pub fn with_dbname<T>(self, dbname: T) -> Self
+    where
+        T: ToString,
+    {
+        let dbname = dbname.to_string();
+        Self { dbname, ..self }
+    }
+
+    /// Change the `setup` flag.
+    pub fn with_setup(self, setup: bool) -> Self {
+        Self { setup, ..self }
+    }
+
+    fn cnn_options(&self) -> MySqlConnectOptions {
+        MySqlConnectOptions::new()
+            .host(&self.host)
+            .port(self.port)
+            .username(&self.user)
+            .password(&self.password)
+            .database(&self.dbname)
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 3306,
+            user: "root".to_string(),
+            password: "".to_string(),
+            dbname: "test".to_string(),
+            setup: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eventsourced::convert;
+    use testcontainers::clients::Cli;
+    use testcontainers_modules::mysql::Mysql;
+
+    #[tokio::test]
+    async fn test_snapshot_store() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let container = client.run(Mysql::default());
+        let port = container.get_host_port_ipv4(3306);
+
+        let config = Config::default().with_port(port).with_setup(true);
+        let snapshot_store = MysqlSnapshotStore::new(config).await?;
+
+        let id = Uuid::now_v7();
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        let seq_no = 42.try_into().unwrap();
+        let state = 666;
+
+        snapshot_store
+            .save(id, seq_no, 1, state, &convert::prost::to_bytes)
+            .await?;
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+
+        assert!(snapshot.is_some());
+        let snapshot = snapshot.unwrap();
+        assert_eq!(snapshot.seq_no, seq_no);
+        assert_eq!(snapshot.state, state);
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 2, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        snapshot_store.delete_before(id, seq_no).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_at() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let container = client.run(Mysql::default());
+        let port = container.get_host_port_ipv4(3306);
+
+        let config = Config::default().with_port(port).with_setup(true);
+        let snapshot_store = MysqlSnapshotStore::new(config).await?;
+
+        let id = Uuid::now_v7();
+
+        snapshot_store
+            .save(id, 21.try_into()?, 1, 21, &convert::prost::to_bytes)
+            .await?;
+        snapshot_store
+            .save(id, 42.try_into()?, 1, 42, &convert::prost::to_bytes)
+            .await?;
+
+        let snapshot = snapshot_store
+            .load_at::<i32, _, _>(id, 42.try_into()?, 1, &convert::prost::from_bytes)
+            .await?;
+        assert_eq!(snapshot.map(|snapshot| snapshot.state), Some(42));
+
+        let snapshot = snapshot_store
+            .load_at::<i32, _, _>(id, 41.try_into()?, 1, &convert::prost::from_bytes)
+            .await?;
+        assert_eq!(snapshot.map(|snapshot| snapshot.state), Some(21));
+
+        let snapshot = snapshot_store
+            .load_at::<i32, _, _>(id, 20.try_into()?, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        let snapshot = snapshot_store
+            .load_at::<i32, _, _>(id, 42.try_into()?, 2, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        Ok(())
+    }
+}
@@ -1,35 +1,60 @@
 //! An [EvtLog] implementation based on [NATS](https://nats.io/).
 
-use crate::Error;
+use crate::{connect, Error};
 use async_nats::{
-    connect,
+    header::HeaderMap,
     jetstream::{
         self,
         consumer::{pull, AckPolicy, DeliverPolicy},
-        context::Publish,
+        context::{Publish, PublishError, PublishErrorKind},
+        kv::Store,
         stream::{LastRawMessageErrorKind, Stream as JetstreamStream},
         Context as Jetstream, Message,
     },
 };
 use bytes::Bytes;
-use eventsourced::{EvtLog, SeqNo};
+use chrono::{DateTime, TimeZone, Utc};
+use eventsourced::{
+    verify_seq_nos, Clock, EvtEnvelope, EvtLog, GlobalSeqNo, RetryPolicy, SeqNo, SystemClock,
+    VerifyReport, ZeroSeqNoError,
+};
 use futures::{future::ready, Stream, StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
 use std::{
+    convert::Infallible,
     error::Error as StdError,
-    fmt::{self, Debug, Formatter},
+    fmt::{self, Debug, Display, Formatter},
+    str::FromStr,
+    sync::Arc,
     time::Duration,
 };
 use tracing::debug;
 use uuid::Uuid;
 
+const ENTITY_TYPE: &str = "EventSourced-Entity-Type";
+
 const TAG: &str = "EventSourced-Tag";
 
+const TIMESTAMP: &str = "EventSourced-Timestamp";
+
+const VERSION: &str = "EventSourced-Version";
+
+/// Token for the `{entity_type}` placeholder of a [SubjectTemplate] for entities without a type.
+const NO_ENTITY_TYPE: &str = "_";
+
 /// An [EvtLog] implementation based on [NATS](https://nats.io/).
+///
+/// NATS does not support transactions, hence [persist_batch](EvtLog::persist_batch) persists the
+/// events one after the other and is not atomic. Entity IDs marked as deleted are stored in the
+/// key-value bucket `<evt_stream_name>_deleted`.
 #[derive(Clone)]
 pub struct NatsEvtLog {
     evt_stream_name: String,
+    deleted_bucket: String,
+    subject_template: SubjectTemplate,
+    consumer_config: ConsumerConfig,
     jetstream: Jetstream,
+    clock: Arc<dyn Clock>,
 }
 
 impl NatsEvtLog {
@@ -38,12 +63,7 @@ impl NatsEvtLog {
         debug!(?config, "creating NatsEvtLog");
 
         let server_addr = config.server_addr;
-        let client = connect(&server_addr).await.map_err(|error| {
-            Error::Nats(
-                format!("cannot connect to NATS server at {server_addr})"),
-                error.into(),
-            )
-        })?;
+        let client = connect(&server_addr, config.retry_policy).await?;
         let jetstream = jetstream::new(client);
 
         // Setup stream.
@@ -51,7 +71,11 @@ impl NatsEvtLog {
             jetstream
                 .create_stream(jetstream::stream::Config {
                     name: config.evt_stream_name.clone(),
-                    subjects: vec![format!("{}.*", config.evt_stream_name)],
+                    subjects: vec![config.subject_template.subject(
+                        &config.evt_stream_name,
+                        "*",
+                        "*",
+                    )],
                     ..Default::default()
                 })
                 .await
@@ -63,19 +87,66 @@ impl NatsEvtLog {
                 })?;
         }
 
+        // Setup deleted bucket.
+        let deleted_bucket = format!("{}_deleted", config.evt_stream_name);
+        if config.setup {
+            jetstream
+                .create_key_value(jetstream::kv::Config {
+                    bucket: deleted_bucket.clone(),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|error| {
+                    Error::Nats("cannot create NATS KV bucket".into(), error.into())
+                })?;
+        }
+
         Ok(Self {
+            clock: Arc::new(SystemClock),
             evt_stream_name: config.evt_stream_name,
+            deleted_bucket,
+            subject_template: config.subject_template,
+            consumer_config: config.consumer_config,
             jetstream,
         })
     }
 
-    async fn evts<E, F, FromBytes, FromBytesError>(
+    /// Use the given [Clock] instead of the [SystemClock] to timestamp persisted events.
+    pub fn with_clock(self, clock: impl Clock) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    async fn deleted_bucket(&self) -> Result<Store, Error> {
+        self.jetstream
+            .get_key_value(&self.deleted_bucket)
+            .await
+            .map_err(|error| Error::Nats("cannot get NATS KV bucket".into(), error.into()))
+    }
+
+    /// The subject for the events of the given entity ID, of whatever type.
+    fn id_subject(&self, id: Uuid) -> String {
+        self.subject_template
+            .subject(&self.evt_stream_name, "*", &id.to_string())
+    }
+
+    /// The subject for the events of all entities of the given type or of all types for `"*"`.
+    fn type_subject(&self, entity_type: &str) -> String {
+        self.subject_template
+            .subject(&self.evt_stream_name, entity_type, "*")
+    }
+
+    async fn evts_by_subject<E, F, FromBytes, FromBytesError>(
         &self,
         subject: String,
         from: SeqNo,
+        consumer_config: pull::Config,
         filter: F,
         from_bytes: FromBytes,
-    ) -> Result<impl Stream<Item = Result<(SeqNo, E), Error>> + Send, Error>
+        current: bool,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send, Error>
     where
         E: Send,
         F: Fn(&Message) -> bool + Send,
@@ -85,8 +156,36 @@ impl NatsEvtLog {
         let msgs = msgs(
             &self.jetstream,
             &self.evt_stream_name,
-            subject,
-            from_seq_no_policy(from),
+            vec![subject],
+            from_seq_no_policy(from.as_u64()),
+            consumer_config,
+            current,
+        )
+        .await?;
+
+        Ok(evts(msgs, filter, from_bytes).await)
+    }
+
+    async fn global_evts<E, F, FromBytes, FromBytesError>(
+        &self,
+        subjects: Vec<String>,
+        from: GlobalSeqNo,
+        filter: F,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send, Error>
+    where
+        E: Send,
+        F: Fn(&Message) -> bool + Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let msgs = msgs(
+            &self.jetstream,
+            &self.evt_stream_name,
+            subjects,
+            from_seq_no_policy(from.as_u64()),
+            ephemeral_consumer_config(),
+            false,
         )
         .await?;
 
@@ -98,6 +197,7 @@ impl Debug for NatsEvtLog {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("NatsEvtLog")
             .field("stream_name", &self.evt_stream_name)
+            .field("subject_template", &self.subject_template)
             .finish()
     }
 }
@@ -106,37 +206,145 @@ impl EvtLog for NatsEvtLog {
     type Error = Error;
 
     async fn persist<E, ToBytes, ToBytesError>(
-        &mut self,
+        &self,
         evt: &E,
-        tag: Option<&str>,
+        version: u32,
+        tags: &[String],
         id: Uuid,
+        entity_type: Option<&str>,
         last_seq_no: Option<SeqNo>,
         to_bytes: &ToBytes,
     ) -> Result<SeqNo, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        self.persist_returning_global_seq_no(
+            evt,
+            version,
+            tags,
+            id,
+            entity_type,
+            last_seq_no,
+            to_bytes,
+        )
+        .await
+        .map(|(seq_no, _)| seq_no)
+    }
+
+    /// As the sequence numbers are the stream sequences, these are the global sequence numbers.
+    async fn persist_returning_global_seq_no<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Uuid,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> Result<(SeqNo, Option<GlobalSeqNo>), Self::Error>
     where
         E: Sync,
         ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
         ToBytesError: StdError + Send + Sync + 'static,
     {
         let bytes = to_bytes(evt).map_err(|error| Error::IntoBytes(error.into()))?;
-        let publish = Publish::build().payload(bytes);
-        let publish = tag.into_iter().fold(publish, |p, tag| p.header(TAG, tag));
+        let mut headers = HeaderMap::new();
+        headers.insert(TIMESTAMP, self.clock.now().to_rfc3339().as_str());
+        headers.insert(VERSION, version.to_string().as_str());
+        if let Some(entity_type) = entity_type {
+            headers.insert(ENTITY_TYPE, entity_type);
+        }
+        let headers = tags.iter().fold(headers, |mut headers, tag| {
+            headers.append(TAG, tag.as_str());
+            headers
+        });
+        let publish = Publish::build().payload(bytes).headers(headers);
         let publish = last_seq_no.into_iter().fold(publish, |p, last_seq_no| {
             p.expected_last_subject_sequence(last_seq_no.as_u64())
         });
 
-        let subject = format!("{}.{id}", self.evt_stream_name);
-        self.jetstream
+        let entity_type = entity_type.unwrap_or(NO_ENTITY_TYPE);
+        if self.subject_template.has_entity_type() && !is_valid_token(entity_type) {
+            return Err(Error::InvalidEntityType(entity_type.to_string()));
+        }
+        let subject =
+            self.subject_template
+                .subject(&self.evt_stream_name, entity_type, &id.to_string());
+        let ack = self
+            .jetstream
             .send_publish(subject, publish)
             .await
             .map_err(|error| Error::Nats("cannot publish event".into(), error.into()))?
+            .await;
+        match ack {
+            Ok(ack) => {
+                let seq_no = ack.sequence.try_into().map_err(Error::InvalidSeqNo)?;
+                let global_seq_no = ack.sequence.try_into().map_err(Error::InvalidSeqNo)?;
+                Ok((seq_no, Some(global_seq_no)))
+            }
+
+            Err(error) if error.kind() == PublishErrorKind::WrongLastSequence => {
+                let actual = self.last_seq_no(id).await?;
+                Err(Error::SeqNoConflict {
+                    expected: last_seq_no,
+                    actual,
+                })
+            }
+
+            Err(error) => Err(Error::Nats(
+                "cannot get ACK for published event".into(),
+                error.into(),
+            )),
+        }
+    }
+
+    async fn delete_to(&self, id: Uuid, to_seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %to_seq_no, "deleting events");
+
+        let Some(last_seq_no) = self.last_seq_no(id).await? else {
+            return Ok(());
+        };
+
+        // Purging deletes the messages before the given sequence, hence the last event is kept.
+        let subject = self.id_subject(id);
+        let sequence = to_seq_no.succ().min(last_seq_no).as_u64();
+        stream(&self.jetstream, &self.evt_stream_name)
+            .await?
+            .purge()
+            .filter(subject)
+            .sequence(sequence)
             .await
-            .map_err(|error| Error::Nats("cannot get ACK for published event".into(), error.into()))
-            .and_then(|ack| ack.sequence.try_into().map_err(Error::InvalidSeqNo))
+            .map_err(|error| Error::Nats("cannot purge events".into(), error.into()))
+            .map(|_| ())
+    }
+
+    async fn mark_deleted(&self, id: Uuid) -> Result<(), Self::Error> {
+        debug!(%id, "marking entity as deleted");
+
+        let timestamp = self.clock.now().to_rfc3339();
+        self.deleted_bucket()
+            .await?
+            .put(id.to_string(), timestamp.into())
+            .await
+            .map_err(|error| Error::Nats("cannot mark entity as deleted".into(), error.into()))
+            .map(|_| ())
+    }
+
+    async fn is_deleted(&self, id: Uuid) -> Result<bool, Self::Error> {
+        debug!(%id, "checking whether entity is marked as deleted");
+
+        self.deleted_bucket()
+            .await?
+            .get(id.to_string())
+            .await
+            .map_err(|error| Error::Nats("cannot get deleted marker".into(), error.into()))
+            .map(|marker| marker.is_some())
     }
 
     async fn last_seq_no(&self, id: Uuid) -> Result<Option<SeqNo>, Self::Error> {
-        let subject = format!("{}.{id}", self.evt_stream_name);
+        let subject = self.id_subject(id);
         stream(&self.jetstream, &self.evt_stream_name)
             .await?
             .get_last_raw_message_by_subject(&subject)
@@ -165,15 +373,90 @@ impl EvtLog for NatsEvtLog {
         id: Uuid,
         from: SeqNo,
         from_bytes: FromBytes,
-    ) -> Result<impl Stream<Item = Result<(SeqNo, E), Self::Error>> + Send, Self::Error>
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
     where
         E: Send,
         FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
         FromBytesError: StdError + Send + Sync + 'static,
     {
         debug!(%id, %from, "building events by ID stream");
-        let subject = format!("{}.{id}", self.evt_stream_name);
-        self.evts(subject, from, |_| true, from_bytes).await
+        let subject = self.id_subject(id);
+        let consumer_config = self.consumer_config.pull_config(id);
+        self.evts_by_subject(subject, from, consumer_config, |_| true, from_bytes, false)
+            .await
+    }
+
+    async fn evts_by_id_from<E, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        from: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %from, "building current events by ID stream");
+        let subject = self.id_subject(id);
+        let consumer_config = self.consumer_config.pull_config(id);
+        self.evts_by_subject(subject, from, consumer_config, |_| true, from_bytes, true)
+            .await
+    }
+
+    async fn evts_by_ids<E, FromBytes, FromBytesError>(
+        &self,
+        ids: Vec<Uuid>,
+        from: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(?ids, %from, "building events by IDs stream");
+
+        let subjects = ids.iter().map(|id| self.id_subject(*id)).collect();
+        self.global_evts(subjects, from, |_| true, from_bytes).await
+    }
+
+    async fn evts<E, FromBytes, FromBytesError>(
+        &self,
+        from: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%from, "building events stream");
+        let subjects = vec![self.type_subject("*")];
+        self.global_evts(subjects, from, |_| true, from_bytes).await
+    }
+
+    async fn evts_by_type<E, FromBytes, FromBytesError>(
+        &self,
+        entity_type: String,
+        from: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(entity_type, %from, "building events by type stream");
+        // With an `{entity_type}` placeholder only the matching subjects are consumed.
+        let subjects = vec![self.type_subject(&entity_type)];
+        self.global_evts(
+            subjects,
+            from,
+            move |msg| self::entity_type(msg).as_deref() == Some(entity_type.as_str()),
+            from_bytes,
+        )
+        .await
     }
 
     async fn evts_by_tag<E, FromBytes, FromBytesError>(
@@ -181,16 +464,55 @@ impl EvtLog for NatsEvtLog {
         tag: String,
         from: SeqNo,
         from_bytes: FromBytes,
-    ) -> Result<impl Stream<Item = Result<(SeqNo, E), Self::Error>> + Send, Self::Error>
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
     where
         E: Send,
         FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
         FromBytesError: StdError + Send + Sync + 'static,
     {
         debug!(tag, %from, "building events by tag stream");
-        let subject = format!("{}.*", self.evt_stream_name);
-        self.evts(subject, from, move |msg| has_tag(msg, &tag), from_bytes)
+        let subject = self.type_subject("*");
+        self.evts_by_subject(
+            subject,
+            from,
+            ephemeral_consumer_config(),
+            move |msg| has_tag(msg, &tag),
+            from_bytes,
+            false,
+        )
+        .await
+    }
+
+    async fn verify(&self, id: Uuid) -> Result<VerifyReport, Self::Error> {
+        debug!(%id, "verifying events");
+
+        // The sequence numbers are the stream sequence numbers shared by all entities, hence they
+        // are strictly increasing, but not contiguous per entity.
+        let seq_nos = self
+            .evts_by_id_from::<Bytes, _, _>(id, SeqNo::MIN, Ok::<_, Infallible>)
+            .await?
+            .map_ok(|evt| evt.seq_no);
+        verify_seq_nos(seq_nos, false).await
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        self.jetstream
+            .query_account()
             .await
+            .map_err(|error| Error::Nats("cannot query NATS account info".into(), error.into()))
+            .map(|_| ())
+    }
+
+    fn is_retryable(error: &Self::Error) -> bool {
+        // A timed out ACK is not retried, as the event might have been persisted nonetheless and
+        // the first event of an entity is published without an expected last sequence.
+        match error {
+            Error::Nats(_, error) => error
+                .downcast_ref::<PublishError>()
+                .is_some_and(|error| error.kind() == PublishErrorKind::BrokenPipe),
+
+            _ => false,
+        }
     }
 }
 
@@ -203,9 +525,18 @@ pub struct Config {
     #[serde(default = "evt_stream_name_default")]
     evt_stream_name: String,
 
+    #[serde(default)]
+    subject_template: SubjectTemplate,
+
     #[serde(default = "tag_stream_name_default")]
     tag_stream_name: String,
 
+    #[serde(default)]
+    consumer_config: ConsumerConfig,
+
+    #[serde(default)]
+    retry_policy: RetryPolicy,
+
     #[serde(default)]
     setup: bool,
 }
@@ -235,6 +566,31 @@ impl Config {
         }
     }
 
+    /// Change the `subject_template`.
+    pub fn with_subject_template(self, subject_template: SubjectTemplate) -> Self {
+        Self {
+            subject_template,
+            ..self
+        }
+    }
+
+    /// Change the `consumer_config`.
+    pub fn with_consumer_config(self, consumer_config: ConsumerConfig) -> Self {
+        Self {
+            consumer_config,
+            ..self
+        }
+    }
+
+    /// Change the `retry_policy` for connecting to the NATS server, e.g. to cope with it not being
+    /// up yet on startup. By default there are no retries.
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy,
+            ..self
+        }
+    }
+
     /// Change the `setup` flag.
     pub fn with_setup(self, setup: bool) -> Self {
         Self { setup, ..self }
@@ -247,17 +603,216 @@ impl Default for Config {
         Self {
             server_addr: "localhost:4222".into(),
             evt_stream_name: evt_stream_name_default(),
+            subject_template: SubjectTemplate::default(),
             tag_stream_name: tag_stream_name_default(),
+            consumer_config: ConsumerConfig::default(),
+            retry_policy: RetryPolicy::default(),
             setup: false,
         }
     }
 }
 
+/// Configuration for the JetStream consumers created by [evts_by_id](EvtLog::evts_by_id), e.g. when
+/// an entity is recovered. By default an ephemeral consumer without acknowledgements is used,
+/// which is the preferable choice for replaying large streams.
+///
+/// Acknowledging messages is only safe if the event stream uses the `Limits` retention policy,
+/// which is the JetStream default and used if `setup` is enabled: with the `Interest` or
+/// `WorkQueue` retention policy acknowledged messages are removed from the stream, i.e. events
+/// would be lost. Also notice that limits like `max_age` or `max_msgs` of the event stream may
+/// remove events during a replay.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsumerConfig {
+    #[serde(default)]
+    durable_name_prefix: Option<String>,
+
+    #[serde(default = "ack_policy_default")]
+    ack_policy: AckPolicy,
+
+    #[serde(default = "max_deliver_default")]
+    max_deliver: i64,
+}
+
+impl ConsumerConfig {
+    /// Change the `durable_name_prefix`. If set, durable consumers named `<prefix>-<id>` are used
+    /// instead of ephemeral ones. Durable consumers outlive the event streams and must be removed
+    /// via NATS, else creating one for a different start sequence number fails.
+    pub fn with_durable_name_prefix<T>(self, durable_name_prefix: T) -> Self
+    where
+        T: ToString,
+    {
+        let durable_name_prefix = Some(durable_name_prefix.to_string());
+        Self {
+            durable_name_prefix,
+            ..self
+        }
+    }
+
+    /// Change the `ack_policy`. Unless `AckPolicy::None`, each message is acknowledged once
+    /// received.
+    pub fn with_ack_policy(self, ack_policy: AckPolicy) -> Self {
+        Self { ack_policy, ..self }
+    }
+
+    /// Change the `max_deliver`, i.e. the maximum number of delivery attempts; `-1` means
+    /// unlimited.
+    pub fn with_max_deliver(self, max_deliver: i64) -> Self {
+        Self {
+            max_deliver,
+            ..self
+        }
+    }
+
+    fn pull_config(&self, id: Uuid) -> pull::Config {
+        pull::Config {
+            durable_name: self
+                .durable_name_prefix
+                .as_ref()
+                .map(|prefix| format!("{prefix}-{id}")),
+            ack_policy: self.ack_policy,
+            max_deliver: self.max_deliver,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for ConsumerConfig {
+    /// The default values used are: ephemeral consumers, `AckPolicy::None` and unlimited delivery
+    /// attempts.
+    fn default() -> Self {
+        Self {
+            durable_name_prefix: None,
+            ack_policy: ack_policy_default(),
+            max_deliver: max_deliver_default(),
+        }
+    }
+}
+
+/// Template for the subjects the events are published to, made up of `.`-separated tokens, each
+/// either a literal or one of the placeholders `{stream_name}`, `{entity_type}` and `{id}`, the
+/// latter being required as last token. Defaults to `{stream_name}.{id}`. The subjects of the
+/// event stream created via `setup` are derived from it by replacing the placeholders with `*`;
+/// hence multiple event logs can coexist on one JetStream account if their templates differ in a
+/// literal.
+///
+/// With an `{entity_type}` placeholder, e.g. `evts.{entity_type}.{id}`, events of different
+/// entity types can be routed natively via subject wildcards, e.g. `evts.account.*`, and
+/// [evts_by_type](EvtLog::evts_by_type) only consumes the matching subjects; events of entities
+/// without a type, see [EventSourced::ENTITY_TYPE](eventsourced::EventSourced::ENTITY_TYPE), use
+/// `_` and entity types which are not valid subject tokens are rejected when persisting.
+///
+/// Templates are validated when created via [FromStr] or deserialized, e.g. when loading the
+/// configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SubjectTemplate(Vec<Token>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    StreamName,
+    EntityType,
+    Id,
+}
+
+impl SubjectTemplate {
+    fn subject(&self, stream_name: &str, entity_type: &str, id: &str) -> String {
+        self.0
+            .iter()
+            .map(|token| match token {
+                Token::Literal(literal) => literal.as_str(),
+                Token::StreamName => stream_name,
+                Token::EntityType => entity_type,
+                Token::Id => id,
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    fn has_entity_type(&self) -> bool {
+        self.0.contains(&Token::EntityType)
+    }
+}
+
+impl Default for SubjectTemplate {
+    fn default() -> Self {
+        Self(vec![Token::StreamName, Token::Id])
+    }
+}
+
+impl FromStr for SubjectTemplate {
+    type Err = Error;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |reason: &str| Error::InvalidSubjectTemplate(template.to_string(), reason.into());
+
+        let tokens = template
+            .split('.')
+            .map(|token| match token {
+                "{stream_name}" => Ok(Token::StreamName),
+                "{entity_type}" => Ok(Token::EntityType),
+                "{id}" => Ok(Token::Id),
+                token if is_valid_token(token) && !token.contains(['{', '}']) => {
+                    Ok(Token::Literal(token.to_string()))
+                }
+                _ => Err(invalid("invalid token")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if tokens.last() != Some(&Token::Id) {
+            return Err(invalid("last token must be {id}"));
+        }
+        if tokens.iter().filter(|token| **token == Token::Id).count() > 1 {
+            return Err(invalid("{id} must occur only once"));
+        }
+        if tokens
+            .iter()
+            .filter(|token| **token == Token::EntityType)
+            .count()
+            > 1
+        {
+            return Err(invalid("{entity_type} must occur at most once"));
+        }
+
+        Ok(Self(tokens))
+    }
+}
+
+impl TryFrom<String> for SubjectTemplate {
+    type Error = Error;
+
+    fn try_from(template: String) -> Result<Self, Self::Error> {
+        template.parse()
+    }
+}
+
+impl From<SubjectTemplate> for String {
+    fn from(template: SubjectTemplate) -> Self {
+        template.to_string()
+    }
+}
+
+impl Display for SubjectTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.subject("{stream_name}", "{entity_type}", "{id}"))
+    }
+}
+
+/// Whether the given value is a valid subject token, i.e. non-empty and without wildcards,
+/// separators or whitespace.
+fn is_valid_token(token: &str) -> bool {
+    !token.is_empty()
+        && !token
+            .chars()
+            .any(|c| c == '.' || c == '*' || c == '>' || c.is_whitespace())
+}
+
 async fn evts<E, F, FromBytes, FromBytesError>(
     msgs: impl Stream<Item = Result<Message, Error>> + Send,
     filter: F,
     from_bytes: FromBytes,
-) -> impl Stream<Item = Result<(SeqNo, E), Error>> + Send
+) -> impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send
 where
     E: Send,
     F: Fn(&Message) -> bool + Send,
@@ -266,14 +821,7 @@ where
 {
     msgs.filter_map(move |msg| {
         let evt = match msg {
-            Ok(msg) if filter(&msg) => {
-                let evt = seq_no(&msg).and_then(|seq_no| {
-                    from_bytes(msg.message.payload)
-                        .map_err(|error| Error::FromBytes(error.into()))
-                        .map(|evt| (seq_no, evt))
-                });
-                Some(evt)
-            }
+            Ok(msg) if filter(&msg) => Some(evt_envelope(msg, from_bytes)),
 
             Ok(_) => None,
 
@@ -286,19 +834,39 @@ where
 async fn msgs(
     jetstream: &Jetstream,
     stream_name: &str,
-    subject: String,
+    mut subjects: Vec<String>,
     deliver_policy: DeliverPolicy,
+    consumer_config: pull::Config,
+    current: bool,
 ) -> Result<impl Stream<Item = Result<Message, Error>> + Send, Error> {
-    stream(jetstream, stream_name)
+    // Use `filter_subject` for a single subject, because `filter_subjects` requires NATS 2.10.
+    let (filter_subject, filter_subjects) = if subjects.len() == 1 {
+        (subjects.remove(0), vec![])
+    } else {
+        (String::new(), subjects)
+    };
+
+    let ack = consumer_config.ack_policy != AckPolicy::None;
+
+    let consumer = stream(jetstream, stream_name)
         .await?
         .create_consumer(pull::Config {
-            filter_subject: subject,
-            ack_policy: AckPolicy::None, // Important!
+            filter_subject,
+            filter_subjects,
             deliver_policy,
-            ..Default::default()
+            ..consumer_config
         })
         .await
-        .map_err(|error| Error::Nats("cannot create NATS consumer".into(), error.into()))?
+        .map_err(|error| Error::Nats("cannot create NATS consumer".into(), error.into()))?;
+
+    // If only the current messages are requested, stop after the ones pending at creation.
+    let n = if current {
+        consumer.cached_info().num_pending as usize
+    } else {
+        usize::MAX
+    };
+
+    consumer
         .stream()
         .heartbeat(Duration::ZERO) // Important! Even if I cannot remember why :-(
         .messages()
@@ -309,16 +877,33 @@ async fn msgs(
                 error.into(),
             )
         })
-        .map(|stream| {
-            stream.map_err(|error| {
-                Error::Nats(
-                    "cannot get message from NATS message stream".into(),
-                    error.into(),
-                )
-            })
+        .map(move |stream| {
+            stream
+                .map_err(|error| {
+                    Error::Nats(
+                        "cannot get message from NATS message stream".into(),
+                        error.into(),
+                    )
+                })
+                .and_then(move |msg| async move {
+                    if ack {
+                        msg.ack().await.map_err(|error| {
+                            Error::Nats("cannot acknowledge NATS message".into(), error)
+                        })?;
+                    }
+                    Ok(msg)
+                })
+                .take(n)
         })
 }
 
+fn ephemeral_consumer_config() -> pull::Config {
+    pull::Config {
+        ack_policy: AckPolicy::None, // Important!
+        ..Default::default()
+    }
+}
+
 async fn stream(jetstream: &Jetstream, stream_name: &str) -> Result<JetstreamStream, Error> {
     jetstream.get_stream(stream_name).await.map_err(|error| {
         Error::Nats(
@@ -328,23 +913,114 @@ async fn stream(jetstream: &Jetstream, stream_name: &str) -> Result<JetstreamStr
     })
 }
 
-fn from_seq_no_policy(from: SeqNo) -> DeliverPolicy {
-    DeliverPolicy::ByStartSequence {
-        start_sequence: from.as_u64(),
-    }
+fn from_seq_no_policy(start_sequence: u64) -> DeliverPolicy {
+    DeliverPolicy::ByStartSequence { start_sequence }
 }
 
-fn seq_no(msg: &Message) -> Result<SeqNo, Error> {
+fn evt_envelope<E, FromBytes, FromBytesError>(
+    msg: Message,
+    from_bytes: FromBytes,
+) -> Result<EvtEnvelope<E>, Error>
+where
+    FromBytes: Fn(Bytes) -> Result<E, FromBytesError>,
+    FromBytesError: StdError + Send + Sync + 'static,
+{
+    // The NATS stream sequence is used as sequence number as well as global sequence number.
+    let id = id(&msg)?;
+    let entity_type = entity_type(&msg);
+    let global_seq_no = seq_no(&msg)?;
+    let seq_no = seq_no(&msg)?;
+    let version = version(&msg)?;
+    let timestamp = timestamp(&msg)?;
+    let tags = tags(&msg);
+    from_bytes(msg.message.payload)
+        .map_err(|error| Error::FromBytes(error.into()))
+        .map(|evt| EvtEnvelope {
+            id,
+            entity_type,
+            seq_no,
+            global_seq_no,
+            version,
+            timestamp,
+            tags,
+            evt,
+        })
+}
+
+fn seq_no<T>(msg: &Message) -> Result<T, Error>
+where
+    T: TryFrom<u64, Error = ZeroSeqNoError>,
+{
     msg.info()
         .map_err(|error| Error::Nats("cannot get message info".into(), error))
         .and_then(|info| info.stream_sequence.try_into().map_err(Error::InvalidSeqNo))
 }
 
+fn version(msg: &Message) -> Result<u32, Error> {
+    match msg
+        .headers
+        .as_ref()
+        .and_then(|headers| headers.get(VERSION))
+    {
+        Some(version) => version
+            .as_str()
+            .parse()
+            .map_err(|_| Error::InvalidVersion(version.to_string())),
+
+        // Events persisted without version header.
+        None => Ok(1),
+    }
+}
+
+fn timestamp(msg: &Message) -> Result<DateTime<Utc>, Error> {
+    match msg
+        .headers
+        .as_ref()
+        .and_then(|headers| headers.get(TIMESTAMP))
+    {
+        Some(timestamp) => DateTime::parse_from_rfc3339(timestamp.as_str())
+            .map(|timestamp| timestamp.with_timezone(&Utc))
+            .map_err(Error::InvalidTimestamp),
+
+        // Events persisted without timestamp header: use the time they were published.
+        None => msg
+            .info()
+            .map_err(|error| Error::Nats("cannot get message info".into(), error))
+            .map(|info| Utc.timestamp_nanos(info.published.unix_timestamp_nanos() as i64)),
+    }
+}
+
+fn id(msg: &Message) -> Result<Uuid, Error> {
+    let subject = msg.subject.as_str();
+    subject
+        .rsplit_once('.')
+        .and_then(|(_, id)| id.parse().ok())
+        .ok_or_else(|| Error::InvalidSubject(subject.to_string()))
+}
+
+fn entity_type(msg: &Message) -> Option<String> {
+    msg.headers
+        .as_ref()
+        .and_then(|headers| headers.get(ENTITY_TYPE))
+        .map(|value| value.as_str().to_string())
+}
+
+fn tags(msg: &Message) -> Vec<String> {
+    msg.headers
+        .as_ref()
+        .map(|headers| {
+            headers
+                .get_all(TAG)
+                .map(|value| value.as_str().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 fn has_tag(msg: &Message, tag: &str) -> bool {
     msg.headers
         .as_ref()
-        .and_then(|headers| headers.get(TAG))
-        .map(|value| value.as_str() == tag)
+        .map(|headers| headers.get_all(TAG).any(|value| value.as_str() == tag))
         .unwrap_or_default()
 }
 
@@ -356,6 +1032,14 @@ fn tag_stream_name_default() -> String {
     "tags".to_string()
 }
 
+const fn ack_policy_default() -> AckPolicy {
+    AckPolicy::None
+}
+
+const fn max_deliver_default() -> i64 {
+    -1
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -366,6 +1050,49 @@ mod tests {
     use testcontainers::{clients::Cli, core::WaitFor};
     use testcontainers_modules::testcontainers::GenericImage;
 
+    #[test]
+    fn test_subject_template() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let template = SubjectTemplate::default();
+        assert_eq!(template.to_string(), "{stream_name}.{id}");
+        assert_eq!(template.subject("evts", "*", "42"), "evts.42");
+
+        let template = "events.{entity_type}.{id}".parse::<SubjectTemplate>()?;
+        assert!(template.has_entity_type());
+        assert_eq!(
+            template.subject("evts", "counter", "42"),
+            "events.counter.42"
+        );
+        assert_eq!(template.subject("evts", "*", "*"), "events.*.*");
+
+        let template = serde_json::from_str::<SubjectTemplate>(r#""{stream_name}.x.{id}""#)?;
+        assert_eq!(
+            serde_json::to_string(&template)?,
+            r#""{stream_name}.x.{id}""#
+        );
+
+        for invalid in [
+            "",
+            "evts",
+            "{id}.evts",
+            "evts.{id}.{id}",
+            "evts.{entity_type}.{entity_type}.{id}",
+            "evts..{id}",
+            "evts.*.{id}",
+            "evts.{foo}.{id}",
+        ] {
+            assert!(
+                matches!(
+                    invalid.parse::<SubjectTemplate>(),
+                    Err(Error::InvalidSubjectTemplate(..))
+                ),
+                "{invalid} should be invalid"
+            );
+        }
+        assert!(serde_json::from_str::<SubjectTemplate>(r#""evts""#).is_err());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_evt_log() -> Result<(), Box<dyn StdError + Send + Sync>> {
         let client = Cli::default();
@@ -377,7 +1104,8 @@ mod tests {
         let config = Config::default()
             .with_server_addr(server_addr)
             .with_setup(true);
-        let mut evt_log = NatsEvtLog::new(config).await?;
+        let evt_log = NatsEvtLog::new(config).await?;
+        evt_log.ping().await?;
 
         let id = Uuid::now_v7();
 
@@ -387,30 +1115,50 @@ mod tests {
         assert_eq!(last_seq_no, None);
 
         let last_seq_no = evt_log
-            .persist(&1, Some("tag"), id, None, &convert::prost::to_bytes)
+            .persist(
+                &1,
+                1,
+                &["tag".to_string()],
+                id,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
             .await?;
         assert!(last_seq_no.as_u64() == 1);
 
         evt_log
-            .persist(&2, None, id, Some(last_seq_no), &convert::prost::to_bytes)
+            .persist(
+                &2,
+                1,
+                &[],
+                id,
+                None,
+                Some(last_seq_no),
+                &convert::prost::to_bytes,
+            )
             .await?;
 
         let result = evt_log
             .persist(
                 &3,
-                Some("tag"),
+                1,
+                &["tag".to_string()],
                 id,
+                None,
                 Some(last_seq_no),
                 &convert::prost::to_bytes,
             )
             .await;
-        assert!(result.is_err());
+        assert!(matches!(result, Err(Error::SeqNoConflict { .. })));
 
         evt_log
             .persist(
                 &3,
-                Some("tag"),
+                1,
+                &["tag".to_string()],
                 id,
+                None,
                 Some(last_seq_no.succ()),
                 &convert::prost::to_bytes,
             )
@@ -419,21 +1167,33 @@ mod tests {
         let last_seq_no = evt_log.last_seq_no(id).await?;
         assert_eq!(last_seq_no, Some(3.try_into()?));
 
+        let report = evt_log.verify(id).await?;
+        assert!(report.is_consistent());
+        assert_eq!(report.evt_count, 3);
+
         let evts = evt_log
             .evts_by_id::<i32, _, _>(id, 2.try_into()?, convert::prost::from_bytes)
             .await?;
         let sum = evts
             .take(2)
-            .try_fold(0i32, |acc, (_, n)| future::ready(Ok(acc + n)))
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
             .await?;
         assert_eq!(sum, 5);
 
+        let current_evts = evt_log
+            .evts_by_id_from::<i32, _, _>(id, 2.try_into()?, convert::prost::from_bytes)
+            .await?
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(current_evts, vec![2, 3]);
+
         let evts_by_tag = evt_log
             .evts_by_tag::<i32, _, _>("tag".to_string(), SeqNo::MIN, convert::prost::from_bytes)
             .await?;
         let sum = evts_by_tag
             .take(2)
-            .try_fold(0i32, |acc, (_, n)| future::ready(Ok(acc + n)))
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
             .await?;
         assert_eq!(sum, 4);
 
@@ -447,14 +1207,16 @@ mod tests {
 
         let last_seq_no = evt_log
             .clone()
-            .persist(&4, None, id, last_seq_no, &convert::prost::to_bytes)
+            .persist(&4, 1, &[], id, None, last_seq_no, &convert::prost::to_bytes)
             .await?;
         evt_log
             .clone()
             .persist(
                 &5,
-                Some("tag"),
+                1,
+                &["tag".to_string()],
                 id,
+                None,
                 Some(last_seq_no),
                 &convert::prost::to_bytes,
             )
@@ -464,16 +1226,89 @@ mod tests {
 
         let sum = evts
             .take(5)
-            .try_fold(0i32, |acc, (_, n)| future::ready(Ok(acc + n)))
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
             .await?;
         assert_eq!(sum, 15);
 
         let sum = evts_by_tag
             .take(3)
-            .try_fold(0i32, |acc, (_, n)| future::ready(Ok(acc + n)))
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
             .await?;
         assert_eq!(sum, 9);
 
+        let id_2 = Uuid::now_v7();
+        evt_log
+            .persist(
+                &6,
+                2,
+                &["tag".to_string(), "other-tag".to_string()],
+                id_2,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let evts_by_tag = evt_log
+            .evts_by_tag::<i32, _, _>(
+                "other-tag".to_string(),
+                SeqNo::MIN,
+                convert::prost::from_bytes,
+            )
+            .await?;
+        let sum = evts_by_tag
+            .take(1)
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
+            .await?;
+        assert_eq!(sum, 6);
+
+        let evts_by_ids = evt_log
+            .evts_by_ids::<i32, _, _>(vec![id, id_2], GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let evts_by_ids = evts_by_ids.take(6).try_collect::<Vec<_>>().await?;
+        assert!(evts_by_ids
+            .windows(2)
+            .all(|evts| evts[0].global_seq_no < evts[1].global_seq_no));
+        assert_eq!(evts_by_ids[4].version, 1);
+        assert_eq!(evts_by_ids[5].version, 2);
+        assert_eq!(
+            evts_by_ids[5].tags,
+            vec!["tag".to_string(), "other-tag".to_string()]
+        );
+        let evts_by_ids = evts_by_ids
+            .into_iter()
+            .map(|evt| (evt.id, evt.evt))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            evts_by_ids,
+            vec![(id, 1), (id, 2), (id, 3), (id, 4), (id, 5), (id_2, 6)]
+        );
+
+        let evts = evt_log
+            .evts::<i32, _, _>(GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let evts = evts
+            .take(6)
+            .map_ok(|evt| (evt.id, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, evts_by_ids);
+
+        evt_log.delete_to(id, 5.try_into()?).await?;
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, Some(5.try_into()?));
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let evts = evts.take(1).try_collect::<Vec<_>>().await?;
+        assert_eq!(evts[0].seq_no, 5.try_into()?);
+
+        assert!(!evt_log.is_deleted(id).await?);
+        evt_log.mark_deleted(id).await?;
+        evt_log.mark_deleted(id).await?;
+        assert!(evt_log.is_deleted(id).await?);
+        assert!(!evt_log.is_deleted(id_2).await?);
+
         Ok(())
     }
 }
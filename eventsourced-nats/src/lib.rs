@@ -4,13 +4,19 @@
 pub mod evt_log;
 mod snapshot_store;
 
-pub use evt_log::{Config as NatsEvtLogConfig, NatsEvtLog};
+pub use evt_log::{
+    Config as NatsEvtLogConfig, ConsumerConfig as NatsConsumerConfig, NatsEvtLog,
+    SubjectTemplate as NatsSubjectTemplate,
+};
 pub use snapshot_store::{Config as NatsSnapshotStoreConfig, NatsSnapshotStore};
 
-use eventsourced::ZeroSeqNoError;
-use prost::{DecodeError, EncodeError};
-use std::error::Error as StdError;
+use async_nats::{Client, ConnectError, ConnectErrorKind};
+use eventsourced::{RetryPolicy, SeqNo, ZeroSeqNoError};
+use prost::DecodeError;
+use std::{error::Error as StdError, fmt::Display, future::Future};
 use thiserror::Error;
+use tokio::time::sleep;
+use tracing::warn;
 
 /// Errors from the [NatsEvtLog] or [NatsSnapshotStore].
 #[derive(Debug, Error)]
@@ -26,20 +32,139 @@ pub enum Error {
     #[error("cannot convert bytes to event")]
     FromBytes(#[source] Box<dyn StdError + Send + Sync + 'static>),
 
-    /// Snapshot cannot be encoded as Protocol Buffers.
-    #[error("cannot encode snapshot as Protocol Buffers")]
-    EncodeSnapshot(#[from] EncodeError),
-
     /// Snapshot cannot be decoded from Protocol Buffers.
     #[error("cannot decode snapshot from Protocol Buffers")]
     DecodeSnapshot(#[from] DecodeError),
 
+    /// Invalid subject, i.e. not ending with an entity ID.
+    #[error("invalid subject {0}")]
+    InvalidSubject(String),
+
+    /// Invalid subject template, see [NatsSubjectTemplate].
+    #[error("invalid subject template {0}: {1}")]
+    InvalidSubjectTemplate(String, String),
+
+    /// Entity type not usable for the `{entity_type}` placeholder of the subject template, i.e.
+    /// not a valid subject token.
+    #[error("invalid entity type {0} for subject")]
+    InvalidEntityType(String),
+
+    /// Invalid version header.
+    #[error("invalid version {0}")]
+    InvalidVersion(String),
+
+    /// Invalid timestamp header.
+    #[error("invalid timestamp")]
+    InvalidTimestamp(#[source] chrono::ParseError),
+
     /// Invalid sequence number.
     #[error("invalid sequence number")]
     InvalidSeqNo(#[source] ZeroSeqNoError),
+
+    /// The given last sequence number does not match the actual one, e.g. because of a concurrent
+    /// writer for the same entity ID.
+    #[error("expected last sequence number {expected:?}, but was {actual:?}")]
+    SeqNoConflict {
+        expected: Option<SeqNo>,
+        actual: Option<SeqNo>,
+    },
+
+    /// Invalid sequence number header of a snapshot.
+    #[error("invalid sequence number header {0}")]
+    InvalidSeqNoHeader(String),
+}
+
+/// Connect to the NATS server at the given address, retrying according to the given
+/// [RetryPolicy] if the error might be transient, e.g. because the server is not up yet, but not if
+/// the address cannot be parsed or authentication fails.
+async fn connect(server_addr: &str, retry_policy: RetryPolicy) -> Result<Client, Error> {
+    retry(
+        retry_policy,
+        || async_nats::connect(server_addr),
+        |error: &ConnectError| {
+            matches!(
+                error.kind(),
+                ConnectErrorKind::Dns | ConnectErrorKind::TimedOut | ConnectErrorKind::Io
+            )
+        },
+    )
+    .await
+    .map_err(|error| {
+        Error::Nats(
+            format!("cannot connect to NATS server at {server_addr}"),
+            error.into(),
+        )
+    })
+}
+
+/// Run the given operation, retrying it according to the given [RetryPolicy] on retryable errors.
+async fn retry<T, E, F, Fut, R>(retry_policy: RetryPolicy, mut f: F, retryable: R) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Display,
+    R: Fn(&E) -> bool,
+{
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Err(error) if retryable(&error) => {
+                let Some(delay) = retry_policy.delay(attempt) else {
+                    return Err(error);
+                };
+                warn!(%error, attempt, ?delay, "retrying NATS operation");
+                sleep(delay).await;
+                attempt += 1;
+            }
+
+            result => return result,
+        }
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
+    use super::*;
+    use eventsourced::ExponentialBackoff;
+    use std::time::Duration;
+
     pub const NATS_VERSION: &str = "2.10.5";
+
+    #[tokio::test]
+    async fn test_retry() {
+        let backoff =
+            ExponentialBackoff::new(Duration::from_millis(1), Duration::from_millis(1), 1);
+        let retry_policy = RetryPolicy::new(2, backoff);
+
+        // Retryable errors are retried until the retries are exhausted.
+        let mut attempts = 0;
+        let result = retry(
+            retry_policy,
+            || {
+                attempts += 1;
+                async { Err::<(), _>("transient") }
+            },
+            |_| true,
+        )
+        .await;
+        assert_eq!(result, Err("transient"));
+        assert_eq!(attempts, 3);
+
+        // Non-retryable errors are not.
+        let mut attempts = 0;
+        let result = retry(
+            retry_policy,
+            || {
+                attempts += 1;
+                async { Err::<(), _>("fatal") }
+            },
+            |_| false,
+        )
+        .await;
+        assert_eq!(result, Err("fatal"));
+        assert_eq!(attempts, 1);
+
+        let result = retry(retry_policy, || async { Ok::<_, &str>(42) }, |_| true).await;
+        assert_eq!(result, Ok(42));
+    }
 }
@@ -1,26 +1,54 @@
 //! A [SnapshotStore] implementation based on [NATS](https://nats.io/).
 
-use crate::Error;
+use crate::{connect, retry, Error};
 use async_nats::{
-    connect,
-    jetstream::{self, kv::Store, Context as Jetstream},
+    header::HeaderMap,
+    jetstream::{
+        self,
+        context::{PublishError, PublishErrorKind},
+        kv::{Operation, Store},
+        stream::{DirectGetError, DirectGetErrorKind},
+        Context as Jetstream,
+    },
+    Message,
 };
-use bytes::{Bytes, BytesMut};
-use eventsourced::{SeqNo, Snapshot, SnapshotStore};
-use prost::Message;
+use bytes::Bytes;
+use eventsourced::{RetryPolicy, SeqNo, Snapshot, SnapshotStore};
+use futures::{future, stream, Stream, StreamExt};
+use prost::Message as _;
 use serde::{Deserialize, Serialize};
 use std::{
     error::Error as StdError,
     fmt::{self, Debug, Formatter},
+    time::Instant,
 };
-use tracing::debug;
+use tracing::{debug, info};
 use uuid::Uuid;
 
-/// A [SnapshotStore] implementation based on [NATS](https://nats.io/).
+const SEQ_NO: &str = "EventSourced-Seq-No";
+
+const STATE_VERSION: &str = "EventSourced-State-Version";
+
+/// Header set by NATS for delete and purge markers of a KV bucket.
+const KV_OPERATION: &str = "KV-Operation";
+
+/// A [SnapshotStore] implementation based on [NATS](https://nats.io/). As a key-value bucket is
+/// used, only the last saved snapshots are kept per entity ID, by default only the very last one,
+/// see [Config::with_history]; former ones can be loaded via
+/// [load_at](SnapshotStore::load_at).
+///
+/// The value of a snapshot is exactly the bytes of its state, its sequence number and state version
+/// are stored in headers. Snapshots saved by former versions, wrapping these in a Protocol Buffers
+/// envelope, can still be loaded, such that no migration is needed: these get replaced by the next
+/// saved snapshot.
+///
+/// Each saved and loaded snapshot is logged at info level along with its ID, sequence number,
+/// length in bytes and the duration of the operation, e.g. to track snapshot sizes over time.
 #[derive(Clone)]
 pub struct NatsSnapshotStore {
     jetstream: Jetstream,
     bucket: String,
+    retry_policy: RetryPolicy,
 }
 
 impl NatsSnapshotStore {
@@ -29,19 +57,15 @@ impl NatsSnapshotStore {
         debug!(?config, "creating NatsSnapshotStore");
 
         let server_addr = config.server_addr;
-        let client = connect(&server_addr).await.map_err(|error| {
-            Error::Nats(
-                format!("cannot connect to NATS server at {server_addr})"),
-                error.into(),
-            )
-        })?;
+        let client = connect(&server_addr, config.retry_policy).await?;
         let jetstream = jetstream::new(client);
 
         // Setup bucket.
         if config.setup {
             let _ = jetstream
                 .create_key_value(jetstream::kv::Config {
-                    bucket: "snapshots".to_string(),
+                    bucket: config.bucket.clone(),
+                    history: config.history,
                     ..Default::default()
                 })
                 .await
@@ -53,6 +77,7 @@ impl NatsSnapshotStore {
         Ok(Self {
             jetstream,
             bucket: config.bucket,
+            retry_policy: config.retry_policy,
         })
     }
 
@@ -76,9 +101,10 @@ impl SnapshotStore for NatsSnapshotStore {
     type Error = Error;
 
     async fn save<S, ToBytes, ToBytesError>(
-        &mut self,
+        &self,
         id: Uuid,
         seq_no: SeqNo,
+        state_version: u32,
         state: S,
         to_bytes: &ToBytes,
     ) -> Result<(), Self::Error>
@@ -87,25 +113,48 @@ impl SnapshotStore for NatsSnapshotStore {
         ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
         ToBytesError: StdError + Send + Sync + 'static,
     {
-        let mut bytes = BytesMut::new();
-        let state = to_bytes(&state).map_err(|error| Error::IntoBytes(Box::new(error)))?;
-        let snapshot = proto::Snapshot {
-            seq_no: seq_no.as_u64(),
-            state,
-        };
-        snapshot.encode(&mut bytes)?;
-
-        self.get_bucket(&self.bucket)
-            .await?
-            .put(id.to_string(), bytes.into())
-            .await
-            .map_err(|error| {
-                Error::Nats(
-                    "cannot store snapshot in NATS KV bucket".into(),
-                    error.into(),
+        let start = Instant::now();
+        let bytes = to_bytes(&state).map_err(|error| Error::IntoBytes(Box::new(error)))?;
+        let bytes_len = bytes.len();
+        let mut headers = HeaderMap::new();
+        headers.insert(SEQ_NO, seq_no.to_string().as_str());
+        headers.insert(STATE_VERSION, state_version.to_string().as_str());
+
+        // Publish to the subject of the key directly, as a KV bucket cannot put headers.
+        let bucket = self.get_bucket(&self.bucket).await?;
+        let subject = format!(
+            "{}{id}",
+            bucket.put_prefix.as_ref().unwrap_or(&bucket.prefix)
+        );
+        retry(
+            self.retry_policy,
+            || async {
+                self.jetstream
+                    .publish_with_headers(subject.clone(), headers.clone(), bytes.clone())
+                    .await?
+                    .await
+            },
+            |error: &PublishError| {
+                matches!(
+                    error.kind(),
+                    PublishErrorKind::TimedOut | PublishErrorKind::BrokenPipe
                 )
-            })?;
-        debug!(%id, %seq_no, "saved snapshot");
+            },
+        )
+        .await
+        .map_err(|error| {
+            Error::Nats(
+                "cannot store snapshot in NATS KV bucket".into(),
+                error.into(),
+            )
+        })?;
+        info!(
+            %id,
+            %seq_no,
+            bytes_len,
+            duration = ?start.elapsed(),
+            "saved snapshot"
+        );
 
         Ok(())
     }
@@ -113,46 +162,270 @@ impl SnapshotStore for NatsSnapshotStore {
     async fn load<S, FromBytes, FromBytesError>(
         &self,
         id: Uuid,
+        state_version: u32,
         from_bytes: FromBytes,
     ) -> Result<Option<Snapshot<S>>, Self::Error>
     where
         FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
         FromBytesError: StdError + Send + Sync + 'static,
     {
-        let snapshot = self
-            .get_bucket(&self.bucket)
-            .await?
-            .get(id.to_string())
-            .await
-            .map_err(|error| {
-                Error::Nats(
+        let start = Instant::now();
+        let bucket = self.get_bucket(&self.bucket).await?;
+        let subject = format!("{}{id}", bucket.prefix);
+        let message = retry(
+            self.retry_policy,
+            || bucket.stream.direct_get_last_for_subject(&subject),
+            |error: &DirectGetError| {
+                matches!(
+                    error.kind(),
+                    DirectGetErrorKind::TimedOut | DirectGetErrorKind::Request
+                )
+            },
+        )
+        .await;
+        let message = match message {
+            Ok(message) => Some(message),
+            Err(error) if error.kind() == DirectGetErrorKind::NotFound => None,
+            Err(error) => {
+                return Err(Error::Nats(
                     "cannot load snapshot from NATS KV bucket".into(),
                     error.into(),
-                )
-            })?
-            .map(|bytes| {
-                proto::Snapshot::decode(bytes)
-                    .map_err(Error::DecodeSnapshot)
-                    .and_then(|proto::Snapshot { seq_no, state }| {
-                        from_bytes(state)
-                            .map_err(|error| Error::FromBytes(Box::new(error)))
-                            .and_then(|state| {
-                                seq_no
-                                    .try_into()
-                                    .map_err(Error::InvalidSeqNo)
-                                    .map(|seq_no| Snapshot::new(seq_no, state))
-                            })
-                    })
+                ))
+            }
+        };
+
+        let snapshot = message
+            .map(|message| raw_snapshot(&message))
+            .transpose()?
+            .flatten()
+            .filter(|snapshot| snapshot.state_version == state_version)
+            .map(|snapshot| {
+                let bytes_len = snapshot.state.len();
+                snapshot
+                    .into_snapshot(&from_bytes)
+                    .map(|snapshot| (snapshot, bytes_len))
             })
             .transpose()?;
 
-        if snapshot.is_some() {
-            debug!(%id, "loaded snapshot");
-        } else {
-            debug!(%id, "no snapshot to load");
+        match snapshot {
+            Some((snapshot, bytes_len)) => {
+                info!(
+                    %id,
+                    seq_no = %snapshot.seq_no,
+                    bytes_len,
+                    duration = ?start.elapsed(),
+                    "loaded snapshot"
+                );
+                Ok(Some(snapshot))
+            }
+
+            None => {
+                debug!(%id, "no snapshot to load");
+                Ok(None)
+            }
+        }
+    }
+
+    async fn load_many<S, FromBytes, FromBytesError>(
+        &self,
+        ids: &[Uuid],
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<(Uuid, Snapshot<S>), Self::Error>> + Send, Self::Error>
+    where
+        S: Send,
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send + Sync,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        // A KV bucket cannot get many keys at once, hence load the snapshots concurrently.
+        let snapshots = future::try_join_all(
+            ids.iter()
+                .map(|&id| self.load(id, state_version, &from_bytes)),
+        )
+        .await?;
+        let snapshots = ids
+            .iter()
+            .zip(snapshots)
+            .filter_map(|(&id, snapshot)| snapshot.map(|snapshot| Ok((id, snapshot))))
+            .collect::<Vec<_>>();
+
+        Ok(stream::iter(snapshots))
+    }
+
+    async fn load_at<S, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        max_seq_no: SeqNo,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> Result<Option<Snapshot<S>>, Self::Error>
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let bucket = self.get_bucket(&self.bucket).await?;
+
+        // The history of a key without any entry would never end.
+        let entry = bucket.entry(id.to_string()).await.map_err(|error| {
+            Error::Nats(
+                "cannot load snapshot from NATS KV bucket".into(),
+                error.into(),
+            )
+        })?;
+        if entry.is_none() {
+            debug!(%id, %max_seq_no, "no snapshot to load");
+            return Ok(None);
+        }
+
+        let mut history = bucket.history(id.to_string()).await.map_err(|error| {
+            Error::Nats(
+                "cannot get snapshot history from NATS KV bucket".into(),
+                error.into(),
+            )
+        })?;
+        // The history is ordered from the oldest to the newest entry. Its entries lack the
+        // headers, hence only their revisions are collected.
+        let mut revisions = vec![];
+        while let Some(entry) = history.next().await {
+            let entry = entry.map_err(|error| {
+                Error::Nats(
+                    "cannot get snapshot history from NATS KV bucket".into(),
+                    error.into(),
+                )
+            })?;
+            if entry.operation == Operation::Put {
+                revisions.push(entry.revision);
+            }
+        }
+
+        for revision in revisions.into_iter().rev() {
+            let message = bucket.stream.direct_get(revision).await.map_err(|error| {
+                Error::Nats(
+                    "cannot load snapshot from NATS KV bucket".into(),
+                    error.into(),
+                )
+            })?;
+            let Some(snapshot) = raw_snapshot(&message)? else {
+                continue;
+            };
+            if snapshot.seq_no <= max_seq_no && snapshot.state_version == state_version {
+                let snapshot = snapshot.into_snapshot(&from_bytes)?;
+                debug!(%id, seq_no = %snapshot.seq_no, "loaded snapshot");
+                return Ok(Some(snapshot));
+            }
+        }
+
+        debug!(%id, %max_seq_no, "no snapshot to load");
+        Ok(None)
+    }
+
+    async fn delete_before(&self, id: Uuid, seq_no: SeqNo) -> Result<(), Self::Error> {
+        let bucket = self.get_bucket(&self.bucket).await?;
+
+        let message = bucket
+            .stream
+            .direct_get_last_for_subject(format!("{}{id}", bucket.prefix))
+            .await;
+        let message = match message {
+            Ok(message) => message,
+            Err(error) if error.kind() == DirectGetErrorKind::NotFound => return Ok(()),
+            Err(error) => {
+                return Err(Error::Nats(
+                    "cannot load snapshot from NATS KV bucket".into(),
+                    error.into(),
+                ))
+            }
+        };
+        let snapshot_seq_no = raw_snapshot(&message)?.map(|snapshot| snapshot.seq_no);
+
+        if snapshot_seq_no.is_some_and(|snapshot_seq_no| snapshot_seq_no < seq_no) {
+            bucket.delete(id.to_string()).await.map_err(|error| {
+                Error::Nats(
+                    "cannot delete snapshot from NATS KV bucket".into(),
+                    error.into(),
+                )
+            })?;
+            debug!(%id, %seq_no, "deleted snapshot");
         }
 
-        Ok(snapshot)
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        self.jetstream
+            .query_account()
+            .await
+            .map_err(|error| Error::Nats("cannot query NATS account info".into(), error.into()))
+            .map(|_| ())
+    }
+}
+
+/// A snapshot as stored in the KV bucket, with its state not yet converted.
+struct RawSnapshot {
+    seq_no: SeqNo,
+    state_version: u32,
+    state: Bytes,
+}
+
+impl RawSnapshot {
+    fn into_snapshot<S, FromBytes, FromBytesError>(
+        self,
+        from_bytes: FromBytes,
+    ) -> Result<Snapshot<S>, Error>
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError>,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        from_bytes(self.state)
+            .map_err(|error| Error::FromBytes(Box::new(error)))
+            .map(|state| Snapshot::new(self.seq_no, state))
+    }
+}
+
+/// Get the snapshot from the given message of the KV bucket or `None` for a delete or purge
+/// marker. The sequence number and state version are taken from the headers and the payload is the
+/// state as is. Snapshots saved before without these headers wrap the state in a Protocol Buffers
+/// envelope; these can still be loaded and are replaced by the next saved snapshot.
+fn raw_snapshot(message: &Message) -> Result<Option<RawSnapshot>, Error> {
+    let headers = message.headers.as_ref();
+    if headers.is_some_and(|headers| headers.get(KV_OPERATION).is_some()) {
+        return Ok(None);
+    }
+
+    match headers.and_then(|headers| headers.get(SEQ_NO)) {
+        Some(seq_no) => {
+            let seq_no = seq_no
+                .as_str()
+                .parse::<u64>()
+                .map_err(|_| Error::InvalidSeqNoHeader(seq_no.to_string()))?
+                .try_into()
+                .map_err(Error::InvalidSeqNo)?;
+            let state_version = match headers.and_then(|headers| headers.get(STATE_VERSION)) {
+                Some(state_version) => state_version
+                    .as_str()
+                    .parse()
+                    .map_err(|_| Error::InvalidVersion(state_version.to_string()))?,
+                None => 1,
+            };
+            Ok(Some(RawSnapshot {
+                seq_no,
+                state_version,
+                state: message.payload.clone(),
+            }))
+        }
+
+        // Snapshots saved before with a Protocol Buffers envelope.
+        None => {
+            let snapshot =
+                proto::Snapshot::decode(message.payload.clone()).map_err(Error::DecodeSnapshot)?;
+            let state_version = snapshot.state_version();
+            let seq_no = snapshot.seq_no.try_into().map_err(Error::InvalidSeqNo)?;
+            Ok(Some(RawSnapshot {
+                seq_no,
+                state_version,
+                state: snapshot.state,
+            }))
+        }
     }
 }
 
@@ -165,6 +438,12 @@ pub struct Config {
     #[serde(default = "bucket_default")]
     bucket: String,
 
+    #[serde(default = "history_default")]
+    history: i64,
+
+    #[serde(default)]
+    retry_policy: RetryPolicy,
+
     #[serde(default)]
     setup: bool,
 }
@@ -191,6 +470,22 @@ impl Config {
         Self { bucket, ..self }
     }
 
+    /// Change the `history`, i.e. the number of snapshots kept per entity ID, which is applied
+    /// when the bucket is set up; NATS allows at most 64.
+    pub fn with_history(self, history: i64) -> Self {
+        Self { history, ..self }
+    }
+
+    /// Change the `retry_policy` for connecting to the NATS server, e.g. to cope with it not being
+    /// up yet on startup, as well as for saving and loading snapshots. By default there are no
+    /// retries.
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy,
+            ..self
+        }
+    }
+
     /// Change the `setup` flag.
     pub fn with_setup(self, setup: bool) -> Self {
         Self { setup, ..self }
@@ -198,11 +493,13 @@ impl Config {
 }
 
 impl Default for Config {
-    /// Use "localhost:4222" for `server_addr` and "snapshots" for `bucket`.
+    /// Use "localhost:4222" for `server_addr`, "snapshots" for `bucket` and 1 for `history`.
     fn default() -> Self {
         Self {
             server_addr: "localhost:4222".to_string(),
             bucket: bucket_default(),
+            history: history_default(),
+            retry_policy: RetryPolicy::default(),
             setup: false,
         }
     }
@@ -212,8 +509,19 @@ fn bucket_default() -> String {
     "snapshots".to_string()
 }
 
+fn history_default() -> i64 {
+    1
+}
+
 mod proto {
     include!(concat!(env!("OUT_DIR"), "/snapshot_store.rs"));
+
+    impl Snapshot {
+        /// The version of the state, treating snapshots saved before it was added like version 1.
+        pub fn state_version(&self) -> u32 {
+            self.state_version.max(1)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -234,13 +542,15 @@ mod tests {
 
         let config = Config::default()
             .with_server_addr(server_addr)
+            .with_history(2)
             .with_setup(true);
-        let mut snapshot_store = NatsSnapshotStore::new(config).await?;
+        let snapshot_store = NatsSnapshotStore::new(config).await?;
+        snapshot_store.ping().await?;
 
         let id = Uuid::now_v7();
 
         let snapshot = snapshot_store
-            .load::<i32, _, _>(id, &convert::prost::from_bytes)
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
             .await?;
         assert!(snapshot.is_none());
 
@@ -248,11 +558,11 @@ mod tests {
         let state = 666;
 
         snapshot_store
-            .save(id, seq_no, state, &convert::prost::to_bytes)
+            .save(id, seq_no, 1, state, &convert::prost::to_bytes)
             .await?;
 
         let snapshot = snapshot_store
-            .load::<i32, _, _>(id, &convert::prost::from_bytes)
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
             .await?;
 
         assert!(snapshot.is_some());
@@ -260,6 +570,58 @@ mod tests {
         assert_eq!(snapshot.seq_no, seq_no);
         assert_eq!(snapshot.state, state);
 
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 2, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        snapshot_store
+            .save(id, seq_no.succ(), 1, 667, &convert::prost::to_bytes)
+            .await?;
+        let snapshot = snapshot_store
+            .load_at::<i32, _, _>(id, seq_no, 1, &convert::prost::from_bytes)
+            .await?;
+        assert_eq!(snapshot.map(|snapshot| snapshot.state), Some(state));
+        let snapshot = snapshot_store
+            .load_at::<i32, _, _>(id, seq_no.succ().succ(), 1, &convert::prost::from_bytes)
+            .await?;
+        assert_eq!(snapshot.map(|snapshot| snapshot.state), Some(667));
+        let snapshot = snapshot_store
+            .load_at::<i32, _, _>(id, 41.try_into()?, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        let seq_no = seq_no.succ();
+        snapshot_store.delete_before(id, seq_no).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_some());
+
+        snapshot_store.delete_before(id, seq_no.succ()).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        // Snapshots wrapped in a Protocol Buffers envelope can still be loaded.
+        let snapshot = proto::Snapshot {
+            seq_no: 42,
+            state: convert::prost::to_bytes(&666)?,
+            state_version: 0,
+        };
+        let bucket = snapshot_store.get_bucket(&snapshot_store.bucket).await?;
+        bucket
+            .put(id.to_string(), snapshot.encode_to_vec().into())
+            .await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert_eq!(
+            snapshot.map(|snapshot| (snapshot.seq_no.as_u64(), snapshot.state)),
+            Some((42, 666))
+        );
+
         Ok(())
     }
 }
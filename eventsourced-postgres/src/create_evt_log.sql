@@ -1,10 +1,45 @@
 CREATE TABLE
-  IF NOT EXISTS evts (
+  IF NOT EXISTS {evts} (
     seq_no bigint,
     id uuid,
     evt bytea,
-    tag text,
+    tags text[],
+    version integer NOT NULL DEFAULT 1,
+    timestamp timestamptz NOT NULL DEFAULT now(),
+    global_seq_no bigserial,
+    entity_type text,
     PRIMARY KEY (seq_no, id)
-  );
+  ){partition_by};
+{partitions}
 
-CREATE INDEX IF NOT EXISTS evts_tag ON evts (tag);
\ No newline at end of file
+ALTER TABLE {evts} ADD COLUMN IF NOT EXISTS tags text[];
+
+ALTER TABLE {evts} ADD COLUMN IF NOT EXISTS version integer NOT NULL DEFAULT 1;
+
+ALTER TABLE {evts} ADD COLUMN IF NOT EXISTS timestamp timestamptz NOT NULL DEFAULT now();
+
+ALTER TABLE {evts} ADD COLUMN IF NOT EXISTS global_seq_no bigserial;
+
+ALTER TABLE {evts} ADD COLUMN IF NOT EXISTS entity_type text;
+
+DO $$
+BEGIN
+  IF EXISTS (
+    SELECT FROM pg_attribute
+    WHERE attrelid = '{evts}'::regclass AND attname = 'tag' AND NOT attisdropped
+  ) THEN
+    UPDATE {evts} SET tags = ARRAY[tag] WHERE tags IS NULL AND tag IS NOT NULL;
+  END IF;
+END $$;
+
+CREATE INDEX IF NOT EXISTS {evts_tags} ON {evts} USING GIN (tags);
+
+CREATE INDEX IF NOT EXISTS {evts_global_seq_no} ON {evts} (global_seq_no);
+
+CREATE INDEX IF NOT EXISTS {evts_entity_type} ON {evts} (entity_type, global_seq_no);
+
+CREATE TABLE
+  IF NOT EXISTS {evts_deleted} (
+    id uuid PRIMARY KEY,
+    timestamp timestamptz NOT NULL DEFAULT now()
+  );
\ No newline at end of file
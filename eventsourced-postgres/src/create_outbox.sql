@@ -0,0 +1,11 @@
+CREATE TABLE
+  IF NOT EXISTS {outbox} (
+    global_seq_no bigint PRIMARY KEY,
+    id uuid NOT NULL,
+    seq_no bigint NOT NULL,
+    entity_type text,
+    version integer NOT NULL,
+    timestamp timestamptz NOT NULL,
+    tags text[] NOT NULL,
+    evt bytea NOT NULL
+  );
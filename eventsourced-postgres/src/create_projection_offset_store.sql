@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS {projection_offsets} (
+  projection_name text PRIMARY KEY,
+  offset_global_seq_no bigint NOT NULL
+);
\ No newline at end of file
@@ -1,6 +1,9 @@
-CREATE TABLE IF NOT EXISTS snapshots (
+CREATE TABLE IF NOT EXISTS {snapshots} (
   id uuid,
   seq_no bigint,
   state bytea,
+  state_version integer NOT NULL DEFAULT 1,
   PRIMARY KEY (id, seq_no)
-);
\ No newline at end of file
+);
+
+ALTER TABLE {snapshots} ADD COLUMN IF NOT EXISTS state_version integer NOT NULL DEFAULT 1;
\ No newline at end of file
@@ -1,20 +1,26 @@
 //! An [EvtLog] implementation based on [PostgreSQL](https://www.postgresql.org/).
 
-use crate::{Cnn, CnnPool, Error};
+use crate::{quote_table_name, statement_timeout_option, Cnn, CnnPool, Error, PoolState};
 use async_stream::stream;
 use bb8_postgres::{bb8::Pool, PostgresConnectionManager};
 use bytes::Bytes;
-use eventsourced::{EvtLog, SeqNo};
+use chrono::{DateTime, Utc};
+use eventsourced::{
+    verify_seq_nos, Clock, EntityEvts, EvtEnvelope, EvtLog, GlobalSeqNo, SeqNo, SystemClock,
+    VerifyReport,
+};
 use futures::{Stream, StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     error::Error as StdError,
     fmt::{self, Debug, Formatter},
-    num::{NonZeroU64, NonZeroUsize},
+    num::{NonZeroU32, NonZeroU64, NonZeroUsize},
+    sync::Arc,
     time::Duration,
 };
 use tokio::time::sleep;
-use tokio_postgres::{types::ToSql, NoTls};
+use tokio_postgres::{error::SqlState, types::ToSql, GenericClient, NoTls, Row};
 use tracing::debug;
 use uuid::Uuid;
 
@@ -22,7 +28,12 @@ use uuid::Uuid;
 #[derive(Clone)]
 pub struct PostgresEvtLog {
     poll_interval: Duration,
+    replay_batch_size: NonZeroUsize,
     cnn_pool: CnnPool<NoTls>,
+    evts_table: String,
+    deleted_table: String,
+    outbox_table: Option<String>,
+    clock: Arc<dyn Clock>,
 }
 
 impl PostgresEvtLog {
@@ -30,16 +41,24 @@ impl PostgresEvtLog {
     pub async fn new(config: Config) -> Result<Self, Error> {
         debug!(?config, "creating PostgresEvtLog");
 
+        let evts_table = quote_table_name(&config.evts_table)?;
+        let deleted_table = quote_table_name(&format!("{}_deleted", config.evts_table))?;
+        let outbox_table = config
+            .outbox_table
+            .as_deref()
+            .map(quote_table_name)
+            .transpose()?;
+
         // Create connection pool.
         let tls = NoTls;
         let cnn_manager = PostgresConnectionManager::new_from_stringlike(config.cnn_config(), tls)
             .map_err(|error| {
-                Error::Postgres("cannot create connection manager".to_string(), error)
+                Error::postgres("cannot create connection manager".to_string(), error)
             })?;
         let cnn_pool = Pool::builder()
             .build(cnn_manager)
             .await
-            .map_err(|error| Error::Postgres("cannot create connection pool".to_string(), error))?;
+            .map_err(|error| Error::postgres("cannot create connection pool".to_string(), error))?;
 
         // Setup tables.
         if config.setup {
@@ -47,29 +66,65 @@ impl PostgresEvtLog {
                 .get()
                 .await
                 .map_err(Error::GetConnection)?
-                .batch_execute(
-                    &include_str!("create_evt_log.sql").replace("evts", &config.evts_table),
-                )
+                .batch_execute(&create_evt_log_sql(&config)?)
                 .await
-                .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))?;
+                .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?;
+
+            if let Some(outbox_table) = &outbox_table {
+                cnn_pool
+                    .get()
+                    .await
+                    .map_err(Error::GetConnection)?
+                    .batch_execute(
+                        &include_str!("create_outbox.sql").replace("{outbox}", outbox_table),
+                    )
+                    .await
+                    .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?;
+            }
         }
 
         Ok(Self {
+            clock: Arc::new(SystemClock),
             poll_interval: config.poll_interval,
+            replay_batch_size: config.replay_batch_size,
             cnn_pool,
+            evts_table,
+            deleted_table,
+            outbox_table,
         })
     }
 
+    /// Use the given [Clock] instead of the [SystemClock] to timestamp persisted events.
+    pub fn with_clock(self, clock: impl Clock) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    /// Get the [PoolState] of the connection pool, i.e. the number of connections and of idle ones,
+    /// e.g. to debug connection exhaustion.
+    pub fn pool_state(&self) -> PoolState {
+        self.cnn_pool.state()
+    }
+
     async fn cnn(&self) -> Result<Cnn<NoTls>, Error> {
         self.cnn_pool.get().await.map_err(Error::GetConnection)
     }
 
+    async fn seq_no_conflict(&self, id: Uuid, expected: Option<SeqNo>) -> Error {
+        match self.last_seq_no(id).await {
+            Ok(actual) => Error::SeqNoConflict { expected, actual },
+            Err(error) => error,
+        }
+    }
+
     async fn next_evts_by_id<E, FromBytes, FromBytesError>(
         &self,
         id: Uuid,
         from_seq_no: SeqNo,
         from_bytes: FromBytes,
-    ) -> Result<impl Stream<Item = Result<(SeqNo, E), Error>> + Send, Error>
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send, Error>
     where
         E: Send,
         FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Send,
@@ -77,29 +132,145 @@ impl PostgresEvtLog {
     {
         debug!(%id, %from_seq_no, "querying events");
 
-        let params: [&(dyn ToSql + Sync); 2] = [&id, &(from_seq_no.as_u64() as i64)];
+        // Keyset pagination bounds the number of events fetched at once.
+        let params: [&(dyn ToSql + Sync); 3] = [
+            &id,
+            &(from_seq_no.as_u64() as i64),
+            &(self.replay_batch_size.get() as i64),
+        ];
         let evts = self
             .cnn()
             .await?
             .query_raw(
-                "SELECT seq_no, evt FROM evts WHERE id = $1 AND seq_no >= $2",
+                &format!(
+                    "SELECT id, seq_no, global_seq_no, version, timestamp, tags, evt, entity_type
+                     FROM {evts}
+                     WHERE id = $1 AND seq_no >= $2
+                     ORDER BY seq_no
+                     LIMIT $3",
+                    evts = self.evts_table
+                ),
                 params,
             )
             .await
-            .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))?
-            .map_err(|error| Error::Postgres("cannot get next row".to_string(), error))
-            .map(move |row| {
-                row.and_then(|row| {
-                    let seq_no = (row.get::<_, i64>(0) as u64)
-                        .try_into()
-                        .map_err(|_| Error::ZeroSeqNo)?;
-                    let bytes = row.get::<_, &[u8]>(1);
-                    let bytes = Bytes::copy_from_slice(bytes);
-                    from_bytes(bytes)
-                        .map_err(|source| Error::FromBytes(Box::new(source)))
-                        .map(|evt| (seq_no, evt))
-                })
-            });
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?
+            .map_err(|error| Error::postgres("cannot get next row".to_string(), error))
+            .map(move |row| row.and_then(|row| evt_envelope(row, &from_bytes)));
+
+        Ok(evts)
+    }
+
+    async fn next_evts_by_global_seq_no<E, FromBytes, FromBytesError>(
+        &self,
+        filter: &Filter,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send, Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(?filter, %from_global_seq_no, "querying events");
+
+        let from_global_seq_no = from_global_seq_no.as_u64() as i64;
+        let (condition, params): (_, Vec<&(dyn ToSql + Sync)>) = match filter {
+            Filter::All => ("global_seq_no >= $1", vec![&from_global_seq_no]),
+            Filter::Ids(ids) => (
+                "id = ANY($2) AND global_seq_no >= $1",
+                vec![&from_global_seq_no, ids],
+            ),
+            Filter::EntityType(entity_type) => (
+                "entity_type = $2 AND global_seq_no >= $1",
+                vec![&from_global_seq_no, entity_type],
+            ),
+        };
+        let query = format!(
+            "SELECT id, seq_no, global_seq_no, version, timestamp, tags, evt, entity_type
+             FROM {evts}
+             WHERE {condition}
+             ORDER BY global_seq_no",
+            evts = self.evts_table
+        );
+        let evts = self
+            .cnn()
+            .await?
+            .query_raw(&query, params)
+            .await
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?
+            .map_err(|error| Error::postgres("cannot get next row".to_string(), error))
+            .map(move |row| row.and_then(|row| evt_envelope(row, &from_bytes)));
+
+        Ok(evts)
+    }
+
+    async fn evts_by_global_seq_no<E, FromBytes, FromBytesError>(
+        &self,
+        filter: Filter,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send, Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let cnn = self.cnn().await?;
+        let query = |condition| {
+            format!(
+                "SELECT COALESCE(MAX(global_seq_no), 1) FROM {evts}{condition}",
+                evts = self.evts_table
+            )
+        };
+        let row = match &filter {
+            Filter::All => cnn.query_one(&query(""), &[]).await,
+            Filter::Ids(ids) => cnn.query_one(&query(" WHERE id = ANY($1)"), &[ids]).await,
+            Filter::EntityType(entity_type) => {
+                cnn.query_one(&query(" WHERE entity_type = $1"), &[entity_type])
+                    .await
+            }
+        };
+        let last_global_seq_no = row
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))
+            .and_then(|row| {
+                (row.get::<_, i64>(0) as u64)
+                    .try_into()
+                    .map_err(|_| Error::ZeroSeqNo)
+            })?;
+
+        // Let the stream own a clone, which is cheap, to not capture the lifetime of `self`.
+        let this = self.clone();
+        let mut current_from_global_seq_no = from_global_seq_no;
+        let evts = stream! {
+            'outer: loop {
+                let evts = this
+                    .next_evts_by_global_seq_no(
+                        &filter,
+                        current_from_global_seq_no,
+                        from_bytes,
+                    )
+                    .await?;
+
+                for await evt in evts {
+                    match evt {
+                        Ok(evt) => {
+                            current_from_global_seq_no = evt.global_seq_no.succ();
+                            yield Ok(evt);
+                        }
+
+                        Err(error) => {
+                            yield Err(error);
+                            break 'outer;
+                        }
+                    }
+                }
+
+                // Only sleep if requesting future events.
+                if current_from_global_seq_no >= last_global_seq_no {
+                    sleep(this.poll_interval).await;
+                }
+            }
+        };
 
         Ok(evts)
     }
@@ -109,7 +280,7 @@ impl PostgresEvtLog {
         tag: &str,
         from_seq_no: SeqNo,
         from_bytes: EvtFromBytes,
-    ) -> Result<impl Stream<Item = Result<(SeqNo, E), Error>> + Send, Error>
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send, Error>
     where
         E: Send,
         EvtFromBytes: Fn(Bytes) -> Result<E, EvtFromBytesError> + Copy + Send + Sync + 'static,
@@ -122,24 +293,19 @@ impl PostgresEvtLog {
             .cnn()
             .await?
             .query_raw(
-                "SELECT seq_no, evt FROM evts WHERE tag = $1 AND seq_no >= $2 ORDER BY seq_no",
+                &format!(
+                    "SELECT id, seq_no, global_seq_no, version, timestamp, tags, evt, entity_type
+                     FROM {evts}
+                     WHERE tags @> ARRAY[$1] AND seq_no >= $2
+                     ORDER BY seq_no",
+                    evts = self.evts_table
+                ),
                 params,
             )
             .await
-            .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))?
-            .map_err(|error| Error::Postgres("cannot get next row".to_string(), error))
-            .map(move |row| {
-                row.and_then(|row| {
-                    let seq_no = (row.get::<_, i64>(0) as u64)
-                        .try_into()
-                        .map_err(|_| Error::ZeroSeqNo)?;
-                    let bytes = row.get::<_, &[u8]>(1);
-                    let bytes = Bytes::copy_from_slice(bytes);
-                    from_bytes(bytes)
-                        .map_err(|source| Error::FromBytes(Box::new(source)))
-                        .map(|evt| (seq_no, evt))
-                })
-            });
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?
+            .map_err(|error| Error::postgres("cannot get next row".to_string(), error))
+            .map(move |row| row.and_then(|row| evt_envelope(row, &from_bytes)));
 
         Ok(evts)
     }
@@ -159,13 +325,43 @@ impl EvtLog for PostgresEvtLog {
     const MAX_SEQ_NO: SeqNo = SeqNo::new(unsafe { NonZeroU64::new_unchecked(i64::MAX as u64) });
 
     async fn persist<E, ToBytes, ToBytesError>(
-        &mut self,
+        &self,
         evt: &E,
-        tag: Option<&str>,
+        version: u32,
+        tags: &[String],
         id: Uuid,
+        entity_type: Option<&str>,
         last_seq_no: Option<SeqNo>,
         to_bytes: &ToBytes,
     ) -> Result<SeqNo, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        self.persist_returning_global_seq_no(
+            evt,
+            version,
+            tags,
+            id,
+            entity_type,
+            last_seq_no,
+            to_bytes,
+        )
+        .await
+        .map(|(seq_no, _)| seq_no)
+    }
+
+    async fn persist_returning_global_seq_no<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Uuid,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> Result<(SeqNo, Option<GlobalSeqNo>), Self::Error>
     where
         E: Sync,
         ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
@@ -173,34 +369,152 @@ impl EvtLog for PostgresEvtLog {
     {
         debug!(%id, "persisting event");
 
-        let seq_no = last_seq_no
-            .map(|seq_no| seq_no.succ())
-            .unwrap_or(SeqNo::MIN)
-            .as_u64() as i64;
+        let seq_nos = insert_evt(
+            &*self.cnn().await?,
+            &self.evts_table,
+            self.outbox_table.as_deref(),
+            evt,
+            version,
+            tags,
+            id,
+            entity_type,
+            last_seq_no,
+            self.clock.now(),
+            to_bytes,
+        )
+        .await?;
+        match seq_nos {
+            Some((seq_no, global_seq_no)) => Ok((seq_no, Some(global_seq_no))),
+            None => Err(self.seq_no_conflict(id, last_seq_no).await),
+        }
+    }
+
+    async fn persist_batch<E, ToBytes, ToBytesError>(
+        &self,
+        batch: &[EntityEvts<'_, E>],
+        to_bytes: &ToBytes,
+    ) -> Result<Vec<Option<SeqNo>>, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(len = batch.len(), "persisting batch");
+
+        let mut cnn = self.cnn().await?;
+        let tx = cnn
+            .transaction()
+            .await
+            .map_err(|error| Error::postgres("cannot start transaction".to_string(), error))?;
+
+        // Dropping the transaction early, e.g. on errors, rolls it back. Later entries for the
+        // same entity ID continue after the former ones.
+        let mut last_seq_nos = Vec::with_capacity(batch.len());
+        let mut chained = HashMap::new();
+        for entity_evts in batch {
+            let mut last_seq_no = chained
+                .get(&entity_evts.id)
+                .copied()
+                .unwrap_or(entity_evts.last_seq_no);
+            for evt in entity_evts.evts {
+                let seq_no = insert_evt(
+                    &tx,
+                    &self.evts_table,
+                    self.outbox_table.as_deref(),
+                    evt.evt(),
+                    entity_evts.version,
+                    evt.tags(),
+                    entity_evts.id,
+                    entity_evts.entity_type,
+                    last_seq_no,
+                    self.clock.now(),
+                    to_bytes,
+                )
+                .await?;
+                match seq_no {
+                    Some((seq_no, _)) => last_seq_no = Some(seq_no),
+                    None => {
+                        drop(tx);
+                        return Err(self.seq_no_conflict(entity_evts.id, last_seq_no).await);
+                    }
+                }
+            }
+            chained.insert(entity_evts.id, last_seq_no);
+            last_seq_nos.push(last_seq_no);
+        }
+
+        tx.commit()
+            .await
+            .map_err(|error| Error::postgres("cannot commit transaction".to_string(), error))?;
+
+        Ok(last_seq_nos)
+    }
+
+    async fn delete_to(&self, id: Uuid, to_seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %to_seq_no, "deleting events");
+
+        // Always keep the last event.
+        self.cnn()
+            .await?
+            .execute(
+                &format!(
+                    "DELETE FROM {evts}
+                     WHERE id = $1
+                     AND seq_no <= $2
+                     AND seq_no < (SELECT MAX(seq_no) FROM {evts} WHERE id = $1)",
+                    evts = self.evts_table
+                ),
+                &[&id, &(to_seq_no.as_u64() as i64)],
+            )
+            .await
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
+
+    async fn mark_deleted(&self, id: Uuid) -> Result<(), Self::Error> {
+        debug!(%id, "marking entity as deleted");
 
-        let bytes = to_bytes(evt).map_err(|error| Error::ToBytes(Box::new(error)))?;
+        self.cnn()
+            .await?
+            .execute(
+                &format!(
+                    "INSERT INTO {deleted} (id) VALUES ($1) ON CONFLICT DO NOTHING",
+                    deleted = self.deleted_table
+                ),
+                &[&id],
+            )
+            .await
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
 
+    async fn is_deleted(&self, id: Uuid) -> Result<bool, Self::Error> {
         self.cnn()
             .await?
             .query_one(
-                "INSERT INTO evts (seq_no, id, evt, tag) VALUES ($1, $2, $3, $4) RETURNING seq_no",
-                &[&seq_no, &id, &bytes.as_ref(), &tag],
+                &format!(
+                    "SELECT EXISTS (SELECT FROM {deleted} WHERE id = $1)",
+                    deleted = self.deleted_table
+                ),
+                &[&id],
             )
             .await
-            .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))
-            .and_then(|row| {
-                (row.get::<_, i64>(0) as u64)
-                    .try_into()
-                    .map_err(|_| Error::ZeroSeqNo)
-            })
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))
+            .map(|row| row.get::<_, bool>(0))
     }
 
     async fn last_seq_no(&self, id: Uuid) -> Result<Option<SeqNo>, Self::Error> {
         self.cnn()
             .await?
-            .query_one("SELECT MAX(seq_no) FROM evts WHERE id = $1", &[&id])
+            .query_one(
+                &format!(
+                    "SELECT MAX(seq_no) FROM {evts} WHERE id = $1",
+                    evts = self.evts_table
+                ),
+                &[&id],
+            )
             .await
-            .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))
             .and_then(|row| {
                 // If there is no seq_no there is one row with a NULL column, hence use `try_get`.
                 row.try_get::<_, i64>(0)
@@ -215,7 +529,7 @@ impl EvtLog for PostgresEvtLog {
         id: Uuid,
         from_seq_no: SeqNo,
         from_bytes: FromBytes,
-    ) -> Result<impl Stream<Item = Result<(SeqNo, E), Self::Error>> + Send, Self::Error>
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
     where
         E: Send,
         FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
@@ -234,8 +548,8 @@ impl EvtLog for PostgresEvtLog {
 
                 for await evt in evts {
                     match evt {
-                        Ok(evt @ (seq_no, _)) => {
-                            current_from_seq_no = seq_no.succ();
+                        Ok(evt) => {
+                            current_from_seq_no = evt.seq_no.succ();
                             yield Ok(evt);
                         }
 
@@ -256,12 +570,109 @@ impl EvtLog for PostgresEvtLog {
         Ok(evts)
     }
 
+    async fn evts_by_id_from<E, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %from_seq_no, "building current events by ID stream");
+
+        // Without an upper bound, terminate after the first batch not filling the page.
+        let mut current_from_seq_no = from_seq_no;
+        let evts = stream! {
+            'outer: loop {
+                let evts = self
+                    .next_evts_by_id(id, current_from_seq_no, from_bytes)
+                    .await?;
+
+                let mut n = 0;
+                for await evt in evts {
+                    match evt {
+                        Ok(evt) => {
+                            n += 1;
+                            current_from_seq_no = evt.seq_no.succ();
+                            yield Ok(evt);
+                        }
+
+                        Err(error) => {
+                            yield Err(error);
+                            break 'outer;
+                        }
+                    }
+                }
+
+                if n < self.replay_batch_size.get() {
+                    break;
+                }
+            }
+        };
+
+        Ok(evts)
+    }
+
+    async fn evts_by_ids<E, FromBytes, FromBytesError>(
+        &self,
+        ids: Vec<Uuid>,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(?ids, %from_global_seq_no, "building events by IDs stream");
+        self.evts_by_global_seq_no(Filter::Ids(ids), from_global_seq_no, from_bytes)
+            .await
+    }
+
+    async fn evts<E, FromBytes, FromBytesError>(
+        &self,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%from_global_seq_no, "building events stream");
+        self.evts_by_global_seq_no(Filter::All, from_global_seq_no, from_bytes)
+            .await
+    }
+
+    async fn evts_by_type<E, FromBytes, FromBytesError>(
+        &self,
+        entity_type: String,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(entity_type, %from_global_seq_no, "building events by type stream");
+        self.evts_by_global_seq_no(
+            Filter::EntityType(entity_type),
+            from_global_seq_no,
+            from_bytes,
+        )
+        .await
+    }
+
     async fn evts_by_tag<E, FromBytes, FromBytesError>(
         &self,
         tag: String,
         from_seq_no: SeqNo,
         from_bytes: FromBytes,
-    ) -> Result<impl Stream<Item = Result<(SeqNo, E), Self::Error>> + Send, Self::Error>
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
     where
         E: Send,
         FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
@@ -278,9 +689,15 @@ impl EvtLog for PostgresEvtLog {
         let last_seq_no = self
             .cnn()
             .await?
-            .query_one("SELECT COALESCE(MAX(seq_no), 1) FROM evts", &[])
+            .query_one(
+                &format!(
+                    "SELECT COALESCE(MAX(seq_no), 1) FROM {evts}",
+                    evts = self.evts_table
+                ),
+                &[],
+            )
             .await
-            .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))
             .and_then(|row| {
                 (row.get::<_, i64>(0) as u64)
                     .try_into()
@@ -296,8 +713,8 @@ impl EvtLog for PostgresEvtLog {
 
                 for await evt in evts {
                     match evt {
-                        Ok(evt @ (seq_no, _)) => {
-                            current_from_seq_no = seq_no.succ();
+                        Ok(evt) => {
+                            current_from_seq_no = evt.seq_no.succ();
                             yield Ok(evt);
                         }
 
@@ -317,6 +734,46 @@ impl EvtLog for PostgresEvtLog {
 
         Ok(evts)
     }
+
+    async fn verify(&self, id: Uuid) -> Result<VerifyReport, Self::Error> {
+        debug!(%id, "verifying events");
+
+        // Only query the sequence numbers, not the events themselves.
+        let cnn = self.cnn().await?;
+        let seq_nos = cnn
+            .query_raw(
+                &format!(
+                    "SELECT seq_no FROM {evts} WHERE id = $1 ORDER BY seq_no",
+                    evts = self.evts_table
+                ),
+                &[&id],
+            )
+            .await
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?
+            .map_err(|error| Error::postgres("cannot get next row".to_string(), error))
+            .map(|row| {
+                row.and_then(|row| {
+                    (row.get::<_, i64>(0) as u64)
+                        .try_into()
+                        .map_err(|_| Error::ZeroSeqNo)
+                })
+            });
+
+        verify_seq_nos(seq_nos, true).await
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        self.cnn()
+            .await?
+            .execute("SELECT 1", &[])
+            .await
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
+
+    fn is_retryable(error: &Self::Error) -> bool {
+        error.is_transient()
+    }
 }
 
 /// Configuration for the [PostgresEvtLog].
@@ -338,12 +795,24 @@ pub struct Config {
     #[serde(default = "evts_table_default")]
     evts_table: String,
 
+    #[serde(default)]
+    outbox_table: Option<String>,
+
     #[serde(default = "poll_interval_default", with = "humantime_serde")]
     poll_interval: Duration,
 
     #[serde(default = "id_broadcast_capacity_default")]
     id_broadcast_capacity: NonZeroUsize,
 
+    #[serde(default = "replay_batch_size_default")]
+    replay_batch_size: NonZeroUsize,
+
+    #[serde(default)]
+    partitions: Option<NonZeroU32>,
+
+    #[serde(default, with = "humantime_serde")]
+    statement_timeout: Option<Duration>,
+
     #[serde(default)]
     setup: bool,
 }
@@ -399,11 +868,28 @@ impl Config {
         Self { sslmode, ..self }
     }
 
-    /// Change the `evts_table`.
+    /// Change the `evts_table`. Entities marked as deleted, see
+    /// [mark_deleted](eventsourced::EvtLog::mark_deleted), are stored in the
+    /// `<evts_table>_deleted` table.
     pub fn with_evts_table(self, evts_table: String) -> Self {
         Self { evts_table, ..self }
     }
 
+    /// Change the `outbox_table`. If set, each persisted event is also inserted into this table
+    /// within the same statement, i.e. atomically, such that an external relay can poll it and
+    /// publish the events to a message bus, thereby avoiding a dual write. The relay is
+    /// responsible for deleting the published events from the outbox table.
+    pub fn with_outbox_table<T>(self, outbox_table: T) -> Self
+    where
+        T: ToString,
+    {
+        let outbox_table = Some(outbox_table.to_string());
+        Self {
+            outbox_table,
+            ..self
+        }
+    }
+
     /// Change the `poll_interval`.
     pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
         Self {
@@ -420,16 +906,52 @@ impl Config {
         }
     }
 
+    /// Change the `replay_batch_size`, i.e. the maximum number of events fetched at once when
+    /// querying events by ID, e.g. on recovery.
+    pub fn with_replay_batch_size(self, replay_batch_size: NonZeroUsize) -> Self {
+        Self {
+            replay_batch_size,
+            ..self
+        }
+    }
+
+    /// Change the `partitions`. If set, the events table is created with declarative partitioning
+    /// by a hash of the entity ID into the given number of partitions named
+    /// `<evts_table>_p0..<evts_table>_p<partitions - 1>`, e.g. for very large event logs. Queries
+    /// still target the events table, letting Postgres route them to the respective partitions.
+    /// Only applies if `setup` creates the events table, i.e. an existing table is neither
+    /// partitioned nor repartitioned. By default the events table is not partitioned.
+    pub fn with_partitions(self, partitions: NonZeroU32) -> Self {
+        Self {
+            partitions: Some(partitions),
+            ..self
+        }
+    }
+
+    /// Change the `statement_timeout`, i.e. the maximum duration of a statement, after which
+    /// Postgres cancels it and [Error::StatementTimeout] is returned. By default statements do not
+    /// time out.
+    pub fn with_statement_timeout(self, statement_timeout: Duration) -> Self {
+        Self {
+            statement_timeout: Some(statement_timeout),
+            ..self
+        }
+    }
+
     /// Change the `setup` flag.
     pub fn with_setup(self, setup: bool) -> Self {
         Self { setup, ..self }
     }
 
     fn cnn_config(&self) -> String {
-        format!(
+        let mut cnn_config = format!(
             "host={} port={} user={} password={} dbname={} sslmode={}",
             self.host, self.port, self.user, self.password, self.dbname, self.sslmode
-        )
+        );
+        if let Some(statement_timeout) = self.statement_timeout {
+            cnn_config.push_str(&statement_timeout_option(statement_timeout));
+        }
+        cnn_config
     }
 }
 
@@ -444,13 +966,192 @@ impl Default for Config {
             dbname: "postgres".to_string(),
             sslmode: "prefer".to_string(),
             evts_table: evts_table_default(),
+            outbox_table: None,
             poll_interval: poll_interval_default(),
             id_broadcast_capacity: id_broadcast_capacity_default(),
+            replay_batch_size: replay_batch_size_default(),
+            partitions: None,
+            statement_timeout: None,
             setup: false,
         }
     }
 }
 
+/// Filter for events ordered by their global sequence numbers.
+#[derive(Debug)]
+enum Filter {
+    All,
+    Ids(Vec<Uuid>),
+    EntityType(String),
+}
+
+fn evt_envelope<E, FromBytes, FromBytesError>(
+    row: Row,
+    from_bytes: &FromBytes,
+) -> Result<EvtEnvelope<E>, Error>
+where
+    FromBytes: Fn(Bytes) -> Result<E, FromBytesError>,
+    FromBytesError: StdError + Send + Sync + 'static,
+{
+    let id = row.get::<_, Uuid>(0);
+    let seq_no = (row.get::<_, i64>(1) as u64)
+        .try_into()
+        .map_err(|_| Error::ZeroSeqNo)?;
+    let global_seq_no = (row.get::<_, i64>(2) as u64)
+        .try_into()
+        .map_err(|_| Error::ZeroSeqNo)?;
+    let version = row.get::<_, i32>(3) as u32;
+    let timestamp = row.get::<_, DateTime<Utc>>(4);
+    let tags = row.get::<_, Vec<String>>(5);
+    let bytes = row.get::<_, &[u8]>(6);
+    let bytes = Bytes::copy_from_slice(bytes);
+    let entity_type = row.get::<_, Option<String>>(7);
+    from_bytes(bytes)
+        .map_err(|source| Error::FromBytes(Box::new(source)))
+        .map(|evt| EvtEnvelope {
+            id,
+            entity_type,
+            seq_no,
+            global_seq_no,
+            version,
+            timestamp,
+            tags,
+            evt,
+        })
+}
+
+/// Insert the given event and, if an outbox table is given, a copy of it into that within the
+/// same statement, returning its sequence number and global sequence number or `None` if the given
+/// last sequence number is not the actual one.
+#[allow(clippy::too_many_arguments)]
+async fn insert_evt<C, E, ToBytes, ToBytesError>(
+    client: &C,
+    evts_table: &str,
+    outbox_table: Option<&str>,
+    evt: &E,
+    version: u32,
+    tags: &[String],
+    id: Uuid,
+    entity_type: Option<&str>,
+    last_seq_no: Option<SeqNo>,
+    timestamp: DateTime<Utc>,
+    to_bytes: &ToBytes,
+) -> Result<Option<(SeqNo, GlobalSeqNo)>, Error>
+where
+    C: GenericClient + Sync,
+    ToBytes: Fn(&E) -> Result<Bytes, ToBytesError>,
+    ToBytesError: StdError + Send + Sync + 'static,
+{
+    let seq_no = last_seq_no
+        .map(|seq_no| seq_no.succ())
+        .unwrap_or(SeqNo::MIN)
+        .as_u64() as i64;
+
+    let bytes = to_bytes(evt).map_err(|error| Error::ToBytes(Box::new(error)))?;
+
+    // Only insert if the given last sequence number is the actual one; a concurrent insert of the
+    // same sequence number is rejected by the primary key.
+    let expected = last_seq_no.map(|seq_no| seq_no.as_u64() as i64);
+    let insert = format!(
+        "INSERT INTO {evts} (seq_no, id, evt, tags, timestamp, version, entity_type)
+         SELECT $1::bigint, $2::uuid, $3::bytea, $4::text[], $5::timestamptz, $7::integer, $8::text
+         WHERE (SELECT MAX(seq_no) FROM {evts} WHERE id = $2) IS NOT DISTINCT FROM $6",
+        evts = evts_table
+    );
+    // A data-modifying CTE makes inserting into the outbox atomic with inserting the event.
+    let query = match outbox_table {
+        Some(outbox) => format!(
+            "WITH evt AS (
+               {insert}
+               RETURNING global_seq_no, id, seq_no, entity_type, version, timestamp, tags, evt
+             )
+             INSERT INTO {outbox}
+               (global_seq_no, id, seq_no, entity_type, version, timestamp, tags, evt)
+             SELECT * FROM evt
+             RETURNING seq_no, global_seq_no"
+        ),
+        None => format!("{insert} RETURNING seq_no, global_seq_no"),
+    };
+    let row = client
+        .query_opt(
+            &query,
+            &[
+                &seq_no,
+                &id,
+                &bytes.as_ref(),
+                &tags,
+                &timestamp,
+                &expected,
+                &(version as i32),
+                &entity_type,
+            ],
+        )
+        .await;
+
+    match row {
+        Ok(Some(row)) => {
+            let seq_no = (row.get::<_, i64>(0) as u64)
+                .try_into()
+                .map_err(|_| Error::ZeroSeqNo)?;
+            let global_seq_no = (row.get::<_, i64>(1) as u64)
+                .try_into()
+                .map_err(|_| Error::ZeroSeqNo)?;
+            Ok(Some((seq_no, global_seq_no)))
+        }
+
+        Ok(None) => Ok(None),
+
+        Err(error) if error.code() == Some(&SqlState::UNIQUE_VIOLATION) => Ok(None),
+
+        Err(error) => Err(Error::postgres("cannot execute query".to_string(), error)),
+    }
+}
+
+/// Generate the statements setting up the events table, possibly partitioned, and its indexes.
+fn create_evt_log_sql(config: &Config) -> Result<String, Error> {
+    let evts_table = quote_table_name(&config.evts_table)?;
+
+    let (partition_by, partitions) = match config.partitions {
+        Some(modulus) => {
+            let partitions = (0..modulus.get())
+                .map(|remainder| {
+                    let partition =
+                        quote_table_name(&format!("{}_p{remainder}", config.evts_table))?;
+                    Ok(format!(
+                        "CREATE TABLE IF NOT EXISTS {partition} PARTITION OF {evts_table} \
+                         FOR VALUES WITH (MODULUS {modulus}, REMAINDER {remainder});\n"
+                    ))
+                })
+                .collect::<Result<String, Error>>()?;
+            (" PARTITION BY HASH (id)", partitions)
+        }
+
+        None => ("", String::new()),
+    };
+
+    let sql = include_str!("create_evt_log.sql")
+        .replace("{evts}", &evts_table)
+        .replace("{partition_by}", partition_by)
+        .replace("{partitions}", &partitions)
+        .replace(
+            "{evts_tags}",
+            &quote_table_name(&format!("{}_tags", config.evts_table))?,
+        )
+        .replace(
+            "{evts_global_seq_no}",
+            &quote_table_name(&format!("{}_global_seq_no", config.evts_table))?,
+        )
+        .replace(
+            "{evts_entity_type}",
+            &quote_table_name(&format!("{}_entity_type", config.evts_table))?,
+        )
+        .replace(
+            "{evts_deleted}",
+            &quote_table_name(&format!("{}_deleted", config.evts_table))?,
+        );
+    Ok(sql)
+}
+
 fn evts_table_default() -> String {
     "evts".to_string()
 }
@@ -463,10 +1164,14 @@ const fn id_broadcast_capacity_default() -> NonZeroUsize {
     NonZeroUsize::MIN
 }
 
+const fn replay_batch_size_default() -> NonZeroUsize {
+    unsafe { NonZeroUsize::new_unchecked(1_000) }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use eventsourced::convert;
+    use eventsourced::{convert, IntoTaggedEvt};
     use std::future;
     use testcontainers::clients::Cli;
     use testcontainers_modules::postgres::Postgres;
@@ -477,8 +1182,13 @@ mod tests {
         let container = client.run(Postgres::default());
         let port = container.get_host_port_ipv4(5432);
 
-        let config = Config::default().with_port(port).with_setup(true);
-        let mut evt_log = PostgresEvtLog::new(config).await?;
+        let config = Config::default()
+            .with_port(port)
+            .with_replay_batch_size(2.try_into()?)
+            .with_setup(true);
+        let evt_log = PostgresEvtLog::new(config).await?;
+        evt_log.ping().await?;
+        assert!(evt_log.pool_state().connections > 0);
 
         let id = Uuid::now_v7();
 
@@ -488,30 +1198,70 @@ mod tests {
         assert_eq!(last_seq_no, None);
 
         let last_seq_no = evt_log
-            .persist(&1, Some("tag"), id, None, &convert::prost::to_bytes)
+            .persist(
+                &1,
+                1,
+                &["tag".to_string()],
+                id,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
             .await?;
         assert!(last_seq_no.as_u64() == 1);
 
         evt_log
-            .persist(&2, None, id, Some(last_seq_no), &convert::prost::to_bytes)
+            .persist(
+                &2,
+                1,
+                &[],
+                id,
+                None,
+                Some(last_seq_no),
+                &convert::prost::to_bytes,
+            )
             .await?;
 
         let result = evt_log
             .persist(
                 &3,
-                Some("tag"),
+                1,
+                &["tag".to_string()],
                 id,
+                None,
                 Some(last_seq_no),
                 &convert::prost::to_bytes,
             )
             .await;
-        assert!(result.is_err());
+        assert!(matches!(
+            result,
+            Err(Error::SeqNoConflict {
+                expected: Some(_),
+                actual: Some(_)
+            })
+        ));
+
+        let result = evt_log
+            .persist(
+                &3,
+                1,
+                &[],
+                id,
+                None,
+                Some(10.try_into()?),
+                &convert::prost::to_bytes,
+            )
+            .await;
+        assert!(matches!(result, Err(Error::SeqNoConflict { .. })));
+        assert!(!PostgresEvtLog::is_retryable(&result.unwrap_err()));
 
         evt_log
             .persist(
                 &3,
-                Some("tag"),
+                1,
+                &["tag".to_string()],
                 id,
+                None,
                 Some(last_seq_no.succ()),
                 &convert::prost::to_bytes,
             )
@@ -520,21 +1270,33 @@ mod tests {
         let last_seq_no = evt_log.last_seq_no(id).await?;
         assert_eq!(last_seq_no, Some(3.try_into()?));
 
+        let report = evt_log.verify(id).await?;
+        assert!(report.is_consistent());
+        assert_eq!(report.evt_count, 3);
+
         let evts = evt_log
             .evts_by_id::<i32, _, _>(id, 2.try_into()?, convert::prost::from_bytes)
             .await?;
         let sum = evts
             .take(2)
-            .try_fold(0i32, |acc, (_, n)| future::ready(Ok(acc + n)))
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
             .await?;
         assert_eq!(sum, 5);
 
+        let current_evts = evt_log
+            .evts_by_id_from::<i32, _, _>(id, 2.try_into()?, convert::prost::from_bytes)
+            .await?
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(current_evts, vec![2, 3]);
+
         let evts_by_tag = evt_log
             .evts_by_tag::<i32, _, _>("tag".to_string(), SeqNo::MIN, convert::prost::from_bytes)
             .await?;
         let sum = evts_by_tag
             .take(2)
-            .try_fold(0i32, |acc, (_, n)| future::ready(Ok(acc + n)))
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
             .await?;
         assert_eq!(sum, 4);
 
@@ -548,14 +1310,16 @@ mod tests {
 
         let last_seq_no = evt_log
             .clone()
-            .persist(&4, None, id, last_seq_no, &convert::prost::to_bytes)
+            .persist(&4, 1, &[], id, None, last_seq_no, &convert::prost::to_bytes)
             .await?;
         evt_log
             .clone()
             .persist(
                 &5,
-                Some("tag"),
+                1,
+                &["tag".to_string()],
                 id,
+                None,
                 Some(last_seq_no),
                 &convert::prost::to_bytes,
             )
@@ -565,16 +1329,385 @@ mod tests {
 
         let sum = evts
             .take(5)
-            .try_fold(0i32, |acc, (_, n)| future::ready(Ok(acc + n)))
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
             .await?;
         assert_eq!(sum, 15);
 
+        // Events are fetched in batches of two, i.e. across three pages.
+        let seq_nos = evt_log
+            .evts_by_id::<i32, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(5)
+            .map_ok(|evt| evt.seq_no.as_u64())
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(seq_nos, vec![1, 2, 3, 4, 5]);
+
         let sum = evts_by_tag
             .take(3)
-            .try_fold(0i32, |acc, (_, n)| future::ready(Ok(acc + n)))
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
             .await?;
         assert_eq!(sum, 9);
 
+        let id_2 = Uuid::now_v7();
+        evt_log
+            .persist(
+                &6,
+                2,
+                &["tag".to_string(), "other-tag".to_string()],
+                id_2,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let evts_by_tag = evt_log
+            .evts_by_tag::<i32, _, _>(
+                "other-tag".to_string(),
+                SeqNo::MIN,
+                convert::prost::from_bytes,
+            )
+            .await?;
+        let sum = evts_by_tag
+            .take(1)
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
+            .await?;
+        assert_eq!(sum, 6);
+
+        let evts_by_ids = evt_log
+            .evts_by_ids::<i32, _, _>(vec![id, id_2], GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let evts_by_ids = evts_by_ids.take(6).try_collect::<Vec<_>>().await?;
+        assert!(evts_by_ids
+            .windows(2)
+            .all(|evts| evts[0].global_seq_no < evts[1].global_seq_no));
+        assert_eq!(evts_by_ids[4].version, 1);
+        assert_eq!(evts_by_ids[5].version, 2);
+        assert_eq!(
+            evts_by_ids[5].tags,
+            vec!["tag".to_string(), "other-tag".to_string()]
+        );
+        let evts_by_ids = evts_by_ids
+            .into_iter()
+            .map(|evt| (evt.id, evt.evt))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            evts_by_ids,
+            vec![(id, 1), (id, 2), (id, 3), (id, 4), (id, 5), (id_2, 6)]
+        );
+
+        let evts = evt_log
+            .evts::<i32, _, _>(GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let evts = evts
+            .take(6)
+            .map_ok(|evt| (evt.id, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, evts_by_ids);
+
+        evt_log.delete_to(id, 5.try_into()?).await?;
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, Some(5.try_into()?));
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let evts = evts.take(1).try_collect::<Vec<_>>().await?;
+        assert_eq!(evts[0].seq_no, 5.try_into()?);
+
+        assert!(!evt_log.is_deleted(id).await?);
+        evt_log.mark_deleted(id).await?;
+        evt_log.mark_deleted(id).await?;
+        assert!(evt_log.is_deleted(id).await?);
+        assert!(!evt_log.is_deleted(id_2).await?);
+
+        // A conflict for one entity must roll back the whole batch.
+        let id_3 = Uuid::now_v7();
+        let batch_evts = [7.into_tagged_evt(), 8.into_tagged_evt()];
+        let batch = [
+            EntityEvts {
+                id: id_3,
+                entity_type: None,
+                last_seq_no: None,
+                version: 1,
+                evts: &batch_evts,
+            },
+            EntityEvts {
+                id: id_2,
+                entity_type: None,
+                last_seq_no: None,
+                version: 1,
+                evts: &batch_evts,
+            },
+        ];
+        let result = evt_log
+            .persist_batch(&batch, &convert::prost::to_bytes)
+            .await;
+        assert!(matches!(result, Err(Error::SeqNoConflict { .. })));
+        let last_seq_no = evt_log.last_seq_no(id_3).await?;
+        assert_eq!(last_seq_no, None);
+
+        let batch = [
+            EntityEvts {
+                id: id_3,
+                entity_type: Some("foo"),
+                last_seq_no: None,
+                version: 1,
+                evts: &batch_evts,
+            },
+            EntityEvts {
+                id: id_2,
+                entity_type: None,
+                last_seq_no: Some(SeqNo::MIN),
+                version: 1,
+                evts: &batch_evts,
+            },
+        ];
+        let last_seq_nos = evt_log
+            .persist_batch(&batch, &convert::prost::to_bytes)
+            .await?;
+        assert_eq!(last_seq_nos, vec![Some(2.try_into()?), Some(3.try_into()?)]);
+
+        let evts_by_type = evt_log
+            .evts_by_type::<i32, _, _>(
+                "foo".to_string(),
+                GlobalSeqNo::MIN,
+                convert::prost::from_bytes,
+            )
+            .await?
+            .take(2)
+            .map_ok(|evt| (evt.id, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_type, vec![(id_3, 7), (id_3, 8)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_evt_log_outbox() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let container = client.run(Postgres::default());
+        let port = container.get_host_port_ipv4(5432);
+
+        let config = Config::default()
+            .with_port(port)
+            .with_outbox_table("outbox")
+            .with_setup(true);
+        let evt_log = PostgresEvtLog::new(config).await?;
+
+        let id = Uuid::now_v7();
+        let outbox = |evt_log: PostgresEvtLog| async move {
+            evt_log
+                .cnn()
+                .await?
+                .query("SELECT id, seq_no FROM outbox ORDER BY global_seq_no", &[])
+                .await
+                .map(|rows| {
+                    rows.into_iter()
+                        .map(|row| (row.get::<_, Uuid>(0), row.get::<_, i64>(1)))
+                        .collect::<Vec<_>>()
+                })
+                .map_err(|error| Error::postgres("cannot execute query".to_string(), error))
+        };
+
+        let last_seq_no = evt_log
+            .persist(&1, 1, &[], id, None, None, &convert::prost::to_bytes)
+            .await?;
+        let batch = [EntityEvts {
+            id,
+            entity_type: None,
+            last_seq_no: Some(last_seq_no),
+            version: 1,
+            evts: &[2.into_tagged_evt(), 3.into_tagged_evt()],
+        }];
+        evt_log
+            .persist_batch(&batch, &convert::prost::to_bytes)
+            .await?;
+        assert_eq!(
+            outbox(evt_log.clone()).await?,
+            vec![(id, 1), (id, 2), (id, 3)]
+        );
+
+        // Rejected events are not inserted into the outbox.
+        let result = evt_log
+            .persist(&4, 1, &[], id, None, None, &convert::prost::to_bytes)
+            .await;
+        assert!(matches!(result, Err(Error::SeqNoConflict { .. })));
+        assert_eq!(outbox(evt_log.clone()).await?.len(), 3);
+
+        // If inserting into the outbox fails, the event is not persisted either.
+        evt_log
+            .cnn()
+            .await?
+            .batch_execute("ALTER TABLE outbox ADD CONSTRAINT reject CHECK (false) NOT VALID")
+            .await?;
+        let result = evt_log
+            .persist(
+                &4,
+                1,
+                &[],
+                id,
+                None,
+                Some(3.try_into()?),
+                &convert::prost::to_bytes,
+            )
+            .await;
+        assert!(matches!(result, Err(Error::Postgres(..))));
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, Some(3.try_into()?));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_evt_log_partitions() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let container = client.run(Postgres::default());
+        let port = container.get_host_port_ipv4(5432);
+
+        let config = Config::default()
+            .with_port(port)
+            .with_partitions(4.try_into()?)
+            .with_setup(true);
+        let evt_log = PostgresEvtLog::new(config).await?;
+
+        let ids = (0..8).map(|_| Uuid::now_v7()).collect::<Vec<_>>();
+        for id in &ids {
+            evt_log
+                .persist(&1, 1, &[], *id, None, None, &convert::prost::to_bytes)
+                .await?;
+        }
+
+        for id in &ids {
+            let last_seq_no = evt_log.last_seq_no(*id).await?;
+            assert_eq!(last_seq_no, Some(SeqNo::MIN));
+        }
+        let evts = evt_log
+            .evts_by_id_from::<i32, _, _>(ids[0], SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .map_ok(|envelope| envelope.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![1]);
+
+        let partitions = evt_log
+            .cnn()
+            .await?
+            .query_one(
+                "SELECT count(*) FROM pg_inherits WHERE inhparent = '\"evts\"'::regclass",
+                &[],
+            )
+            .await?
+            .get::<_, i64>(0);
+        assert_eq!(partitions, 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_evt_log_is_retryable() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let container = client.run(Postgres::default());
+        let port = container.get_host_port_ipv4(5432);
+
+        let config = Config::default().with_port(port);
+        let (cnn, connection) = tokio_postgres::connect(&config.cnn_config(), NoTls).await?;
+        tokio::spawn(connection);
+
+        let error = cnn.batch_execute("SELECT 1 / 0").await.unwrap_err();
+        let error = Error::postgres("cannot divide".to_string(), error);
+        assert!(!PostgresEvtLog::is_retryable(&error));
+
+        let error = cnn
+            .batch_execute("SELECT pg_terminate_backend(pg_backend_pid())")
+            .await
+            .unwrap_err();
+        let error = Error::postgres("cannot terminate".to_string(), error);
+        assert!(PostgresEvtLog::is_retryable(&error));
+
+        let error = cnn.batch_execute("SELECT 1").await.unwrap_err();
+        let error = Error::postgres("cannot select".to_string(), error);
+        assert!(PostgresEvtLog::is_retryable(&error));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_evt_log_setup_migrates() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let container = client.run(Postgres::default());
+        let port = container.get_host_port_ipv4(5432);
+
+        // Events table as created by former versions.
+        let config = Config::default().with_port(port);
+        let (cnn, connection) = tokio_postgres::connect(&config.cnn_config(), NoTls).await?;
+        tokio::spawn(connection);
+        let id = Uuid::now_v7();
+        cnn.batch_execute(&format!(
+            "CREATE TABLE evts (
+               seq_no bigint,
+               id uuid,
+               evt bytea,
+               tag text,
+               PRIMARY KEY (seq_no, id)
+             );
+             INSERT INTO evts (seq_no, id, evt, tag) VALUES (1, '{id}', '\\x0801', 'tag');"
+        ))
+        .await?;
+
+        let evt_log = PostgresEvtLog::new(config.with_setup(true)).await?;
+        let evts = evt_log
+            .evts_by_tag::<i32, _, _>("tag".to_string(), SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(1)
+            .map_ok(|envelope| {
+                (
+                    envelope.id,
+                    envelope.seq_no.as_u64(),
+                    envelope.version,
+                    envelope.evt,
+                )
+            })
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(id, 1, 1, 1)]);
+
+        evt_log
+            .persist(
+                &2,
+                1,
+                &[],
+                id,
+                None,
+                Some(SeqNo::MIN),
+                &convert::prost::to_bytes,
+            )
+            .await?;
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, Some(2.try_into()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_evt_log_sql() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let sql = create_evt_log_sql(&Config::default())?;
+        assert!(!sql.contains("PARTITION"));
+
+        let sql = create_evt_log_sql(&Config::default().with_partitions(2.try_into()?))?;
+        assert!(sql.contains(") PARTITION BY HASH (id);"));
+        assert!(sql.contains(
+            "CREATE TABLE IF NOT EXISTS \"evts_p0\" PARTITION OF \"evts\" \
+             FOR VALUES WITH (MODULUS 2, REMAINDER 0);"
+        ));
+        assert!(sql.contains(
+            "CREATE TABLE IF NOT EXISTS \"evts_p1\" PARTITION OF \"evts\" \
+             FOR VALUES WITH (MODULUS 2, REMAINDER 1);"
+        ));
+        assert!(!sql.contains("evts_p2"));
+
         Ok(())
     }
 }
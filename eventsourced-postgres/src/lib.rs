@@ -1,30 +1,45 @@
-//! [EvtLog](eventsourced::EvtLog) and [SnapshotStore](eventsourced::SnapshotStore) implementations
-//! based upon [PostgreSQL](https://www.postgresql.org/).
+//! [EvtLog](eventsourced::EvtLog), [SnapshotStore](eventsourced::SnapshotStore) and
+//! [ProjectionOffsetStore](eventsourced::ProjectionOffsetStore) implementations based upon
+//! [PostgreSQL](https://www.postgresql.org/).
 
 mod evt_log;
+mod projection_offset_store;
 mod snapshot_store;
 
 pub use evt_log::{Config as PostgresEvtLogConfig, PostgresEvtLog};
+pub use projection_offset_store::{
+    Config as PostgresProjectionOffsetStoreConfig, PostgresProjectionOffsetStore,
+};
 pub use snapshot_store::{Config as PostgresSnapshotStoreConfig, PostgresSnapshotStore};
 
+/// State of the connection pool of a [PostgresEvtLog] or [PostgresSnapshotStore].
+pub use bb8_postgres::bb8::State as PoolState;
+
 use bb8_postgres::{
-    bb8::{Pool, PooledConnection},
+    bb8::{Pool, PooledConnection, RunError},
     PostgresConnectionManager,
 };
 use eventsourced::SeqNo;
+use std::{error::Error as StdError, time::Duration};
 use thiserror::Error;
+use tokio_postgres::error::SqlState;
 
 type CnnPool<T> = Pool<PostgresConnectionManager<T>>;
 
 type Cnn<'a, T> = PooledConnection<'a, PostgresConnectionManager<T>>;
 
-/// Errors from the [PostgresEvtLog] or [PostgresSnapshotStore].
+/// Errors from the [PostgresEvtLog], [PostgresSnapshotStore] or [PostgresProjectionOffsetStore].
 #[derive(Debug, Error)]
 pub enum Error {
     /// Postgres error.
     #[error("Postgres error: {0}")]
     Postgres(String, #[source] tokio_postgres::Error),
 
+    /// A statement has been canceled, because it has exceeded the configured
+    /// `statement_timeout`.
+    #[error("statement timeout exceeded")]
+    StatementTimeout(#[source] tokio_postgres::Error),
+
     /// Cannot get connection from pool.
     #[error("cannot get connection from pool")]
     GetConnection(#[source] bb8_postgres::bb8::RunError<tokio_postgres::Error>),
@@ -41,7 +56,117 @@ pub enum Error {
     #[error("sequence number must not be zero")]
     ZeroSeqNo,
 
-    /// Sequence number must not be zero.
-    #[error("invalid last sequence number: {0:?} {1:?}")]
-    InvalidLastSeqNo(Option<SeqNo>, Option<SeqNo>),
+    /// The given last sequence number does not match the actual one, e.g. because of a concurrent
+    /// writer for the same entity ID.
+    #[error("expected last sequence number {expected:?}, but was {actual:?}")]
+    SeqNoConflict {
+        expected: Option<SeqNo>,
+        actual: Option<SeqNo>,
+    },
+
+    /// A configured table name does not match `^[A-Za-z_][A-Za-z0-9_]*$`.
+    #[error("invalid table name {0:?}")]
+    InvalidTableName(String),
+}
+
+impl Error {
+    /// Create an [Error::Postgres] with the given message or an [Error::StatementTimeout] if the
+    /// given error signals a canceled statement.
+    fn postgres(message: String, error: tokio_postgres::Error) -> Self {
+        if error.code() == Some(&SqlState::QUERY_CANCELED) {
+            Error::StatementTimeout(error)
+        } else {
+            Error::Postgres(message, error)
+        }
+    }
+
+    /// Whether this error is transient, i.e. caused by a lost or refused connection, a
+    /// serialization failure or a deadlock, such that the failed operation can be retried.
+    fn is_transient(&self) -> bool {
+        match self {
+            Error::Postgres(_, error) => is_transient(error),
+            Error::GetConnection(RunError::User(error)) => is_transient(error),
+            Error::GetConnection(RunError::TimedOut) => true,
+            _ => false,
+        }
+    }
+}
+
+fn is_transient(error: &tokio_postgres::Error) -> bool {
+    match error.code() {
+        Some(code) => {
+            code.code().starts_with("08")
+                || [
+                    SqlState::T_R_SERIALIZATION_FAILURE,
+                    SqlState::T_R_DEADLOCK_DETECTED,
+                    SqlState::ADMIN_SHUTDOWN,
+                    SqlState::CANNOT_CONNECT_NOW,
+                ]
+                .contains(code)
+        }
+
+        // Errors without SQLSTATE are transient only if the connection is closed or broken.
+        None => {
+            error.is_closed()
+                || error
+                    .source()
+                    .is_some_and(|source| source.is::<std::io::Error>())
+        }
+    }
+}
+
+/// Connection option making Postgres cancel statements running longer than the given timeout,
+/// to be appended to the connection configuration. As it is set when connecting, it applies to all
+/// statements on all connections of the pool.
+fn statement_timeout_option(statement_timeout: Duration) -> String {
+    format!(
+        " options='-c statement_timeout={}'",
+        statement_timeout.as_millis().max(1)
+    )
+}
+
+/// Validate the given table name and quote it for use as identifier in SQL. As quoted identifiers
+/// are case-sensitive, the name must be given in the case of the table.
+fn quote_table_name(name: &str) -> Result<String, Error> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if valid {
+        Ok(format!("\"{name}\""))
+    } else {
+        Err(Error::InvalidTableName(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_table_name() {
+        assert_eq!(quote_table_name("evts").unwrap(), "\"evts\"");
+        assert_eq!(quote_table_name("_Evts_2").unwrap(), "\"_Evts_2\"");
+        for name in ["", "2evts", "evts; DROP TABLE evts", "evts\"", "évts"] {
+            assert!(matches!(
+                quote_table_name(name),
+                Err(Error::InvalidTableName(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_statement_timeout_option() {
+        assert_eq!(
+            statement_timeout_option(Duration::from_secs(5)),
+            " options='-c statement_timeout=5000'"
+        );
+        // Zero would disable the timeout.
+        assert_eq!(
+            statement_timeout_option(Duration::ZERO),
+            " options='-c statement_timeout=1'"
+        );
+    }
 }
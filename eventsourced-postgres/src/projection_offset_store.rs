@@ -0,0 +1,260 @@
+//! A [ProjectionOffsetStore] implementation based on [PostgreSQL](https://www.postgresql.org/).
+
+use crate::{quote_table_name, Cnn, CnnPool, Error};
+use bb8_postgres::{bb8::Pool, PostgresConnectionManager};
+use eventsourced::{GlobalSeqNo, ProjectionOffsetStore};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug, Formatter};
+use tokio_postgres::NoTls;
+use tracing::debug;
+
+/// A [ProjectionOffsetStore] implementation based on [PostgreSQL](https://www.postgresql.org/),
+/// storing one row with the projection name and its offset per projection.
+#[derive(Clone)]
+pub struct PostgresProjectionOffsetStore {
+    cnn_pool: CnnPool<NoTls>,
+    projection_offsets_table: String,
+}
+
+impl PostgresProjectionOffsetStore {
+    #[allow(missing_docs)]
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating PostgresProjectionOffsetStore");
+
+        let projection_offsets_table = quote_table_name(&config.projection_offsets_table)?;
+
+        // Create connection pool.
+        let tls = NoTls;
+        let cnn_manager = PostgresConnectionManager::new_from_stringlike(config.cnn_config(), tls)
+            .map_err(|error| {
+                Error::Postgres("cannot create connection manager".to_string(), error)
+            })?;
+        let cnn_pool = Pool::builder()
+            .build(cnn_manager)
+            .await
+            .map_err(|error| Error::Postgres("cannot create connection pool".to_string(), error))?;
+
+        // Setup tables.
+        if config.setup {
+            cnn_pool
+                .get()
+                .await
+                .map_err(Error::GetConnection)?
+                .execute(
+                    &include_str!("create_projection_offset_store.sql")
+                        .replace("{projection_offsets}", &projection_offsets_table),
+                    &[],
+                )
+                .await
+                .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))?;
+        }
+
+        Ok(Self {
+            cnn_pool,
+            projection_offsets_table,
+        })
+    }
+
+    async fn cnn(&self) -> Result<Cnn<NoTls>, Error> {
+        self.cnn_pool.get().await.map_err(Error::GetConnection)
+    }
+}
+
+impl Debug for PostgresProjectionOffsetStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostgresProjectionOffsetStore").finish()
+    }
+}
+
+impl ProjectionOffsetStore for PostgresProjectionOffsetStore {
+    type Error = Error;
+
+    async fn save_offset(&mut self, name: &str, offset: GlobalSeqNo) -> Result<(), Self::Error> {
+        debug!(name, %offset, "saving projection offset");
+
+        self.cnn()
+            .await?
+            .execute(
+                &format!(
+                    "INSERT INTO {projection_offsets} VALUES ($1, $2)
+                     ON CONFLICT (projection_name)
+                     DO UPDATE SET offset_global_seq_no = EXCLUDED.offset_global_seq_no",
+                    projection_offsets = self.projection_offsets_table
+                ),
+                &[&name, &(offset.as_u64() as i64)],
+            )
+            .await
+            .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
+
+    async fn load_offset(&self, name: &str) -> Result<Option<GlobalSeqNo>, Self::Error> {
+        debug!(name, "loading projection offset");
+
+        self.cnn()
+            .await?
+            .query_opt(
+                &format!(
+                    "SELECT offset_global_seq_no FROM {projection_offsets}
+                     WHERE projection_name = $1",
+                    projection_offsets = self.projection_offsets_table
+                ),
+                &[&name],
+            )
+            .await
+            .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))?
+            .map(|row| {
+                (row.get::<_, i64>(0) as u64)
+                    .try_into()
+                    .map_err(|_| Error::ZeroSeqNo)
+            })
+            .transpose()
+    }
+}
+
+/// Configuration for the [PostgresProjectionOffsetStore].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    host: String,
+
+    port: u16,
+
+    user: String,
+
+    password: String,
+
+    dbname: String,
+
+    sslmode: String,
+
+    #[serde(default = "projection_offsets_table_default")]
+    projection_offsets_table: String,
+
+    #[serde(default)]
+    setup: bool,
+}
+
+impl Config {
+    /// Change the `host`.
+    pub fn with_host<T>(self, host: T) -> Self
+    where
+        T: ToString,
+    {
+        let host = host.to_string();
+        Self { host, ..self }
+    }
+
+    /// Change the `port`.
+    pub fn with_port(self, port: u16) -> Self {
+        Self { port, ..self }
+    }
+
+    /// Change the `user`.
+    pub fn with_user<T>(self, user: T) -> Self
+    where
+        T: ToString,
+    {
+        let user = user.to_string();
+        Self { user, ..self }
+    }
+
+    /// Change the `password`.
+    pub fn with_password<T>(self, password: T) -> Self
+    where
+        T: ToString,
+    {
+        let password = password.to_string();
+        Self { password, ..self }
+    }
+
+    /// Change the `dbname`.
+    pub fn with_dbname<T>(self, dbname: T) -> Self
+    where
+        T: ToString,
+    {
+        let dbname = dbname.to_string();
+        Self { dbname, ..self }
+    }
+
+    /// Change the `sslmode`.
+    pub fn with_sslmode<T>(self, sslmode: T) -> Self
+    where
+        T: ToString,
+    {
+        let sslmode = sslmode.to_string();
+        Self { sslmode, ..self }
+    }
+
+    /// Change the `projection_offsets_table`.
+    pub fn with_projection_offsets_table(self, projection_offsets_table: String) -> Self {
+        Self {
+            projection_offsets_table,
+            ..self
+        }
+    }
+
+    /// Change the `setup` flag.
+    pub fn with_setup(self, setup: bool) -> Self {
+        Self { setup, ..self }
+    }
+
+    fn cnn_config(&self) -> String {
+        format!(
+            "host={} port={} user={} password={} dbname={} sslmode={}",
+            self.host, self.port, self.user, self.password, self.dbname, self.sslmode
+        )
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 5432,
+            user: "postgres".to_string(),
+            password: "".to_string(),
+            dbname: "postgres".to_string(),
+            sslmode: "prefer".to_string(),
+            projection_offsets_table: projection_offsets_table_default(),
+            setup: false,
+        }
+    }
+}
+
+fn projection_offsets_table_default() -> String {
+    "projection_offsets".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as StdError;
+    use testcontainers::clients::Cli;
+    use testcontainers_modules::postgres::Postgres;
+
+    #[tokio::test]
+    async fn test_projection_offset_store() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let container = client.run(Postgres::default());
+        let port = container.get_host_port_ipv4(5432);
+
+        let config = Config::default().with_port(port).with_setup(true);
+        let mut offset_store = PostgresProjectionOffsetStore::new(config).await?;
+
+        let offset = offset_store.load_offset("foo").await?;
+        assert_eq!(offset, None);
+
+        offset_store.save_offset("foo", 42.try_into()?).await?;
+        offset_store.save_offset("foo", 43.try_into()?).await?;
+        offset_store.save_offset("bar", 1.try_into()?).await?;
+
+        let offset = offset_store.load_offset("foo").await?;
+        assert_eq!(offset, Some(43.try_into()?));
+        let offset = offset_store.load_offset("bar").await?;
+        assert_eq!(offset, Some(1.try_into()?));
+
+        Ok(())
+    }
+}
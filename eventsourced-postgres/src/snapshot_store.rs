@@ -1,22 +1,30 @@
 //! A [SnapshotStore] implementation based on [PostgreSQL](https://www.postgresql.org/).
 
-use crate::{Cnn, CnnPool, Error};
+use crate::{quote_table_name, statement_timeout_option, Cnn, CnnPool, Error, PoolState};
 use bb8_postgres::{bb8::Pool, PostgresConnectionManager};
 use bytes::Bytes;
 use eventsourced::{SeqNo, Snapshot, SnapshotStore};
+use futures::{stream, Stream};
 use serde::{Deserialize, Serialize};
 use std::{
     error::Error as StdError,
     fmt::{self, Debug, Formatter},
+    num::NonZeroUsize,
+    time::{Duration, Instant},
 };
 use tokio_postgres::NoTls;
-use tracing::debug;
+use tracing::{debug, info};
 use uuid::Uuid;
 
 /// A [SnapshotStore] implementation based on [PostgreSQL](https://www.postgresql.org/).
+///
+/// Each saved and loaded snapshot is logged at info level along with its ID, sequence number,
+/// length in bytes and the duration of the operation, e.g. to track snapshot sizes over time.
 #[derive(Clone)]
 pub struct PostgresSnapshotStore {
     cnn_pool: CnnPool<NoTls>,
+    keep_n: Option<NonZeroUsize>,
+    snapshots_table: String,
 }
 
 impl PostgresSnapshotStore {
@@ -24,16 +32,18 @@ impl PostgresSnapshotStore {
     pub async fn new(config: Config) -> Result<Self, Error> {
         debug!(?config, "creating PostgresSnapshotStore");
 
+        let snapshots_table = quote_table_name(&config.snapshots_table)?;
+
         // Create connection pool.
         let tls = NoTls;
         let cnn_manager = PostgresConnectionManager::new_from_stringlike(config.cnn_config(), tls)
             .map_err(|error| {
-                Error::Postgres("cannot create connection manager".to_string(), error)
+                Error::postgres("cannot create connection manager".to_string(), error)
             })?;
         let cnn_pool = Pool::builder()
             .build(cnn_manager)
             .await
-            .map_err(|error| Error::Postgres("cannot create connection pool".to_string(), error))?;
+            .map_err(|error| Error::postgres("cannot create connection pool".to_string(), error))?;
 
         // Setup tables.
         if config.setup {
@@ -41,16 +51,25 @@ impl PostgresSnapshotStore {
                 .get()
                 .await
                 .map_err(Error::GetConnection)?
-                .execute(
+                .batch_execute(
                     &include_str!("create_snapshot_store.sql")
-                        .replace("snapshots", &config.snapshots_table),
-                    &[],
+                        .replace("{snapshots}", &snapshots_table),
                 )
                 .await
-                .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))?;
+                .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?;
         }
 
-        Ok(Self { cnn_pool })
+        Ok(Self {
+            cnn_pool,
+            keep_n: config.keep_n,
+            snapshots_table,
+        })
+    }
+
+    /// Get the [PoolState] of the connection pool, i.e. the number of connections and of idle ones,
+    /// e.g. to debug connection exhaustion.
+    pub fn pool_state(&self) -> PoolState {
+        self.cnn_pool.state()
     }
 
     async fn cnn(&self) -> Result<Cnn<NoTls>, Error> {
@@ -68,9 +87,10 @@ impl SnapshotStore for PostgresSnapshotStore {
     type Error = Error;
 
     async fn save<S, ToBytes, ToBytesError>(
-        &mut self,
+        &self,
         id: Uuid,
         seq_no: SeqNo,
+        state_version: u32,
         state: S,
         to_bytes: &ToBytes,
     ) -> Result<(), Self::Error>
@@ -81,21 +101,67 @@ impl SnapshotStore for PostgresSnapshotStore {
     {
         debug!(%id, %seq_no, "saving snapshot");
 
+        let start = Instant::now();
         let bytes = to_bytes(&state).map_err(|source| Error::ToBytes(Box::new(source)))?;
-        self.cnn()
-            .await?
-            .execute(
-                "INSERT INTO snapshots VALUES ($1, $2, $3)",
-                &[&id, &(seq_no.as_u64() as i64), &bytes.as_ref()],
+        let mut cnn = self.cnn().await?;
+        let tx = cnn
+            .transaction()
+            .await
+            .map_err(|error| Error::postgres("cannot start transaction".to_string(), error))?;
+
+        tx.execute(
+            &format!(
+                "INSERT INTO {snapshots} (id, seq_no, state, state_version)
+                 VALUES ($1, $2, $3, $4)",
+                snapshots = self.snapshots_table
+            ),
+            &[
+                &id,
+                &(seq_no.as_u64() as i64),
+                &bytes.as_ref(),
+                &(state_version as i32),
+            ],
+        )
+        .await
+        .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?;
+
+        // Delete the oldest snapshots beyond `keep_n`.
+        if let Some(keep_n) = self.keep_n {
+            tx.execute(
+                &format!(
+                    "DELETE FROM {snapshots}
+                     WHERE id = $1
+                     AND seq_no < (
+                       SELECT MIN(seq_no) FROM (
+                         SELECT seq_no FROM {snapshots} WHERE id = $1 ORDER BY seq_no DESC LIMIT $2
+                       ) AS kept
+                     )",
+                    snapshots = self.snapshots_table
+                ),
+                &[&id, &(keep_n.get() as i64)],
             )
             .await
-            .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))
-            .map(|_| ())
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|error| Error::postgres("cannot commit transaction".to_string(), error))?;
+        info!(
+            %id,
+            %seq_no,
+            bytes_len = bytes.len(),
+            duration = ?start.elapsed(),
+            "saved snapshot"
+        );
+
+        Ok(())
     }
 
     async fn load<S, FromBytes, FromBytesError>(
         &self,
         id: Uuid,
+        state_version: u32,
         from_bytes: FromBytes,
     ) -> Result<Option<Snapshot<S>>, Self::Error>
     where
@@ -104,28 +170,156 @@ impl SnapshotStore for PostgresSnapshotStore {
     {
         debug!(%id, "loading snapshot");
 
+        let start = Instant::now();
+        self.cnn()
+            .await?
+            .query_opt(
+                &format!(
+                    "SELECT seq_no, state FROM {snapshots}
+                     WHERE id = $1 AND state_version = $2
+                     ORDER BY seq_no DESC
+                     LIMIT 1",
+                    snapshots = self.snapshots_table
+                ),
+                &[&id, &(state_version as i32)],
+            )
+            .await
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?
+            .map(move |row| {
+                let seq_no = (row.get::<_, i64>(0) as u64)
+                    .try_into()
+                    .map_err(|_| Error::ZeroSeqNo)?;
+                let bytes = row.get::<_, &[u8]>(1);
+                let bytes = Bytes::copy_from_slice(bytes);
+                info!(
+                    %id,
+                    %seq_no,
+                    bytes_len = bytes.len(),
+                    duration = ?start.elapsed(),
+                    "loaded snapshot"
+                );
+                from_bytes(bytes)
+                    .map_err(|source| Error::FromBytes(Box::new(source)))
+                    .map(|state| Snapshot::new(seq_no, state))
+            })
+            .transpose()
+    }
+
+    async fn load_at<S, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        max_seq_no: SeqNo,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> Result<Option<Snapshot<S>>, Self::Error>
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %max_seq_no, "loading snapshot");
+
+        let start = Instant::now();
         self.cnn()
             .await?
             .query_opt(
-                "SELECT seq_no, state FROM snapshots
-                 WHERE id = $1
-                 AND seq_no = (select max(seq_no) from snapshots where id = $1)",
-                &[&id],
+                &format!(
+                    "SELECT seq_no, state FROM {snapshots}
+                     WHERE id = $1 AND seq_no <= $2 AND state_version = $3
+                     ORDER BY seq_no DESC
+                     LIMIT 1",
+                    snapshots = self.snapshots_table
+                ),
+                &[&id, &(max_seq_no.as_u64() as i64), &(state_version as i32)],
             )
             .await
-            .map_err(|error| Error::Postgres("cannot execute query".to_string(), error))?
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?
             .map(move |row| {
                 let seq_no = (row.get::<_, i64>(0) as u64)
                     .try_into()
                     .map_err(|_| Error::ZeroSeqNo)?;
                 let bytes = row.get::<_, &[u8]>(1);
                 let bytes = Bytes::copy_from_slice(bytes);
+                info!(
+                    %id,
+                    %seq_no,
+                    bytes_len = bytes.len(),
+                    duration = ?start.elapsed(),
+                    "loaded snapshot"
+                );
                 from_bytes(bytes)
                     .map_err(|source| Error::FromBytes(Box::new(source)))
                     .map(|state| Snapshot::new(seq_no, state))
             })
             .transpose()
     }
+
+    async fn load_many<S, FromBytes, FromBytesError>(
+        &self,
+        ids: &[Uuid],
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<(Uuid, Snapshot<S>), Self::Error>> + Send, Self::Error>
+    where
+        S: Send,
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send + Sync,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(n = ids.len(), "loading snapshots");
+
+        let rows = self
+            .cnn()
+            .await?
+            .query(
+                &format!(
+                    "SELECT DISTINCT ON (id) id, seq_no, state FROM {snapshots}
+                     WHERE id = ANY($1) AND state_version = $2
+                     ORDER BY id, seq_no DESC",
+                    snapshots = self.snapshots_table
+                ),
+                &[&ids, &(state_version as i32)],
+            )
+            .await
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))?;
+
+        let snapshots = rows.into_iter().map(move |row| {
+            let id = row.get::<_, Uuid>(0);
+            let seq_no = (row.get::<_, i64>(1) as u64)
+                .try_into()
+                .map_err(|_| Error::ZeroSeqNo)?;
+            let bytes = row.get::<_, &[u8]>(2);
+            let bytes = Bytes::copy_from_slice(bytes);
+            from_bytes(bytes)
+                .map_err(|source| Error::FromBytes(Box::new(source)))
+                .map(|state| (id, Snapshot::new(seq_no, state)))
+        });
+        Ok(stream::iter(snapshots))
+    }
+
+    async fn delete_before(&self, id: Uuid, seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %seq_no, "deleting snapshots");
+
+        self.cnn()
+            .await?
+            .execute(
+                &format!(
+                    "DELETE FROM {snapshots} WHERE id = $1 AND seq_no < $2",
+                    snapshots = self.snapshots_table
+                ),
+                &[&id, &(seq_no.as_u64() as i64)],
+            )
+            .await
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        self.cnn()
+            .await?
+            .execute("SELECT 1", &[])
+            .await
+            .map_err(|error| Error::postgres("cannot execute query".to_string(), error))
+            .map(|_| ())
+    }
 }
 
 /// Configuration for the [PostgresSnapshotStore].
@@ -147,6 +341,12 @@ pub struct Config {
     #[serde(default = "snapshots_table_default")]
     snapshots_table: String,
 
+    #[serde(default)]
+    keep_n: Option<NonZeroUsize>,
+
+    #[serde(default, with = "humantime_serde")]
+    statement_timeout: Option<Duration>,
+
     #[serde(default)]
     setup: bool,
 }
@@ -210,16 +410,39 @@ impl Config {
         }
     }
 
+    /// Change `keep_n`, the number of snapshots to keep per entity ID. Older ones are deleted when
+    /// saving a new snapshot. By default all snapshots are kept.
+    pub fn with_keep_n(self, keep_n: NonZeroUsize) -> Self {
+        Self {
+            keep_n: Some(keep_n),
+            ..self
+        }
+    }
+
+    /// Change the `statement_timeout`, i.e. the maximum duration of a statement, after which
+    /// Postgres cancels it and [Error::StatementTimeout] is returned. By default statements do not
+    /// time out.
+    pub fn with_statement_timeout(self, statement_timeout: Duration) -> Self {
+        Self {
+            statement_timeout: Some(statement_timeout),
+            ..self
+        }
+    }
+
     /// Change the `setup` flag.
     pub fn with_setup(self, setup: bool) -> Self {
         Self { setup, ..self }
     }
 
     fn cnn_config(&self) -> String {
-        format!(
+        let mut cnn_config = format!(
             "host={} port={} user={} password={} dbname={} sslmode={}",
             self.host, self.port, self.user, self.password, self.dbname, self.sslmode
-        )
+        );
+        if let Some(statement_timeout) = self.statement_timeout {
+            cnn_config.push_str(&statement_timeout_option(statement_timeout));
+        }
+        cnn_config
     }
 }
 
@@ -234,6 +457,8 @@ impl Default for Config {
             dbname: "postgres".to_string(),
             sslmode: "prefer".to_string(),
             snapshots_table: snapshots_table_default(),
+            keep_n: None,
+            statement_timeout: None,
             setup: false,
         }
     }
@@ -247,6 +472,7 @@ fn snapshots_table_default() -> String {
 mod tests {
     use super::*;
     use eventsourced::convert;
+    use futures::TryStreamExt;
     use testcontainers::clients::Cli;
     use testcontainers_modules::postgres::Postgres;
 
@@ -256,13 +482,18 @@ mod tests {
         let container = client.run(Postgres::default());
         let port = container.get_host_port_ipv4(5432);
 
-        let config = Config::default().with_port(port).with_setup(true);
-        let mut snapshot_store = PostgresSnapshotStore::new(config).await?;
+        let config = Config::default()
+            .with_port(port)
+            .with_keep_n(2.try_into()?)
+            .with_setup(true);
+        let snapshot_store = PostgresSnapshotStore::new(config).await?;
+        snapshot_store.ping().await?;
+        assert!(snapshot_store.pool_state().connections > 0);
 
         let id = Uuid::now_v7();
 
         let snapshot = snapshot_store
-            .load::<i32, _, _>(id, &convert::prost::from_bytes)
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
             .await?;
         assert!(snapshot.is_none());
 
@@ -270,11 +501,11 @@ mod tests {
         let state = 666;
 
         snapshot_store
-            .save(id, seq_no, state, &convert::prost::to_bytes)
+            .save(id, seq_no, 1, state, &convert::prost::to_bytes)
             .await?;
 
         let snapshot = snapshot_store
-            .load::<i32, _, _>(id, &convert::prost::from_bytes)
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
             .await?;
 
         assert!(snapshot.is_some());
@@ -282,6 +513,63 @@ mod tests {
         assert_eq!(snapshot.seq_no, seq_no);
         assert_eq!(snapshot.state, state);
 
+        snapshot_store
+            .save(id, 43.try_into()?, 1, 667, &convert::prost::to_bytes)
+            .await?;
+        snapshot_store
+            .save(id, 44.try_into()?, 1, 668, &convert::prost::to_bytes)
+            .await?;
+        let count = snapshot_store
+            .cnn()
+            .await?
+            .query_one("SELECT COUNT(*) FROM snapshots WHERE id = $1", &[&id])
+            .await?
+            .get::<_, i64>(0);
+        assert_eq!(count, 2);
+
+        let snapshot = snapshot_store
+            .load_at::<i32, _, _>(id, 43.try_into()?, 1, &convert::prost::from_bytes)
+            .await?
+            .map(|snapshot| (snapshot.seq_no.as_u64(), snapshot.state));
+        assert_eq!(snapshot, Some((43, 667)));
+        let snapshot = snapshot_store
+            .load_at::<i32, _, _>(id, 42.try_into()?, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        let snapshots = snapshot_store
+            .load_many::<i32, _, _>(&[id, Uuid::now_v7()], 1, &convert::prost::from_bytes)
+            .await?
+            .map_ok(|(id, snapshot)| (id, snapshot.seq_no.as_u64(), snapshot.state))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(snapshots, vec![(id, 44, 668)]);
+
+        // Only snapshots with the given state version are considered.
+        snapshot_store
+            .save(id, 45.try_into()?, 2, 669, &convert::prost::to_bytes)
+            .await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?
+            .map(|snapshot| (snapshot.seq_no.as_u64(), snapshot.state));
+        assert_eq!(snapshot, Some((44, 668)));
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 2, &convert::prost::from_bytes)
+            .await?
+            .map(|snapshot| (snapshot.seq_no.as_u64(), snapshot.state));
+        assert_eq!(snapshot, Some((45, 669)));
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 3, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        snapshot_store.delete_before(id, 46.try_into()?).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
         Ok(())
     }
 }
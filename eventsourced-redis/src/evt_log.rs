@@ -0,0 +1,717 @@
+//! An [EvtLog] implementation based on [Redis Streams](https://redis.io/docs/data-types/streams/).
+
+use crate::{cnn, Error};
+use async_stream::stream;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use eventsourced::{Clock, EvtEnvelope, EvtLog, GlobalSeqNo, SeqNo, SystemClock};
+use futures::Stream;
+use redis::{aio::ConnectionManager, cmd, Script};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+    num::{NonZeroU64, NonZeroUsize},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::sleep;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Only append if the given last sequence number is the actual one; appends to both the stream of
+/// the entity and the global stream, using the (global) sequence numbers as entry IDs. An empty
+/// entity type is not stored.
+const PERSIST: &str = r"
+local last = redis.call('XREVRANGE', KEYS[1], '+', '-', 'COUNT', 1)
+local actual = 0
+if #last > 0 then
+  actual = tonumber(string.match(last[1][1], '^%d+'))
+end
+if actual ~= tonumber(ARGV[1]) then
+  return {0, actual}
+end
+local seq_no = string.format('%d', actual + 1)
+local global_seq_no = string.format('%d', redis.call('INCR', KEYS[3]))
+local fields = {
+  'id', ARGV[2],
+  'seq_no', seq_no,
+  'global_seq_no', global_seq_no,
+  'version', ARGV[3],
+  'timestamp', ARGV[4],
+  'tags', ARGV[5],
+  'evt', ARGV[6]
+}
+if ARGV[7] ~= '' then
+  table.insert(fields, 'entity_type')
+  table.insert(fields, ARGV[7])
+end
+redis.call('XADD', KEYS[1], seq_no .. '-0', unpack(fields))
+redis.call('XADD', KEYS[2], global_seq_no .. '-0', unpack(fields))
+return {1, actual + 1}
+";
+
+/// Delete the entries of the entity up to and including the given sequence number, but always
+/// keep the last one, from both the stream of the entity and the global stream.
+const DELETE_TO: &str = r"
+local last = redis.call('XREVRANGE', KEYS[1], '+', '-', 'COUNT', 1)
+if #last == 0 then
+  return 0
+end
+local last_seq_no = tonumber(string.match(last[1][1], '^%d+'))
+local to_seq_no = math.min(tonumber(ARGV[1]), last_seq_no - 1)
+if to_seq_no < 1 then
+  return 0
+end
+local entries = redis.call('XRANGE', KEYS[1], '-', string.format('%d-0', to_seq_no))
+for _, entry in ipairs(entries) do
+  local fields = entry[2]
+  for i = 1, #fields, 2 do
+    if fields[i] == 'global_seq_no' then
+      redis.call('XDEL', KEYS[2], fields[i + 1] .. '-0')
+    end
+  end
+  redis.call('XDEL', KEYS[1], entry[1])
+end
+return #entries
+";
+
+type Entry = (String, HashMap<String, Vec<u8>>);
+
+/// An [EvtLog] implementation based on [Redis Streams](https://redis.io/docs/data-types/streams/).
+///
+/// Each event is appended to a stream for its entity ID, keyed `<key_prefix>:<id>`, as well as to
+/// a global stream, keyed `<key_prefix>`, using the sequence number and the global sequence number
+/// respectively as entry IDs. Both appends happen atomically in a Lua script which also checks the
+/// last sequence number. Entity IDs marked as deleted are kept in a set, keyed
+/// `<key_prefix>:deleted`. As these keys belong to different hash slots, Redis Cluster is not
+/// supported.
+#[derive(Clone)]
+pub struct RedisEvtLog {
+    key_prefix: String,
+    poll_interval: Duration,
+    read_batch_size: NonZeroUsize,
+    cnn: ConnectionManager,
+    persist: Script,
+    delete_to: Script,
+    clock: Arc<dyn Clock>,
+}
+
+impl RedisEvtLog {
+    #[allow(missing_docs)]
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating RedisEvtLog");
+
+        let cnn = cnn(&config.url).await?;
+
+        Ok(Self {
+            clock: Arc::new(SystemClock),
+            key_prefix: config.key_prefix,
+            poll_interval: config.poll_interval,
+            read_batch_size: config.read_batch_size,
+            cnn,
+            persist: Script::new(PERSIST),
+            delete_to: Script::new(DELETE_TO),
+        })
+    }
+
+    /// Use the given [Clock] instead of the [SystemClock] to timestamp persisted events.
+    pub fn with_clock(self, clock: impl Clock) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    fn entity_key(&self, id: Uuid) -> String {
+        format!("{}:{id}", self.key_prefix)
+    }
+
+    fn global_seq_no_key(&self) -> String {
+        format!("{}:global_seq_no", self.key_prefix)
+    }
+
+    fn deleted_key(&self) -> String {
+        format!("{}:deleted", self.key_prefix)
+    }
+
+    /// Read the entries of the stream with the given key, starting with the given entry ID, in
+    /// batches and keep polling for new entries.
+    fn entries<E, F, FromBytes, FromBytesError>(
+        &self,
+        key: String,
+        from: u64,
+        filter: F,
+        from_bytes: FromBytes,
+    ) -> impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send
+    where
+        E: Send,
+        F: Fn(&EvtEnvelope<Bytes>) -> bool + Send + 'static,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let mut cnn = self.cnn.clone();
+        let poll_interval = self.poll_interval;
+        let read_batch_size = self.read_batch_size.get();
+        let mut from = from;
+
+        stream! {
+            'outer: loop {
+                let entries = cmd("XRANGE")
+                    .arg(&key)
+                    .arg(format!("{from}-0"))
+                    .arg("+")
+                    .arg("COUNT")
+                    .arg(read_batch_size)
+                    .query_async::<_, Vec<Entry>>(&mut cnn)
+                    .await
+                    .map_err(|error| Error::Redis("cannot read events".to_string(), error));
+                let entries = match entries {
+                    Ok(entries) => entries,
+
+                    Err(error) => {
+                        yield Err(error);
+                        break 'outer;
+                    }
+                };
+
+                let n = entries.len();
+                for entry in entries {
+                    let evt = entry_seq_no(&entry.0).and_then(|entry_seq_no| {
+                        from = entry_seq_no + 1;
+                        evt_envelope(entry.1)
+                    });
+
+                    match evt {
+                        Ok(evt) if filter(&evt) => {
+                            let evt = from_bytes(evt.evt.clone())
+                                .map_err(|error| Error::FromBytes(Box::new(error)))
+                                .map(|payload| EvtEnvelope {
+                                    id: evt.id,
+                                    entity_type: evt.entity_type,
+                                    seq_no: evt.seq_no,
+                                    global_seq_no: evt.global_seq_no,
+                                    version: evt.version,
+                                    timestamp: evt.timestamp,
+                                    tags: evt.tags,
+                                    evt: payload,
+                                });
+                            let is_err = evt.is_err();
+                            yield evt;
+                            if is_err {
+                                break 'outer;
+                            }
+                        }
+
+                        Ok(_) => {}
+
+                        Err(error) => {
+                            yield Err(error);
+                            break 'outer;
+                        }
+                    }
+                }
+
+                // Only sleep if all current entries have been read.
+                if n < read_batch_size {
+                    sleep(poll_interval).await;
+                }
+            }
+        }
+    }
+}
+
+impl Debug for RedisEvtLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisEvtLog")
+            .field("key_prefix", &self.key_prefix)
+            .finish()
+    }
+}
+
+impl EvtLog for RedisEvtLog {
+    type Error = Error;
+
+    /// The maximum value for sequence numbers. As Lua scripts use doubles, this is `2^53 - 1` or
+    /// `9_007_199_254_740_991`.
+    const MAX_SEQ_NO: SeqNo =
+        SeqNo::new(unsafe { NonZeroU64::new_unchecked(9_007_199_254_740_991) });
+
+    async fn persist<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Uuid,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> Result<SeqNo, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, "persisting event");
+
+        let bytes = to_bytes(evt).map_err(|error| Error::ToBytes(Box::new(error)))?;
+        let tags = serde_json::to_string(tags).expect("tags can be serialized");
+
+        let (persisted, seq_no) = self
+            .persist
+            .key(self.entity_key(id))
+            .key(&self.key_prefix)
+            .key(self.global_seq_no_key())
+            .arg(
+                last_seq_no
+                    .map(|seq_no| seq_no.as_u64())
+                    .unwrap_or_default(),
+            )
+            .arg(id.to_string())
+            .arg(version)
+            .arg(self.clock.now().to_rfc3339())
+            .arg(tags)
+            .arg(bytes.as_ref())
+            .arg(entity_type.unwrap_or_default())
+            .invoke_async::<_, (u8, u64)>(&mut self.cnn.clone())
+            .await
+            .map_err(|error| Error::Redis("cannot persist event".to_string(), error))?;
+
+        if persisted == 1 {
+            seq_no.try_into().map_err(|_| Error::ZeroSeqNo)
+        } else {
+            Err(Error::SeqNoConflict {
+                expected: last_seq_no,
+                actual: seq_no.try_into().ok(),
+            })
+        }
+    }
+
+    async fn delete_to(&self, id: Uuid, to_seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %to_seq_no, "deleting events");
+
+        self.delete_to
+            .key(self.entity_key(id))
+            .key(&self.key_prefix)
+            .arg(to_seq_no.as_u64())
+            .invoke_async::<_, u64>(&mut self.cnn.clone())
+            .await
+            .map_err(|error| Error::Redis("cannot delete events".to_string(), error))
+            .map(|_| ())
+    }
+
+    async fn mark_deleted(&self, id: Uuid) -> Result<(), Self::Error> {
+        debug!(%id, "marking entity as deleted");
+
+        cmd("SADD")
+            .arg(self.deleted_key())
+            .arg(id.to_string())
+            .query_async::<_, u64>(&mut self.cnn.clone())
+            .await
+            .map_err(|error| Error::Redis("cannot mark entity as deleted".to_string(), error))
+            .map(|_| ())
+    }
+
+    async fn is_deleted(&self, id: Uuid) -> Result<bool, Self::Error> {
+        debug!(%id, "checking whether entity is marked as deleted");
+
+        cmd("SISMEMBER")
+            .arg(self.deleted_key())
+            .arg(id.to_string())
+            .query_async::<_, bool>(&mut self.cnn.clone())
+            .await
+            .map_err(|error| Error::Redis("cannot get deleted marker".to_string(), error))
+    }
+
+    async fn last_seq_no(&self, id: Uuid) -> Result<Option<SeqNo>, Self::Error> {
+        debug!(%id, "getting last seq_no");
+
+        let entries = cmd("XREVRANGE")
+            .arg(self.entity_key(id))
+            .arg("+")
+            .arg("-")
+            .arg("COUNT")
+            .arg(1)
+            .query_async::<_, Vec<Entry>>(&mut self.cnn.clone())
+            .await
+            .map_err(|error| Error::Redis("cannot get last seq_no".to_string(), error))?;
+
+        entries
+            .first()
+            .map(|(entry_id, _)| {
+                entry_seq_no(entry_id)
+                    .and_then(|seq_no| seq_no.try_into().map_err(|_| Error::ZeroSeqNo))
+            })
+            .transpose()
+    }
+
+    async fn evts_by_id<E, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %from_seq_no, "building events by ID stream");
+        Ok(self.entries(
+            self.entity_key(id),
+            from_seq_no.as_u64(),
+            |_| true,
+            from_bytes,
+        ))
+    }
+
+    async fn evts_by_ids<E, FromBytes, FromBytesError>(
+        &self,
+        ids: Vec<Uuid>,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(?ids, %from_global_seq_no, "building events by IDs stream");
+        Ok(self.entries(
+            self.key_prefix.clone(),
+            from_global_seq_no.as_u64(),
+            move |evt| ids.contains(&evt.id),
+            from_bytes,
+        ))
+    }
+
+    async fn evts<E, FromBytes, FromBytesError>(
+        &self,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%from_global_seq_no, "building events stream");
+        Ok(self.entries(
+            self.key_prefix.clone(),
+            from_global_seq_no.as_u64(),
+            |_| true,
+            from_bytes,
+        ))
+    }
+
+    async fn evts_by_type<E, FromBytes, FromBytesError>(
+        &self,
+        entity_type: String,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(entity_type, %from_global_seq_no, "building events by type stream");
+        Ok(self.entries(
+            self.key_prefix.clone(),
+            from_global_seq_no.as_u64(),
+            move |evt| evt.entity_type.as_deref() == Some(entity_type.as_str()),
+            from_bytes,
+        ))
+    }
+
+    /// The given sequence number is used as global sequence number, like for the NATS
+    /// implementation.
+    async fn evts_by_tag<E, FromBytes, FromBytesError>(
+        &self,
+        tag: String,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(tag, %from_seq_no, "building events by tag stream");
+        Ok(self.entries(
+            self.key_prefix.clone(),
+            from_seq_no.as_u64(),
+            move |evt| evt.tags.contains(&tag),
+            from_bytes,
+        ))
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        cmd("PING")
+            .query_async::<_, ()>(&mut self.cnn.clone())
+            .await
+            .map_err(|error| Error::Redis("cannot ping".to_string(), error))
+    }
+
+    fn is_retryable(error: &Self::Error) -> bool {
+        error.is_transient()
+    }
+}
+
+/// Configuration for the [RedisEvtLog].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    url: String,
+
+    #[serde(default = "key_prefix_default")]
+    key_prefix: String,
+
+    #[serde(default = "poll_interval_default", with = "humantime_serde")]
+    poll_interval: Duration,
+
+    #[serde(default = "read_batch_size_default")]
+    read_batch_size: NonZeroUsize,
+}
+
+impl Config {
+    /// Change the `url`.
+    pub fn with_url<T>(self, url: T) -> Self
+    where
+        T: ToString,
+    {
+        let url = url.to_string();
+        Self { url, ..self }
+    }
+
+    /// Change the `key_prefix`.
+    pub fn with_key_prefix<T>(self, key_prefix: T) -> Self
+    where
+        T: ToString,
+    {
+        let key_prefix = key_prefix.to_string();
+        Self { key_prefix, ..self }
+    }
+
+    /// Change the `poll_interval`.
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            ..self
+        }
+    }
+
+    /// Change the `read_batch_size`, i.e. the maximum number of entries read at once.
+    pub fn with_read_batch_size(self, read_batch_size: NonZeroUsize) -> Self {
+        Self {
+            read_batch_size,
+            ..self
+        }
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            url: "redis://localhost:6379".to_string(),
+            key_prefix: key_prefix_default(),
+            poll_interval: poll_interval_default(),
+            read_batch_size: read_batch_size_default(),
+        }
+    }
+}
+
+/// Get the sequence number from an entry ID of the form `<seq_no>-0`.
+fn entry_seq_no(entry_id: &str) -> Result<u64, Error> {
+    entry_id
+        .split_once('-')
+        .and_then(|(seq_no, _)| seq_no.parse().ok())
+        .ok_or_else(|| Error::InvalidEntry(format!("invalid entry ID {entry_id}")))
+}
+
+fn evt_envelope(mut fields: HashMap<String, Vec<u8>>) -> Result<EvtEnvelope<Bytes>, Error> {
+    let mut field = |name: &str| {
+        fields
+            .remove(name)
+            .ok_or_else(|| Error::InvalidEntry(format!("missing field {name}")))
+    };
+    let evt = field("evt")?;
+    let id = field("id")?;
+    let seq_no = field("seq_no")?;
+    let global_seq_no = field("global_seq_no")?;
+    let version = field("version")?;
+    let timestamp = field("timestamp")?;
+    let tags = field("tags")?;
+    let entity_type = fields.remove("entity_type");
+
+    let id = parse::<Uuid>("id", &id)?;
+    let seq_no = parse::<u64>("seq_no", &seq_no)?
+        .try_into()
+        .map_err(|_| Error::ZeroSeqNo)?;
+    let global_seq_no = parse::<u64>("global_seq_no", &global_seq_no)?
+        .try_into()
+        .map_err(|_| Error::ZeroSeqNo)?;
+    let version = parse::<u32>("version", &version)?;
+    let timestamp = parse::<DateTime<Utc>>("timestamp", &timestamp)?;
+    let tags = serde_json::from_slice::<Vec<String>>(&tags)
+        .map_err(|error| Error::InvalidEntry(format!("invalid field tags: {error}")))?;
+    let entity_type = entity_type
+        .map(|entity_type| {
+            String::from_utf8(entity_type)
+                .map_err(|_| Error::InvalidEntry("invalid field entity_type".to_string()))
+        })
+        .transpose()?;
+
+    Ok(EvtEnvelope {
+        id,
+        entity_type,
+        seq_no,
+        global_seq_no,
+        version,
+        timestamp,
+        tags,
+        evt: evt.into(),
+    })
+}
+
+fn parse<T>(name: &str, value: &[u8]) -> Result<T, Error>
+where
+    T: std::str::FromStr,
+{
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| Error::InvalidEntry(format!("invalid field {name}")))
+}
+
+fn key_prefix_default() -> String {
+    "evts".to_string()
+}
+
+const fn poll_interval_default() -> Duration {
+    Duration::from_secs(2)
+}
+
+const fn read_batch_size_default() -> NonZeroUsize {
+    unsafe { NonZeroUsize::new_unchecked(1_000) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eventsourced::convert;
+    use futures::{StreamExt, TryStreamExt};
+    use testcontainers::clients::Cli;
+    use testcontainers_modules::redis::Redis;
+
+    #[tokio::test]
+    async fn test_evt_log() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let container = client.run(Redis);
+        let url = format!("redis://localhost:{}", container.get_host_port_ipv4(6379));
+
+        let config = Config::default()
+            .with_url(url)
+            .with_read_batch_size(2.try_into()?);
+        let evt_log = RedisEvtLog::new(config).await?;
+
+        let id = Uuid::now_v7();
+
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, None);
+
+        let last_seq_no = evt_log
+            .persist(
+                &1,
+                1,
+                &["tag".to_string()],
+                id,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+        assert_eq!(last_seq_no, SeqNo::MIN);
+
+        let result = evt_log
+            .persist(&2, 1, &[], id, None, None, &convert::prost::to_bytes)
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::SeqNoConflict {
+                expected: None,
+                actual: Some(_)
+            })
+        ));
+
+        let mut last_seq_no = Some(last_seq_no);
+        for n in 2..=5 {
+            let seq_no = evt_log
+                .persist(&n, 1, &[], id, None, last_seq_no, &convert::prost::to_bytes)
+                .await?;
+            last_seq_no = Some(seq_no);
+        }
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+
+        // Entries are read in batches of two, i.e. across three batches.
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(5)
+            .map_ok(|evt| (evt.seq_no.as_u64(), evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(1, 1), (2, 2), (3, 3), (4, 4), (5, 5)]);
+
+        let id_2 = Uuid::now_v7();
+        evt_log
+            .persist(
+                &6,
+                2,
+                &["tag".to_string()],
+                id_2,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let evts_by_tag = evt_log
+            .evts_by_tag::<i32, _, _>("tag".to_string(), SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(2)
+            .map_ok(|evt| (evt.id, evt.version, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_tag, vec![(id, 1, 1), (id_2, 2, 6)]);
+
+        let evts_by_ids = evt_log
+            .evts_by_ids::<i32, _, _>(vec![id_2], GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(1)
+            .map_ok(|evt| (evt.global_seq_no.as_u64(), evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_ids, vec![(6, 6)]);
+
+        evt_log.delete_to(id, 10.try_into()?).await?;
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+        let evts = evt_log
+            .evts::<i32, _, _>(GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(2)
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![5, 6]);
+
+        assert!(!evt_log.is_deleted(id).await?);
+        evt_log.mark_deleted(id).await?;
+        evt_log.mark_deleted(id).await?;
+        assert!(evt_log.is_deleted(id).await?);
+        assert!(!evt_log.is_deleted(id_2).await?);
+
+        Ok(())
+    }
+}
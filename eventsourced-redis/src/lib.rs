@@ -0,0 +1,76 @@
+//! [EvtLog](eventsourced::EvtLog) and [SnapshotStore](eventsourced::SnapshotStore) implementations
+//! based upon [Redis](https://redis.io/).
+
+mod evt_log;
+mod snapshot_store;
+
+pub use evt_log::{Config as RedisEvtLogConfig, RedisEvtLog};
+pub use snapshot_store::{Config as RedisSnapshotStoreConfig, RedisSnapshotStore};
+
+use eventsourced::SeqNo;
+use redis::{aio::ConnectionManager, Client, ErrorKind, RedisError};
+use thiserror::Error;
+
+/// Errors from the [RedisEvtLog] or [RedisSnapshotStore].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Redis error.
+    #[error("Redis error: {0}")]
+    Redis(String, #[source] RedisError),
+
+    /// Cannot convert an event to bytes.
+    #[error("cannot convert an event to bytes")]
+    ToBytes(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Cannot convert bytes to an event.
+    #[error("cannot convert bytes to an event")]
+    FromBytes(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Sequence number must not be zero.
+    #[error("sequence number must not be zero")]
+    ZeroSeqNo,
+
+    /// The given last sequence number does not match the actual one, e.g. because of a concurrent
+    /// writer for the same entity ID.
+    #[error("expected last sequence number {expected:?}, but was {actual:?}")]
+    SeqNoConflict {
+        expected: Option<SeqNo>,
+        actual: Option<SeqNo>,
+    },
+
+    /// Invalid stream entry, e.g. with a missing or malformed field.
+    #[error("invalid stream entry: {0}")]
+    InvalidEntry(String),
+}
+
+impl Error {
+    /// Whether this error is transient, i.e. caused by a lost or refused connection, a timeout or
+    /// a server not (yet) able to serve requests, such that the failed operation can be retried.
+    fn is_transient(&self) -> bool {
+        match self {
+            Error::Redis(_, error) => {
+                error.is_io_error()
+                    || error.is_connection_refusal()
+                    || error.is_connection_dropped()
+                    || error.is_timeout()
+                    || matches!(
+                        error.kind(),
+                        ErrorKind::BusyLoadingError
+                            | ErrorKind::TryAgain
+                            | ErrorKind::ClusterDown
+                            | ErrorKind::MasterDown
+                    )
+            }
+
+            _ => false,
+        }
+    }
+}
+
+async fn cnn(url: &str) -> Result<ConnectionManager, Error> {
+    let client = Client::open(url)
+        .map_err(|error| Error::Redis(format!("cannot create client for {url}"), error))?;
+    ConnectionManager::new(client)
+        .await
+        .map_err(|error| Error::Redis(format!("cannot connect to {url}"), error))
+}
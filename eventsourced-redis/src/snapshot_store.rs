@@ -0,0 +1,249 @@
+//! A [SnapshotStore] implementation based on [Redis](https://redis.io/).
+
+use crate::{cnn, Error};
+use bytes::Bytes;
+use eventsourced::{SeqNo, Snapshot, SnapshotStore};
+use redis::{aio::ConnectionManager, cmd, Script};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+};
+use tracing::debug;
+use uuid::Uuid;
+
+/// Delete the snapshot if its sequence number is less than the given one.
+const DELETE_BEFORE: &str = r"
+local seq_no = redis.call('HGET', KEYS[1], 'seq_no')
+if seq_no and tonumber(seq_no) < tonumber(ARGV[1]) then
+  redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// A [SnapshotStore] implementation based on [Redis](https://redis.io/). Snapshots are stored as
+/// hashes keyed `<key_prefix>:<id>`, hence only the last saved snapshot per entity ID is kept.
+#[derive(Clone)]
+pub struct RedisSnapshotStore {
+    key_prefix: String,
+    cnn: ConnectionManager,
+    delete_before: Script,
+}
+
+impl RedisSnapshotStore {
+    #[allow(missing_docs)]
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating RedisSnapshotStore");
+
+        let cnn = cnn(&config.url).await?;
+
+        Ok(Self {
+            key_prefix: config.key_prefix,
+            cnn,
+            delete_before: Script::new(DELETE_BEFORE),
+        })
+    }
+
+    fn key(&self, id: Uuid) -> String {
+        format!("{}:{id}", self.key_prefix)
+    }
+}
+
+impl Debug for RedisSnapshotStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisSnapshotStore")
+            .field("key_prefix", &self.key_prefix)
+            .finish()
+    }
+}
+
+impl SnapshotStore for RedisSnapshotStore {
+    type Error = Error;
+
+    async fn save<S, ToBytes, ToBytesError>(
+        &self,
+        id: Uuid,
+        seq_no: SeqNo,
+        state_version: u32,
+        state: S,
+        to_bytes: &ToBytes,
+    ) -> Result<(), Self::Error>
+    where
+        S: Send,
+        ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %seq_no, state_version, "saving snapshot");
+
+        let bytes = to_bytes(&state).map_err(|source| Error::ToBytes(Box::new(source)))?;
+        cmd("HSET")
+            .arg(self.key(id))
+            .arg("seq_no")
+            .arg(seq_no.as_u64())
+            .arg("state")
+            .arg(bytes.as_ref())
+            .arg("state_version")
+            .arg(state_version)
+            .query_async::<_, ()>(&mut self.cnn.clone())
+            .await
+            .map_err(|error| Error::Redis("cannot save snapshot".to_string(), error))
+    }
+
+    async fn load<S, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> Result<Option<Snapshot<S>>, Self::Error>
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, state_version, "loading snapshot");
+
+        let (seq_no, bytes, snapshot_state_version) = cmd("HMGET")
+            .arg(self.key(id))
+            .arg("seq_no")
+            .arg("state")
+            .arg("state_version")
+            .query_async::<_, (Option<u64>, Option<Vec<u8>>, Option<u32>)>(&mut self.cnn.clone())
+            .await
+            .map_err(|error| Error::Redis("cannot load snapshot".to_string(), error))?;
+
+        // Snapshots saved before the state version was added do not have one.
+        if snapshot_state_version.unwrap_or(1) != state_version {
+            return Ok(None);
+        }
+
+        seq_no
+            .zip(bytes)
+            .map(|(seq_no, bytes)| {
+                let seq_no = seq_no.try_into().map_err(|_| Error::ZeroSeqNo)?;
+                from_bytes(bytes.into())
+                    .map_err(|source| Error::FromBytes(Box::new(source)))
+                    .map(|state| Snapshot::new(seq_no, state))
+            })
+            .transpose()
+    }
+
+    async fn delete_before(&self, id: Uuid, seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %seq_no, "deleting snapshot");
+
+        self.delete_before
+            .key(self.key(id))
+            .arg(seq_no.as_u64())
+            .invoke_async::<_, ()>(&mut self.cnn.clone())
+            .await
+            .map_err(|error| Error::Redis("cannot delete snapshot".to_string(), error))
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        cmd("PING")
+            .query_async::<_, ()>(&mut self.cnn.clone())
+            .await
+            .map_err(|error| Error::Redis("cannot ping".to_string(), error))
+    }
+}
+
+/// Configuration for the [RedisSnapshotStore].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    url: String,
+
+    #[serde(default = "key_prefix_default")]
+    key_prefix: String,
+}
+
+impl Config {
+    /// Change the `url`.
+    pub fn with_url<T>(self, url: T) -> Self
+    where
+        T: ToString,
+    {
+        let url = url.to_string();
+        Self { url, ..self }
+    }
+
+    /// Change the `key_prefix`.
+    pub fn with_key_prefix<T>(self, key_prefix: T) -> Self
+    where
+        T: ToString,
+    {
+        let key_prefix = key_prefix.to_string();
+        Self { key_prefix, ..self }
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            url: "redis://localhost:6379".to_string(),
+            key_prefix: key_prefix_default(),
+        }
+    }
+}
+
+fn key_prefix_default() -> String {
+    "snapshots".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eventsourced::convert;
+    use testcontainers::clients::Cli;
+    use testcontainers_modules::redis::Redis;
+
+    #[tokio::test]
+    async fn test_snapshot_store() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let container = client.run(Redis);
+        let url = format!("redis://localhost:{}", container.get_host_port_ipv4(6379));
+
+        let config = Config::default().with_url(url);
+        let snapshot_store = RedisSnapshotStore::new(config).await?;
+
+        let id = Uuid::now_v7();
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        let seq_no = 42.try_into().unwrap();
+        let state = 666;
+
+        snapshot_store
+            .save(id, seq_no, 1, state, &convert::prost::to_bytes)
+            .await?;
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+
+        assert!(snapshot.is_some());
+        let snapshot = snapshot.unwrap();
+        assert_eq!(snapshot.seq_no, seq_no);
+        assert_eq!(snapshot.state, state);
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 2, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        snapshot_store.delete_before(id, seq_no).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_some());
+
+        snapshot_store.delete_before(id, seq_no.succ()).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        Ok(())
+    }
+}
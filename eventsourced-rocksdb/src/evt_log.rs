@@ -0,0 +1,697 @@
+//! An [EvtLog] implementation based on [RocksDB](https://rocksdb.org/).
+
+use crate::{open, Error};
+use async_stream::stream;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use eventsourced::{Clock, EvtEnvelope, EvtLog, GlobalSeqNo, SeqNo, SystemClock};
+use futures::Stream;
+use prost::Message;
+use rocksdb::{ColumnFamily, Direction, IteratorMode, WriteBatch, DB};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{sync::Mutex, time::sleep};
+use tracing::debug;
+use uuid::Uuid;
+
+/// Column family for the events, keyed by entity ID and sequence number.
+const EVTS: &str = "evts";
+
+/// Column family for the keys of the events, keyed by global sequence number.
+const GLOBAL: &str = "global";
+
+/// Column family for the entity IDs marked as deleted, keyed by entity ID.
+const DELETED: &str = "deleted";
+
+/// An [EvtLog] implementation based on [RocksDB](https://rocksdb.org/).
+///
+/// Events are keyed by the bytes of the entity ID followed by the big-endian bytes of the sequence
+/// number, hence the events of an entity can be read via a prefix range scan in sequence number
+/// order. Additionally the keys of the events are stored keyed by the big-endian bytes of the
+/// global sequence number.
+///
+/// As RocksDB only allows a single process to open a database, the [RocksDbEvtLog] must not be
+/// created more than once for the same path; clone it instead. Persisting events is serialized
+/// among all clones: the last sequence number is read before writing the event in a batch.
+#[derive(Clone)]
+pub struct RocksDbEvtLog {
+    poll_interval: Duration,
+    read_batch_size: NonZeroUsize,
+    db: Arc<DB>,
+    last_global_seq_no: Arc<Mutex<u64>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl RocksDbEvtLog {
+    #[allow(missing_docs)]
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating RocksDbEvtLog");
+
+        let db = open(&config.path, &[EVTS, GLOBAL, DELETED])?;
+        let last_global_seq_no = db
+            .iterator_cf(global_cf(&db), IteratorMode::End)
+            .next()
+            .transpose()
+            .map_err(|error| Error::RocksDb("cannot get last global seq_no".to_string(), error))?
+            .map(|(key, _)| global_seq_no(&key))
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self {
+            clock: Arc::new(SystemClock),
+            poll_interval: config.poll_interval,
+            read_batch_size: config.read_batch_size,
+            db: Arc::new(db),
+            last_global_seq_no: Arc::new(Mutex::new(last_global_seq_no)),
+        })
+    }
+
+    /// Use the given [Clock] instead of the [SystemClock] to timestamp persisted events.
+    pub fn with_clock(self, clock: impl Clock) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    /// Read the events for the given entity ID or, if none, all events, starting with the given
+    /// (global) sequence number, in batches and keep polling for new events.
+    fn evts_stream<E, F, FromBytes, FromBytesError>(
+        &self,
+        id: Option<Uuid>,
+        from: u64,
+        filter: F,
+        from_bytes: FromBytes,
+    ) -> impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send
+    where
+        E: Send,
+        F: Fn(&EvtEnvelope<Bytes>) -> bool + Send + 'static,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let db = self.db.clone();
+        let poll_interval = self.poll_interval;
+        let read_batch_size = self.read_batch_size.get();
+        let mut from = from;
+
+        stream! {
+            'outer: loop {
+                let evts = match id {
+                    Some(id) => read_evts_by_id(&db, id, from, read_batch_size),
+                    None => read_evts(&db, from, read_batch_size),
+                };
+                let evts = match evts {
+                    Ok(evts) => evts,
+
+                    Err(error) => {
+                        yield Err(error);
+                        break 'outer;
+                    }
+                };
+
+                let n = evts.len();
+                for evt in evts {
+                    from = match id {
+                        Some(_) => evt.seq_no.as_u64() + 1,
+                        None => evt.global_seq_no.as_u64() + 1,
+                    };
+
+                    if filter(&evt) {
+                        let evt = from_bytes(evt.evt.clone())
+                            .map_err(|error| Error::FromBytes(Box::new(error)))
+                            .map(|payload| EvtEnvelope {
+                                id: evt.id,
+                                entity_type: evt.entity_type,
+                                seq_no: evt.seq_no,
+                                global_seq_no: evt.global_seq_no,
+                                version: evt.version,
+                                timestamp: evt.timestamp,
+                                tags: evt.tags,
+                                evt: payload,
+                            });
+                        let is_err = evt.is_err();
+                        yield evt;
+                        if is_err {
+                            break 'outer;
+                        }
+                    }
+                }
+
+                // Only sleep if all current events have been read.
+                if n < read_batch_size {
+                    sleep(poll_interval).await;
+                }
+            }
+        }
+    }
+}
+
+impl Debug for RocksDbEvtLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RocksDbEvtLog")
+            .field("path", &self.db.path())
+            .finish()
+    }
+}
+
+impl EvtLog for RocksDbEvtLog {
+    type Error = Error;
+
+    async fn persist<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Uuid,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> Result<SeqNo, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, "persisting event");
+
+        let bytes = to_bytes(evt).map_err(|error| Error::ToBytes(Box::new(error)))?;
+
+        let mut last_global_seq_no = self.last_global_seq_no.lock().await;
+
+        let actual = read_last_seq_no(&self.db, id)?;
+        if actual != last_seq_no {
+            return Err(Error::SeqNoConflict {
+                expected: last_seq_no,
+                actual,
+            });
+        }
+
+        let seq_no = last_seq_no
+            .map(|seq_no| seq_no.succ())
+            .unwrap_or(SeqNo::MIN);
+        let global_seq_no = *last_global_seq_no + 1;
+        let key = evt_key(id, seq_no.as_u64());
+        let value = proto::Evt {
+            global_seq_no,
+            version,
+            timestamp: self.clock.now().to_rfc3339(),
+            tags: tags.to_vec(),
+            evt: bytes,
+            entity_type: entity_type.map(ToOwned::to_owned),
+        };
+
+        let mut batch = WriteBatch::default();
+        batch.put_cf(evts_cf(&self.db), key, value.encode_to_vec());
+        batch.put_cf(global_cf(&self.db), global_seq_no.to_be_bytes(), key);
+        self.db
+            .write(batch)
+            .map_err(|error| Error::RocksDb("cannot persist event".to_string(), error))?;
+        *last_global_seq_no = global_seq_no;
+
+        Ok(seq_no)
+    }
+
+    async fn delete_to(&self, id: Uuid, to_seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %to_seq_no, "deleting events");
+
+        // Always keep the last event.
+        let Some(last_seq_no) = read_last_seq_no(&self.db, id)? else {
+            return Ok(());
+        };
+        let to_seq_no = to_seq_no.as_u64().min(last_seq_no.as_u64() - 1);
+
+        let mut batch = WriteBatch::default();
+        let from = evt_key(id, SeqNo::MIN.as_u64());
+        let evts = self.db.iterator_cf(
+            evts_cf(&self.db),
+            IteratorMode::From(&from, Direction::Forward),
+        );
+        for evt in evts {
+            let (key, value) =
+                evt.map_err(|error| Error::RocksDb("cannot read event".to_string(), error))?;
+            if !key.starts_with(id.as_bytes()) || key_seq_no(&key)? > to_seq_no {
+                break;
+            }
+
+            let evt = proto::Evt::decode(value.as_ref())?;
+            batch.delete_cf(global_cf(&self.db), evt.global_seq_no.to_be_bytes());
+            batch.delete_cf(evts_cf(&self.db), key);
+        }
+
+        self.db
+            .write(batch)
+            .map_err(|error| Error::RocksDb("cannot delete events".to_string(), error))
+    }
+
+    async fn mark_deleted(&self, id: Uuid) -> Result<(), Self::Error> {
+        debug!(%id, "marking entity as deleted");
+
+        let timestamp = self.clock.now().to_rfc3339();
+        self.db
+            .put_cf(deleted_cf(&self.db), id.as_bytes(), timestamp)
+            .map_err(|error| Error::RocksDb("cannot mark entity as deleted".to_string(), error))
+    }
+
+    async fn is_deleted(&self, id: Uuid) -> Result<bool, Self::Error> {
+        debug!(%id, "checking whether entity is marked as deleted");
+
+        self.db
+            .get_pinned_cf(deleted_cf(&self.db), id.as_bytes())
+            .map(|marker| marker.is_some())
+            .map_err(|error| Error::RocksDb("cannot get deleted marker".to_string(), error))
+    }
+
+    async fn last_seq_no(&self, id: Uuid) -> Result<Option<SeqNo>, Self::Error> {
+        debug!(%id, "getting last seq_no");
+        read_last_seq_no(&self.db, id)
+    }
+
+    async fn evts_by_id<E, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %from_seq_no, "building events by ID stream");
+        Ok(self.evts_stream(Some(id), from_seq_no.as_u64(), |_| true, from_bytes))
+    }
+
+    async fn evts_by_ids<E, FromBytes, FromBytesError>(
+        &self,
+        ids: Vec<Uuid>,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(?ids, %from_global_seq_no, "building events by IDs stream");
+        Ok(self.evts_stream(
+            None,
+            from_global_seq_no.as_u64(),
+            move |evt| ids.contains(&evt.id),
+            from_bytes,
+        ))
+    }
+
+    async fn evts<E, FromBytes, FromBytesError>(
+        &self,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%from_global_seq_no, "building events stream");
+        Ok(self.evts_stream(None, from_global_seq_no.as_u64(), |_| true, from_bytes))
+    }
+
+    async fn evts_by_type<E, FromBytes, FromBytesError>(
+        &self,
+        entity_type: String,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(entity_type, %from_global_seq_no, "building events by type stream");
+        Ok(self.evts_stream(
+            None,
+            from_global_seq_no.as_u64(),
+            move |evt| evt.entity_type.as_deref() == Some(entity_type.as_str()),
+            from_bytes,
+        ))
+    }
+
+    /// The given sequence number is used as global sequence number, like for the NATS
+    /// implementation.
+    async fn evts_by_tag<E, FromBytes, FromBytesError>(
+        &self,
+        tag: String,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(tag, %from_seq_no, "building events by tag stream");
+        Ok(self.evts_stream(
+            None,
+            from_seq_no.as_u64(),
+            move |evt| evt.tags.contains(&tag),
+            from_bytes,
+        ))
+    }
+}
+
+/// Configuration for the [RocksDbEvtLog].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default = "path_default")]
+    path: PathBuf,
+
+    #[serde(default = "poll_interval_default", with = "humantime_serde")]
+    poll_interval: Duration,
+
+    #[serde(default = "read_batch_size_default")]
+    read_batch_size: NonZeroUsize,
+}
+
+impl Config {
+    /// Change the `path`.
+    pub fn with_path<T>(self, path: T) -> Self
+    where
+        T: Into<PathBuf>,
+    {
+        let path = path.into();
+        Self { path, ..self }
+    }
+
+    /// Change the `poll_interval`.
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            ..self
+        }
+    }
+
+    /// Change the `read_batch_size`, i.e. the maximum number of events read at once.
+    pub fn with_read_batch_size(self, read_batch_size: NonZeroUsize) -> Self {
+        Self {
+            read_batch_size,
+            ..self
+        }
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            path: path_default(),
+            poll_interval: poll_interval_default(),
+            read_batch_size: read_batch_size_default(),
+        }
+    }
+}
+
+fn evts_cf(db: &DB) -> &ColumnFamily {
+    db.cf_handle(EVTS).expect("evts column family exists")
+}
+
+fn global_cf(db: &DB) -> &ColumnFamily {
+    db.cf_handle(GLOBAL).expect("global column family exists")
+}
+
+fn deleted_cf(db: &DB) -> &ColumnFamily {
+    db.cf_handle(DELETED).expect("deleted column family exists")
+}
+
+/// The bytes of the entity ID followed by the big-endian bytes of the sequence number.
+fn evt_key(id: Uuid, seq_no: u64) -> [u8; 24] {
+    let mut key = [0; 24];
+    key[..16].copy_from_slice(id.as_bytes());
+    key[16..].copy_from_slice(&seq_no.to_be_bytes());
+    key
+}
+
+fn key_seq_no(key: &[u8]) -> Result<u64, Error> {
+    key.get(16..)
+        .and_then(|seq_no| seq_no.try_into().ok())
+        .map(u64::from_be_bytes)
+        .ok_or(Error::InvalidKey)
+}
+
+fn global_seq_no(key: &[u8]) -> Result<u64, Error> {
+    key.try_into()
+        .map(u64::from_be_bytes)
+        .map_err(|_| Error::InvalidKey)
+}
+
+fn read_last_seq_no(db: &DB, id: Uuid) -> Result<Option<SeqNo>, Error> {
+    let from = evt_key(id, u64::MAX);
+    db.iterator_cf(evts_cf(db), IteratorMode::From(&from, Direction::Reverse))
+        .next()
+        .transpose()
+        .map_err(|error| Error::RocksDb("cannot get last seq_no".to_string(), error))?
+        .filter(|(key, _)| key.starts_with(id.as_bytes()))
+        .map(|(key, _)| {
+            key_seq_no(&key).and_then(|seq_no| seq_no.try_into().map_err(|_| Error::ZeroSeqNo))
+        })
+        .transpose()
+}
+
+/// Read at most `limit` events for the given entity ID, starting with the given sequence number.
+fn read_evts_by_id(
+    db: &DB,
+    id: Uuid,
+    from: u64,
+    limit: usize,
+) -> Result<Vec<EvtEnvelope<Bytes>>, Error> {
+    let from = evt_key(id, from);
+    let mut evts = vec![];
+
+    for evt in db.iterator_cf(evts_cf(db), IteratorMode::From(&from, Direction::Forward)) {
+        let (key, value) =
+            evt.map_err(|error| Error::RocksDb("cannot read event".to_string(), error))?;
+        if !key.starts_with(id.as_bytes()) || evts.len() == limit {
+            break;
+        }
+        evts.push(evt_envelope(&key, &value)?);
+    }
+
+    Ok(evts)
+}
+
+/// Read at most `limit` events, starting with the given global sequence number.
+fn read_evts(db: &DB, from: u64, limit: usize) -> Result<Vec<EvtEnvelope<Bytes>>, Error> {
+    let from = from.to_be_bytes();
+    let mut evts = vec![];
+
+    for entry in db
+        .iterator_cf(global_cf(db), IteratorMode::From(&from, Direction::Forward))
+        .take(limit)
+    {
+        let (_, key) =
+            entry.map_err(|error| Error::RocksDb("cannot read event key".to_string(), error))?;
+        let value = db
+            .get_cf(evts_cf(db), &key)
+            .map_err(|error| Error::RocksDb("cannot read event".to_string(), error))?;
+
+        // The event might have been deleted concurrently.
+        if let Some(value) = value {
+            evts.push(evt_envelope(&key, &value)?);
+        }
+    }
+
+    Ok(evts)
+}
+
+fn evt_envelope(key: &[u8], value: &[u8]) -> Result<EvtEnvelope<Bytes>, Error> {
+    let id = key
+        .get(..16)
+        .and_then(|id| Uuid::from_slice(id).ok())
+        .ok_or(Error::InvalidKey)?;
+    let seq_no = key_seq_no(key)?.try_into().map_err(|_| Error::ZeroSeqNo)?;
+
+    let proto::Evt {
+        global_seq_no,
+        version,
+        timestamp,
+        tags,
+        evt,
+        entity_type,
+    } = proto::Evt::decode(value)?;
+    let global_seq_no = global_seq_no.try_into().map_err(|_| Error::ZeroSeqNo)?;
+    let timestamp = DateTime::parse_from_rfc3339(&timestamp)
+        .map_err(Error::InvalidTimestamp)?
+        .with_timezone(&Utc);
+
+    Ok(EvtEnvelope {
+        id,
+        entity_type,
+        seq_no,
+        global_seq_no,
+        version,
+        timestamp,
+        tags,
+        evt,
+    })
+}
+
+fn path_default() -> PathBuf {
+    PathBuf::from("evts")
+}
+
+const fn poll_interval_default() -> Duration {
+    Duration::from_secs(2)
+}
+
+const fn read_batch_size_default() -> NonZeroUsize {
+    unsafe { NonZeroUsize::new_unchecked(1_000) }
+}
+
+mod proto {
+    include!(concat!(env!("OUT_DIR"), "/evt_log.rs"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eventsourced::convert;
+    use futures::{StreamExt, TryStreamExt};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_evt_log() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let dir = tempdir()?;
+
+        let config = Config::default()
+            .with_path(dir.path())
+            .with_read_batch_size(2.try_into()?);
+        let evt_log = RocksDbEvtLog::new(config.clone()).await?;
+
+        let id = Uuid::now_v7();
+
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, None);
+
+        let last_seq_no = evt_log
+            .persist(
+                &1,
+                1,
+                &["tag".to_string()],
+                id,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+        assert_eq!(last_seq_no, SeqNo::MIN);
+
+        let result = evt_log
+            .persist(&2, 1, &[], id, None, None, &convert::prost::to_bytes)
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::SeqNoConflict {
+                expected: None,
+                actual: Some(_)
+            })
+        ));
+
+        let mut last_seq_no = Some(last_seq_no);
+        for n in 2..=5 {
+            let seq_no = evt_log
+                .persist(&n, 1, &[], id, None, last_seq_no, &convert::prost::to_bytes)
+                .await?;
+            last_seq_no = Some(seq_no);
+        }
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+
+        // Events are read in batches of two.
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(5)
+            .map_ok(|evt| (evt.seq_no.as_u64(), evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(1, 1), (2, 2), (3, 3), (4, 4), (5, 5)]);
+
+        let id_2 = Uuid::now_v7();
+        evt_log
+            .persist(
+                &6,
+                2,
+                &["tag".to_string()],
+                id_2,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let evts_by_tag = evt_log
+            .evts_by_tag::<i32, _, _>("tag".to_string(), SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(2)
+            .map_ok(|evt| (evt.id, evt.version, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_tag, vec![(id, 1, 1), (id_2, 2, 6)]);
+
+        let evts_by_ids = evt_log
+            .evts_by_ids::<i32, _, _>(vec![id_2], GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(1)
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_ids, vec![6]);
+
+        evt_log.delete_to(id, 10.try_into()?).await?;
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+        let evts = evt_log
+            .evts::<i32, _, _>(GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(2)
+            .map_ok(|evt| (evt.global_seq_no.as_u64(), evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(5, 5), (6, 6)]);
+
+        // Events and global sequence numbers survive reopening the database.
+        drop(evt_log);
+        let evt_log = RocksDbEvtLog::new(config).await?;
+        assert_eq!(evt_log.last_seq_no(id_2).await?, Some(SeqNo::MIN));
+        evt_log
+            .persist(
+                &7,
+                1,
+                &[],
+                id_2,
+                None,
+                Some(SeqNo::MIN),
+                &convert::prost::to_bytes,
+            )
+            .await?;
+        let evts = evt_log
+            .evts_by_ids::<i32, _, _>(vec![id_2], GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(2)
+            .map_ok(|evt| (evt.global_seq_no.as_u64(), evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(6, 6), (7, 7)]);
+
+        assert!(!evt_log.is_deleted(id).await?);
+        evt_log.mark_deleted(id).await?;
+        evt_log.mark_deleted(id).await?;
+        assert!(evt_log.is_deleted(id).await?);
+        assert!(!evt_log.is_deleted(id_2).await?);
+
+        Ok(())
+    }
+}
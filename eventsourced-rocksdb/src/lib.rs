@@ -0,0 +1,66 @@
+//! [EvtLog](eventsourced::EvtLog) and [SnapshotStore](eventsourced::SnapshotStore) implementations
+//! based upon [RocksDB](https://rocksdb.org/), i.e. embedded and persistent without any server, e.g.
+//! for single-node or edge deployments and for local development.
+
+mod evt_log;
+mod snapshot_store;
+
+pub use evt_log::{Config as RocksDbEvtLogConfig, RocksDbEvtLog};
+pub use snapshot_store::{Config as RocksDbSnapshotStoreConfig, RocksDbSnapshotStore};
+
+use eventsourced::SeqNo;
+use prost::DecodeError;
+use rocksdb::{Options, DB};
+use std::path::Path;
+use thiserror::Error;
+
+/// Errors from the [RocksDbEvtLog] or [RocksDbSnapshotStore].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// RocksDB error.
+    #[error("RocksDB error: {0}")]
+    RocksDb(String, #[source] rocksdb::Error),
+
+    /// Cannot convert an event to bytes.
+    #[error("cannot convert an event to bytes")]
+    ToBytes(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Cannot convert bytes to an event.
+    #[error("cannot convert bytes to an event")]
+    FromBytes(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Stored value cannot be decoded from Protocol Buffers.
+    #[error("cannot decode value from Protocol Buffers")]
+    Decode(#[from] DecodeError),
+
+    /// Sequence number must not be zero.
+    #[error("sequence number must not be zero")]
+    ZeroSeqNo,
+
+    /// The given last sequence number does not match the actual one, e.g. because of a concurrent
+    /// writer for the same entity ID.
+    #[error("expected last sequence number {expected:?}, but was {actual:?}")]
+    SeqNoConflict {
+        expected: Option<SeqNo>,
+        actual: Option<SeqNo>,
+    },
+
+    /// Invalid key, e.g. with an unexpected length.
+    #[error("invalid key")]
+    InvalidKey,
+
+    /// Invalid timestamp.
+    #[error("invalid timestamp")]
+    InvalidTimestamp(#[source] chrono::ParseError),
+}
+
+/// Open the database at the given path with the given column families, creating missing ones.
+fn open(path: &Path, cfs: &[&str]) -> Result<DB, Error> {
+    let mut options = Options::default();
+    options.create_if_missing(true);
+    options.create_missing_column_families(true);
+
+    DB::open_cf(&options, path, cfs).map_err(|error| {
+        Error::RocksDb(format!("cannot open database at {}", path.display()), error)
+    })
+}
@@ -0,0 +1,218 @@
+//! A [SnapshotStore] implementation based on [RocksDB](https://rocksdb.org/).
+
+use crate::{open, Error};
+use bytes::Bytes;
+use eventsourced::{SeqNo, Snapshot, SnapshotStore};
+use prost::Message;
+use rocksdb::DB;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+    path::PathBuf,
+    sync::Arc,
+};
+use tracing::debug;
+use uuid::Uuid;
+
+/// A [SnapshotStore] implementation based on [RocksDB](https://rocksdb.org/). Snapshots are keyed
+/// by the bytes of the entity ID, hence only the last saved snapshot per entity ID is kept.
+///
+/// As RocksDB only allows a single process to open a database, the [RocksDbSnapshotStore] must not
+/// be created more than once for the same path – which must also differ from the one of the
+/// [RocksDbEvtLog](crate::RocksDbEvtLog); clone it instead.
+#[derive(Clone)]
+pub struct RocksDbSnapshotStore {
+    db: Arc<DB>,
+}
+
+impl RocksDbSnapshotStore {
+    #[allow(missing_docs)]
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating RocksDbSnapshotStore");
+
+        let db = open(&config.path, &[])?;
+
+        Ok(Self { db: Arc::new(db) })
+    }
+}
+
+impl Debug for RocksDbSnapshotStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RocksDbSnapshotStore")
+            .field("path", &self.db.path())
+            .finish()
+    }
+}
+
+impl SnapshotStore for RocksDbSnapshotStore {
+    type Error = Error;
+
+    async fn save<S, ToBytes, ToBytesError>(
+        &self,
+        id: Uuid,
+        seq_no: SeqNo,
+        state_version: u32,
+        state: S,
+        to_bytes: &ToBytes,
+    ) -> Result<(), Self::Error>
+    where
+        S: Send,
+        ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %seq_no, state_version, "saving snapshot");
+
+        let state = to_bytes(&state).map_err(|source| Error::ToBytes(Box::new(source)))?;
+        let snapshot = proto::Snapshot {
+            seq_no: seq_no.as_u64(),
+            state,
+            state_version,
+        };
+        self.db
+            .put(id.as_bytes(), snapshot.encode_to_vec())
+            .map_err(|error| Error::RocksDb("cannot save snapshot".to_string(), error))
+    }
+
+    async fn load<S, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> Result<Option<Snapshot<S>>, Self::Error>
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, state_version, "loading snapshot");
+
+        read_snapshot(&self.db, id)?
+            .filter(|snapshot| snapshot.state_version() == state_version)
+            .map(|proto::Snapshot { seq_no, state, .. }| {
+                let seq_no = seq_no.try_into().map_err(|_| Error::ZeroSeqNo)?;
+                from_bytes(state)
+                    .map_err(|source| Error::FromBytes(Box::new(source)))
+                    .map(|state| Snapshot::new(seq_no, state))
+            })
+            .transpose()
+    }
+
+    async fn delete_before(&self, id: Uuid, seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %seq_no, "deleting snapshot");
+
+        match read_snapshot(&self.db, id)? {
+            Some(snapshot) if snapshot.seq_no < seq_no.as_u64() => self
+                .db
+                .delete(id.as_bytes())
+                .map_err(|error| Error::RocksDb("cannot delete snapshot".to_string(), error)),
+
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Configuration for the [RocksDbSnapshotStore].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default = "path_default")]
+    path: PathBuf,
+}
+
+impl Config {
+    /// Change the `path`.
+    pub fn with_path<T>(self, path: T) -> Self
+    where
+        T: Into<PathBuf>,
+    {
+        let path = path.into();
+        Self { path }
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            path: path_default(),
+        }
+    }
+}
+
+fn read_snapshot(db: &DB, id: Uuid) -> Result<Option<proto::Snapshot>, Error> {
+    db.get(id.as_bytes())
+        .map_err(|error| Error::RocksDb("cannot load snapshot".to_string(), error))?
+        .map(|bytes| proto::Snapshot::decode(bytes.as_slice()).map_err(Error::Decode))
+        .transpose()
+}
+
+fn path_default() -> PathBuf {
+    PathBuf::from("snapshots")
+}
+
+mod proto {
+    include!(concat!(env!("OUT_DIR"), "/snapshot_store.rs"));
+
+    impl Snapshot {
+        /// The version of the state, treating snapshots saved before it was added like version 1.
+        pub fn state_version(&self) -> u32 {
+            self.state_version.max(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eventsourced::convert;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_snapshot_store() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let dir = tempdir()?;
+
+        let config = Config::default().with_path(dir.path());
+        let snapshot_store = RocksDbSnapshotStore::new(config).await?;
+
+        let id = Uuid::now_v7();
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        let seq_no = 42.try_into().unwrap();
+        let state = 666;
+
+        snapshot_store
+            .save(id, seq_no, 1, state, &convert::prost::to_bytes)
+            .await?;
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+
+        assert!(snapshot.is_some());
+        let snapshot = snapshot.unwrap();
+        assert_eq!(snapshot.seq_no, seq_no);
+        assert_eq!(snapshot.state, state);
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 2, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        snapshot_store.delete_before(id, seq_no).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_some());
+
+        snapshot_store.delete_before(id, seq_no.succ()).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,924 @@
+//! An [EvtLog] implementation based on [ScyllaDB](https://www.scylladb.com/).
+
+use crate::{applied, query_unpaged, seq_no, session, Error};
+use async_stream::stream;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use eventsourced::{Clock, EvtEnvelope, EvtLog, GlobalSeqNo, SeqNo, SystemClock};
+use futures::{Stream, StreamExt};
+use scylla::{
+    frame::value::CqlTimestamp, prepared_statement::PreparedStatement,
+    serialize::row::SerializeRow, Session,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+    num::{NonZeroU64, NonZeroUsize},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::sleep;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Name of the single row of the global sequence number table.
+const GLOBAL_SEQ_NO: &str = "evts";
+
+/// Single partition of the global events table.
+const GLOBAL_BUCKET: i32 = 0;
+
+type Row = (
+    Uuid,
+    i64,
+    i64,
+    i32,
+    CqlTimestamp,
+    Option<Vec<String>>,
+    Vec<u8>,
+    Option<String>,
+);
+
+/// An [EvtLog] implementation based on [ScyllaDB](https://www.scylladb.com/), which also works
+/// with [Apache Cassandra](https://cassandra.apache.org/).
+///
+/// Events are stored in the `evts` table, partitioned by entity ID and clustered by sequence
+/// number, using a lightweight transaction on the static `last_seq_no` column to detect concurrent
+/// writers. The global sequence number is allocated via a lightweight transaction, too, and a copy
+/// of each event is stored in the `global_evts` table, clustered by global sequence number within
+/// a single partition. As the latter happens after the event has been persisted, it is not atomic
+/// and concurrently persisted events might become visible out of global order, hence
+/// [EvtLog::evts], [EvtLog::evts_by_ids], [EvtLog::evts_by_type] and [EvtLog::evts_by_tag] should
+/// only be used with a single writer. Entity IDs marked as deleted are stored in the `deleted`
+/// table.
+#[derive(Clone)]
+pub struct ScyllaEvtLog {
+    keyspace: String,
+    poll_interval: Duration,
+    session: Arc<Session>,
+    persist: PreparedStatement,
+    insert_global_evt: PreparedStatement,
+    last_seq_no: PreparedStatement,
+    select_global_seq_no: PreparedStatement,
+    insert_global_seq_no: PreparedStatement,
+    update_global_seq_no: PreparedStatement,
+    select_global_seq_nos: PreparedStatement,
+    delete_to: PreparedStatement,
+    delete_global_evt: PreparedStatement,
+    mark_deleted: PreparedStatement,
+    is_deleted: PreparedStatement,
+    evts_by_id: PreparedStatement,
+    evts: PreparedStatement,
+    clock: Arc<dyn Clock>,
+}
+
+impl ScyllaEvtLog {
+    #[allow(missing_docs)]
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating ScyllaEvtLog");
+
+        let session = session(
+            &config.nodes,
+            &config.keyspace,
+            config.replication_factor,
+            config.setup,
+        )
+        .await?;
+        let keyspace = config.keyspace;
+
+        if config.setup {
+            setup(&session, &keyspace).await?;
+        }
+
+        let page_size = i32::try_from(config.read_batch_size.get()).unwrap_or(i32::MAX);
+        let prepare = |query: String| {
+            let session = session.clone();
+            async move {
+                session.prepare(query).await.map_err(|error| {
+                    Error::Scylla("cannot prepare query".to_string(), Box::new(error))
+                })
+            }
+        };
+
+        let persist = prepare(format!(
+            "UPDATE {keyspace}.evts \
+             SET last_seq_no = ?, global_seq_no = ?, version = ?, timestamp = ?, tags = ?, evt = ?, \
+             entity_type = ? \
+             WHERE id = ? AND seq_no = ? \
+             IF last_seq_no = ?"
+        ))
+        .await?;
+        let insert_global_evt = prepare(format!(
+            "INSERT INTO {keyspace}.global_evts \
+             (bucket, global_seq_no, id, seq_no, version, timestamp, tags, evt, entity_type) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .await?;
+        let last_seq_no = prepare(format!(
+            "SELECT last_seq_no FROM {keyspace}.evts WHERE id = ? LIMIT 1"
+        ))
+        .await?;
+        let select_global_seq_no = prepare(format!(
+            "SELECT value FROM {keyspace}.global_seq_no WHERE name = ?"
+        ))
+        .await?;
+        let insert_global_seq_no = prepare(format!(
+            "INSERT INTO {keyspace}.global_seq_no (name, value) VALUES (?, ?) IF NOT EXISTS"
+        ))
+        .await?;
+        let update_global_seq_no = prepare(format!(
+            "UPDATE {keyspace}.global_seq_no SET value = ? WHERE name = ? IF value = ?"
+        ))
+        .await?;
+        let select_global_seq_nos = prepare(format!(
+            "SELECT global_seq_no FROM {keyspace}.evts WHERE id = ? AND seq_no <= ?"
+        ))
+        .await?;
+        let delete_to = prepare(format!(
+            "DELETE FROM {keyspace}.evts WHERE id = ? AND seq_no <= ?"
+        ))
+        .await?;
+        let delete_global_evt = prepare(format!(
+            "DELETE FROM {keyspace}.global_evts WHERE bucket = ? AND global_seq_no = ?"
+        ))
+        .await?;
+        let mark_deleted =
+            prepare(format!("INSERT INTO {keyspace}.deleted (id) VALUES (?)")).await?;
+        let is_deleted = prepare(format!("SELECT id FROM {keyspace}.deleted WHERE id = ?")).await?;
+        let mut evts_by_id = prepare(format!(
+            "SELECT id, seq_no, global_seq_no, version, timestamp, tags, evt, entity_type \
+             FROM {keyspace}.evts WHERE id = ? AND seq_no >= ?"
+        ))
+        .await?;
+        evts_by_id.set_page_size(page_size);
+        let mut evts = prepare(format!(
+            "SELECT id, seq_no, global_seq_no, version, timestamp, tags, evt, entity_type \
+             FROM {keyspace}.global_evts WHERE bucket = ? AND global_seq_no >= ?"
+        ))
+        .await?;
+        evts.set_page_size(page_size);
+
+        Ok(Self {
+            clock: Arc::new(SystemClock),
+            keyspace,
+            poll_interval: config.poll_interval,
+            session,
+            persist,
+            insert_global_evt,
+            last_seq_no,
+            select_global_seq_no,
+            insert_global_seq_no,
+            update_global_seq_no,
+            select_global_seq_nos,
+            delete_to,
+            delete_global_evt,
+            mark_deleted,
+            is_deleted,
+            evts_by_id,
+            evts,
+        })
+    }
+
+    /// Use the given [Clock] instead of the [SystemClock] to timestamp persisted events.
+    pub fn with_clock(self, clock: impl Clock) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    /// Allocate the next global sequence number via a lightweight transaction, retrying on
+    /// contention.
+    async fn next_global_seq_no(&self) -> Result<i64, Error> {
+        loop {
+            let current = self
+                .session
+                .execute(&self.select_global_seq_no, (GLOBAL_SEQ_NO,))
+                .await
+                .map_err(|error| {
+                    Error::Scylla("cannot get global seq_no".to_string(), Box::new(error))
+                })?
+                .maybe_first_row_typed::<(i64,)>()
+                .map_err(|error| {
+                    Error::Scylla("cannot get global seq_no".to_string(), Box::new(error))
+                })?
+                .map(|(value,)| value);
+
+            let next = current.map(|current| current + 1).unwrap_or(1);
+            let result = match current {
+                Some(current) => {
+                    self.session
+                        .execute(&self.update_global_seq_no, (next, GLOBAL_SEQ_NO, current))
+                        .await
+                }
+                None => {
+                    self.session
+                        .execute(&self.insert_global_seq_no, (GLOBAL_SEQ_NO, next))
+                        .await
+                }
+            }
+            .map_err(|error| {
+                Error::Scylla("cannot update global seq_no".to_string(), Box::new(error))
+            })?;
+
+            if applied(result)? {
+                return Ok(next);
+            }
+        }
+    }
+
+    /// Read the rows for the given prepared statement, binding the given partition key and
+    /// starting with the given clustering key, page by page and keep polling for new rows.
+    fn rows<K, E, F, FromBytes, FromBytesError>(
+        &self,
+        statement: PreparedStatement,
+        key: K,
+        from: i64,
+        to_from: fn(&EvtEnvelope<Bytes>) -> u64,
+        filter: F,
+        from_bytes: FromBytes,
+    ) -> impl Stream<Item = Result<EvtEnvelope<E>, Error>> + Send
+    where
+        K: Copy + Send + Sync + 'static,
+        (K, i64): SerializeRow,
+        E: Send,
+        F: Fn(&EvtEnvelope<Bytes>) -> bool + Send + 'static,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let session = self.session.clone();
+        let poll_interval = self.poll_interval;
+        let mut from = from;
+
+        stream! {
+            'outer: loop {
+                let rows = session
+                    .execute_iter(statement.clone(), (key, from))
+                    .await
+                    .map_err(|error| Error::Scylla("cannot read events".to_string(), Box::new(error)));
+                let mut rows = match rows {
+                    Ok(rows) => rows.into_typed::<Row>(),
+
+                    Err(error) => {
+                        yield Err(error);
+                        break 'outer;
+                    }
+                };
+
+                while let Some(row) = rows.next().await {
+                    let evt = row
+                        .map_err(|error| {
+                            Error::Scylla("cannot read events".to_string(), Box::new(error))
+                        })
+                        .and_then(evt_envelope);
+
+                    match evt {
+                        Ok(evt) => {
+                            from = to_from(&evt) as i64 + 1;
+
+                            if filter(&evt) {
+                                let evt = from_bytes(evt.evt.clone())
+                                    .map_err(|error| Error::FromBytes(Box::new(error)))
+                                    .map(|payload| EvtEnvelope {
+                                        id: evt.id,
+                                        entity_type: evt.entity_type,
+                                        seq_no: evt.seq_no,
+                                        global_seq_no: evt.global_seq_no,
+                                        version: evt.version,
+                                        timestamp: evt.timestamp,
+                                        tags: evt.tags,
+                                        evt: payload,
+                                    });
+                                let is_err = evt.is_err();
+                                yield evt;
+                                if is_err {
+                                    break 'outer;
+                                }
+                            }
+                        }
+
+                        Err(error) => {
+                            yield Err(error);
+                            break 'outer;
+                        }
+                    }
+                }
+
+                // All current rows have been read.
+                sleep(poll_interval).await;
+            }
+        }
+    }
+}
+
+impl Debug for ScyllaEvtLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScyllaEvtLog")
+            .field("keyspace", &self.keyspace)
+            .finish()
+    }
+}
+
+impl EvtLog for ScyllaEvtLog {
+    type Error = Error;
+
+    /// The maximum value for sequence numbers. As ScyllaDB uses signed 64 bit integers, this is
+    /// `i64::MAX` or `9_223_372_036_854_775_807`.
+    const MAX_SEQ_NO: SeqNo = SeqNo::new(unsafe { NonZeroU64::new_unchecked(i64::MAX as u64) });
+
+    async fn persist<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Uuid,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> Result<SeqNo, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, "persisting event");
+
+        let bytes = to_bytes(evt).map_err(|error| Error::ToBytes(Box::new(error)))?;
+        let global_seq_no = self.next_global_seq_no().await?;
+        let expected = last_seq_no.map(|seq_no| seq_no.as_u64() as i64);
+        let seq_no = expected.unwrap_or_default() + 1;
+        let version = version as i32;
+        let timestamp = CqlTimestamp(self.clock.now().timestamp_millis());
+        let tags = tags.to_vec();
+        let bytes = bytes.to_vec();
+
+        let result = self
+            .session
+            .execute(
+                &self.persist,
+                (
+                    seq_no,
+                    global_seq_no,
+                    version,
+                    timestamp,
+                    &tags,
+                    &bytes,
+                    entity_type,
+                    id,
+                    seq_no,
+                    expected,
+                ),
+            )
+            .await
+            .map_err(|error| Error::Scylla("cannot persist event".to_string(), Box::new(error)))?;
+
+        if !applied(result)? {
+            return Err(Error::SeqNoConflict {
+                expected: last_seq_no,
+                actual: self.last_seq_no(id).await?,
+            });
+        }
+
+        self.session
+            .execute(
+                &self.insert_global_evt,
+                (
+                    GLOBAL_BUCKET,
+                    global_seq_no,
+                    id,
+                    seq_no,
+                    version,
+                    timestamp,
+                    &tags,
+                    &bytes,
+                    entity_type,
+                ),
+            )
+            .await
+            .map_err(|error| {
+                Error::Scylla("cannot persist global event".to_string(), Box::new(error))
+            })?;
+
+        crate::seq_no(seq_no)
+    }
+
+    async fn delete_to(&self, id: Uuid, to_seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %to_seq_no, "deleting events");
+
+        // Always keep the last event.
+        let Some(last_seq_no) = self.last_seq_no(id).await? else {
+            return Ok(());
+        };
+        let to_seq_no = (to_seq_no.as_u64() as i64).min(last_seq_no.as_u64() as i64 - 1);
+        if to_seq_no < 1 {
+            return Ok(());
+        }
+
+        let global_seq_nos = self
+            .session
+            .execute_iter(self.select_global_seq_nos.clone(), (id, to_seq_no))
+            .await
+            .map_err(|error| Error::Scylla("cannot delete events".to_string(), Box::new(error)))?
+            .into_typed::<(i64,)>()
+            .map(|row| {
+                row.map(|(global_seq_no,)| global_seq_no).map_err(|error| {
+                    Error::Scylla("cannot delete events".to_string(), Box::new(error))
+                })
+            })
+            .collect::<Vec<_>>()
+            .await;
+
+        for global_seq_no in global_seq_nos {
+            self.session
+                .execute(&self.delete_global_evt, (GLOBAL_BUCKET, global_seq_no?))
+                .await
+                .map_err(|error| {
+                    Error::Scylla("cannot delete events".to_string(), Box::new(error))
+                })?;
+        }
+
+        self.session
+            .execute(&self.delete_to, (id, to_seq_no))
+            .await
+            .map(|_| ())
+            .map_err(|error| Error::Scylla("cannot delete events".to_string(), Box::new(error)))
+    }
+
+    async fn mark_deleted(&self, id: Uuid) -> Result<(), Self::Error> {
+        debug!(%id, "marking entity as deleted");
+
+        self.session
+            .execute(&self.mark_deleted, (id,))
+            .await
+            .map(|_| ())
+            .map_err(|error| {
+                Error::Scylla("cannot mark entity as deleted".to_string(), Box::new(error))
+            })
+    }
+
+    async fn is_deleted(&self, id: Uuid) -> Result<bool, Self::Error> {
+        debug!(%id, "checking whether entity is marked as deleted");
+
+        self.session
+            .execute(&self.is_deleted, (id,))
+            .await
+            .map_err(|error| {
+                Error::Scylla("cannot get deleted marker".to_string(), Box::new(error))
+            })?
+            .maybe_first_row_typed::<(Uuid,)>()
+            .map(|row| row.is_some())
+            .map_err(|error| {
+                Error::Scylla("cannot get deleted marker".to_string(), Box::new(error))
+            })
+    }
+
+    async fn last_seq_no(&self, id: Uuid) -> Result<Option<SeqNo>, Self::Error> {
+        debug!(%id, "getting last seq_no");
+
+        self.session
+            .execute(&self.last_seq_no, (id,))
+            .await
+            .map_err(|error| Error::Scylla("cannot get last seq_no".to_string(), Box::new(error)))?
+            .maybe_first_row_typed::<(Option<i64>,)>()
+            .map_err(|error| Error::Scylla("cannot get last seq_no".to_string(), Box::new(error)))?
+            .and_then(|(last_seq_no,)| last_seq_no)
+            .map(seq_no)
+            .transpose()
+    }
+
+    async fn evts_by_id<E, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %from_seq_no, "building events by ID stream");
+        Ok(self.rows(
+            self.evts_by_id.clone(),
+            id,
+            from_seq_no.as_u64() as i64,
+            |evt| evt.seq_no.as_u64(),
+            |_| true,
+            from_bytes,
+        ))
+    }
+
+    async fn evts_by_ids<E, FromBytes, FromBytesError>(
+        &self,
+        ids: Vec<Uuid>,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(?ids, %from_global_seq_no, "building events by IDs stream");
+        Ok(self.rows(
+            self.evts.clone(),
+            GLOBAL_BUCKET,
+            from_global_seq_no.as_u64() as i64,
+            |evt| evt.global_seq_no.as_u64(),
+            move |evt| ids.contains(&evt.id),
+            from_bytes,
+        ))
+    }
+
+    async fn evts<E, FromBytes, FromBytesError>(
+        &self,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%from_global_seq_no, "building events stream");
+        Ok(self.rows(
+            self.evts.clone(),
+            GLOBAL_BUCKET,
+            from_global_seq_no.as_u64() as i64,
+            |evt| evt.global_seq_no.as_u64(),
+            |_| true,
+            from_bytes,
+        ))
+    }
+
+    async fn evts_by_type<E, FromBytes, FromBytesError>(
+        &self,
+        entity_type: String,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(entity_type, %from_global_seq_no, "building events by type stream");
+        Ok(self.rows(
+            self.evts.clone(),
+            GLOBAL_BUCKET,
+            from_global_seq_no.as_u64() as i64,
+            |evt| evt.global_seq_no.as_u64(),
+            move |evt| evt.entity_type.as_deref() == Some(entity_type.as_str()),
+            from_bytes,
+        ))
+    }
+
+    /// The given sequence number is used as global sequence number, like for the NATS
+    /// implementation.
+    async fn evts_by_tag<E, FromBytes, FromBytesError>(
+        &self,
+        tag: String,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(tag, %from_seq_no, "building events by tag stream");
+        Ok(self.rows(
+            self.evts.clone(),
+            GLOBAL_BUCKET,
+            from_seq_no.as_u64() as i64,
+            |evt| evt.global_seq_no.as_u64(),
+            move |evt| evt.tags.contains(&tag),
+            from_bytes,
+        ))
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        query_unpaged(
+            &self.session,
+            "SELECT now() FROM system.local".to_string(),
+            "cannot ping",
+        )
+        .await
+    }
+
+    fn is_retryable(error: &Self::Error) -> bool {
+        error.is_transient()
+    }
+}
+
+/// Configuration for the [ScyllaEvtLog].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    nodes: Vec<String>,
+
+    #[serde(default = "keyspace_default")]
+    keyspace: String,
+
+    #[serde(default = "replication_factor_default")]
+    replication_factor: u8,
+
+    #[serde(default = "poll_interval_default", with = "humantime_serde")]
+    poll_interval: Duration,
+
+    #[serde(default = "read_batch_size_default")]
+    read_batch_size: NonZeroUsize,
+
+    #[serde(default)]
+    setup: bool,
+}
+
+impl Config {
+    /// Change the `nodes`, i.e. the known nodes of the cluster in the form `<host>:<port>`.
+    pub fn with_nodes<T>(self, nodes: impl IntoIterator<Item = T>) -> Self
+    where
+        T: ToString,
+    {
+        let nodes = nodes.into_iter().map(|node| node.to_string()).collect();
+        Self { nodes, ..self }
+    }
+
+    /// Change the `keyspace`.
+    pub fn with_keyspace<T>(self, keyspace: T) -> Self
+    where
+        T: ToString,
+    {
+        let keyspace = keyspace.to_string();
+        Self { keyspace, ..self }
+    }
+
+    /// Change the `replication_factor`, only used if `setup` is enabled.
+    pub fn with_replication_factor(self, replication_factor: u8) -> Self {
+        Self {
+            replication_factor,
+            ..self
+        }
+    }
+
+    /// Change the `poll_interval`.
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            ..self
+        }
+    }
+
+    /// Change the `read_batch_size`, i.e. the page size for reading events.
+    pub fn with_read_batch_size(self, read_batch_size: NonZeroUsize) -> Self {
+        Self {
+            read_batch_size,
+            ..self
+        }
+    }
+
+    /// Change the `setup` flag, i.e. whether to create the keyspace and tables if they do not
+    /// exist.
+    pub fn with_setup(self, setup: bool) -> Self {
+        Self { setup, ..self }
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            nodes: vec!["localhost:9042".to_string()],
+            keyspace: keyspace_default(),
+            replication_factor: replication_factor_default(),
+            poll_interval: poll_interval_default(),
+            read_batch_size: read_batch_size_default(),
+            setup: false,
+        }
+    }
+}
+
+async fn setup(session: &Session, keyspace: &str) -> Result<(), Error> {
+    query_unpaged(
+        session,
+        format!(
+            "CREATE TABLE IF NOT EXISTS {keyspace}.evts (
+                id uuid,
+                seq_no bigint,
+                last_seq_no bigint static,
+                global_seq_no bigint,
+                version int,
+                timestamp timestamp,
+                tags list<text>,
+                evt blob,
+                entity_type text,
+                PRIMARY KEY ((id), seq_no)
+            )"
+        ),
+        "cannot create evts table",
+    )
+    .await?;
+
+    query_unpaged(
+        session,
+        format!(
+            "CREATE TABLE IF NOT EXISTS {keyspace}.global_evts (
+                bucket int,
+                global_seq_no bigint,
+                id uuid,
+                seq_no bigint,
+                version int,
+                timestamp timestamp,
+                tags list<text>,
+                evt blob,
+                entity_type text,
+                PRIMARY KEY ((bucket), global_seq_no)
+            )"
+        ),
+        "cannot create global_evts table",
+    )
+    .await?;
+
+    query_unpaged(
+        session,
+        format!(
+            "CREATE TABLE IF NOT EXISTS {keyspace}.global_seq_no (
+                name text PRIMARY KEY,
+                value bigint
+            )"
+        ),
+        "cannot create global_seq_no table",
+    )
+    .await?;
+
+    query_unpaged(
+        session,
+        format!(
+            "CREATE TABLE IF NOT EXISTS {keyspace}.deleted (
+                id uuid PRIMARY KEY
+            )"
+        ),
+        "cannot create deleted table",
+    )
+    .await
+}
+
+fn evt_envelope(row: Row) -> Result<EvtEnvelope<Bytes>, Error> {
+    let (id, seq_no, global_seq_no, version, timestamp, tags, evt, entity_type) = row;
+
+    let seq_no = crate::seq_no(seq_no)?;
+    let global_seq_no = u64::try_from(global_seq_no)
+        .ok()
+        .and_then(|global_seq_no| global_seq_no.try_into().ok())
+        .ok_or(Error::ZeroSeqNo)?;
+
+    let timestamp =
+        DateTime::<Utc>::from_timestamp_millis(timestamp.0).ok_or(Error::InvalidTimestamp)?;
+
+    Ok(EvtEnvelope {
+        id,
+        entity_type,
+        seq_no,
+        global_seq_no,
+        version: version as u32,
+        timestamp,
+        tags: tags.unwrap_or_default(),
+        evt: evt.into(),
+    })
+}
+
+fn keyspace_default() -> String {
+    "eventsourced".to_string()
+}
+
+const fn replication_factor_default() -> u8 {
+    1
+}
+
+const fn poll_interval_default() -> Duration {
+    Duration::from_secs(2)
+}
+
+const fn read_batch_size_default() -> NonZeroUsize {
+    unsafe { NonZeroUsize::new_unchecked(1_000) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::SCYLLA_VERSION;
+    use eventsourced::convert;
+    use futures::TryStreamExt;
+    use testcontainers::{clients::Cli, core::WaitFor, GenericImage};
+
+    #[tokio::test]
+    async fn test_evt_log() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let scylla_image = GenericImage::new("scylladb/scylla", SCYLLA_VERSION)
+            .with_wait_for(WaitFor::message_on_stderr("init - serving"));
+        let container = client.run((scylla_image, vec!["--smp".to_string(), "1".to_string()]));
+        let node = format!("localhost:{}", container.get_host_port_ipv4(9042));
+
+        let config = Config::default()
+            .with_nodes([node])
+            .with_read_batch_size(2.try_into()?)
+            .with_setup(true);
+        let evt_log = ScyllaEvtLog::new(config).await?;
+
+        let id = Uuid::now_v7();
+
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, None);
+
+        let last_seq_no = evt_log
+            .persist(
+                &1,
+                1,
+                &["tag".to_string()],
+                id,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+        assert_eq!(last_seq_no, SeqNo::MIN);
+
+        let result = evt_log
+            .persist(&2, 1, &[], id, None, None, &convert::prost::to_bytes)
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::SeqNoConflict {
+                expected: None,
+                actual: Some(_)
+            })
+        ));
+
+        let mut last_seq_no = Some(last_seq_no);
+        for n in 2..=5 {
+            let seq_no = evt_log
+                .persist(&n, 1, &[], id, None, last_seq_no, &convert::prost::to_bytes)
+                .await?;
+            last_seq_no = Some(seq_no);
+        }
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+
+        // Events are read in pages of two, i.e. across three pages.
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(5)
+            .map_ok(|evt| (evt.seq_no.as_u64(), evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(1, 1), (2, 2), (3, 3), (4, 4), (5, 5)]);
+
+        let id_2 = Uuid::now_v7();
+        evt_log
+            .persist(
+                &6,
+                2,
+                &["tag".to_string()],
+                id_2,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let evts_by_tag = evt_log
+            .evts_by_tag::<i32, _, _>("tag".to_string(), SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(2)
+            .map_ok(|evt| (evt.id, evt.version, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_tag, vec![(id, 1, 1), (id_2, 2, 6)]);
+
+        let evts_by_ids = evt_log
+            .evts_by_ids::<i32, _, _>(vec![id_2], GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(1)
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_ids, vec![6]);
+
+        evt_log.delete_to(id, 10.try_into()?).await?;
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(5.try_into()?));
+        let evts = evt_log
+            .evts::<i32, _, _>(GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(2)
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![5, 6]);
+
+        assert!(!evt_log.is_deleted(id).await?);
+        evt_log.mark_deleted(id).await?;
+        evt_log.mark_deleted(id).await?;
+        assert!(evt_log.is_deleted(id).await?);
+        assert!(!evt_log.is_deleted(id_2).await?);
+
+        Ok(())
+    }
+}
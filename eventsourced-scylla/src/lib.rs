@@ -0,0 +1,141 @@
+//! [EvtLog](eventsourced::EvtLog) and [SnapshotStore](eventsourced::SnapshotStore) implementations
+//! based upon [ScyllaDB](https://www.scylladb.com/), which also work with
+//! [Apache Cassandra](https://cassandra.apache.org/).
+
+mod evt_log;
+mod snapshot_store;
+
+pub use evt_log::{Config as ScyllaEvtLogConfig, ScyllaEvtLog};
+pub use snapshot_store::{Config as ScyllaSnapshotStoreConfig, ScyllaSnapshotStore};
+
+use eventsourced::SeqNo;
+use scylla::{
+    frame::response::result::CqlValue,
+    transport::errors::{DbError, QueryError},
+    QueryResult, Session, SessionBuilder,
+};
+use std::{error::Error as StdError, sync::Arc};
+use thiserror::Error;
+
+/// Errors from the [ScyllaEvtLog] or [ScyllaSnapshotStore].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// ScyllaDB error.
+    #[error("ScyllaDB error: {0}")]
+    Scylla(String, #[source] Box<dyn StdError + Send + Sync>),
+
+    /// Cannot convert an event to bytes.
+    #[error("cannot convert an event to bytes")]
+    ToBytes(#[source] Box<dyn StdError + Send + Sync + 'static>),
+
+    /// Cannot convert bytes to an event.
+    #[error("cannot convert bytes to an event")]
+    FromBytes(#[source] Box<dyn StdError + Send + Sync + 'static>),
+
+    /// Sequence number must not be zero or negative.
+    #[error("sequence number must not be zero or negative")]
+    ZeroSeqNo,
+
+    /// The given last sequence number does not match the actual one, e.g. because of a concurrent
+    /// writer for the same entity ID.
+    #[error("expected last sequence number {expected:?}, but was {actual:?}")]
+    SeqNoConflict {
+        expected: Option<SeqNo>,
+        actual: Option<SeqNo>,
+    },
+
+    /// Invalid timestamp, i.e. out of range.
+    #[error("invalid timestamp")]
+    InvalidTimestamp,
+
+    /// Invalid result of a lightweight transaction, i.e. without the `[applied]` column.
+    #[error("invalid result of lightweight transaction")]
+    InvalidLwtResult,
+}
+
+impl Error {
+    /// Whether this error is transient, i.e. caused by a lost connection, a timeout or an
+    /// unavailable or overloaded cluster, such that the failed operation can be retried.
+    fn is_transient(&self) -> bool {
+        match self {
+            Error::Scylla(_, error) => error.downcast_ref::<QueryError>().is_some_and(|error| {
+                matches!(
+                    error,
+                    QueryError::IoError(_)
+                        | QueryError::TimeoutError
+                        | QueryError::RequestTimeout(_)
+                        | QueryError::TooManyOrphanedStreamIds(_)
+                        | QueryError::UnableToAllocStreamId
+                        | QueryError::DbError(
+                            DbError::Unavailable { .. }
+                                | DbError::Overloaded
+                                | DbError::IsBootstrapping
+                                | DbError::ReadTimeout { .. }
+                                | DbError::WriteTimeout { .. }
+                                | DbError::RateLimitReached { .. },
+                            _
+                        )
+                )
+            }),
+
+            _ => false,
+        }
+    }
+}
+
+/// Create a session connected to the given nodes and, if `setup` is enabled, create the given
+/// keyspace with the given replication factor, using `SimpleStrategy`, if it does not exist.
+async fn session(
+    nodes: &[String],
+    keyspace: &str,
+    replication_factor: u8,
+    setup: bool,
+) -> Result<Arc<Session>, Error> {
+    let session = SessionBuilder::new()
+        .known_nodes(nodes)
+        .build()
+        .await
+        .map_err(|error| Error::Scylla(format!("cannot connect to {nodes:?}"), Box::new(error)))?;
+
+    if setup {
+        let query = format!(
+            "CREATE KEYSPACE IF NOT EXISTS {keyspace} WITH REPLICATION = \
+             {{ 'class': 'SimpleStrategy', 'replication_factor': {replication_factor} }}"
+        );
+        query_unpaged(&session, query, "cannot create keyspace").await?;
+    }
+
+    Ok(Arc::new(session))
+}
+
+/// Run the given query without values, e.g. for schema changes.
+async fn query_unpaged(session: &Session, query: String, context: &str) -> Result<(), Error> {
+    session
+        .query(query, ())
+        .await
+        .map(|_| ())
+        .map_err(|error| Error::Scylla(context.to_string(), Box::new(error)))
+}
+
+/// Whether the lightweight transaction with the given result has been applied.
+fn applied(result: QueryResult) -> Result<bool, Error> {
+    result
+        .first_row()
+        .ok()
+        .and_then(|row| row.columns.into_iter().next().flatten())
+        .as_ref()
+        .and_then(CqlValue::as_boolean)
+        .ok_or(Error::InvalidLwtResult)
+}
+
+fn seq_no(seq_no: i64) -> Result<SeqNo, Error> {
+    u64::try_from(seq_no)
+        .ok()
+        .and_then(|seq_no| seq_no.try_into().ok())
+        .ok_or(Error::ZeroSeqNo)
+}
+
+#[cfg(test)]
+pub mod tests {
+    pub const SCYLLA_VERSION: &str = "5.4.0";
+}
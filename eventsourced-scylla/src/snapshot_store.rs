@@ -0,0 +1,314 @@
+//! A [SnapshotStore] implementation based on [ScyllaDB](https://www.scylladb.com/).
+
+use crate::{query_unpaged, seq_no, session, Error};
+use bytes::Bytes;
+use eventsourced::{SeqNo, Snapshot, SnapshotStore};
+use scylla::{prepared_statement::PreparedStatement, Session};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+use tracing::debug;
+use uuid::Uuid;
+
+/// A [SnapshotStore] implementation based on [ScyllaDB](https://www.scylladb.com/), which also
+/// works with [Apache Cassandra](https://cassandra.apache.org/). Snapshots are stored in the
+/// `snapshots` table keyed by entity ID, hence only the last saved snapshot per entity ID is kept.
+#[derive(Clone)]
+pub struct ScyllaSnapshotStore {
+    keyspace: String,
+    session: Arc<Session>,
+    save: PreparedStatement,
+    load: PreparedStatement,
+    delete_before: PreparedStatement,
+}
+
+impl ScyllaSnapshotStore {
+    #[allow(missing_docs)]
+    pub async fn new(config: Config) -> Result<Self, Error> {
+        debug!(?config, "creating ScyllaSnapshotStore");
+
+        let session = session(
+            &config.nodes,
+            &config.keyspace,
+            config.replication_factor,
+            config.setup,
+        )
+        .await?;
+        let keyspace = config.keyspace;
+
+        if config.setup {
+            setup(&session, &keyspace).await?;
+        }
+
+        let prepare = |query: String| {
+            let session = session.clone();
+            async move {
+                session.prepare(query).await.map_err(|error| {
+                    Error::Scylla("cannot prepare query".to_string(), Box::new(error))
+                })
+            }
+        };
+
+        let save = prepare(format!(
+            "INSERT INTO {keyspace}.snapshots (id, seq_no, state, state_version) VALUES (?, ?, ?, ?)"
+        ))
+        .await?;
+        let load = prepare(format!(
+            "SELECT seq_no, state, state_version FROM {keyspace}.snapshots WHERE id = ?"
+        ))
+        .await?;
+        let delete_before = prepare(format!(
+            "DELETE FROM {keyspace}.snapshots WHERE id = ? IF seq_no < ?"
+        ))
+        .await?;
+
+        Ok(Self {
+            keyspace,
+            session,
+            save,
+            load,
+            delete_before,
+        })
+    }
+}
+
+impl Debug for ScyllaSnapshotStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScyllaSnapshotStore")
+            .field("keyspace", &self.keyspace)
+            .finish()
+    }
+}
+
+impl SnapshotStore for ScyllaSnapshotStore {
+    type Error = Error;
+
+    async fn save<S, ToBytes, ToBytesError>(
+        &self,
+        id: Uuid,
+        seq_no: SeqNo,
+        state_version: u32,
+        state: S,
+        to_bytes: &ToBytes,
+    ) -> Result<(), Self::Error>
+    where
+        S: Send,
+        ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, %seq_no, state_version, "saving snapshot");
+
+        let bytes = to_bytes(&state).map_err(|source| Error::ToBytes(Box::new(source)))?;
+        self.session
+            .execute(
+                &self.save,
+                (
+                    id,
+                    seq_no.as_u64() as i64,
+                    bytes.to_vec(),
+                    state_version as i32,
+                ),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|error| Error::Scylla("cannot save snapshot".to_string(), Box::new(error)))
+    }
+
+    async fn load<S, FromBytes, FromBytesError>(
+        &self,
+        id: Uuid,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> Result<Option<Snapshot<S>>, Self::Error>
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        debug!(%id, state_version, "loading snapshot");
+
+        self.session
+            .execute(&self.load, (id,))
+            .await
+            .map_err(|error| Error::Scylla("cannot load snapshot".to_string(), Box::new(error)))?
+            .maybe_first_row_typed::<(i64, Vec<u8>, Option<i32>)>()
+            .map_err(|error| Error::Scylla("cannot load snapshot".to_string(), Box::new(error)))?
+            // Snapshots saved before the state version was added do not have one.
+            .filter(|(_, _, version)| version.unwrap_or(1) as u32 == state_version)
+            .map(|(snapshot_seq_no, bytes, _)| {
+                let seq_no = seq_no(snapshot_seq_no)?;
+                from_bytes(bytes.into())
+                    .map_err(|source| Error::FromBytes(Box::new(source)))
+                    .map(|state| Snapshot::new(seq_no, state))
+            })
+            .transpose()
+    }
+
+    async fn delete_before(&self, id: Uuid, seq_no: SeqNo) -> Result<(), Self::Error> {
+        debug!(%id, %seq_no, "deleting snapshot");
+
+        self.session
+            .execute(&self.delete_before, (id, seq_no.as_u64() as i64))
+            .await
+            .map(|_| ())
+            .map_err(|error| Error::Scylla("cannot delete snapshot".to_string(), Box::new(error)))
+    }
+
+    async fn ping(&self) -> Result<(), Self::Error> {
+        query_unpaged(
+            &self.session,
+            "SELECT now() FROM system.local".to_string(),
+            "cannot ping",
+        )
+        .await
+    }
+}
+
+/// Configuration for the [ScyllaSnapshotStore].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    nodes: Vec<String>,
+
+    #[serde(default = "keyspace_default")]
+    keyspace: String,
+
+    #[serde(default = "replication_factor_default")]
+    replication_factor: u8,
+
+    #[serde(default)]
+    setup: bool,
+}
+
+impl Config {
+    /// Change the `nodes`, i.e. the known nodes of the cluster in the form `<host>:<port>`.
+    pub fn with_nodes<T>(self, nodes: impl IntoIterator<Item = T>) -> Self
+    where
+        T: ToString,
+    {
+        let nodes = nodes.into_iter().map(|node| node.to_string()).collect();
+        Self { nodes, ..self }
+    }
+
+    /// Change the `keyspace`.
+    pub fn with_keyspace<T>(self, keyspace: T) -> Self
+    where
+        T: ToString,
+    {
+        let keyspace = keyspace.to_string();
+        Self { keyspace, ..self }
+    }
+
+    /// Change the `replication_factor`, only used if `setup` is enabled.
+    pub fn with_replication_factor(self, replication_factor: u8) -> Self {
+        Self {
+            replication_factor,
+            ..self
+        }
+    }
+
+    /// Change the `setup` flag, i.e. whether to create the keyspace and table if they do not
+    /// exist.
+    pub fn with_setup(self, setup: bool) -> Self {
+        Self { setup, ..self }
+    }
+}
+
+impl Default for Config {
+    /// Default values suitable for local testing only.
+    fn default() -> Self {
+        Self {
+            nodes: vec!["localhost:9042".to_string()],
+            keyspace: keyspace_default(),
+            replication_factor: replication_factor_default(),
+            setup: false,
+        }
+    }
+}
+
+async fn setup(session: &Session, keyspace: &str) -> Result<(), Error> {
+    query_unpaged(
+        session,
+        format!(
+            "CREATE TABLE IF NOT EXISTS {keyspace}.snapshots (
+                id uuid PRIMARY KEY,
+                seq_no bigint,
+                state blob,
+                state_version int
+            )"
+        ),
+        "cannot create snapshots table",
+    )
+    .await
+}
+
+fn keyspace_default() -> String {
+    "eventsourced".to_string()
+}
+
+const fn replication_factor_default() -> u8 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::SCYLLA_VERSION;
+    use eventsourced::convert;
+    use testcontainers::{clients::Cli, core::WaitFor, GenericImage};
+
+    #[tokio::test]
+    async fn test_snapshot_store() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = Cli::default();
+        let scylla_image = GenericImage::new("scylladb/scylla", SCYLLA_VERSION)
+            .with_wait_for(WaitFor::message_on_stderr("init - serving"));
+        let container = client.run((scylla_image, vec!["--smp".to_string(), "1".to_string()]));
+        let node = format!("localhost:{}", container.get_host_port_ipv4(9042));
+
+        let config = Config::default().with_nodes([node]).with_setup(true);
+        let snapshot_store = ScyllaSnapshotStore::new(config).await?;
+
+        let id = Uuid::now_v7();
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        let seq_no = 42.try_into().unwrap();
+        let state = 666;
+
+        snapshot_store
+            .save(id, seq_no, 1, state, &convert::prost::to_bytes)
+            .await?;
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+
+        assert!(snapshot.is_some());
+        let snapshot = snapshot.unwrap();
+        assert_eq!(snapshot.seq_no, seq_no);
+        assert_eq!(snapshot.state, state);
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 2, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        snapshot_store.delete_before(id, seq_no).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_some());
+
+        snapshot_store.delete_before(id, seq_no.succ()).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, &convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Source of the current time, used by the [EvtLog](crate::EvtLog) implementations to timestamp
+/// persisted events, see [EvtEnvelope](crate::EvtEnvelope). They use the [SystemClock] by default;
+/// tests can use a [TestClock] to get deterministic timestamps without sleeping.
+pub trait Clock: Debug + Send + Sync + 'static {
+    /// The current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// [Clock] returning the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// [Clock] returning a fixed time which can be set or advanced, e.g. for testing. Clones share the
+/// same time.
+#[derive(Debug, Clone)]
+pub struct TestClock(Arc<Mutex<DateTime<Utc>>>);
+
+impl TestClock {
+    /// Create a [TestClock] returning the given time.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self(Arc::new(Mutex::new(now)))
+    }
+
+    /// Set the time to the given one.
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.0.lock().expect("lock not poisoned") = now;
+    }
+
+    /// Advance the time by the given duration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resulting time is out of range.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.0.lock().expect("lock not poisoned");
+        let duration = chrono::Duration::from_std(duration).expect("duration in range");
+        *now = now
+            .checked_add_signed(duration)
+            .expect("advanced time in range");
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().expect("lock not poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_test_clock() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let clock = TestClock::new(now);
+        assert_eq!(clock.now(), now);
+
+        clock.clone().advance(Duration::from_secs(42));
+        assert_eq!(clock.now(), now + chrono::Duration::seconds(42));
+
+        clock.set(now);
+        assert_eq!(clock.now(), now);
+    }
+}
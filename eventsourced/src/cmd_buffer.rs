@@ -0,0 +1,480 @@
+use crate::{CmdMsg, EntityRefError};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug, Formatter},
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::Duration,
+};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    Notify,
+};
+
+/// Size of the buffer for commands sent to a spawned entity along with the strategy for when it is
+/// full. Can be created from a [NonZeroUsize], using [Overflow::Block].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CmdBuffer {
+    pub size: NonZeroUsize,
+    pub overflow: Overflow,
+}
+
+impl CmdBuffer {
+    #[allow(missing_docs)]
+    pub fn new(size: NonZeroUsize, overflow: Overflow) -> Self {
+        Self { size, overflow }
+    }
+
+    /// Recommend a buffer size for the given observed throughput in commands per second and the
+    /// given maximum time a command may wait in the buffer, i.e. the number of commands arriving
+    /// within that time. A larger buffer absorbs longer bursts, but increases latency and memory
+    /// usage; a full buffer applies the [Overflow] strategy.
+    pub fn recommended_size(throughput: f64, max_wait: Duration) -> NonZeroUsize {
+        let size = (throughput * max_wait.as_secs_f64()).ceil();
+        // Float to int casts saturate, NaN becomes 0.
+        NonZeroUsize::new(size as usize).unwrap_or(NonZeroUsize::MIN)
+    }
+}
+
+impl From<NonZeroUsize> for CmdBuffer {
+    fn from(size: NonZeroUsize) -> Self {
+        Self::new(size, Overflow::Block)
+    }
+}
+
+/// Strategy for sending a command to a spawned entity with a full [CmdBuffer].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Wait until the buffer has capacity again.
+    #[default]
+    Block,
+
+    /// Do not buffer the new command, which results in [EntityRefError::Overflow].
+    DropNewest,
+
+    /// Drop the oldest buffered command, which results in [EntityRefError::Overflow] for it, and
+    /// buffer the new one.
+    DropOldest,
+}
+
+/// Sending side of a [CmdBuffer].
+pub(crate) enum CmdSender<C, Err> {
+    Channel(mpsc::Sender<CmdMsg<C, Err>>, Overflow),
+    Ring(RingSender<C, Err>),
+}
+
+/// Sending side of a [CmdBuffer] which does not keep it open, see [CmdSender::downgrade].
+pub(crate) enum WeakCmdSender<C, Err> {
+    Channel(mpsc::WeakSender<CmdMsg<C, Err>>, Overflow),
+    Ring(WeakRingSender<C, Err>),
+}
+
+/// Receiving side of a [CmdBuffer].
+pub(crate) enum CmdReceiver<C, Err> {
+    Channel(mpsc::Receiver<CmdMsg<C, Err>>),
+    Ring(RingReceiver<C, Err>),
+}
+
+/// Create the sending and receiving sides for the given [CmdBuffer].
+pub(crate) fn cmd_channel<C, Err>(
+    cmd_buffer: CmdBuffer,
+) -> (CmdSender<C, Err>, CmdReceiver<C, Err>) {
+    let CmdBuffer { size, overflow } = cmd_buffer;
+
+    match overflow {
+        Overflow::Block | Overflow::DropNewest => {
+            let (cmd_in, cmd_out) = mpsc::channel(size.get());
+            (
+                CmdSender::Channel(cmd_in, overflow),
+                CmdReceiver::Channel(cmd_out),
+            )
+        }
+
+        Overflow::DropOldest => {
+            let ring = Arc::new(Ring {
+                state: Mutex::new(RingState {
+                    cmd_msgs: VecDeque::with_capacity(size.get()),
+                    closed: false,
+                }),
+                size: size.get(),
+                senders: AtomicUsize::new(1),
+                notify: Notify::new(),
+            });
+            (
+                CmdSender::Ring(RingSender(ring.clone())),
+                CmdReceiver::Ring(RingReceiver(ring)),
+            )
+        }
+    }
+}
+
+impl<C, Err> CmdSender<C, Err> {
+    /// Send the given [CmdMsg] according to the [Overflow] strategy. In case of an error, the
+    /// given function is used to map technical errors. The unsent [CmdMsg] is not part of these,
+    /// such that commands need neither be `Send` nor `Sync`.
+    pub(crate) async fn send(
+        &self,
+        cmd_msg: CmdMsg<C, Err>,
+        send_error: impl FnOnce(EntityRefError) -> EntityRefError,
+    ) -> Result<(), EntityRefError> {
+        let closed = || {
+            send_error(EntityRefError::SendCmd(Box::new(
+                mpsc::error::SendError(()),
+            )))
+        };
+
+        match self {
+            CmdSender::Channel(cmd_in, Overflow::DropNewest) => {
+                cmd_in.try_send(cmd_msg).map_err(|error| match error {
+                    TrySendError::Full(_) => EntityRefError::Overflow,
+                    TrySendError::Closed(_) => closed(),
+                })
+            }
+
+            CmdSender::Channel(cmd_in, _) => cmd_in.send(cmd_msg).await.map_err(|_| closed()),
+
+            CmdSender::Ring(RingSender(ring)) => ring.push(cmd_msg).map_err(|_| closed()),
+        }
+    }
+}
+
+impl<C, Err> CmdSender<C, Err> {
+    /// Create a [WeakCmdSender] which does not count as a sender, i.e. once all [CmdSender]s have
+    /// been dropped, the receiver returns `None` after all buffered [CmdMsg]s.
+    pub(crate) fn downgrade(&self) -> WeakCmdSender<C, Err> {
+        match self {
+            CmdSender::Channel(cmd_in, overflow) => {
+                WeakCmdSender::Channel(cmd_in.downgrade(), *overflow)
+            }
+            CmdSender::Ring(RingSender(ring)) => {
+                WeakCmdSender::Ring(WeakRingSender(Arc::downgrade(ring)))
+            }
+        }
+    }
+}
+
+impl<C, Err> Clone for CmdSender<C, Err> {
+    fn clone(&self) -> Self {
+        match self {
+            CmdSender::Channel(cmd_in, overflow) => CmdSender::Channel(cmd_in.clone(), *overflow),
+            CmdSender::Ring(ring_sender) => CmdSender::Ring(ring_sender.clone()),
+        }
+    }
+}
+
+impl<C, Err> Debug for CmdSender<C, Err> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CmdSender::Channel(cmd_in, overflow) => f
+                .debug_tuple("Channel")
+                .field(cmd_in)
+                .field(overflow)
+                .finish(),
+            CmdSender::Ring(_) => f.write_str("Ring"),
+        }
+    }
+}
+
+impl<C, Err> WeakCmdSender<C, Err> {
+    /// Try to create a [CmdSender], which fails if all [CmdSender]s have already been dropped.
+    pub(crate) fn upgrade(&self) -> Option<CmdSender<C, Err>> {
+        match self {
+            WeakCmdSender::Channel(cmd_in, overflow) => cmd_in
+                .upgrade()
+                .map(|cmd_in| CmdSender::Channel(cmd_in, *overflow)),
+
+            WeakCmdSender::Ring(WeakRingSender(ring)) => {
+                let ring = ring.upgrade()?;
+                // Once the number of senders has dropped to zero, it must not be increased again.
+                ring.senders
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |senders| {
+                        (senders > 0).then_some(senders + 1)
+                    })
+                    .ok()
+                    .map(|_| CmdSender::Ring(RingSender(ring)))
+            }
+        }
+    }
+}
+
+impl<C, Err> Clone for WeakCmdSender<C, Err> {
+    fn clone(&self) -> Self {
+        match self {
+            WeakCmdSender::Channel(cmd_in, overflow) => {
+                WeakCmdSender::Channel(cmd_in.clone(), *overflow)
+            }
+            WeakCmdSender::Ring(WeakRingSender(ring)) => {
+                WeakCmdSender::Ring(WeakRingSender(ring.clone()))
+            }
+        }
+    }
+}
+
+impl<C, Err> Debug for WeakCmdSender<C, Err> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            WeakCmdSender::Channel(cmd_in, overflow) => f
+                .debug_tuple("Channel")
+                .field(cmd_in)
+                .field(overflow)
+                .finish(),
+            WeakCmdSender::Ring(_) => f.write_str("Ring"),
+        }
+    }
+}
+
+impl<C, Err> CmdReceiver<C, Err> {
+    /// Receive the next [CmdMsg] or `None`, if all senders have been dropped and all buffered
+    /// [CmdMsg]s have been received, i.e. dropping the last sender does not lose buffered ones.
+    pub(crate) async fn recv(&mut self) -> Option<CmdMsg<C, Err>> {
+        match self {
+            CmdReceiver::Channel(cmd_out) => cmd_out.recv().await,
+            CmdReceiver::Ring(RingReceiver(ring)) => ring.pop().await,
+        }
+    }
+
+    /// The number of currently buffered [CmdMsg]s.
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            CmdReceiver::Channel(cmd_out) => cmd_out.len(),
+            CmdReceiver::Ring(RingReceiver(ring)) => {
+                ring.state.lock().expect("lock ring state").cmd_msgs.len()
+            }
+        }
+    }
+}
+
+/// Bounded buffer dropping the oldest [CmdMsg] when full, used for [Overflow::DropOldest].
+struct Ring<C, Err> {
+    state: Mutex<RingState<C, Err>>,
+    size: usize,
+    senders: AtomicUsize,
+    notify: Notify,
+}
+
+struct RingState<C, Err> {
+    cmd_msgs: VecDeque<CmdMsg<C, Err>>,
+    /// Whether the receiver has been dropped.
+    closed: bool,
+}
+
+impl<C, Err> Ring<C, Err> {
+    /// Push the given [CmdMsg], possibly dropping the oldest one; fails if the receiver has been
+    /// dropped.
+    fn push(&self, cmd_msg: CmdMsg<C, Err>) -> Result<(), ()> {
+        let dropped = {
+            let mut state = self.state.lock().expect("lock ring state");
+            if state.closed {
+                return Err(());
+            }
+            let dropped = if state.cmd_msgs.len() >= self.size {
+                state.cmd_msgs.pop_front()
+            } else {
+                None
+            };
+            state.cmd_msgs.push_back(cmd_msg);
+            dropped
+        };
+        self.notify.notify_one();
+
+        if let Some(dropped) = dropped {
+            dropped.reject(EntityRefError::Overflow);
+        }
+
+        Ok(())
+    }
+
+    /// Pop the oldest [CmdMsg], waiting if none is buffered, or return `None` if all senders have
+    /// been dropped.
+    async fn pop(&self) -> Option<CmdMsg<C, Err>> {
+        loop {
+            if let Some(cmd_msg) = self
+                .state
+                .lock()
+                .expect("lock ring state")
+                .cmd_msgs
+                .pop_front()
+            {
+                return Some(cmd_msg);
+            }
+            if self.senders.load(Ordering::Acquire) == 0 {
+                return None;
+            }
+            self.notify.notified().await;
+        }
+    }
+}
+
+pub(crate) struct RingSender<C, Err>(Arc<Ring<C, Err>>);
+
+impl<C, Err> Clone for RingSender<C, Err> {
+    fn clone(&self) -> Self {
+        self.0.senders.fetch_add(1, Ordering::AcqRel);
+        Self(self.0.clone())
+    }
+}
+
+impl<C, Err> Drop for RingSender<C, Err> {
+    fn drop(&mut self) {
+        if self.0.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.notify.notify_one();
+        }
+    }
+}
+
+pub(crate) struct WeakRingSender<C, Err>(Weak<Ring<C, Err>>);
+
+pub(crate) struct RingReceiver<C, Err>(Arc<Ring<C, Err>>);
+
+impl<C, Err> Drop for RingReceiver<C, Err> {
+    fn drop(&mut self) {
+        // Drop buffered commands, such that their senders do not wait forever.
+        let mut state = self.0.state.lock().expect("lock ring state");
+        state.closed = true;
+        state.cmd_msgs.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CmdContext, EvtMeta, GlobalSeqNo, Rejected, SeqNo};
+    use std::convert::Infallible;
+    use tokio::sync::oneshot;
+
+    #[allow(clippy::type_complexity)]
+    fn cmd_msg(
+        cmd: u64,
+    ) -> (
+        CmdMsg<u64, Infallible>,
+        oneshot::Receiver<
+            Result<Result<(SeqNo, Option<GlobalSeqNo>), Rejected<Infallible>>, EntityRefError>,
+        >,
+    ) {
+        let (result_sender, result_receiver) = oneshot::channel();
+        let cmd_msg = CmdMsg::Single {
+            cmd,
+            ctx: CmdContext::default(),
+            meta: EvtMeta::default(),
+            expected_seq_no: None,
+            result_sender,
+        };
+        (cmd_msg, result_receiver)
+    }
+
+    #[tokio::test]
+    async fn test_drop_newest() {
+        let size = NonZeroUsize::new(1).unwrap();
+        let (cmd_in, _cmd_out) =
+            cmd_channel::<u64, Infallible>(CmdBuffer::new(size, Overflow::DropNewest));
+
+        let result = cmd_in.send(cmd_msg(1).0, |error| error).await;
+        assert!(result.is_ok());
+        let result = cmd_in.send(cmd_msg(2).0, |error| error).await;
+        assert!(matches!(result, Err(EntityRefError::Overflow)));
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let size = NonZeroUsize::new(1).unwrap();
+        let (cmd_in, mut cmd_out) =
+            cmd_channel::<u64, Infallible>(CmdBuffer::new(size, Overflow::DropOldest));
+
+        let (msg, result_receiver) = cmd_msg(1);
+        let result = cmd_in.send(msg, |error| error).await;
+        assert!(result.is_ok());
+        let result = cmd_in.send(cmd_msg(2).0, |error| error).await;
+        assert!(result.is_ok());
+        let result = result_receiver.await;
+        assert!(matches!(result, Ok(Err(EntityRefError::Overflow))));
+
+        let cmd_msg = cmd_out.recv().await;
+        assert!(matches!(cmd_msg, Some(CmdMsg::Single { cmd: 2, .. })));
+
+        drop(cmd_in);
+        let cmd_msg = cmd_out.recv().await;
+        assert!(cmd_msg.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_len() {
+        let size = NonZeroUsize::new(2).unwrap();
+
+        for overflow in [Overflow::Block, Overflow::DropNewest, Overflow::DropOldest] {
+            let (cmd_in, mut cmd_out) =
+                cmd_channel::<u64, Infallible>(CmdBuffer::new(size, overflow));
+            assert_eq!(cmd_out.len(), 0);
+
+            let result = cmd_in.send(cmd_msg(1).0, |error| error).await;
+            assert!(result.is_ok());
+            let result = cmd_in.send(cmd_msg(2).0, |error| error).await;
+            assert!(result.is_ok());
+            assert_eq!(cmd_out.len(), 2);
+
+            cmd_out.recv().await;
+            assert_eq!(cmd_out.len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_recommended_size() {
+        let size = CmdBuffer::recommended_size(1_000.0, Duration::from_millis(50));
+        assert_eq!(size.get(), 50);
+
+        let size = CmdBuffer::recommended_size(10.0, Duration::from_millis(1));
+        assert_eq!(size.get(), 1);
+
+        let size = CmdBuffer::recommended_size(f64::NAN, Duration::from_secs(1));
+        assert_eq!(size.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_drain_on_close() {
+        let size = NonZeroUsize::new(2).unwrap();
+
+        for overflow in [Overflow::Block, Overflow::DropNewest, Overflow::DropOldest] {
+            let (cmd_in, mut cmd_out) =
+                cmd_channel::<u64, Infallible>(CmdBuffer::new(size, overflow));
+
+            let result = cmd_in.send(cmd_msg(1).0, |error| error).await;
+            assert!(result.is_ok());
+            let result = cmd_in.send(cmd_msg(2).0, |error| error).await;
+            assert!(result.is_ok());
+            drop(cmd_in);
+
+            let cmd_msg = cmd_out.recv().await;
+            assert!(matches!(cmd_msg, Some(CmdMsg::Single { cmd: 1, .. })));
+            let cmd_msg = cmd_out.recv().await;
+            assert!(matches!(cmd_msg, Some(CmdMsg::Single { cmd: 2, .. })));
+            let cmd_msg = cmd_out.recv().await;
+            assert!(cmd_msg.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_downgrade() {
+        let size = NonZeroUsize::new(2).unwrap();
+
+        for overflow in [Overflow::Block, Overflow::DropNewest, Overflow::DropOldest] {
+            let (cmd_in, mut cmd_out) =
+                cmd_channel::<u64, Infallible>(CmdBuffer::new(size, overflow));
+
+            let weak_cmd_in = cmd_in.downgrade();
+            let upgraded = weak_cmd_in.upgrade().expect("sender not yet dropped");
+            let result = upgraded.send(cmd_msg(1).0, |error| error).await;
+            assert!(result.is_ok());
+            drop(upgraded);
+            drop(cmd_in);
+
+            // The weak sender neither keeps the buffer open nor can it be upgraded anymore.
+            assert!(weak_cmd_in.upgrade().is_none());
+            let cmd_msg = cmd_out.recv().await;
+            assert!(matches!(cmd_msg, Some(CmdMsg::Single { cmd: 1, .. })));
+            let cmd_msg = cmd_out.recv().await;
+            assert!(cmd_msg.is_none());
+        }
+    }
+}
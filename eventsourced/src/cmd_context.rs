@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+
+/// Context for handling a command, supplied by the caller via
+/// [handle_cmd_with_ctx](crate::EntityRef::handle_cmd_with_ctx), e.g. a correlation ID or the
+/// user, which can be used to enrich the event with cross-cutting metadata before it gets
+/// persisted, see [EventSourced::enrich](crate::EventSourced::enrich).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CmdContext(BTreeMap<String, String>);
+
+impl CmdContext {
+    /// Add the given entry, which allows for chaining calls to `with`.
+    pub fn with<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: ToString,
+        V: ToString,
+    {
+        self.0.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Get the value for the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Iterate over all entries ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Whether there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cmd_context() {
+        let ctx = CmdContext::default();
+        assert!(ctx.is_empty());
+
+        let ctx = ctx.with("user", "joe").with("correlation-id", 42);
+        assert_eq!(ctx.get("user"), Some("joe"));
+        assert_eq!(ctx.get("correlation-id"), Some("42"));
+        assert_eq!(ctx.get("foo"), None);
+        assert_eq!(
+            ctx.iter().collect::<Vec<_>>(),
+            vec![("correlation-id", "42"), ("user", "joe")]
+        );
+    }
+}
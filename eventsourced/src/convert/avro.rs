@@ -0,0 +1,232 @@
+//! Conversion to [Bytes] for any type that implements [Serialize] and from any type that implements
+//! [DeserializeOwned] based upon [Avro](https://avro.apache.org/) via
+//! [apache-avro](https://docs.rs/apache-avro/latest/apache_avro). Requires the `avro` feature.
+//!
+//! Values are written with the
+//! [single object encoding](https://avro.apache.org/docs/current/specification/#single-object-encoding),
+//! i.e. prefixed with the fingerprint of the writer schema, which is used to look up the writer
+//! schema when reading, such that values written with a previous schema are resolved against the
+//! current one.
+
+use crate::Binarizer;
+use apache_avro::{from_avro_datum, from_value, rabin::Rabin, to_avro_datum, to_value, Schema};
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Marker of the single object encoding, followed by the 8 byte fingerprint.
+const MARKER: [u8; 2] = [0xc3, 0x01];
+
+/// Length of the header, i.e. the marker and the fingerprint.
+const HEADER_LEN: usize = 10;
+
+/// Avro schemas: the current one used for writing and as reader schema and possibly previous
+/// ones, which are looked up by their fingerprint as writer schemas, thereby supporting schema
+/// evolution.
+#[derive(Debug, Clone)]
+pub struct AvroSchemas {
+    current: Schema,
+    current_fingerprint: [u8; 8],
+    schemas: HashMap<[u8; 8], Schema>,
+}
+
+impl AvroSchemas {
+    /// Create [AvroSchemas] with the given current schema.
+    pub fn new(current: Schema) -> Self {
+        let current_fingerprint = fingerprint(&current);
+        let schemas = HashMap::from([(current_fingerprint, current.clone())]);
+        Self {
+            current,
+            current_fingerprint,
+            schemas,
+        }
+    }
+
+    /// Add the given previous schema, only used as writer schema for reading.
+    pub fn with_previous(mut self, previous: Schema) -> Self {
+        self.schemas.insert(fingerprint(&previous), previous);
+        self
+    }
+
+    /// Convert the given value to bytes with the current schema, prefixed with its fingerprint.
+    pub fn to_bytes<T>(&self, value: &T) -> Result<Bytes, AvroError>
+    where
+        T: Serialize,
+    {
+        let value = to_value(value)?;
+        let datum = to_avro_datum(&self.current, value)?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + datum.len());
+        bytes.extend_from_slice(&MARKER);
+        bytes.extend_from_slice(&self.current_fingerprint);
+        bytes.extend_from_slice(&datum);
+        Ok(bytes.into())
+    }
+
+    /// Convert the given bytes to a value, resolving the writer schema given by the fingerprint
+    /// against the current schema.
+    pub fn from_bytes<T>(&self, bytes: Bytes) -> Result<T, AvroError>
+    where
+        T: DeserializeOwned,
+    {
+        if bytes.len() < HEADER_LEN || bytes[..2] != MARKER {
+            return Err(AvroError::InvalidHeader);
+        }
+        let fingerprint = <[u8; 8]>::try_from(&bytes[2..HEADER_LEN]).expect("8 bytes");
+        let writer_schema = self
+            .schemas
+            .get(&fingerprint)
+            .ok_or(AvroError::UnknownSchema(u64::from_le_bytes(fingerprint)))?;
+
+        let value = from_avro_datum(
+            writer_schema,
+            &mut &bytes[HEADER_LEN..],
+            Some(&self.current),
+        )?;
+        let value = from_value(&value)?;
+        Ok(value)
+    }
+}
+
+/// Create an Avro based [Binarizer] with the given schemas for events and snapshot state. As the
+/// conversion functions from bytes must be `Copy`, the schemas must be `'static`, e.g. via
+/// [Box::leak].
+#[allow(clippy::type_complexity)]
+pub fn binarizer<E, S>(
+    evt_schemas: &'static AvroSchemas,
+    state_schemas: &'static AvroSchemas,
+) -> Binarizer<
+    impl Fn(&E) -> Result<Bytes, AvroError> + Send + Sync + 'static,
+    impl Fn(Bytes) -> Result<E, AvroError> + Copy + Send + Sync + 'static,
+    impl Fn(&S) -> Result<Bytes, AvroError> + Send + Sync + 'static,
+    impl Fn(Bytes) -> Result<S, AvroError> + Copy + Send + Sync + 'static,
+>
+where
+    E: Serialize + DeserializeOwned,
+    S: Serialize + DeserializeOwned,
+{
+    Binarizer {
+        evt_to_bytes: |evt: &E| evt_schemas.to_bytes(evt),
+        evt_from_bytes: |bytes| evt_schemas.from_bytes(bytes),
+        state_to_bytes: |state: &S| state_schemas.to_bytes(state),
+        state_from_bytes: |bytes| state_schemas.from_bytes(bytes),
+    }
+}
+
+/// Error from converting with [AvroSchemas].
+#[derive(Debug, Error)]
+pub enum AvroError {
+    /// Avro error.
+    #[error("Avro error")]
+    Avro(#[from] apache_avro::Error),
+
+    /// The bytes do not start with the header of the single object encoding.
+    #[error("invalid single object encoding header")]
+    InvalidHeader,
+
+    /// No schema is known for the fingerprint.
+    #[error("unknown schema with fingerprint {0:#018x}")]
+    UnknownSchema(u64),
+}
+
+/// The CRC-64-AVRO fingerprint of the given schema, as used by the single object encoding.
+fn fingerprint(schema: &Schema) -> [u8; 8] {
+    schema
+        .fingerprint::<Rabin>()
+        .bytes
+        .try_into()
+        .expect("8 byte fingerprint")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    const SCHEMA_V1: &str = r#"
+        {
+            "type": "record",
+            "name": "Foo",
+            "fields": [
+                { "name": "n", "type": "long" },
+                { "name": "s", "type": "string" }
+            ]
+        }
+    "#;
+
+    const SCHEMA_V2: &str = r#"
+        {
+            "type": "record",
+            "name": "Foo",
+            "fields": [
+                { "name": "n", "type": "long" },
+                { "name": "s", "type": "string" },
+                { "name": "b", "type": "boolean", "default": false }
+            ]
+        }
+    "#;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct FooV1 {
+        n: i64,
+        s: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct FooV2 {
+        n: i64,
+        s: String,
+        b: bool,
+    }
+
+    #[test]
+    fn test_convert_avro() {
+        let schemas_v1 = AvroSchemas::new(Schema::parse_str(SCHEMA_V1).unwrap());
+        let schemas_v2 = AvroSchemas::new(Schema::parse_str(SCHEMA_V2).unwrap())
+            .with_previous(Schema::parse_str(SCHEMA_V1).unwrap());
+
+        let foo = FooV2 {
+            n: 42,
+            s: "test".to_string(),
+            b: true,
+        };
+        let bytes = schemas_v2.to_bytes(&foo);
+        assert!(bytes.is_ok());
+        let bar = schemas_v2.from_bytes::<FooV2>(bytes.unwrap());
+        assert!(bar.is_ok());
+        assert_eq!(bar.unwrap(), foo);
+
+        // Written with the previous schema, resolved against the current one.
+        let foo = FooV1 {
+            n: 42,
+            s: "test".to_string(),
+        };
+        let bytes = schemas_v1.to_bytes(&foo).unwrap();
+        let bar = schemas_v2.from_bytes::<FooV2>(bytes);
+        assert!(bar.is_ok());
+        assert_eq!(
+            bar.unwrap(),
+            FooV2 {
+                n: 42,
+                s: "test".to_string(),
+                b: false
+            }
+        );
+
+        // Written with an unknown schema.
+        let result = schemas_v1.from_bytes::<FooV1>(
+            schemas_v2
+                .to_bytes(&FooV2 {
+                    n: 42,
+                    s: "test".to_string(),
+                    b: true,
+                })
+                .unwrap(),
+        );
+        assert!(matches!(result, Err(AvroError::UnknownSchema(_))));
+
+        let result = schemas_v1.from_bytes::<FooV1>(Bytes::from_static(b"foo"));
+        assert!(matches!(result, Err(AvroError::InvalidHeader)));
+    }
+}
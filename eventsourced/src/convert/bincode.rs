@@ -0,0 +1,77 @@
+//! Conversion to [Bytes] for any type that implements [Serialize] and from any type that implements
+//! [DeserializeOwned] based upon [bincode](https://docs.rs/bincode/latest/bincode).
+//!
+//! Bincode is fast and compact, but not self-describing: the bytes do not contain any field names
+//! or type information and can only be read with the exact same type definition. Hence it is not
+//! suited for long-lived event logs whose event types will evolve.
+
+use crate::Binarizer;
+use bincode::{deserialize, serialize, Error};
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Create a bincode based [Binarizer].
+#[allow(clippy::type_complexity)]
+pub fn binarizer<E, S>() -> Binarizer<
+    for<'a> fn(&'a E) -> Result<Bytes, Error>,
+    fn(Bytes) -> Result<E, Error>,
+    for<'a> fn(&'a S) -> Result<Bytes, Error>,
+    fn(Bytes) -> Result<S, Error>,
+>
+where
+    E: Serialize + DeserializeOwned,
+    S: Serialize + DeserializeOwned,
+{
+    Binarizer {
+        evt_to_bytes: to_bytes::<E>,
+        evt_from_bytes: from_bytes::<E>,
+        state_to_bytes: to_bytes::<S>,
+        state_from_bytes: from_bytes::<S>,
+    }
+}
+
+pub fn to_bytes<T>(value: &T) -> Result<Bytes, Error>
+where
+    T: Serialize,
+{
+    serialize(value).map(Bytes::from)
+}
+
+pub fn from_bytes<T>(bytes: Bytes) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    deserialize(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    enum Evt {
+        Created { name: String, tags: Vec<String> },
+        Increased(u64),
+    }
+
+    #[test]
+    fn test_convert_bincode() {
+        let evts = vec![
+            Evt::Created {
+                name: "test".to_string(),
+                tags: vec!["a".to_string(), "b".to_string()],
+            },
+            Evt::Increased(42),
+        ];
+
+        let bytes = to_bytes(&evts);
+        assert!(bytes.is_ok());
+        let bytes = bytes.unwrap();
+
+        let evts_2 = from_bytes::<Vec<Evt>>(bytes);
+        assert!(evts_2.is_ok());
+        let evts_2 = evts_2.unwrap();
+        assert_eq!(evts_2, evts);
+    }
+}
@@ -0,0 +1,76 @@
+//! Conversion to [Bytes] for any type that implements [Serialize] and from any type that implements
+//! [DeserializeOwned] based upon [CBOR](https://cbor.io/) via
+//! [ciborium](https://docs.rs/ciborium/latest/ciborium).
+
+use crate::Binarizer;
+use bytes::Bytes;
+use ciborium::{de, from_reader, into_writer, ser};
+use serde::{de::DeserializeOwned, Serialize};
+use std::io;
+
+/// Create a CBOR based [Binarizer].
+#[allow(clippy::type_complexity)]
+pub fn binarizer<E, S>() -> Binarizer<
+    for<'a> fn(&'a E) -> Result<Bytes, ser::Error<io::Error>>,
+    fn(Bytes) -> Result<E, de::Error<io::Error>>,
+    for<'a> fn(&'a S) -> Result<Bytes, ser::Error<io::Error>>,
+    fn(Bytes) -> Result<S, de::Error<io::Error>>,
+>
+where
+    E: Serialize + DeserializeOwned,
+    S: Serialize + DeserializeOwned,
+{
+    Binarizer {
+        evt_to_bytes: to_bytes::<E>,
+        evt_from_bytes: from_bytes::<E>,
+        state_to_bytes: to_bytes::<S>,
+        state_from_bytes: from_bytes::<S>,
+    }
+}
+
+pub fn to_bytes<T>(value: &T) -> Result<Bytes, ser::Error<io::Error>>
+where
+    T: Serialize,
+{
+    let mut bytes = Vec::new();
+    into_writer(value, &mut bytes)?;
+    Ok(bytes.into())
+}
+
+pub fn from_bytes<T>(bytes: Bytes) -> Result<T, de::Error<io::Error>>
+where
+    T: DeserializeOwned,
+{
+    from_reader(bytes.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Foo {
+        n: u64,
+        s: String,
+        v: Vec<Option<bool>>,
+    }
+
+    #[test]
+    fn test_convert_cbor() {
+        let foo = Foo {
+            n: 42,
+            s: "test".to_string(),
+            v: vec![Some(true), None],
+        };
+
+        let bytes = to_bytes(&foo);
+        assert!(bytes.is_ok());
+        let bytes = bytes.unwrap();
+
+        let bar = from_bytes::<Foo>(bytes);
+        assert!(bar.is_ok());
+        let bar = bar.unwrap();
+        assert_eq!(bar, foo);
+    }
+}
@@ -0,0 +1,187 @@
+//! Compression of the [Bytes] produced by the conversion functions of a [Binarizer] based upon
+//! [zstd](https://docs.rs/zstd/latest/zstd). Requires the `zstd` feature.
+
+use crate::Binarizer;
+use bytes::Bytes;
+use std::{error::Error as StdError, io};
+use thiserror::Error;
+
+/// Header prepended to compressed bytes, such that uncompressed ones, e.g. persisted before
+/// compression has been enabled, remain readable.
+const MAGIC: &[u8] = b"ES\xFAZ";
+
+/// Header prepended to bytes stored raw because they are smaller than the `min_size` given to
+/// [compressed], such that they cannot be mistaken for compressed ones.
+const RAW_MAGIC: &[u8] = b"ES\xFAR";
+
+/// Wrap the conversion functions of the given [Binarizer] with zstd compression at the given level
+/// (`0` meaning the zstd default), i.e. compress the bytes when writing and transparently
+/// decompress them when reading. Bytes smaller than `min_size` are not worth compressing and hence
+/// stored raw, yet with a header marking them as such. Bytes without any header are passed to the
+/// inner conversion functions unchanged.
+#[allow(clippy::type_complexity)]
+pub fn compressed<
+    E,
+    S,
+    EvtToBytes,
+    EvtToBytesError,
+    EvtFromBytes,
+    EvtFromBytesError,
+    StateToBytes,
+    StateToBytesError,
+    StateFromBytes,
+    StateFromBytesError,
+>(
+    inner: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
+    level: i32,
+    min_size: usize,
+) -> Binarizer<
+    impl Fn(&E) -> Result<Bytes, CompressionError<EvtToBytesError>> + Send + Sync + 'static,
+    impl Fn(Bytes) -> Result<E, CompressionError<EvtFromBytesError>> + Copy + Send + Sync + 'static,
+    impl Fn(&S) -> Result<Bytes, CompressionError<StateToBytesError>> + Send + Sync + 'static,
+    impl Fn(Bytes) -> Result<S, CompressionError<StateFromBytesError>> + Copy + Send + Sync + 'static,
+>
+where
+    EvtToBytes: Fn(&E) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+    EvtToBytesError: StdError + Send + Sync + 'static,
+    EvtFromBytes: Fn(Bytes) -> Result<E, EvtFromBytesError> + Copy + Send + Sync + 'static,
+    EvtFromBytesError: StdError + Send + Sync + 'static,
+    StateToBytes: Fn(&S) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
+    StateToBytesError: StdError + Send + Sync + 'static,
+    StateFromBytes: Fn(Bytes) -> Result<S, StateFromBytesError> + Copy + Send + Sync + 'static,
+    StateFromBytesError: StdError + Send + Sync + 'static,
+{
+    let Binarizer {
+        evt_to_bytes,
+        evt_from_bytes,
+        state_to_bytes,
+        state_from_bytes,
+    } = inner;
+
+    Binarizer {
+        evt_to_bytes: move |evt: &E| {
+            let bytes = evt_to_bytes(evt).map_err(CompressionError::Inner)?;
+            compress_min_size(&bytes, level, min_size).map_err(CompressionError::Codec)
+        },
+        evt_from_bytes: move |bytes| {
+            let bytes = decompress(bytes).map_err(CompressionError::Codec)?;
+            evt_from_bytes(bytes).map_err(CompressionError::Inner)
+        },
+        state_to_bytes: move |state: &S| {
+            let bytes = state_to_bytes(state).map_err(CompressionError::Inner)?;
+            compress_min_size(&bytes, level, min_size).map_err(CompressionError::Codec)
+        },
+        state_from_bytes: move |bytes| {
+            let bytes = decompress(bytes).map_err(CompressionError::Codec)?;
+            state_from_bytes(bytes).map_err(CompressionError::Inner)
+        },
+    }
+}
+
+/// Compress the given bytes at the given level and prepend the compression header.
+pub fn compress(bytes: &[u8], level: i32) -> Result<Bytes, io::Error> {
+    let mut compressed = MAGIC.to_vec();
+    zstd::stream::copy_encode(bytes, &mut compressed, level)?;
+    Ok(compressed.into())
+}
+
+/// Compress the given bytes at the given level and prepend the compression header, unless they
+/// are smaller than `min_size`, in which case they are only prepended the raw header.
+pub fn compress_min_size(bytes: &[u8], level: i32, min_size: usize) -> Result<Bytes, io::Error> {
+    if bytes.len() < min_size {
+        Ok([RAW_MAGIC, bytes].concat().into())
+    } else {
+        compress(bytes, level)
+    }
+}
+
+/// Decompress the given bytes if they start with the compression header, strip the raw header if
+/// they start with that one, else return them unchanged.
+pub fn decompress(bytes: Bytes) -> Result<Bytes, io::Error> {
+    if let Some(compressed) = bytes.strip_prefix(MAGIC) {
+        zstd::stream::decode_all(compressed).map(Into::into)
+    } else if bytes.starts_with(RAW_MAGIC) {
+        Ok(bytes.slice(RAW_MAGIC.len()..))
+    } else {
+        Ok(bytes)
+    }
+}
+
+/// Error from conversion functions wrapped via [compressed].
+#[derive(Debug, Error)]
+pub enum CompressionError<E> {
+    /// Bytes cannot be compressed or decompressed.
+    #[error("cannot compress or decompress bytes")]
+    Codec(#[source] io::Error),
+
+    /// Error from the inner conversion function.
+    #[error(transparent)]
+    Inner(E),
+}
+
+#[cfg(all(test, feature = "serde_json"))]
+mod tests {
+    use super::*;
+    use crate::convert::serde_json;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Foo(String);
+
+    #[test]
+    fn test_compressed() {
+        let Binarizer {
+            evt_to_bytes,
+            evt_from_bytes,
+            ..
+        } = compressed(serde_json::binarizer::<Foo, Foo>(), 0, 0);
+
+        let foo = Foo("foo".repeat(42));
+
+        let bytes = evt_to_bytes(&foo);
+        assert!(bytes.is_ok());
+        let bytes = bytes.unwrap();
+        assert!(bytes.starts_with(MAGIC));
+        assert!(bytes.len() < serde_json::to_bytes(&foo).unwrap().len());
+
+        let bar = evt_from_bytes(bytes);
+        assert!(bar.is_ok());
+        let bar = bar.unwrap();
+        assert_eq!(bar, foo);
+
+        // Uncompressed bytes remain readable.
+        let bar = evt_from_bytes(serde_json::to_bytes(&foo).unwrap());
+        assert!(bar.is_ok());
+        let bar = bar.unwrap();
+        assert_eq!(bar, foo);
+    }
+
+    #[test]
+    fn test_compressed_min_size() {
+        let Binarizer {
+            state_to_bytes,
+            state_from_bytes,
+            ..
+        } = compressed(serde_json::binarizer::<Foo, Foo>(), 0, 64);
+
+        // Below the threshold: stored raw.
+        let small = Foo("foo".to_string());
+        let bytes = state_to_bytes(&small).unwrap();
+        assert!(bytes.starts_with(RAW_MAGIC));
+        assert_eq!(
+            &bytes[RAW_MAGIC.len()..],
+            serde_json::to_bytes(&small).unwrap()
+        );
+        let bar = state_from_bytes(bytes);
+        assert!(bar.is_ok());
+        assert_eq!(bar.unwrap(), small);
+
+        // At or above the threshold: compressed.
+        let large = Foo("foo".repeat(42));
+        let bytes = state_to_bytes(&large).unwrap();
+        assert!(bytes.starts_with(MAGIC));
+        let bar = state_from_bytes(bytes);
+        assert!(bar.is_ok());
+        assert_eq!(bar.unwrap(), large);
+    }
+}
@@ -0,0 +1,198 @@
+//! Encryption of the [Bytes] produced by the conversion functions of a [Binarizer] based upon
+//! [AES-GCM](https://docs.rs/aes-gcm/latest/aes_gcm). Requires the `aes-gcm` feature.
+
+use crate::Binarizer;
+use aes_gcm::{
+    aead::{Aead, AeadCore, OsRng},
+    Aes256Gcm, KeyInit, Nonce,
+};
+use bytes::Bytes;
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+};
+use thiserror::Error;
+
+/// Length of the random nonce prepended to each encrypted payload.
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM keys: the current one used for encryption and possibly previous ones, which are
+/// tried in order for decryption if the current one fails, thereby supporting key rotation.
+#[derive(Clone)]
+pub struct EncryptionKeys {
+    ciphers: Vec<Aes256Gcm>,
+}
+
+impl EncryptionKeys {
+    /// Create [EncryptionKeys] with the given current key.
+    pub fn new(current: [u8; 32]) -> Self {
+        Self {
+            ciphers: vec![Aes256Gcm::new(&current.into())],
+        }
+    }
+
+    /// Add the given previous key, only used for decryption.
+    pub fn with_previous(mut self, previous: [u8; 32]) -> Self {
+        self.ciphers.push(Aes256Gcm::new(&previous.into()));
+        self
+    }
+
+    /// Encrypt the given bytes with the current key and a random nonce, which is prepended.
+    pub fn encrypt(&self, bytes: &[u8]) -> Result<Bytes, aes_gcm::Error> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self.ciphers[0].encrypt(&nonce, bytes)?;
+
+        let mut encrypted = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        encrypted.extend_from_slice(&nonce);
+        encrypted.extend_from_slice(&ciphertext);
+        Ok(encrypted.into())
+    }
+
+    /// Decrypt the given bytes, trying the current and then the previous keys.
+    pub fn decrypt(&self, bytes: &[u8]) -> Result<Bytes, aes_gcm::Error> {
+        if bytes.len() < NONCE_LEN {
+            return Err(aes_gcm::Error);
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let nonce = Nonce::from_slice(nonce);
+
+        self.ciphers
+            .iter()
+            .find_map(|cipher| cipher.decrypt(nonce, ciphertext).ok())
+            .map(Into::into)
+            .ok_or(aes_gcm::Error)
+    }
+}
+
+impl Debug for EncryptionKeys {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionKeys")
+            .field("count", &self.ciphers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Wrap the conversion functions of the given [Binarizer] with AES-GCM encryption using the given
+/// keys, i.e. encrypt the bytes when writing and decrypt them when reading. As the conversion
+/// functions from bytes must be `Copy`, the keys must be `'static`, e.g. via [Box::leak].
+#[allow(clippy::type_complexity)]
+pub fn encrypted<
+    E,
+    S,
+    EvtToBytes,
+    EvtToBytesError,
+    EvtFromBytes,
+    EvtFromBytesError,
+    StateToBytes,
+    StateToBytesError,
+    StateFromBytes,
+    StateFromBytesError,
+>(
+    inner: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
+    keys: &'static EncryptionKeys,
+) -> Binarizer<
+    impl Fn(&E) -> Result<Bytes, EncryptionError<EvtToBytesError>> + Send + Sync + 'static,
+    impl Fn(Bytes) -> Result<E, EncryptionError<EvtFromBytesError>> + Copy + Send + Sync + 'static,
+    impl Fn(&S) -> Result<Bytes, EncryptionError<StateToBytesError>> + Send + Sync + 'static,
+    impl Fn(Bytes) -> Result<S, EncryptionError<StateFromBytesError>> + Copy + Send + Sync + 'static,
+>
+where
+    EvtToBytes: Fn(&E) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+    EvtToBytesError: StdError + Send + Sync + 'static,
+    EvtFromBytes: Fn(Bytes) -> Result<E, EvtFromBytesError> + Copy + Send + Sync + 'static,
+    EvtFromBytesError: StdError + Send + Sync + 'static,
+    StateToBytes: Fn(&S) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
+    StateToBytesError: StdError + Send + Sync + 'static,
+    StateFromBytes: Fn(Bytes) -> Result<S, StateFromBytesError> + Copy + Send + Sync + 'static,
+    StateFromBytesError: StdError + Send + Sync + 'static,
+{
+    let Binarizer {
+        evt_to_bytes,
+        evt_from_bytes,
+        state_to_bytes,
+        state_from_bytes,
+    } = inner;
+
+    Binarizer {
+        evt_to_bytes: move |evt: &E| {
+            let bytes = evt_to_bytes(evt).map_err(EncryptionError::Inner)?;
+            keys.encrypt(&bytes).map_err(|_| EncryptionError::Encrypt)
+        },
+        evt_from_bytes: move |bytes: Bytes| {
+            let bytes = keys.decrypt(&bytes).map_err(|_| EncryptionError::Decrypt)?;
+            evt_from_bytes(bytes).map_err(EncryptionError::Inner)
+        },
+        state_to_bytes: move |state: &S| {
+            let bytes = state_to_bytes(state).map_err(EncryptionError::Inner)?;
+            keys.encrypt(&bytes).map_err(|_| EncryptionError::Encrypt)
+        },
+        state_from_bytes: move |bytes: Bytes| {
+            let bytes = keys.decrypt(&bytes).map_err(|_| EncryptionError::Decrypt)?;
+            state_from_bytes(bytes).map_err(EncryptionError::Inner)
+        },
+    }
+}
+
+/// Error from conversion functions wrapped via [encrypted].
+#[derive(Debug, Error)]
+pub enum EncryptionError<E> {
+    /// Bytes cannot be encrypted.
+    #[error("cannot encrypt bytes")]
+    Encrypt,
+
+    /// Bytes cannot be decrypted with any of the keys.
+    #[error("cannot decrypt bytes with any of the keys")]
+    Decrypt,
+
+    /// Error from the inner conversion function.
+    #[error(transparent)]
+    Inner(E),
+}
+
+#[cfg(all(test, feature = "serde_json"))]
+mod tests {
+    use super::*;
+    use crate::convert::serde_json;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Foo(String);
+
+    #[test]
+    fn test_encrypted() {
+        let keys = Box::leak(Box::new(EncryptionKeys::new([1; 32])));
+        let Binarizer {
+            evt_to_bytes,
+            evt_from_bytes,
+            ..
+        } = encrypted(serde_json::binarizer::<Foo, Foo>(), keys);
+
+        let foo = Foo("foo".to_string());
+
+        let bytes = evt_to_bytes(&foo);
+        assert!(bytes.is_ok());
+        let bytes = bytes.unwrap();
+        assert!(!bytes.windows(3).any(|window| window == b"foo"));
+
+        let bar = evt_from_bytes(bytes.clone());
+        assert!(bar.is_ok());
+        let bar = bar.unwrap();
+        assert_eq!(bar, foo);
+
+        // Rotated keys can still decrypt with the previous key.
+        let keys = Box::leak(Box::new(
+            EncryptionKeys::new([2; 32]).with_previous([1; 32]),
+        ));
+        let Binarizer { evt_from_bytes, .. } = encrypted(serde_json::binarizer::<Foo, Foo>(), keys);
+        let bar = evt_from_bytes(bytes.clone());
+        assert!(bar.is_ok());
+        let bar = bar.unwrap();
+        assert_eq!(bar, foo);
+
+        // Unknown keys cannot decrypt.
+        let keys = Box::leak(Box::new(EncryptionKeys::new([2; 32])));
+        let Binarizer { evt_from_bytes, .. } = encrypted(serde_json::binarizer::<Foo, Foo>(), keys);
+        let bar = evt_from_bytes(bytes);
+        assert!(matches!(bar, Err(EncryptionError::Decrypt)));
+    }
+}
@@ -0,0 +1,77 @@
+//! Conversion to [Bytes] for any type that implements [Serialize] and from any type that implements
+//! [DeserializeOwned] based upon [MessagePack](https://msgpack.org/) via
+//! [rmp-serde](https://docs.rs/rmp-serde/latest/rmp_serde).
+//!
+//! Structs are serialized as maps with field names, which keeps the bytes readable after adding
+//! fields with defaults or reordering fields.
+
+use crate::Binarizer;
+use bytes::Bytes;
+use rmp_serde::{decode, encode, from_slice, to_vec_named};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Create a MessagePack based [Binarizer].
+#[allow(clippy::type_complexity)]
+pub fn binarizer<E, S>() -> Binarizer<
+    for<'a> fn(&'a E) -> Result<Bytes, encode::Error>,
+    fn(Bytes) -> Result<E, decode::Error>,
+    for<'a> fn(&'a S) -> Result<Bytes, encode::Error>,
+    fn(Bytes) -> Result<S, decode::Error>,
+>
+where
+    E: Serialize + DeserializeOwned,
+    S: Serialize + DeserializeOwned,
+{
+    Binarizer {
+        evt_to_bytes: to_bytes::<E>,
+        evt_from_bytes: from_bytes::<E>,
+        state_to_bytes: to_bytes::<S>,
+        state_from_bytes: from_bytes::<S>,
+    }
+}
+
+pub fn to_bytes<T>(value: &T) -> Result<Bytes, encode::Error>
+where
+    T: Serialize,
+{
+    to_vec_named(value).map(Bytes::from)
+}
+
+pub fn from_bytes<T>(bytes: Bytes) -> Result<T, decode::Error>
+where
+    T: DeserializeOwned,
+{
+    from_slice(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    enum Evt {
+        Created { name: String, tags: Vec<String> },
+        Increased(u64),
+    }
+
+    #[test]
+    fn test_convert_messagepack() {
+        let evts = vec![
+            Evt::Created {
+                name: "test".to_string(),
+                tags: vec!["a".to_string(), "b".to_string()],
+            },
+            Evt::Increased(42),
+        ];
+
+        let bytes = to_bytes(&evts);
+        assert!(bytes.is_ok());
+        let bytes = bytes.unwrap();
+
+        let evts_2 = from_bytes::<Vec<Evt>>(bytes);
+        assert!(evts_2.is_ok());
+        let evts_2 = evts_2.unwrap();
+        assert_eq!(evts_2, evts);
+    }
+}
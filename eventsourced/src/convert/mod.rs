@@ -1,4 +1,80 @@
+//! Conversion functions from and to [Bytes](bytes::Bytes) for events and snapshot states.
+
+#[cfg(feature = "avro")]
+pub mod avro;
+#[cfg(feature = "bincode")]
+pub mod bincode;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "zstd")]
+mod compression;
+#[cfg(feature = "aes-gcm")]
+mod encryption;
+#[cfg(feature = "messagepack")]
+pub mod messagepack;
 #[cfg(feature = "prost")]
 pub mod prost;
 #[cfg(feature = "serde_json")]
 pub mod serde_json;
+
+#[cfg(feature = "zstd")]
+pub use compression::*;
+#[cfg(feature = "aes-gcm")]
+pub use encryption::*;
+
+use crate::Binarizer;
+
+/// Create a [Binarizer] from the given pairs of conversion functions, one pair for events and one
+/// for snapshot states, e.g. to mix different codecs:
+///
+/// ```ignore
+/// convert::mixed(
+///     (prost::to_bytes, prost::from_bytes),
+///     (serde_json::to_bytes, serde_json::from_bytes),
+/// )
+/// ```
+pub fn mixed<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>(
+    (evt_to_bytes, evt_from_bytes): (EvtToBytes, EvtFromBytes),
+    (state_to_bytes, state_from_bytes): (StateToBytes, StateFromBytes),
+) -> Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes> {
+    Binarizer {
+        evt_to_bytes,
+        evt_from_bytes,
+        state_to_bytes,
+        state_from_bytes,
+    }
+}
+
+#[cfg(all(test, feature = "prost", feature = "serde_json"))]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct State(String);
+
+    #[test]
+    fn test_mixed() {
+        let Binarizer {
+            evt_to_bytes,
+            evt_from_bytes,
+            state_to_bytes,
+            state_from_bytes,
+        } = mixed(
+            (prost::to_bytes::<i32>, prost::from_bytes::<i32>),
+            (
+                serde_json::to_bytes::<State>,
+                serde_json::from_bytes::<State>,
+            ),
+        );
+
+        let bytes = evt_to_bytes(&42).unwrap();
+        assert_eq!(bytes, prost::to_bytes(&42).unwrap());
+        assert_eq!(evt_from_bytes(bytes).unwrap(), 42);
+
+        let state = State("foo".to_string());
+        let bytes = state_to_bytes(&state).unwrap();
+        assert_eq!(&bytes[..], br#""foo""#);
+        assert_eq!(state_from_bytes(bytes).unwrap(), state);
+    }
+}
@@ -1,5 +1,6 @@
 //! Conversion to [Bytes] for any type that implements [Serialize] and from any type that implements
 //! [DeserializeOwned] based upon [serde_json](https://docs.rs/serde_json/latest/serde_json).
+//! Requires the `serde_json` feature or its alias `json`.
 
 use crate::Binarizer;
 use bytes::Bytes;
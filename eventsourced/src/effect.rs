@@ -0,0 +1,18 @@
+/// Execution of deferred side effects, e.g. sending emails or enqueueing jobs, returned by the
+/// command handler along with the event, see
+/// [TaggedEvt::with_effect](crate::TaggedEvt::with_effect). Typically used via
+/// [EventSourced::effect_executor](crate::EventSourced::effect_executor).
+pub trait EffectExecutor<Eff> {
+    /// Execute the given effect. Only invoked after the event it was returned with has been
+    /// persisted, hence it must not block, e.g. spawn a task for asynchronous work.
+    fn execute(&self, effect: Eff);
+}
+
+impl<F, Eff> EffectExecutor<Eff> for F
+where
+    F: Fn(Eff),
+{
+    fn execute(&self, effect: Eff) {
+        self(effect)
+    }
+}
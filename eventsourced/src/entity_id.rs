@@ -0,0 +1,16 @@
+use std::{
+    fmt::{Debug, Display},
+    hash::Hash,
+};
+
+/// The ID of an entity, by default a [Uuid](uuid::Uuid), but any type with the required
+/// properties can be used, e.g. a `String` or an integer for entities with natural keys. This
+/// trait is implemented for all such types.
+///
+/// The traits which depend on the ID type, i.e. [EventSourced](crate::EventSourced),
+/// [EvtLog](crate::EvtLog) and [SnapshotStore](crate::SnapshotStore), take it as a type parameter
+/// which defaults to [Uuid](uuid::Uuid). The in-memory implementations support any ID type,
+/// whereas the backend implementations in the respective crates store [Uuid](uuid::Uuid)s.
+pub trait EntityId: Debug + Display + Clone + Eq + Hash + Send + Sync + 'static {}
+
+impl<T> EntityId for T where T: Debug + Display + Clone + Eq + Hash + Send + Sync + 'static {}
@@ -0,0 +1,254 @@
+use crate::{
+    Binarizer, EntityId, EntityRef, EventSourced, EventSourcedExt, EvtLog, SnapshotStore,
+    SpawnConfig, SpawnError,
+};
+use bytes::Bytes;
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt::{self, Debug, Formatter},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::Mutex as AsyncMutex;
+use uuid::Uuid;
+
+/// Slot for the [EntityRef] of an ID; locked while spawning.
+type EntityRefSlot<E, Id> = Arc<AsyncMutex<Option<EntityRef<E, Id>>>>;
+
+/// Registry for many entities of the same [EventSourced] implementation by ID, spawning them on
+/// first access via [get_or_spawn](EntityManager::get_or_spawn) and caching their [EntityRef]s.
+/// Concurrent spawns for the same ID are deduplicated via a per-ID lock.
+pub struct EntityManager<
+    E,
+    L,
+    S,
+    F,
+    EvtToBytes,
+    EvtFromBytes,
+    StateToBytes,
+    StateFromBytes,
+    Id = Uuid,
+> where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    new_entity: F,
+    config: SpawnConfig,
+    evt_log: L,
+    snapshot_store: S,
+    binarizer: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
+    entity_refs: Mutex<HashMap<Id, EntityRefSlot<E, Id>>>,
+}
+
+impl<E, L, S, F, EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes, Id>
+    EntityManager<E, L, S, F, EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes, Id>
+where
+    E: EventSourced<Id> + Send,
+    E::Cmd: Send + Sync,
+    E::Evt: Send + Sync,
+    E::State: Send,
+    E::Effect: Send,
+    L: EvtLog<Id>,
+    S: SnapshotStore<Id>,
+    F: Fn(Id) -> E,
+    Id: EntityId,
+    EvtToBytes: Clone,
+    EvtFromBytes: Clone,
+    StateToBytes: Clone,
+    StateFromBytes: Clone,
+{
+    /// Create an [EntityManager], using the given function to create the [EventSourced] value for
+    /// an ID when spawning it.
+    pub fn new(
+        new_entity: F,
+        config: impl Into<SpawnConfig>,
+        evt_log: L,
+        snapshot_store: S,
+        binarizer: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
+    ) -> Self {
+        Self {
+            new_entity,
+            config: config.into(),
+            evt_log,
+            snapshot_store,
+            binarizer,
+            entity_refs: Mutex::default(),
+        }
+    }
+
+    /// Get the [EntityRef] for the given ID, spawning the entity if it has not yet been spawned,
+    /// has been evicted or has panicked.
+    pub async fn get_or_spawn<
+        EvtToBytesError,
+        StateToBytesError,
+        EvtFromBytesError,
+        StateFromBytesError,
+    >(
+        &self,
+        id: Id,
+    ) -> Result<EntityRef<E, Id>, SpawnError>
+    where
+        EvtToBytes: Fn(&E::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+        EvtToBytesError: StdError + Send + Sync + 'static,
+        StateToBytes: Fn(&E::State) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
+        StateToBytesError: StdError + Send + Sync + 'static,
+        EvtFromBytes: Fn(Bytes) -> Result<E::Evt, EvtFromBytesError> + Copy + Send + Sync + 'static,
+        EvtFromBytesError: StdError + Send + Sync + 'static,
+        StateFromBytes:
+            Fn(Bytes) -> Result<E::State, StateFromBytesError> + Copy + Send + Sync + 'static,
+        StateFromBytesError: StdError + Send + Sync + 'static,
+    {
+        let entity_ref = self
+            .entity_refs
+            .lock()
+            .expect("lock entity refs")
+            .entry(id.clone())
+            .or_default()
+            .clone();
+        let mut entity_ref = entity_ref.lock().await;
+
+        match &*entity_ref {
+            Some(entity_ref) if !entity_ref.is_panicked() => Ok(entity_ref.clone()),
+
+            _ => {
+                let spawned = (self.new_entity)(id.clone())
+                    .spawn(
+                        id,
+                        self.config,
+                        self.evt_log.clone(),
+                        self.snapshot_store.clone(),
+                        self.binarizer.clone(),
+                    )
+                    .await?;
+                *entity_ref = Some(spawned.clone());
+                Ok(spawned)
+            }
+        }
+    }
+
+    /// Evict the [EntityRef] for the given ID, e.g. for passivation, such that the entity gets
+    /// spawned again on the next access. Returns whether there has been a cached [EntityRef].
+    pub fn evict(&self, id: Id) -> bool {
+        self.entity_refs
+            .lock()
+            .expect("lock entity refs")
+            .remove(&id)
+            .is_some()
+    }
+
+    /// The number of cached [EntityRef]s.
+    pub fn len(&self) -> usize {
+        self.entity_refs.lock().expect("lock entity refs").len()
+    }
+
+    /// Whether there are no cached [EntityRef]s.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<E, L, S, F, EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes, Id> Debug
+    for EntityManager<E, L, S, F, EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes, Id>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+    L: Debug,
+    S: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntityManager")
+            .field("config", &self.config)
+            .field("evt_log", &self.evt_log)
+            .field("snapshot_store", &self.snapshot_store)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use crate::{convert, IntoTaggedEvt, MemoryEvtLog, MemorySnapshotStore};
+    use std::{
+        convert::Infallible,
+        num::NonZeroUsize,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    #[derive(Debug)]
+    struct Counter(u64);
+
+    impl EventSourced for Counter {
+        type Cmd = ();
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = Infallible;
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            _cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            Ok(1)
+        }
+
+        fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            self.0 += evt;
+            Ok(None)
+        }
+
+        fn set_state(&mut self, state: Self::State) {
+            self.0 = state;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_or_spawn() -> Result<(), Box<dyn StdError>> {
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let entity_manager = EntityManager::new(
+            {
+                let spawned = spawned.clone();
+                move |_| {
+                    spawned.fetch_add(1, Ordering::SeqCst);
+                    Counter(0)
+                }
+            },
+            NonZeroUsize::MIN,
+            MemoryEvtLog::default(),
+            MemorySnapshotStore::default(),
+            convert::prost::binarizer(),
+        );
+
+        let id = Uuid::now_v7();
+
+        // Concurrent accesses only spawn once.
+        let (entity_ref, entity_ref_2) = tokio::join!(
+            entity_manager.get_or_spawn(id),
+            entity_manager.get_or_spawn(id)
+        );
+        let entity_ref = entity_ref?;
+        let entity_ref_2 = entity_ref_2?;
+        assert_eq!(entity_ref.id(), id);
+        assert_eq!(entity_ref_2.id(), id);
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
+        assert_eq!(entity_manager.len(), 1);
+
+        entity_ref.handle_cmd(()).await??;
+
+        let entity_ref = entity_manager.get_or_spawn(Uuid::now_v7()).await?;
+        assert_ne!(entity_ref.id(), id);
+        assert_eq!(spawned.load(Ordering::SeqCst), 2);
+        assert_eq!(entity_manager.len(), 2);
+
+        // Evicted entities are spawned again.
+        assert!(entity_manager.evict(id));
+        assert!(!entity_manager.evict(id));
+        entity_manager.get_or_spawn(id).await?;
+        assert_eq!(spawned.load(Ordering::SeqCst), 3);
+
+        Ok(())
+    }
+}
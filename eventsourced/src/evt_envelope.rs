@@ -0,0 +1,41 @@
+use crate::{EvtMeta, GlobalSeqNo, SeqNo};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// A persisted event along with its metadata, as yielded by the query methods of
+/// [EvtLog](crate::EvtLog).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvtEnvelope<E, Id = Uuid> {
+    /// The ID of the entity the event belongs to.
+    pub id: Id,
+
+    /// The type of the entity the event belongs to, if persisted with one, see
+    /// [EventSourced::ENTITY_TYPE](crate::EventSourced::ENTITY_TYPE).
+    pub entity_type: Option<String>,
+
+    /// The sequence number of the event, unique for the entity ID.
+    pub seq_no: SeqNo,
+
+    /// The global sequence number of the event, reflecting the order in which all events have
+    /// been persisted.
+    pub global_seq_no: GlobalSeqNo,
+
+    /// The version the event has been persisted with, e.g. for upcasting.
+    pub version: u32,
+
+    /// The time the event has been persisted.
+    pub timestamp: DateTime<Utc>,
+
+    /// The tags of the event.
+    pub tags: Vec<String>,
+
+    /// The event itself.
+    pub evt: E,
+}
+
+impl<E, Id> EvtEnvelope<E, Id> {
+    /// The [EvtMeta] decoded from the tags.
+    pub fn meta(&self) -> EvtMeta {
+        EvtMeta::from_tags(&self.tags)
+    }
+}
@@ -0,0 +1,786 @@
+//! An in-memory [EvtLog] implementation, e.g. for testing.
+
+use crate::{Clock, EntityEvts, EntityId, EvtEnvelope, EvtLog, GlobalSeqNo, SeqNo, SystemClock};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{stream, Stream};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error as StdError,
+    num::NonZeroU64,
+    sync::{Arc, Mutex},
+};
+use thiserror::Error;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+/// An in-memory [EvtLog] implementation, e.g. for testing, for any [EntityId] type. Like the other
+/// implementations it rejects events not continuing the sequence numbers of their entity and its
+/// query streams are live. Clones share the same events.
+#[derive(Debug, Clone)]
+pub struct MemoryEvtLog<Id = Uuid> {
+    evts: Arc<Mutex<Evts<Id>>>,
+    evt_count: Arc<watch::Sender<usize>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<Id> MemoryEvtLog<Id>
+where
+    Id: EntityId,
+{
+    /// Use the given [Clock] instead of the [SystemClock] to timestamp persisted events.
+    pub fn with_clock(self, clock: impl Clock) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    fn persisted_evts<E, P, FromBytes, FromBytesError>(
+        &self,
+        from_index: usize,
+        predicate: P,
+        from_bytes: FromBytes,
+    ) -> impl Stream<Item = Result<EvtEnvelope<E, Id>, MemoryEvtLogError>> + Send
+    where
+        E: Send,
+        P: Fn(&PersistedEvt<Id>) -> bool + Send + 'static,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let state = (
+            self.evts.clone(),
+            self.evt_count.subscribe(),
+            from_index,
+            predicate,
+        );
+
+        stream::unfold(Some(state), move |state| async move {
+            let (evts, mut evt_count, mut index, predicate) = state?;
+
+            loop {
+                // Mark the current count as seen before looking for events, to not miss any.
+                evt_count.borrow_and_update();
+
+                let evt = {
+                    let evts = evts.lock().expect("lock not poisoned");
+                    let evt = evts
+                        .evts
+                        .iter()
+                        .enumerate()
+                        .skip(index)
+                        .find_map(|(n, evt)| {
+                            evt.as_ref()
+                                .filter(|evt| predicate(evt))
+                                .map(|evt| (n, evt.clone()))
+                        });
+                    index = match &evt {
+                        Some((n, _)) => n + 1,
+                        None => index.max(evts.evts.len()),
+                    };
+                    evt
+                };
+
+                match evt {
+                    Some((n, evt)) => {
+                        let evt = from_bytes(evt.bytes)
+                            .map_err(|error| MemoryEvtLogError::FromBytes(error.into()))
+                            .map(|payload| EvtEnvelope {
+                                id: evt.id,
+                                entity_type: evt.entity_type,
+                                seq_no: evt.seq_no,
+                                global_seq_no: GlobalSeqNo::new(
+                                    NonZeroU64::MIN.saturating_add(n as u64),
+                                ),
+                                version: evt.version,
+                                timestamp: evt.timestamp,
+                                tags: evt.tags,
+                                evt: payload,
+                            });
+                        let state = evt.is_ok().then_some((evts, evt_count, index, predicate));
+                        return Some((evt, state));
+                    }
+
+                    // Wait for further events; the sender lives as long as this stream.
+                    None => evt_count.changed().await.ok()?,
+                }
+            }
+        })
+    }
+}
+
+impl<Id> Default for MemoryEvtLog<Id> {
+    fn default() -> Self {
+        Self {
+            evts: Default::default(),
+            evt_count: Arc::new(watch::Sender::new(0)),
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl<Id> EvtLog<Id> for MemoryEvtLog<Id>
+where
+    Id: EntityId,
+{
+    type Error = MemoryEvtLogError;
+
+    async fn persist<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Id,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> Result<SeqNo, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        self.persist_returning_global_seq_no(
+            evt,
+            version,
+            tags,
+            id,
+            entity_type,
+            last_seq_no,
+            to_bytes,
+        )
+        .await
+        .map(|(seq_no, _)| seq_no)
+    }
+
+    async fn persist_returning_global_seq_no<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Id,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> Result<(SeqNo, Option<GlobalSeqNo>), Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        let bytes = to_bytes(evt).map_err(|error| MemoryEvtLogError::ToBytes(error.into()))?;
+
+        let (seq_no, evt_count) = {
+            let mut evts = self.evts.lock().expect("lock not poisoned");
+
+            let actual = evts.last_seq_nos.get(&id).copied();
+            if actual != last_seq_no {
+                return Err(MemoryEvtLogError::SeqNoConflict {
+                    expected: last_seq_no,
+                    actual,
+                });
+            }
+
+            let seq_no = last_seq_no
+                .map(|seq_no| seq_no.succ())
+                .unwrap_or(SeqNo::MIN);
+            evts.last_seq_nos.insert(id.clone(), seq_no);
+            evts.evts.push(Some(PersistedEvt {
+                id,
+                entity_type: entity_type.map(ToOwned::to_owned),
+                seq_no,
+                version,
+                timestamp: self.clock.now(),
+                tags: tags.to_vec(),
+                bytes,
+            }));
+
+            (seq_no, evts.evts.len())
+        };
+        self.evt_count.send_replace(evt_count);
+
+        // Global sequence numbers are the (one-based) indexes of the events.
+        let global_seq_no = GlobalSeqNo::new(
+            NonZeroU64::new(evt_count as u64).expect("at least one persisted event"),
+        );
+        Ok((seq_no, Some(global_seq_no)))
+    }
+
+    async fn persist_batch<E, ToBytes, ToBytesError>(
+        &self,
+        batch: &[EntityEvts<'_, E, Id>],
+        to_bytes: &ToBytes,
+    ) -> Result<Vec<Option<SeqNo>>, Self::Error>
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        let batch = batch
+            .iter()
+            .map(|entity_evts| {
+                entity_evts
+                    .evts
+                    .iter()
+                    .map(|evt| {
+                        to_bytes(evt.evt())
+                            .map_err(|error| MemoryEvtLogError::ToBytes(error.into()))
+                            .map(|bytes| (evt.tags().to_vec(), bytes))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(|evts| (entity_evts, evts))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (last_seq_nos, evt_count) = {
+            let mut evts = self.evts.lock().expect("lock not poisoned");
+
+            // Check all sequence numbers upfront to persist either all or no events. Later
+            // entries for the same entity ID continue after the former ones.
+            let mut last_seq_nos = evts.last_seq_nos.clone();
+            let mut chained = HashMap::new();
+            for (entity_evts, tagged_bytes) in &batch {
+                let actual = last_seq_nos.get(&entity_evts.id).copied();
+                if !chained.contains_key(&entity_evts.id) {
+                    if actual != entity_evts.last_seq_no {
+                        return Err(MemoryEvtLogError::SeqNoConflict {
+                            expected: entity_evts.last_seq_no,
+                            actual,
+                        });
+                    }
+                    chained.insert(entity_evts.id.clone(), entity_evts.last_seq_no);
+                }
+                if let Some(seq_no) = (0..tagged_bytes.len()).fold(actual, |seq_no, _| {
+                    Some(seq_no.map(|seq_no| seq_no.succ()).unwrap_or(SeqNo::MIN))
+                }) {
+                    last_seq_nos.insert(entity_evts.id.clone(), seq_no);
+                }
+            }
+
+            let timestamp = self.clock.now();
+            let mut batch_last_seq_nos = Vec::with_capacity(batch.len());
+            for (entity_evts, tagged_bytes) in batch {
+                let mut last_seq_no = chained[&entity_evts.id];
+                for (tags, bytes) in tagged_bytes {
+                    let seq_no = last_seq_no
+                        .map(|seq_no| seq_no.succ())
+                        .unwrap_or(SeqNo::MIN);
+                    evts.evts.push(Some(PersistedEvt {
+                        id: entity_evts.id.clone(),
+                        entity_type: entity_evts.entity_type.map(ToOwned::to_owned),
+                        seq_no,
+                        version: entity_evts.version,
+                        timestamp,
+                        tags,
+                        bytes,
+                    }));
+                    last_seq_no = Some(seq_no);
+                }
+                chained.insert(entity_evts.id.clone(), last_seq_no);
+                batch_last_seq_nos.push(last_seq_no);
+            }
+            evts.last_seq_nos = last_seq_nos;
+
+            (batch_last_seq_nos, evts.evts.len())
+        };
+        self.evt_count.send_replace(evt_count);
+
+        Ok(last_seq_nos)
+    }
+
+    async fn delete_to(&self, id: Id, to_seq_no: SeqNo) -> Result<(), Self::Error> {
+        let mut evts = self.evts.lock().expect("lock not poisoned");
+
+        // Always keep the last event.
+        let Some(last_seq_no) = evts.last_seq_nos.get(&id).copied() else {
+            return Ok(());
+        };
+
+        for evt in evts.evts.iter_mut() {
+            if evt.as_ref().is_some_and(|evt| {
+                evt.id == id && evt.seq_no <= to_seq_no && evt.seq_no < last_seq_no
+            }) {
+                *evt = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn mark_deleted(&self, id: Id) -> Result<(), Self::Error> {
+        let mut evts = self.evts.lock().expect("lock not poisoned");
+        evts.deleted.insert(id);
+        Ok(())
+    }
+
+    async fn is_deleted(&self, id: Id) -> Result<bool, Self::Error> {
+        let evts = self.evts.lock().expect("lock not poisoned");
+        Ok(evts.deleted.contains(&id))
+    }
+
+    async fn last_seq_no(&self, id: Id) -> Result<Option<SeqNo>, Self::Error> {
+        let evts = self.evts.lock().expect("lock not poisoned");
+        Ok(evts.last_seq_nos.get(&id).copied())
+    }
+
+    async fn evts_by_id<E, FromBytes, FromBytesError>(
+        &self,
+        id: Id,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let predicate = move |evt: &PersistedEvt<Id>| evt.id == id && evt.seq_no >= from_seq_no;
+        Ok(self.persisted_evts(0, predicate, from_bytes))
+    }
+
+    async fn evts_by_ids<E, FromBytes, FromBytesError>(
+        &self,
+        ids: Vec<Id>,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let from_index = from_global_seq_no.as_u64() as usize - 1;
+        let predicate = move |evt: &PersistedEvt<Id>| ids.contains(&evt.id);
+        Ok(self.persisted_evts(from_index, predicate, from_bytes))
+    }
+
+    async fn evts<E, FromBytes, FromBytesError>(
+        &self,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let from_index = from_global_seq_no.as_u64() as usize - 1;
+        Ok(self.persisted_evts(from_index, |_| true, from_bytes))
+    }
+
+    async fn evts_by_type<E, FromBytes, FromBytesError>(
+        &self,
+        entity_type: String,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let from_index = from_global_seq_no.as_u64() as usize - 1;
+        let predicate =
+            move |evt: &PersistedEvt<Id>| evt.entity_type.as_deref() == Some(entity_type.as_str());
+        Ok(self.persisted_evts(from_index, predicate, from_bytes))
+    }
+
+    async fn evts_by_tag<E, FromBytes, FromBytesError>(
+        &self,
+        tag: String,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> Result<impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send, Self::Error>
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let predicate =
+            move |evt: &PersistedEvt<Id>| evt.tags.contains(&tag) && evt.seq_no >= from_seq_no;
+        Ok(self.persisted_evts(0, predicate, from_bytes))
+    }
+}
+
+/// Errors from the [MemoryEvtLog].
+#[derive(Debug, Error)]
+pub enum MemoryEvtLogError {
+    /// The given last sequence number does not match the actual one of the entity.
+    #[error("expected last sequence number {expected:?}, but was {actual:?}")]
+    SeqNoConflict {
+        expected: Option<SeqNo>,
+        actual: Option<SeqNo>,
+    },
+
+    /// An event cannot be converted into bytes.
+    #[error("cannot convert an event to bytes")]
+    ToBytes(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// Bytes cannot be converted to an event.
+    #[error("cannot convert bytes to an event")]
+    FromBytes(#[source] Box<dyn StdError + Send + Sync>),
+}
+
+#[derive(Debug)]
+struct Evts<Id> {
+    /// Deleted events are `None` to keep the global sequence numbers, which are the indices plus
+    /// one, stable.
+    evts: Vec<Option<PersistedEvt<Id>>>,
+    last_seq_nos: HashMap<Id, SeqNo>,
+    deleted: HashSet<Id>,
+}
+
+impl<Id> Default for Evts<Id> {
+    fn default() -> Self {
+        Self {
+            evts: Vec::new(),
+            last_seq_nos: HashMap::new(),
+            deleted: HashSet::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PersistedEvt<Id> {
+    id: Id,
+    entity_type: Option<String>,
+    seq_no: SeqNo,
+    version: u32,
+    timestamp: DateTime<Utc>,
+    tags: Vec<String>,
+    bytes: Bytes,
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use crate::{convert, EvtExt, IntoTaggedEvt, TestClock};
+    use futures::{StreamExt, TryStreamExt};
+    use std::{future, time::Duration};
+
+    #[tokio::test]
+    async fn test_evt_log() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let evt_log = MemoryEvtLog::default();
+
+        let id = Uuid::now_v7();
+
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, None);
+
+        let last_seq_no = evt_log
+            .persist(
+                &1,
+                1,
+                &["tag".to_string()],
+                id,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+        assert!(last_seq_no.as_u64() == 1);
+
+        evt_log
+            .persist(
+                &2,
+                1,
+                &[],
+                id,
+                None,
+                Some(last_seq_no),
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let result = evt_log
+            .persist(
+                &3,
+                1,
+                &[],
+                id,
+                None,
+                Some(last_seq_no),
+                &convert::prost::to_bytes,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(MemoryEvtLogError::SeqNoConflict { .. })
+        ));
+
+        evt_log
+            .persist(
+                &3,
+                1,
+                &["tag".to_string()],
+                id,
+                None,
+                Some(last_seq_no.succ()),
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, Some(3.try_into()?));
+
+        let report = evt_log.verify(id).await?;
+        assert!(report.is_consistent());
+        assert_eq!(report.evt_count, 3);
+
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id, 2.try_into()?, convert::prost::from_bytes)
+            .await?;
+        let sum = evts
+            .take(2)
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
+            .await?;
+        assert_eq!(sum, 5);
+
+        let current_evts = evt_log
+            .evts_by_id_from::<i32, _, _>(id, 2.try_into()?, convert::prost::from_bytes)
+            .await?
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(current_evts, vec![2, 3]);
+
+        let current_evts = evt_log
+            .evts_by_id_from::<i32, _, _>(id, 4.try_into()?, convert::prost::from_bytes)
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert!(current_evts.is_empty());
+
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let evts_by_tag = evt_log
+            .evts_by_tag::<i32, _, _>("tag".to_string(), SeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+
+        let id_2 = Uuid::now_v7();
+        evt_log
+            .clone()
+            .persist(&4, 1, &[], id, None, last_seq_no, &convert::prost::to_bytes)
+            .await?;
+        evt_log
+            .clone()
+            .persist(
+                &5,
+                1,
+                &["tag".to_string()],
+                id_2,
+                None,
+                None,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let sum = evts
+            .take(4)
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
+            .await?;
+        assert_eq!(sum, 10);
+
+        let sum = evts_by_tag
+            .take(3)
+            .try_fold(0i32, |acc, evt| future::ready(Ok(acc + evt.evt)))
+            .await?;
+        assert_eq!(sum, 9);
+
+        let evts = evt_log
+            .evts::<i32, _, _>(GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let evts = evts
+            .take(5)
+            .map_ok(|evt| (evt.global_seq_no.as_u64(), evt.id, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(
+            evts,
+            vec![(1, id, 1), (2, id, 2), (3, id, 3), (4, id, 4), (5, id_2, 5)]
+        );
+
+        let evts_by_ids = evt_log
+            .evts_by_ids::<i32, _, _>(vec![id_2], GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let evts_by_ids = evts_by_ids
+            .take(1)
+            .map_ok(|evt| (evt.seq_no, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts_by_ids, vec![(SeqNo::MIN, 5)]);
+
+        evt_log.delete_to(id, 2.try_into()?).await?;
+        evt_log.delete_to(id_2, SeqNo::MIN).await?;
+        let last_seq_no = evt_log.last_seq_no(id_2).await?;
+        assert_eq!(last_seq_no, Some(SeqNo::MIN));
+        let evts = evt_log
+            .evts::<i32, _, _>(GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let evts = evts
+            .take(3)
+            .map_ok(|evt| (evt.global_seq_no.as_u64(), evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(3, 3), (4, 4), (5, 5)]);
+
+        assert!(!evt_log.is_deleted(id).await?);
+        evt_log.mark_deleted(id).await?;
+        evt_log.mark_deleted(id).await?;
+        assert!(evt_log.is_deleted(id).await?);
+        assert!(!evt_log.is_deleted(id_2).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_persist_batch() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let evt_log = MemoryEvtLog::default();
+
+        let id = Uuid::now_v7();
+        let id_2 = Uuid::now_v7();
+
+        let evts = [1.with_tag("tag"), 2.into_tagged_evt()];
+        let evts_2 = [3.into_tagged_evt()];
+        let batch = [
+            EntityEvts {
+                id,
+                entity_type: Some("foo"),
+                last_seq_no: None,
+                version: 1,
+                evts: &evts,
+            },
+            EntityEvts {
+                id: id_2,
+                entity_type: None,
+                last_seq_no: None,
+                version: 2,
+                evts: &evts_2,
+            },
+        ];
+        let last_seq_nos = evt_log
+            .persist_batch(&batch, &convert::prost::to_bytes)
+            .await?;
+        assert_eq!(last_seq_nos, vec![Some(2.try_into()?), Some(SeqNo::MIN)]);
+
+        // A conflict for one entity must not persist any events.
+        let batch = [
+            EntityEvts {
+                id,
+                entity_type: Some("foo"),
+                last_seq_no: Some(2.try_into()?),
+                version: 1,
+                evts: &evts,
+            },
+            EntityEvts {
+                id: id_2,
+                entity_type: None,
+                last_seq_no: None,
+                version: 2,
+                evts: &evts_2,
+            },
+        ];
+        let result = evt_log
+            .persist_batch(&batch, &convert::prost::to_bytes)
+            .await;
+        assert!(matches!(
+            result,
+            Err(MemoryEvtLogError::SeqNoConflict { .. })
+        ));
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(2.try_into()?));
+
+        let evts = evt_log
+            .evts::<i32, _, _>(GlobalSeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let evts = evts
+            .take(3)
+            .map_ok(|evt| (evt.id, evt.seq_no.as_u64(), evt.version, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(id, 1, 1, 1), (id, 2, 1, 2), (id_2, 1, 2, 3)]);
+
+        let evts_by_type = evt_log
+            .evts_by_type::<i32, _, _>(
+                "foo".to_string(),
+                GlobalSeqNo::MIN,
+                convert::prost::from_bytes,
+            )
+            .await?
+            .take(2)
+            .map_ok(|evt| (evt.entity_type, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(
+            evts_by_type,
+            vec![(Some("foo".to_string()), 1), (Some("foo".to_string()), 2)]
+        );
+
+        // Later entries for the same entity continue after the former ones.
+        let evts_3 = [4.into_tagged_evt()];
+        let batch = [
+            EntityEvts {
+                id: id_2,
+                entity_type: None,
+                last_seq_no: Some(SeqNo::MIN),
+                version: 2,
+                evts: &evts_2,
+            },
+            EntityEvts {
+                id: id_2,
+                entity_type: None,
+                last_seq_no: None,
+                version: 3,
+                evts: &evts_3,
+            },
+        ];
+        let last_seq_nos = evt_log
+            .persist_batch(&batch, &convert::prost::to_bytes)
+            .await?;
+        assert_eq!(last_seq_nos, vec![Some(2.try_into()?), Some(3.try_into()?)]);
+        let evts = evt_log
+            .evts_by_id::<i32, _, _>(id_2, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .take(3)
+            .map_ok(|evt| (evt.seq_no.as_u64(), evt.version, evt.evt))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![(1, 2, 3), (2, 2, 3), (3, 3, 4)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_evt_log_clock() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let now = Utc::now();
+        let clock = TestClock::new(now);
+        let evt_log = MemoryEvtLog::default().with_clock(clock.clone());
+
+        let id = Uuid::now_v7();
+        let last_seq_no = evt_log
+            .persist(&1, 1, &[], id, None, None, &convert::prost::to_bytes)
+            .await?;
+        clock.advance(Duration::from_secs(1));
+        evt_log
+            .persist(
+                &2,
+                1,
+                &[],
+                id,
+                None,
+                Some(last_seq_no),
+                &convert::prost::to_bytes,
+            )
+            .await?;
+
+        let timestamps = evt_log
+            .evts_by_id_from::<i32, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .map_ok(|evt| evt.timestamp)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(timestamps, vec![now, now + chrono::Duration::seconds(1)]);
+
+        Ok(())
+    }
+}
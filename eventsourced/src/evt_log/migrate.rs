@@ -0,0 +1,264 @@
+use crate::{EntityId, EvtEnvelope, EvtLog, SeqNo, TestClock};
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use std::{convert::Infallible, error::Error as StdError, pin::pin};
+use thiserror::Error;
+use tracing::debug;
+
+/// Result of migrating events via [migrate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MigrateReport {
+    /// The number of entities, i.e. of given IDs.
+    pub entity_count: u64,
+
+    /// The number of events persisted to the target event log.
+    pub migrated_evts: u64,
+
+    /// The number of events skipped, because the target event log already contained them, e.g.
+    /// when resuming an interrupted migration.
+    pub skipped_evts: u64,
+}
+
+/// Error from [migrate].
+#[derive(Debug, Error)]
+pub enum MigrateError {
+    /// The last sequence number cannot be obtained from the target event log.
+    #[error("cannot get last sequence number from target event log")]
+    LastSeqNo(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// Events by ID cannot be obtained from the source event log.
+    #[error("cannot get events by ID from source event log")]
+    EvtsById(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// The next event cannot be obtained from the source event log.
+    #[error("cannot get next event from source event log")]
+    NextEvt(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// An event cannot be persisted to the target event log, e.g. because of a concurrent write.
+    #[error("cannot persist event to target event log")]
+    Persist(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// The sequence number of an event of the source event log does not continue the ones in the
+    /// target event log, e.g. because the events before have been deleted via
+    /// [EvtLog::delete_to].
+    #[error("sequence number {actual} of entity {id} does not continue with {expected}")]
+    SeqNo {
+        id: String,
+        expected: SeqNo,
+        actual: SeqNo,
+    },
+}
+
+/// Migrate the events of the entities with the given IDs from the source to the target event log,
+/// e.g. when moving to another backend, preserving their sequence numbers, versions, tags and
+/// entity types; global sequence numbers are assigned by the target event log.
+///
+/// For each entity, only the events after the last one in the target event log are migrated, such
+/// that an interrupted migration can simply be resumed. The target event log must not be written
+/// to concurrently; its optimistic concurrency check makes the migration fail otherwise.
+///
+/// To also preserve the timestamps, the target event log must use the given [TestClock], which is
+/// set to the timestamp of each event before persisting it; else the events are timestamped with
+/// the time of the migration.
+pub async fn migrate<S, T, I, Id>(
+    source: &S,
+    target: &mut T,
+    ids: I,
+    clock: Option<&TestClock>,
+) -> Result<MigrateReport, MigrateError>
+where
+    S: EvtLog<Id>,
+    T: EvtLog<Id>,
+    I: Stream<Item = Id>,
+    Id: EntityId,
+{
+    let mut report = MigrateReport::default();
+
+    let mut ids = pin!(ids);
+    while let Some(id) = ids.next().await {
+        report.entity_count += 1;
+
+        let mut last_seq_no = target
+            .last_seq_no(id.clone())
+            .await
+            .map_err(|error| MigrateError::LastSeqNo(error.into()))?;
+        report.skipped_evts += last_seq_no
+            .map(|seq_no| seq_no.as_u64())
+            .unwrap_or_default();
+        let from_seq_no = last_seq_no
+            .map(|seq_no| seq_no.succ())
+            .unwrap_or(SeqNo::MIN);
+        debug!(%id, %from_seq_no, "migrating events");
+
+        // Migrate the raw bytes, which need neither be converted nor upcasted.
+        let evts = source
+            .evts_by_id_from::<Bytes, _, _>(id.clone(), from_seq_no, Ok::<_, Infallible>)
+            .await
+            .map_err(|error| MigrateError::EvtsById(error.into()))?;
+        let mut evts = pin!(evts);
+        while let Some(evt) = evts.next().await {
+            let EvtEnvelope {
+                entity_type,
+                seq_no,
+                version,
+                timestamp,
+                tags,
+                evt,
+                ..
+            } = evt.map_err(|error| MigrateError::NextEvt(error.into()))?;
+
+            let expected = last_seq_no
+                .map(|seq_no| seq_no.succ())
+                .unwrap_or(SeqNo::MIN);
+            if seq_no != expected {
+                return Err(MigrateError::SeqNo {
+                    id: id.to_string(),
+                    expected,
+                    actual: seq_no,
+                });
+            }
+
+            if let Some(clock) = clock {
+                clock.set(timestamp);
+            }
+            let persisted_seq_no = target
+                .persist(
+                    &evt,
+                    version,
+                    &tags,
+                    id.clone(),
+                    entity_type.as_deref(),
+                    last_seq_no,
+                    &|bytes: &Bytes| Ok::<_, Infallible>(bytes.clone()),
+                )
+                .await
+                .map_err(|error| MigrateError::Persist(error.into()))?;
+            last_seq_no = Some(persisted_seq_no);
+            report.migrated_evts += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use crate::{convert, MemoryEvtLog};
+    use chrono::{DateTime, TimeZone, Utc};
+    use futures::{stream, TryStreamExt};
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    async fn evts(
+        evt_log: &MemoryEvtLog,
+        id: Uuid,
+    ) -> Result<Vec<(u64, u32, DateTime<Utc>, Vec<String>, Option<String>, u64)>, Box<dyn StdError>>
+    {
+        let evts = evt_log
+            .evts_by_id_from::<u64, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .map_ok(|evt| {
+                (
+                    evt.seq_no.as_u64(),
+                    evt.version,
+                    evt.timestamp,
+                    evt.tags,
+                    evt.entity_type,
+                    evt.evt,
+                )
+            })
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(evts)
+    }
+
+    #[tokio::test]
+    async fn test_migrate() -> Result<(), Box<dyn StdError>> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let source_clock = TestClock::new(start);
+        let source = MemoryEvtLog::default().with_clock(source_clock.clone());
+        let target_clock = TestClock::new(start);
+        let mut target = MemoryEvtLog::default().with_clock(target_clock.clone());
+
+        let id_1 = Uuid::now_v7();
+        let id_2 = Uuid::now_v7();
+        let mut last_seq_no = None;
+        for n in 1..=3_u64 {
+            source_clock.advance(Duration::from_secs(1));
+            let seq_no = source
+                .persist(
+                    &n,
+                    n as u32,
+                    &[format!("tag-{n}")],
+                    id_1,
+                    Some("type"),
+                    last_seq_no,
+                    &convert::prost::to_bytes,
+                )
+                .await?;
+            last_seq_no = Some(seq_no);
+        }
+        source
+            .persist(&4_u64, 1, &[], id_2, None, None, &convert::prost::to_bytes)
+            .await?;
+
+        let report = migrate(
+            &source,
+            &mut target,
+            stream::iter([id_1, id_2]),
+            Some(&target_clock),
+        )
+        .await?;
+        assert_eq!(
+            report,
+            MigrateReport {
+                entity_count: 2,
+                migrated_evts: 4,
+                skipped_evts: 0
+            }
+        );
+        assert_eq!(evts(&target, id_1).await?, evts(&source, id_1).await?);
+        assert_eq!(evts(&target, id_2).await?, evts(&source, id_2).await?);
+
+        // Resuming only migrates new events.
+        source
+            .persist(
+                &5_u64,
+                1,
+                &[],
+                id_2,
+                None,
+                Some(SeqNo::MIN),
+                &convert::prost::to_bytes,
+            )
+            .await?;
+        let report = migrate(&source, &mut target, stream::iter([id_1, id_2]), None).await?;
+        assert_eq!(
+            report,
+            MigrateReport {
+                entity_count: 2,
+                migrated_evts: 1,
+                skipped_evts: 4
+            }
+        );
+        assert_eq!(evts(&target, id_2).await?.len(), 2);
+
+        // Deleted events cannot be migrated with their sequence numbers preserved.
+        source.delete_to(id_1, 2.try_into()?).await?;
+        let result = migrate(
+            &source,
+            &mut MemoryEvtLog::default(),
+            stream::iter([id_1]),
+            None,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(MigrateError::SeqNo { expected, actual, .. })
+                if expected == SeqNo::MIN && actual.as_u64() == 3
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+//! Persistence for events.
+
+mod memory;
+mod migrate;
+mod verify;
+
+pub use memory::*;
+pub use migrate::*;
+pub use verify::*;
+
+use crate::{EntityId, EvtEnvelope, GlobalSeqNo, SeqNo, TaggedEvt};
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use std::{
+    collections::HashMap, convert::Infallible, error::Error as StdError, future::Future,
+    num::NonZeroU64,
+};
+use uuid::Uuid;
+
+/// Persistence for events of entities with IDs of the given type, see [EntityId].
+///
+/// The query methods yield the events wrapped in an [EvtEnvelope] carrying their metadata.
+///
+/// The streams returned by the query methods like [evts_by_id](EvtLog::evts_by_id) are live: they
+/// first yield the already persisted events and then stay open, yielding newly persisted events as
+/// they arrive, i.e. they do not terminate unless an error occurs. Hence they can be used for
+/// real-time projections; consumers only interested in the current events must stop consuming
+/// themselves, e.g. once the sequence number from [last_seq_no](EvtLog::last_seq_no) is reached,
+/// or use [evts_by_id_from](EvtLog::evts_by_id_from) which terminates after the current events.
+///
+/// Implementations must be cheap to clone and clones must share the underlying backend, e.g. a
+/// connection pool, such that a clone can be kept for administrative operations like
+/// [verify](EvtLog::verify) or [delete_to](EvtLog::delete_to) after another one has been passed
+/// to [spawn](crate::EventSourcedExt::spawn). Hence all methods take `&self`.
+pub trait EvtLog<Id = Uuid>: Clone + Send + 'static
+where
+    Id: EntityId,
+{
+    type Error: StdError + Send + Sync + 'static;
+
+    /// The maximum value for sequence numbers. Defaults to `u64::MAX` unless overriden by an
+    /// implementation.
+    const MAX_SEQ_NO: SeqNo = SeqNo::new(NonZeroU64::MAX);
+
+    /// Persist the given event with the given version and the given tags for the given entity ID
+    /// and optional entity type along with the current time as timestamp and return the sequence
+    /// number for the persisted event.
+    #[allow(clippy::too_many_arguments)]
+    fn persist<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Id,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> impl Future<Output = Result<SeqNo, Self::Error>> + Send
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static;
+
+    /// Like [persist](EvtLog::persist), but also return the global sequence number assigned to the
+    /// persisted event, e.g. to wait for a projection to catch up with it. Implementations which
+    /// do not know it when persisting may return `None`, which the default implementation, simply
+    /// delegating to [persist](EvtLog::persist), does.
+    #[allow(clippy::too_many_arguments)]
+    fn persist_returning_global_seq_no<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Id,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> impl Future<Output = Result<(SeqNo, Option<GlobalSeqNo>), Self::Error>> + Send
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        let seq_no = self.persist(evt, version, tags, id, entity_type, last_seq_no, to_bytes);
+        async move {
+            let seq_no = seq_no.await?;
+            Ok((seq_no, None))
+        }
+    }
+
+    /// Persist the given events for possibly multiple entities and return the new last sequence
+    /// numbers in the order of the given batch. Implementations should persist all events
+    /// atomically, i.e. either all or none. The default implementation persists one event after the
+    /// other via [persist](EvtLog::persist) and hence is not atomic.
+    ///
+    /// The batch may contain multiple entries for the same entity ID, e.g. for events with
+    /// different versions: only the `last_seq_no` of the first one is checked, the events of the
+    /// later ones continue after the events of the former ones.
+    fn persist_batch<E, ToBytes, ToBytesError>(
+        &self,
+        batch: &[EntityEvts<'_, E, Id>],
+        to_bytes: &ToBytes,
+    ) -> impl Future<Output = Result<Vec<Option<SeqNo>>, Self::Error>> + Send
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        // Clone to not require `Self: Sync`.
+        let evt_log = self.clone();
+
+        async move {
+            let mut last_seq_nos = Vec::with_capacity(batch.len());
+            let mut chained = HashMap::new();
+
+            for entity_evts in batch {
+                let mut last_seq_no = chained
+                    .get(&entity_evts.id)
+                    .copied()
+                    .unwrap_or(entity_evts.last_seq_no);
+                for evt in entity_evts.evts {
+                    let seq_no = evt_log
+                        .persist(
+                            evt.evt(),
+                            entity_evts.version,
+                            evt.tags(),
+                            entity_evts.id.clone(),
+                            entity_evts.entity_type,
+                            last_seq_no,
+                            to_bytes,
+                        )
+                        .await?;
+                    last_seq_no = Some(seq_no);
+                }
+                chained.insert(entity_evts.id.clone(), last_seq_no);
+                last_seq_nos.push(last_seq_no);
+            }
+
+            Ok(last_seq_nos)
+        }
+    }
+
+    /// Delete the events for the given entity ID up to and including the given sequence number,
+    /// e.g. to reclaim storage once a snapshot has been saved. The last event of the entity is
+    /// always kept, hence [last_seq_no](EvtLog::last_seq_no) is not affected.
+    fn delete_to(
+        &self,
+        id: Id,
+        to_seq_no: SeqNo,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Mark the given entity ID as deleted, such that it cannot be spawned anymore, see
+    /// [SpawnError::Deleted](crate::SpawnError::Deleted). The marker is stored in plain text, i.e.
+    /// without converting it to bytes, hence it stays readable when the events cannot be converted
+    /// anymore, e.g. after crypto-shredding. Events are neither deleted nor is an already spawned
+    /// entity affected. Marking an already deleted entity ID succeeds.
+    fn mark_deleted(&self, id: Id) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Whether the given entity ID has been marked as deleted, see
+    /// [mark_deleted](EvtLog::mark_deleted).
+    fn is_deleted(&self, id: Id) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Get the last sequence number for the given entity ID or `None` if no events have been
+    /// persisted for it yet.
+    fn last_seq_no(
+        &self,
+        id: Id,
+    ) -> impl Future<Output = Result<Option<SeqNo>, Self::Error>> + Send;
+
+    /// Get the events for the given entity ID starting with the given sequence number.
+    fn evts_by_id<E, FromBytes, FromBytesError>(
+        &self,
+        id: Id,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static;
+
+    /// Get the events for the given entity ID starting with the given sequence number up to and
+    /// including the current last one, i.e. unlike [evts_by_id](EvtLog::evts_by_id) the returned
+    /// stream is not live but terminates, e.g. for recovering an entity.
+    ///
+    /// The default implementation determines the last sequence number via
+    /// [last_seq_no](EvtLog::last_seq_no) beforehand; implementations should override it to avoid
+    /// this extra round-trip.
+    fn evts_by_id_from<E, FromBytes, FromBytesError>(
+        &self,
+        id: Id,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        // Create the futures upfront to not require `Self: Sync`.
+        let last_seq_no = self.last_seq_no(id.clone());
+        let evts = self.evts_by_id(id, from_seq_no, from_bytes);
+
+        async move {
+            let evts = match last_seq_no.await? {
+                Some(last_seq_no) if from_seq_no <= last_seq_no => {
+                    Some((Box::pin(evts.await?), last_seq_no))
+                }
+                _ => None,
+            };
+
+            // The events by ID stream is live, hence terminate after the last event or an error
+            // without polling it any further.
+            let evts = stream::unfold(evts, |state| async move {
+                let (mut evts, last_seq_no) = state?;
+                let evt = evts.next().await?;
+                let done = evt
+                    .as_ref()
+                    .map(|evt| evt.seq_no >= last_seq_no)
+                    .unwrap_or(true);
+                Some((evt, (!done).then_some((evts, last_seq_no))))
+            });
+            Ok(evts)
+        }
+    }
+
+    /// Get the events for the given entity IDs starting with the given global sequence number,
+    /// ordered by their global sequence numbers, i.e. interleaved in the order they were persisted.
+    fn evts_by_ids<E, FromBytes, FromBytesError>(
+        &self,
+        ids: Vec<Id>,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static;
+
+    /// Get all events starting with the given global sequence number, ordered by their global
+    /// sequence numbers, i.e. in the order they were persisted.
+    fn evts<E, FromBytes, FromBytesError>(
+        &self,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static;
+
+    /// Get the events of entities of the given type, see
+    /// [EventSourced::ENTITY_TYPE](crate::EventSourced::ENTITY_TYPE), starting with the given
+    /// global sequence number, ordered by their global sequence numbers, i.e. in the order they
+    /// were persisted.
+    fn evts_by_type<E, FromBytes, FromBytesError>(
+        &self,
+        entity_type: String,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static;
+
+    /// Get the events having the given tag starting with the given sequence number.
+    fn evts_by_tag<E, FromBytes, FromBytesError>(
+        &self,
+        tag: String,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static;
+
+    /// Verify that the sequence numbers of the events for the given entity ID are consistent, i.e.
+    /// strictly increasing without gaps, e.g. after a migration or on suspected corruption. The
+    /// events are scanned in a streaming fashion, stopping at the first issue.
+    ///
+    /// The default implementation scans the current events via
+    /// [evts_by_id_from](EvtLog::evts_by_id_from) without converting them; implementations should
+    /// override it to avoid loading the events themselves or if their sequence numbers are not
+    /// contiguous.
+    fn verify(&self, id: Id) -> impl Future<Output = Result<VerifyReport, Self::Error>> + Send {
+        // Create the future upfront to not require `Self: Sync`.
+        let evts = self.evts_by_id_from::<Bytes, _, _>(id, SeqNo::MIN, Ok::<_, Infallible>);
+
+        async move {
+            let seq_nos = evts.await?.map_ok(|evt| evt.seq_no);
+            verify_seq_nos(seq_nos, true).await
+        }
+    }
+
+    /// Check whether this event log is reachable, e.g. for a readiness probe before serving
+    /// traffic. The default implementation always succeeds and hence is only appropriate for
+    /// implementations without a remote backend.
+    fn ping(&self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async { Ok(()) }
+    }
+
+    /// Whether the given error is transient, e.g. caused by a lost connection or a timeout, such
+    /// that persisting can be retried according to the
+    /// [persist_retry_policy](crate::SpawnConfig::persist_retry_policy), as opposed to e.g. a
+    /// sequence number conflict, which would only occur again. As the failed attempt might have
+    /// been persisted nonetheless, only errors for which retrying cannot duplicate events must be
+    /// considered retryable. Returns `false` by default, i.e. no error is retried.
+    fn is_retryable(_error: &Self::Error) -> bool {
+        false
+    }
+}
+
+/// Events to be persisted for the given entity ID via [EvtLog::persist_batch].
+pub struct EntityEvts<'a, E, Id = Uuid> {
+    /// The entity ID.
+    pub id: Id,
+
+    /// The optional entity type, see [EvtLog::persist].
+    pub entity_type: Option<&'a str>,
+
+    /// The last sequence number of the entity, see [EvtLog::persist]; ignored for later entries
+    /// for the same entity ID in a batch, see [EvtLog::persist_batch].
+    pub last_seq_no: Option<SeqNo>,
+
+    /// The version for all events.
+    pub version: u32,
+
+    /// The events along with their tags.
+    pub evts: &'a [TaggedEvt<E>],
+}
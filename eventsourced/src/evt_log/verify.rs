@@ -0,0 +1,144 @@
+use crate::SeqNo;
+use futures::{Stream, StreamExt};
+use std::pin::pin;
+
+/// Result of verifying the sequence numbers of the events of an entity via
+/// [EvtLog::verify](crate::EvtLog::verify).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// The number of scanned events, i.e. up to and including the first issue, if any.
+    pub evt_count: u64,
+
+    /// The sequence number of the first scanned event.
+    pub first_seq_no: Option<SeqNo>,
+
+    /// The sequence number of the last scanned event.
+    pub last_seq_no: Option<SeqNo>,
+
+    /// The first issue, if any; scanning stops there.
+    pub issue: Option<VerifyIssue>,
+}
+
+impl VerifyReport {
+    /// Whether no issue has been found.
+    pub fn is_consistent(&self) -> bool {
+        self.issue.is_none()
+    }
+}
+
+/// Issue found by [EvtLog::verify](crate::EvtLog::verify).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyIssue {
+    /// The sequence numbers between the given ones are missing.
+    Gap { prev: SeqNo, next: SeqNo },
+
+    /// The given sequence number occurs more than once.
+    Duplicate(SeqNo),
+
+    /// The next sequence number is less than the previous one.
+    OutOfOrder { prev: SeqNo, next: SeqNo },
+}
+
+/// Verify the given sequence numbers of the events of an entity, stopping at the first error or
+/// issue. If `contiguous` is `false`, gaps are allowed, e.g. for event logs using sequence numbers
+/// shared by many entities. The first sequence number is not required to be [SeqNo::MIN], as
+/// the events before might have been deleted via [EvtLog::delete_to](crate::EvtLog::delete_to).
+pub async fn verify_seq_nos<S, E>(seq_nos: S, contiguous: bool) -> Result<VerifyReport, E>
+where
+    S: Stream<Item = Result<SeqNo, E>>,
+{
+    let mut report = VerifyReport {
+        evt_count: 0,
+        first_seq_no: None,
+        last_seq_no: None,
+        issue: None,
+    };
+
+    let mut seq_nos = pin!(seq_nos);
+    while let Some(seq_no) = seq_nos.next().await {
+        let seq_no = seq_no?;
+        report.evt_count += 1;
+        report.first_seq_no.get_or_insert(seq_no);
+
+        if let Some(prev) = report.last_seq_no {
+            let issue = if seq_no == prev {
+                Some(VerifyIssue::Duplicate(seq_no))
+            } else if seq_no < prev {
+                Some(VerifyIssue::OutOfOrder { prev, next: seq_no })
+            } else if contiguous && seq_no != prev.succ() {
+                Some(VerifyIssue::Gap { prev, next: seq_no })
+            } else {
+                None
+            };
+            if issue.is_some() {
+                report.last_seq_no = Some(seq_no);
+                report.issue = issue;
+                break;
+            }
+        }
+
+        report.last_seq_no = Some(seq_no);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use std::convert::Infallible;
+
+    async fn verify(seq_nos: &[u64], contiguous: bool) -> VerifyReport {
+        let seq_nos = seq_nos
+            .iter()
+            .map(|&seq_no| Ok::<_, Infallible>(seq_no.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        verify_seq_nos(stream::iter(seq_nos), contiguous)
+            .await
+            .unwrap()
+    }
+
+    fn seq_no(n: u64) -> SeqNo {
+        n.try_into().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verify_seq_nos() {
+        let report = verify(&[], true).await;
+        assert!(report.is_consistent());
+        assert_eq!(report.evt_count, 0);
+        assert_eq!(report.first_seq_no, None);
+
+        let report = verify(&[3, 4, 5], true).await;
+        assert!(report.is_consistent());
+        assert_eq!(report.evt_count, 3);
+        assert_eq!(report.first_seq_no, Some(seq_no(3)));
+        assert_eq!(report.last_seq_no, Some(seq_no(5)));
+
+        let report = verify(&[1, 2, 4, 5], true).await;
+        assert_eq!(
+            report.issue,
+            Some(VerifyIssue::Gap {
+                prev: seq_no(2),
+                next: seq_no(4)
+            })
+        );
+        assert_eq!(report.evt_count, 3);
+
+        let report = verify(&[1, 2, 4, 5], false).await;
+        assert!(report.is_consistent());
+
+        let report = verify(&[1, 2, 2, 3], false).await;
+        assert_eq!(report.issue, Some(VerifyIssue::Duplicate(seq_no(2))));
+
+        let report = verify(&[1, 3, 2], false).await;
+        assert_eq!(
+            report.issue,
+            Some(VerifyIssue::OutOfOrder {
+                prev: seq_no(3),
+                next: seq_no(2)
+            })
+        );
+    }
+}
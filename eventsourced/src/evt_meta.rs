@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+/// Prefix of the tags encoding [EvtMeta].
+const PREFIX: &str = "meta:";
+const CORRELATION_ID: &str = "correlation-id";
+const CAUSATION_ID: &str = "causation-id";
+const VALUE: &str = "value:";
+
+/// Metadata for tracing a chain of commands and events across entities, supplied via
+/// [handle_cmd_with_meta](crate::EntityRef::handle_cmd_with_meta). It is persisted along with the
+/// resulting event as tags with the reserved "meta:" prefix, such that it can be obtained via
+/// [EvtEnvelope::meta](crate::EvtEnvelope::meta) and the events with some correlation ID can be
+/// queried via [evts_by_tag](crate::EvtLog::evts_by_tag) with
+/// [correlation_tag](EvtMeta::correlation_tag). Keys of values must not contain "=".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EvtMeta {
+    pub correlation_id: Option<String>,
+    pub causation_id: Option<String>,
+    pub values: BTreeMap<String, String>,
+}
+
+impl EvtMeta {
+    /// Change the `correlation_id`.
+    pub fn with_correlation_id<T>(self, correlation_id: T) -> Self
+    where
+        T: ToString,
+    {
+        let correlation_id = Some(correlation_id.to_string());
+        Self {
+            correlation_id,
+            ..self
+        }
+    }
+
+    /// Change the `causation_id`.
+    pub fn with_causation_id<T>(self, causation_id: T) -> Self
+    where
+        T: ToString,
+    {
+        let causation_id = Some(causation_id.to_string());
+        Self {
+            causation_id,
+            ..self
+        }
+    }
+
+    /// Add the given value, which allows for chaining calls to `with_value`.
+    pub fn with_value<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: ToString,
+        V: ToString,
+    {
+        self.values.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Whether there is no metadata at all.
+    pub fn is_empty(&self) -> bool {
+        self.correlation_id.is_none() && self.causation_id.is_none() && self.values.is_empty()
+    }
+
+    /// The tag for events persisted with the given correlation ID.
+    pub fn correlation_tag(correlation_id: &str) -> String {
+        format!("{PREFIX}{CORRELATION_ID}={correlation_id}")
+    }
+
+    /// Encode this [EvtMeta] as tags.
+    pub fn to_tags(&self) -> Vec<String> {
+        let correlation_id = self.correlation_id.as_deref().map(Self::correlation_tag);
+        let causation_id = self
+            .causation_id
+            .as_deref()
+            .map(|causation_id| format!("{PREFIX}{CAUSATION_ID}={causation_id}"));
+        let values = self
+            .values
+            .iter()
+            .map(|(key, value)| format!("{PREFIX}{VALUE}{key}={value}"));
+        correlation_id
+            .into_iter()
+            .chain(causation_id)
+            .chain(values)
+            .collect()
+    }
+
+    /// Decode an [EvtMeta] from the given tags, ignoring all tags without the reserved prefix.
+    pub fn from_tags(tags: &[String]) -> Self {
+        tags.iter()
+            .filter_map(|tag| tag.strip_prefix(PREFIX))
+            .filter_map(|tag| tag.split_once('='))
+            .fold(Self::default(), |mut meta, (key, value)| {
+                match key {
+                    CORRELATION_ID => meta.correlation_id = Some(value.to_string()),
+                    CAUSATION_ID => meta.causation_id = Some(value.to_string()),
+                    key => {
+                        if let Some(key) = key.strip_prefix(VALUE) {
+                            meta.values.insert(key.to_string(), value.to_string());
+                        }
+                    }
+                }
+                meta
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evt_meta() {
+        let meta = EvtMeta::default();
+        assert!(meta.is_empty());
+        assert!(meta.to_tags().is_empty());
+
+        let meta = meta
+            .with_correlation_id("c1")
+            .with_causation_id("c0")
+            .with_value("user", "joe");
+        let tags = meta.to_tags();
+        assert_eq!(
+            tags,
+            vec![
+                "meta:correlation-id=c1",
+                "meta:causation-id=c0",
+                "meta:value:user=joe"
+            ]
+        );
+        assert_eq!(tags[0], EvtMeta::correlation_tag("c1"));
+
+        let mut tags = tags;
+        tags.push("tag".to_string());
+        assert_eq!(EvtMeta::from_tags(&tags), meta);
+    }
+}
@@ -11,92 +11,331 @@
 //!
 //! The [EvtLog] and [SnapshotStore] traits define a pluggable event log and a pluggable snapshot
 //! store respectively. For [NATS](https://nats.io/) and [Postgres](https://www.postgresql.org/)
-//! these are implemented in the respective crates.
+//! these are implemented in the respective crates. In-memory implementations, e.g. for testing, are
+//! provided by [MemoryEvtLog] and [MemorySnapshotStore]. To not overwhelm a backend, e.g. when
+//! spawning many entities at once, [ThrottledEvtLog] and [ThrottledSnapshotStore] limit the number
+//! of concurrent operations of any implementation and [CachingSnapshotStore] caches recently loaded
+//! snapshots, e.g. for frequently re-spawned passivated entities.
 //!
 //! The [spawn](EventSourcedExt::spawn) extension method provides for creating entities – "running"
-//! instances of an [EventSourced] implementation, identifiable by a [Uuid] – for some event log and
-//!  some snapshot store. Conversion of events and snapshot state to and from bytes happens via
-//! given [Binarizer] functions; for [prost](https://github.com/tokio-rs/prost) and
-//! [serde_json](https://github.com/serde-rs/json) these are already provided.
+//! instances of an [EventSourced] implementation, identifiable by an [EntityId], by default a
+//! [Uuid] – for some event log and some snapshot store. Conversion of events and snapshot state to
+//! and from bytes happens via given [Binarizer] functions; for
+//! [prost](https://github.com/tokio-rs/prost), [serde_json](https://github.com/serde-rs/json),
+//! [CBOR](https://cbor.io/), [bincode](https://github.com/bincode-org/bincode),
+//! [MessagePack](https://msgpack.org/) and [Avro](https://avro.apache.org/) these are already
+//! provided.
 //!
 //! Calling [spawn](EventSourcedExt::spawn) results in a cloneable [EntityRef] which can be used to
 //! pass commands to the spawned entity by invoking [handle_cmd](EntityRef::handle_cmd). Commands
 //! are handled by the command handler of the spawned entity. They can be rejected by returning an
-//! error. Valid commands produce an event with an optional tag which gets persisted to the [EvtLog]
+//! error. Valid commands produce an event with optional tags which gets persisted to the [EvtLog]
 //! and then applied to the event handler of the respective entity. The event handler may decide to
-//! save a snapshot which is used to speed up future spawning.
+//! save a snapshot which is used to speed up future spawning. On single-threaded runtimes,
+//! [spawn_local](EventSourcedExt::spawn_local) spawns entities which are not `Send`.
 //!
-//! Events can be queried from the event log by ID or by tag. These queries can be used to build
-//! read side projections.
+//! For applications with many entities, an [EntityManager] spawns them on first access by ID and
+//! caches their [EntityRef]s. For read-heavy deployments,
+//! [spawn_read_only](EventSourcedExt::spawn_read_only) creates a cheap, yet possibly stale
+//! [ReadOnlyEntity] from the latest snapshot only, whereas [replay_state] fully recovers the state
+//! of an entity without spawning it.
+//!
+//! Behind the `derive` feature, `#[derive(CmdDispatch)]` on a command enum generates a handler
+//! trait with one method per command variant, to which the command handler can dispatch instead
+//! of matching on the command itself.
+//!
+//! The command and event handlers of an [EventSourced] implementation can be tested
+//! deterministically and without any event log via the given-when-then helpers in the `test`
+//! module.
+//!
+//! Events can be queried from the event log by ID, by a set of IDs, by entity type, by tag or all
+//! together in the order they were persisted. These queries can be used to build read side
+//! projections which can persist their progress in a [ProjectionOffsetStore] to resume after a
+//! restart; [run_projection] drives a [Projection] that way with at-least-once delivery. When
+//! moving to another backend, [migrate] copies the events of entities between event logs.
+//!
+//! Behind the `metrics` feature, counters and histograms for handled and rejected commands,
+//! persisted events, saved snapshots, command handling and recovery durations are recorded via the
+//! [metrics](https://github.com/metrics-rs/metrics) crate, labeled with the entity type; their
+//! names are defined in the `metrics` module.
 
 pub mod convert;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod test;
 
+mod clock;
+mod cmd_buffer;
+mod cmd_context;
+mod effect;
+mod entity_id;
+mod entity_manager;
+mod evt_envelope;
 mod evt_log;
+mod evt_meta;
+mod projection;
+mod projection_offset_store;
+mod read_only_entity;
+mod retry;
 mod seq_no;
 mod snapshot_store;
+mod spawn_config;
 mod tagged_evt;
+mod throttle;
+mod upcaster;
 
+pub use clock::*;
+pub use cmd_buffer::{CmdBuffer, Overflow};
+pub use cmd_context::CmdContext;
+pub use effect::EffectExecutor;
+pub use entity_id::EntityId;
+pub use entity_manager::EntityManager;
+#[cfg(feature = "derive")]
+pub use eventsourced_derive::CmdDispatch;
+pub use evt_envelope::*;
 pub use evt_log::*;
+pub use evt_meta::EvtMeta;
+pub use projection::{run_projection, Projection, ProjectionError};
+pub use projection_offset_store::*;
+pub use read_only_entity::ReadOnlyEntity;
+pub use retry::*;
 pub use seq_no::*;
 pub use snapshot_store::*;
+pub use spawn_config::SpawnConfig;
 pub use tagged_evt::*;
+pub use throttle::{ThrottleError, ThrottledEvtLog, ThrottledSnapshotStore};
+pub use upcaster::*;
 
 use bytes::Bytes;
-use futures::StreamExt;
-use std::{error::Error as StdError, fmt::Debug, num::NonZeroUsize};
+use cmd_buffer::{cmd_channel, CmdSender, WeakCmdSender};
+use futures::{FutureExt, StreamExt};
+use std::{
+    any::Any,
+    convert::Infallible,
+    error::Error as StdError,
+    fmt::Debug,
+    future::Future,
+    num::NonZeroU64,
+    ops::RangeInclusive,
+    panic::AssertUnwindSafe,
+    slice,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 use thiserror::Error;
 use tokio::{
-    pin,
-    sync::{mpsc, oneshot},
-    task,
+    pin, select,
+    sync::{mpsc, oneshot, Notify},
+    task::{self, JoinError, JoinHandle},
+    time::{sleep, timeout},
+    try_join,
 };
-use tracing::{debug, error};
+use tracing::{debug, error, field, warn, Instrument, Level, Span};
 use uuid::Uuid;
 
-/// Command and event handling for an event sourced entity.
-pub trait EventSourced: Sized + Send + 'static {
+/// Like [tracing::span], but for a level only known at runtime.
+macro_rules! span {
+    ($level:expr, $($arg:tt)+) => {
+        match $level {
+            Level::ERROR => tracing::error_span!($($arg)+),
+            Level::WARN => tracing::warn_span!($($arg)+),
+            Level::INFO => tracing::info_span!($($arg)+),
+            Level::DEBUG => tracing::debug_span!($($arg)+),
+            Level::TRACE => tracing::trace_span!($($arg)+),
+        }
+    };
+}
+
+/// Command and event handling for an event sourced entity with an ID of the given type, see
+/// [EntityId].
+///
+/// Spawning an entity onto a multi-threaded runtime via [spawn](EventSourcedExt::spawn) requires
+/// the implementation as well as its command, event and state types to be `Send` – commands and
+/// events also to be `Sync`; [spawn_local](EventSourcedExt::spawn_local) does not.
+pub trait EventSourced<Id = Uuid>: Sized + 'static
+where
+    Id: EntityId,
+{
     /// Command type.
-    type Cmd: Send + Sync;
+    type Cmd;
 
-    /// Event type.
-    type Evt: Send + Sync;
+    /// Event type. Events of commands handled via [handle_cmds](EntityRef::handle_cmds) are
+    /// cloned, as these are applied before being persisted, yet passed to
+    /// [on_evts_persisted](EventSourced::on_evts_persisted) afterwards.
+    type Evt: Clone;
 
     /// Snapshot state type.
-    type State: Send;
+    type State;
 
     /// Error type for rejected (a.k.a. invalid) commands.
     type Error: StdError + Send + Sync + 'static;
 
-    /// Command handler, returning the to be persisted event or an error.
+    /// Deferred side effect type, e.g. sending an email, returned by the command handler along
+    /// with the event via [TaggedEvt::with_effect] and executed via the
+    /// [effect_executor](EventSourced::effect_executor) only after the event has been persisted.
+    /// Use `()` if not needed.
+    type Effect;
+
+    /// The type of the entity, persisted along with its events, such that entities of different
+    /// types can share one [EvtLog], yet their events can be told apart, see
+    /// [EvtLog::evts_by_type]. Defaults to `None`.
+    const ENTITY_TYPE: Option<&'static str> = None;
+
+    /// The version of the [State](EventSourced::State), saved along with snapshots. Snapshots saved
+    /// with another version are not loaded, but the entity is recovered from its events, hence it
+    /// must be increased when the state changes incompatibly. Defaults to `1`.
+    const STATE_VERSION: u32 = 1;
+
+    /// Command handler, returning the to be persisted event, possibly along with tags and deferred
+    /// effects, or an error.
     fn handle_cmd(
         &self,
-        id: Uuid,
+        id: Id,
+        cmd: Self::Cmd,
+    ) -> Result<impl IntoTaggedEvt<Self::Evt, Self::Effect>, Self::Error>;
+
+    /// Command handler for commands sent via
+    /// [handle_cmd_streaming](EntityRef::handle_cmd_streaming), returning any number of to be
+    /// persisted events or an error, e.g. for bulk operations. The events are persisted via a
+    /// single [EvtLog::persist_batch] call and then applied one after the other, reporting the
+    /// progress to the caller after each one. Delegates to
+    /// [handle_cmd](EventSourced::handle_cmd), i.e. returns a single event, by default.
+    #[allow(clippy::type_complexity)]
+    fn handle_cmd_streaming(
+        &self,
+        id: Id,
         cmd: Self::Cmd,
-    ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error>;
+    ) -> Result<Vec<TaggedEvt<Self::Evt, Self::Effect>>, Self::Error> {
+        self.handle_cmd(id, cmd)
+            .map(|tagged_evt| vec![tagged_evt.into_tagged_evt()])
+    }
 
-    /// Event handler, returning whether to take a snapshot or not.
-    fn handle_evt(&mut self, evt: Self::Evt) -> Option<Self::State>;
+    /// Event handler, returning whether to take a snapshot or not or an error, e.g. if applying
+    /// the event would violate an invariant. An error during recovery fails spawning the entity
+    /// with [SpawnError::ApplyEvt], an error after persisting the event for a command terminates
+    /// the entity.
+    fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error>;
+
+    /// Event handler used when replaying events on recovery, with the sequence number of the event.
+    /// As no snapshots are taken on recovery, implementations can skip deciding whether to take one
+    /// on this hot path. An error fails spawning the entity with [SpawnError::ApplyEvt]. Delegates
+    /// to [handle_evt](EventSourced::handle_evt), ignoring its snapshot state, by default.
+    fn apply_evt(&mut self, _seq_no: SeqNo, evt: Self::Evt) -> Result<(), Self::Error> {
+        self.handle_evt(evt).map(|_| ())
+    }
 
     /// Snapshot state handler.
     fn set_state(&mut self, state: Self::State);
+
+    /// Invoked once after the given events with the given range of sequence numbers have been
+    /// successfully persisted for a command, e.g. to update a secondary index in one go. Unlike
+    /// [handle_evt](EventSourced::handle_evt) this gets all events persisted at once: for
+    /// [handle_cmd](EntityRef::handle_cmd) the single event of the command, for
+    /// [handle_cmd_streaming](EntityRef::handle_cmd_streaming) all events of the command, before
+    /// they are applied, and for [handle_cmds](EntityRef::handle_cmds) all events of the batch,
+    /// after they have been applied, as each command must see the former events. Depending on the
+    /// [EvtLog] the range may contain gaps, e.g. for NATS. Does nothing by default.
+    fn on_evts_persisted(&self, _seq_nos: RangeInclusive<SeqNo>, _evts: &[Self::Evt]) {}
+
+    /// Invoked when recovery – restoring the snapshot and replaying the events – has completed,
+    /// with the sequence number of the last applied event, if any. Runs before any command is
+    /// handled, so it can be used e.g. to schedule timers or warm caches. Does nothing by default.
+    fn on_recovery_completed(&mut self, _seq_no: Option<SeqNo>) {}
+
+    /// Whether this entity is in a terminal state, e.g. after having applied a tombstone event
+    /// signaling that it has been (soft) deleted. Checked after each applied event; once terminal,
+    /// the entity saves a final snapshot, see [terminal_state](EventSourced::terminal_state),
+    /// terminates and rejects all further commands with [EntityRefError::Deleted]. Also checked
+    /// after recovery, where spawning a deleted entity fails with [SpawnError::Deleted], which
+    /// thanks to the final snapshot does not need to replay its history. Combined with encrypting
+    /// the events with a per-entity key which gets discarded on deletion (crypto-shredding), this
+    /// allows for erasure without modifying the append-only event log; then the entity should also
+    /// be marked as deleted via [EvtLog::mark_deleted], as its events and snapshots cannot be
+    /// converted anymore. Returns `false` by default.
+    fn is_terminal(&self) -> bool {
+        false
+    }
+
+    /// The snapshot state saved as final snapshot once an applied event has made the entity
+    /// terminal, see [is_terminal](EventSourced::is_terminal), unless the event handler has
+    /// already returned one for that event. Returns `None`, i.e. no final snapshot, by default.
+    fn terminal_state(&self) -> Option<Self::State> {
+        None
+    }
+
+    /// Enrich the given event produced by the command handler with cross-cutting metadata from the
+    /// given [CmdContext] supplied by the caller, e.g. a correlation ID or the user, before it gets
+    /// persisted and applied. Commands handled as a batch get an empty context. Returns the given
+    /// event as is by default.
+    fn enrich(&self, evt: Self::Evt, _ctx: &CmdContext) -> Self::Evt {
+        evt
+    }
+
+    /// The version to persist the given event with, e.g. to be used for upcasting, see
+    /// [upcaster](EventSourced::upcaster). Returns `1` by default.
+    fn evt_version(&self, _evt: &Self::Evt) -> u32 {
+        1
+    }
+
+    /// Optional [Upcaster] applied to the bytes of persisted events along with the version they
+    /// were persisted with, before converting them into events during recovery. Returns `None`
+    /// by default.
+    fn upcaster(&self) -> Option<&dyn Upcaster> {
+        None
+    }
+
+    /// Optional [EffectExecutor] for the deferred effects returned by the command handler, invoked
+    /// only after their event has been persisted, such that no effect is performed for an event
+    /// which failed to persist. Without one, effects are dropped with a warning. Returns `None` by
+    /// default.
+    fn effect_executor(&self) -> Option<&dyn EffectExecutor<Self::Effect>> {
+        None
+    }
 }
 
 /// Extension methods for types implementing [EventSourced].
-pub trait EventSourcedExt {
+pub trait EventSourcedExt<Id = Uuid>
+where
+    Id: EntityId,
+{
     /// Spawns an entity implementing [EventSourced] with the given ID and creates an [EntityRef]
     /// as a handle for it.
     ///
     /// First the given [SnapshotStore] is used to find and possibly load a snapshot. Then the
-    /// [EvtLog] is used to find the last sequence number and then to load any remaining events.
+    /// [EvtLog] is used to load any remaining events up to the current last one.
     ///
     /// Commands can be passed to the spawned entity by invoking `handle_cmd` on the returned
-    /// [EntityRef] which uses a buffered channel with the given size.
+    /// [EntityRef] which uses a buffer with the size and [Overflow] strategy of the given
+    /// [SpawnConfig], see [CmdBuffer]; a plain [NonZeroUsize](std::num::NonZeroUsize) size means
+    /// [Overflow::Block] and the defaults for the other options of the [SpawnConfig]. The buffer
+    /// decouples callers from command handling: it absorbs bursts of up to its size, beyond
+    /// which the [Overflow] strategy applies. As commands are handled one after the other, a
+    /// larger buffer increases the latency of buffered commands. See
+    /// [CmdBuffer::recommended_size] and [SpawnConfig::cmd_buffer_full_threshold] for tuning.
     ///
     /// Commands are handled by the command handler of the spawned entity. They can be rejected by
-    /// returning an error. Valid commands produce an event with an optional tag which gets
+    /// returning an error. Valid commands produce an event with optional tags which gets
     /// persisted to the [EvtLog] and then applied to the event handler of the respective
     /// entity. The event handler may decide to save a snapshot which is used to speed up future
     /// spawning.
+    ///
+    /// If the entity has been marked as deleted (see [EvtLog::mark_deleted]), spawning fails with
+    /// [SpawnError::Deleted] before loading its snapshot or events; likewise if it is in a
+    /// terminal state after recovery (see [EventSourced::is_terminal]). A spawned entity can be
+    /// shut down, awaiting its termination, via [EntityRef::shutdown].
+    ///
+    /// The given [EvtLog] and [SnapshotStore] are moved into the spawned task. As clones share the
+    /// underlying backend, a clone can be kept for administrative operations:
+    ///
+    /// ```ignore
+    /// let entity = Counter::default()
+    ///     .spawn(id, config, evt_log.clone(), snapshot_store.clone(), binarizer)
+    ///     .await?;
+    /// let report = evt_log.verify(id).await?;
+    /// ```
     #[allow(async_fn_in_trait)]
     async fn spawn<
         L,
@@ -110,17 +349,77 @@ pub trait EventSourcedExt {
         StateFromBytes,
         StateFromBytesError,
     >(
-        mut self,
-        id: Uuid,
-        cmd_buffer: NonZeroUsize,
+        self,
+        id: Id,
+        config: impl Into<SpawnConfig>,
         evt_log: L,
         snapshot_store: S,
         binarizer: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
-    ) -> Result<EntityRef<Self>, SpawnError>
+    ) -> Result<EntityRef<Self, Id>, SpawnError>
     where
-        Self: EventSourced,
-        L: EvtLog,
-        S: SnapshotStore,
+        Self: EventSourced<Id> + Send,
+        Self::Cmd: Send + Sync,
+        Self::Evt: Send + Sync,
+        Self::State: Send,
+        Self::Effect: Send,
+        L: EvtLog<Id>,
+        S: SnapshotStore<Id>,
+        EvtToBytes: Fn(&Self::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+        EvtToBytesError: StdError + Send + Sync + 'static,
+        StateToBytes: Fn(&Self::State) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
+        StateToBytesError: StdError + Send + Sync + 'static,
+        EvtFromBytes:
+            Fn(Bytes) -> Result<Self::Evt, EvtFromBytesError> + Copy + Send + Sync + 'static,
+        EvtFromBytesError: StdError + Send + Sync + 'static,
+        StateFromBytes:
+            Fn(Bytes) -> Result<Self::State, StateFromBytesError> + Copy + Send + Sync + 'static,
+        StateFromBytesError: StdError + Send + Sync + 'static,
+    {
+        spawn_entity(
+            self,
+            id,
+            config.into(),
+            evt_log,
+            snapshot_store,
+            binarizer,
+            None,
+            None::<(_, fn(SeqNo, SeqNo))>,
+        )
+        .await
+        .map(|(entity_ref, _)| entity_ref)
+    }
+
+    /// Like [spawn](EventSourcedExt::spawn), but also returning a [SpawnReport] on how the entity
+    /// has been recovered, e.g. to assert that snapshots are actually used or to measure the cost
+    /// of a cold start.
+    #[allow(async_fn_in_trait)]
+    async fn spawn_with_report<
+        L,
+        S,
+        EvtToBytes,
+        EvtToBytesError,
+        StateToBytes,
+        StateToBytesError,
+        EvtFromBytes,
+        EvtFromBytesError,
+        StateFromBytes,
+        StateFromBytesError,
+    >(
+        self,
+        id: Id,
+        config: impl Into<SpawnConfig>,
+        evt_log: L,
+        snapshot_store: S,
+        binarizer: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
+    ) -> Result<(EntityRef<Self, Id>, SpawnReport), SpawnError>
+    where
+        Self: EventSourced<Id> + Send,
+        Self::Cmd: Send + Sync,
+        Self::Evt: Send + Sync,
+        Self::State: Send,
+        Self::Effect: Send,
+        L: EvtLog<Id>,
+        S: SnapshotStore<Id>,
         EvtToBytes: Fn(&Self::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
         EvtToBytesError: StdError + Send + Sync + 'static,
         StateToBytes: Fn(&Self::State) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
@@ -132,149 +431,1328 @@ pub trait EventSourcedExt {
             Fn(Bytes) -> Result<Self::State, StateFromBytesError> + Copy + Send + Sync + 'static,
         StateFromBytesError: StdError + Send + Sync + 'static,
     {
-        let Binarizer {
+        spawn_entity(
+            self,
+            id,
+            config.into(),
+            evt_log,
+            snapshot_store,
+            binarizer,
+            None,
+            None::<(_, fn(SeqNo, SeqNo))>,
+        )
+        .await
+    }
+
+    /// Like [spawn](EventSourcedExt::spawn), but without any snapshots, i.e. using the
+    /// [NoopSnapshotStore] and hence always replaying all events, such that only conversion
+    /// functions for events are needed.
+    #[allow(async_fn_in_trait)]
+    async fn spawn_without_snapshots<
+        L,
+        EvtToBytes,
+        EvtToBytesError,
+        EvtFromBytes,
+        EvtFromBytesError,
+    >(
+        self,
+        id: Id,
+        config: impl Into<SpawnConfig>,
+        evt_log: L,
+        evt_to_bytes: EvtToBytes,
+        evt_from_bytes: EvtFromBytes,
+    ) -> Result<EntityRef<Self, Id>, SpawnError>
+    where
+        Self: EventSourced<Id> + Send,
+        Self::Cmd: Send + Sync,
+        Self::Evt: Send + Sync,
+        Self::State: Send,
+        Self::Effect: Send,
+        L: EvtLog<Id>,
+        EvtToBytes: Fn(&Self::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+        EvtToBytesError: StdError + Send + Sync + 'static,
+        EvtFromBytes:
+            Fn(Bytes) -> Result<Self::Evt, EvtFromBytesError> + Copy + Send + Sync + 'static,
+        EvtFromBytesError: StdError + Send + Sync + 'static,
+    {
+        // The state conversion functions are never invoked by the NoopSnapshotStore.
+        let binarizer = Binarizer {
             evt_to_bytes,
             evt_from_bytes,
-            state_to_bytes,
-            state_from_bytes,
-        } = binarizer;
+            state_to_bytes: |_: &Self::State| Ok::<_, Infallible>(Bytes::new()),
+            state_from_bytes: |_| -> Result<Self::State, Infallible> {
+                unreachable!("NoopSnapshotStore never loads a snapshot")
+            },
+        };
 
-        // Restore snapshot.
-        let snapshot_seq_no = snapshot_store
-            .load::<Self::State, _, _>(id, state_from_bytes)
-            .await
-            .map_err(|error| SpawnError::LoadSnapshot(error.into()))?
-            .map(|Snapshot { seq_no, state }| {
-                debug!(%id, %seq_no, "restoring snapshot");
-                self.set_state(state);
-                seq_no
-            });
-
-        // Replay latest events.
-        let last_seq_no = evt_log
-            .last_seq_no(id)
+        self.spawn(id, config, evt_log, NoopSnapshotStore, binarizer)
             .await
-            .map_err(|error| SpawnError::LastSeqNo(error.into()))?;
-        assert!(
-            snapshot_seq_no <= last_seq_no,
-            "snapshot_seq_no must be less than or equal to last_seq_no"
-        );
-        if snapshot_seq_no < last_seq_no {
-            let from_seq_no = snapshot_seq_no.unwrap_or(SeqNo::MIN);
-            let to_seq_no = last_seq_no.unwrap_or(SeqNo::MIN);
-            debug!(%id, %from_seq_no, %to_seq_no , "replaying evts");
-            let evts = evt_log
-                .evts_by_id::<Self::Evt, _, _>(id, from_seq_no, evt_from_bytes)
-                .await
-                .map_err(|error| SpawnError::EvtsById(error.into()))?;
-            pin!(evts);
-            while let Some(evt) = evts.next().await {
-                let (seq_no, evt) = evt.map_err(|error| SpawnError::NextEvt(error.into()))?;
-                self.handle_evt(evt);
-                if seq_no == to_seq_no {
-                    break;
-                }
-            }
-        }
+    }
 
-        // Create entity.
-        let mut entity = Entity {
-            event_sourced: self,
+    /// Like [spawn](EventSourcedExt::spawn), but invoking the given `on_progress` callback with
+    /// the sequence numbers of the current and of the last event every `progress_interval`
+    /// replayed events, e.g. to report the progress of replaying a long history.
+    #[allow(async_fn_in_trait)]
+    #[allow(clippy::too_many_arguments)]
+    async fn spawn_with_progress<
+        L,
+        S,
+        EvtToBytes,
+        EvtToBytesError,
+        StateToBytes,
+        StateToBytesError,
+        EvtFromBytes,
+        EvtFromBytesError,
+        StateFromBytes,
+        StateFromBytesError,
+        P,
+    >(
+        self,
+        id: Id,
+        config: impl Into<SpawnConfig>,
+        evt_log: L,
+        snapshot_store: S,
+        binarizer: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
+        progress_interval: NonZeroU64,
+        on_progress: P,
+    ) -> Result<EntityRef<Self, Id>, SpawnError>
+    where
+        Self: EventSourced<Id> + Send,
+        Self::Cmd: Send + Sync,
+        Self::Evt: Send + Sync,
+        Self::State: Send,
+        Self::Effect: Send,
+        L: EvtLog<Id>,
+        S: SnapshotStore<Id>,
+        EvtToBytes: Fn(&Self::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+        EvtToBytesError: StdError + Send + Sync + 'static,
+        StateToBytes: Fn(&Self::State) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
+        StateToBytesError: StdError + Send + Sync + 'static,
+        EvtFromBytes:
+            Fn(Bytes) -> Result<Self::Evt, EvtFromBytesError> + Copy + Send + Sync + 'static,
+        EvtFromBytesError: StdError + Send + Sync + 'static,
+        StateFromBytes:
+            Fn(Bytes) -> Result<Self::State, StateFromBytesError> + Copy + Send + Sync + 'static,
+        StateFromBytesError: StdError + Send + Sync + 'static,
+        P: Fn(SeqNo, SeqNo) + Send,
+    {
+        spawn_entity(
+            self,
             id,
-            last_seq_no,
+            config.into(),
             evt_log,
             snapshot_store,
-            evt_to_bytes,
-            state_to_bytes,
-        };
-        debug!(%id, "entity created");
-
-        let (cmd_in, mut cmd_out) = mpsc::channel::<(
-            Self::Cmd,
-            oneshot::Sender<Result<(), Self::Error>>,
-        )>(cmd_buffer.get());
-
-        // Spawn handler loop.
-        task::spawn(async move {
-            while let Some((cmd, result_sender)) = cmd_out.recv().await {
-                match entity.handle_cmd(cmd).await {
-                    Ok(result) => {
-                        if result_sender.send(result).is_err() {
-                            error!(%id, "cannot send command handler result");
-                        };
-                    }
-                    Err(error) => {
-                        error!(%id, %error, "cannot persist event");
-                        break;
-                    }
-                }
-            }
-            debug!(%id, "entity terminated");
-        });
+            binarizer,
+            None,
+            Some((progress_interval, on_progress)),
+        )
+        .await
+        .map(|(entity_ref, _)| entity_ref)
+    }
 
-        Ok(EntityRef { id, cmd_in })
+    /// Like [spawn](EventSourcedExt::spawn), but restoring the given snapshot, e.g. one already
+    /// held in memory, instead of loading one from the [SnapshotStore], which is only used for
+    /// saving further snapshots. Only the events after the sequence number of the given snapshot
+    /// are replayed; if it is greater than the last sequence number of the [EvtLog],
+    /// [SpawnError::InvalidSnapshot] is returned.
+    #[allow(async_fn_in_trait)]
+    async fn spawn_with_snapshot<
+        L,
+        S,
+        EvtToBytes,
+        EvtToBytesError,
+        StateToBytes,
+        StateToBytesError,
+        EvtFromBytes,
+        EvtFromBytesError,
+        StateFromBytes,
+        StateFromBytesError,
+    >(
+        self,
+        id: Id,
+        config: impl Into<SpawnConfig>,
+        evt_log: L,
+        snapshot_store: S,
+        binarizer: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
+        snapshot: Snapshot<Self::State>,
+    ) -> Result<EntityRef<Self, Id>, SpawnError>
+    where
+        Self: EventSourced<Id> + Send,
+        Self::Cmd: Send + Sync,
+        Self::Evt: Send + Sync,
+        Self::State: Send,
+        Self::Effect: Send,
+        L: EvtLog<Id>,
+        S: SnapshotStore<Id>,
+        EvtToBytes: Fn(&Self::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+        EvtToBytesError: StdError + Send + Sync + 'static,
+        StateToBytes: Fn(&Self::State) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
+        StateToBytesError: StdError + Send + Sync + 'static,
+        EvtFromBytes:
+            Fn(Bytes) -> Result<Self::Evt, EvtFromBytesError> + Copy + Send + Sync + 'static,
+        EvtFromBytesError: StdError + Send + Sync + 'static,
+        StateFromBytes:
+            Fn(Bytes) -> Result<Self::State, StateFromBytesError> + Copy + Send + Sync + 'static,
+        StateFromBytesError: StdError + Send + Sync + 'static,
+    {
+        spawn_entity(
+            self,
+            id,
+            config.into(),
+            evt_log,
+            snapshot_store,
+            binarizer,
+            Some(snapshot),
+            None::<(_, fn(SeqNo, SeqNo))>,
+        )
+        .await
+        .map(|(entity_ref, _)| entity_ref)
+    }
+
+    /// Like [spawn](EventSourcedExt::spawn), but spawns the entity onto the current
+    /// [LocalSet](tokio::task::LocalSet) via [spawn_local](tokio::task::spawn_local), such that
+    /// neither the [EventSourced] implementation nor its command, event and state types need to
+    /// be `Send` or `Sync`, e.g. to hold an `Rc` on a single-threaded runtime. Then also the
+    /// returned [EntityRef] is neither `Send` nor `Sync`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if not called from within a [LocalSet](tokio::task::LocalSet).
+    #[allow(async_fn_in_trait)]
+    async fn spawn_local<
+        L,
+        S,
+        EvtToBytes,
+        EvtToBytesError,
+        StateToBytes,
+        StateToBytesError,
+        EvtFromBytes,
+        EvtFromBytesError,
+        StateFromBytes,
+        StateFromBytesError,
+    >(
+        self,
+        id: Id,
+        config: impl Into<SpawnConfig>,
+        evt_log: L,
+        snapshot_store: S,
+        binarizer: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
+    ) -> Result<EntityRef<Self, Id>, SpawnError>
+    where
+        Self: EventSourced<Id>,
+        L: EvtLog<Id>,
+        S: SnapshotStore<Id>,
+        EvtToBytes: Fn(&Self::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+        EvtToBytesError: StdError + Send + Sync + 'static,
+        StateToBytes: Fn(&Self::State) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
+        StateToBytesError: StdError + Send + Sync + 'static,
+        EvtFromBytes:
+            Fn(Bytes) -> Result<Self::Evt, EvtFromBytesError> + Copy + Send + Sync + 'static,
+        EvtFromBytesError: StdError + Send + Sync + 'static,
+        StateFromBytes:
+            Fn(Bytes) -> Result<Self::State, StateFromBytesError> + Copy + Send + Sync + 'static,
+        StateFromBytesError: StdError + Send + Sync + 'static,
+    {
+        let config = config.into();
+        let (entity, _) = recover_entity(
+            self,
+            id,
+            evt_log,
+            snapshot_store,
+            binarizer,
+            &config,
+            None,
+            None::<(_, fn(SeqNo, SeqNo))>,
+        )
+        .await?;
+        let (entity_ref, run) = run_entity(entity, config);
+        entity_ref.set_task(task::spawn_local(run));
+        Ok(entity_ref)
     }
-}
 
-impl<E> EventSourcedExt for E where E: EventSourced {}
+    /// Create a [ReadOnlyEntity] with the given ID from the latest snapshot loaded from the given
+    /// [SnapshotStore], e.g. as a cheap materialized view for read-heavy deployments.
+    ///
+    /// Unlike [spawn](EventSourcedExt::spawn), neither are the events after the snapshot replayed
+    /// nor is a task spawned. This trades freshness for speed: the [ReadOnlyEntity] is stale by the
+    /// events persisted after the snapshot, i.e. by up to the interval in which the entity saves
+    /// snapshots, and it does not accept any commands. Also
+    /// [on_recovery_completed](EventSourced::on_recovery_completed) is not invoked. If there is no
+    /// snapshot, the given value is left as is.
+    #[allow(async_fn_in_trait)]
+    async fn spawn_read_only<S, StateFromBytes, StateFromBytesError>(
+        mut self,
+        id: Id,
+        mut snapshot_store: S,
+        state_from_bytes: StateFromBytes,
+    ) -> Result<ReadOnlyEntity<Self, Id>, SpawnError>
+    where
+        Self: EventSourced<Id>,
+        S: SnapshotStore<Id>,
+        StateFromBytes:
+            Fn(Bytes) -> Result<Self::State, StateFromBytesError> + Copy + Send + Sync + 'static,
+        StateFromBytesError: StdError + Send + Sync + 'static,
+    {
+        let snapshot = load_snapshot(
+            &mut snapshot_store,
+            id.clone(),
+            Self::STATE_VERSION,
+            state_from_bytes,
+        )
+        .await?;
+        let seq_no = snapshot.map(|Snapshot { seq_no, state }| {
+            debug!(%id, %seq_no, "restoring snapshot");
+            self.set_state(state);
+            seq_no
+        });
+        debug!(%id, ?seq_no, "read-only entity created");
 
-/// Error from spawning an event sourced entity.
-#[derive(Debug, Error)]
-pub enum SpawnError {
-    /// A snapshot cannot be loaded from the snapshot store.
-    #[error("cannot load snapshot from snapshot store")]
-    LoadSnapshot(#[source] Box<dyn StdError + Send + Sync>),
+        Ok(ReadOnlyEntity {
+            id,
+            event_sourced: self,
+            seq_no,
+        })
+    }
+}
 
-    /// The last seqence number cannot be obtained from the event log.
-    #[error("cannot get last seqence number from event log")]
-    LastSeqNo(#[source] Box<dyn StdError + Send + Sync>),
+impl<E, Id> EventSourcedExt<Id> for E
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+}
 
-    /// Events by ID cannot be obtained from the event log.
-    #[error("cannot get events by ID from event log")]
-    EvtsById(#[source] Box<dyn StdError + Send + Sync>),
+/// Rebuild the state of the entity with the given ID without spawning it, e.g. for reporting, CLI
+/// tools or one-off queries: like [spawn](EventSourcedExt::spawn), the snapshot is loaded and the
+/// remaining events are replayed, but then the given [EventSourced] value with the recovered state
+/// is returned along with the sequence number of the last applied event, if any. Neither is
+/// [on_recovery_completed](EventSourced::on_recovery_completed) invoked nor is a task spawned.
+/// Events which cannot be deserialized are handled according to the given [ReplayErrorPolicy];
+/// the `replay_state` span has the given [Level].
+pub async fn replay_state<
+    E,
+    L,
+    S,
+    EvtToBytes,
+    StateToBytes,
+    EvtFromBytes,
+    EvtFromBytesError,
+    StateFromBytes,
+    StateFromBytesError,
+    Id,
+>(
+    mut event_sourced: E,
+    id: Id,
+    mut evt_log: L,
+    mut snapshot_store: S,
+    binarizer: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
+    replay_error_policy: ReplayErrorPolicy,
+    span_level: Level,
+) -> Result<(Option<SeqNo>, E), SpawnError>
+where
+    E: EventSourced<Id>,
+    L: EvtLog<Id>,
+    S: SnapshotStore<Id>,
+    EvtFromBytes: Fn(Bytes) -> Result<E::Evt, EvtFromBytesError> + Copy + Send + Sync + 'static,
+    EvtFromBytesError: StdError + Send + Sync + 'static,
+    StateFromBytes:
+        Fn(Bytes) -> Result<E::State, StateFromBytesError> + Copy + Send + Sync + 'static,
+    StateFromBytesError: StdError + Send + Sync + 'static,
+    Id: EntityId,
+{
+    let span = span!(span_level, "replay_state", %id);
+    let report = recover(
+        &mut event_sourced,
+        &id,
+        &mut evt_log,
+        &mut snapshot_store,
+        binarizer.evt_from_bytes,
+        binarizer.state_from_bytes,
+        replay_error_policy,
+        None,
+        None::<(_, fn(SeqNo, SeqNo))>,
+    )
+    .instrument(span)
+    .await?;
 
-    /// The next event cannot be obtained from the event log.
-    #[error("cannot get next event from event log")]
-    NextEvt(#[source] Box<dyn StdError + Send + Sync>),
+    Ok((report.last_seq_no, event_sourced))
 }
 
-/// A handle for a spawned [EventSourced] entity which can be used to invoke its command handler.
-#[derive(Debug, Clone)]
-#[allow(clippy::type_complexity)]
-pub struct EntityRef<E>
+/// Load the snapshot with the given state version as raw bytes and convert these afterwards, such
+/// that conversion errors can be told apart from errors of the snapshot store. Takes a mutable
+/// reference, as the snapshot store is not required to be `Sync`.
+async fn load_snapshot<S, StateFromBytes, State, StateFromBytesError, Id>(
+    snapshot_store: &mut S,
+    id: Id,
+    state_version: u32,
+    state_from_bytes: StateFromBytes,
+) -> Result<Option<Snapshot<State>>, SpawnError>
 where
-    E: EventSourced,
+    S: SnapshotStore<Id>,
+    StateFromBytes: Fn(Bytes) -> Result<State, StateFromBytesError>,
+    StateFromBytesError: StdError + Send + Sync + 'static,
+    Id: EntityId,
 {
-    id: Uuid,
-    cmd_in: mpsc::Sender<(E::Cmd, oneshot::Sender<Result<(), E::Error>>)>,
+    let snapshot = snapshot_store
+        .load::<Bytes, _, _>(id, state_version, Ok::<_, Infallible>)
+        .await
+        .map_err(|error| SpawnError::LoadSnapshot(error.into()))?;
+    snapshot
+        .map(|Snapshot { seq_no, state }| {
+            state_from_bytes(state)
+                .map(|state| Snapshot { seq_no, state })
+                .map_err(|error| SpawnError::DeserializeState {
+                    seq_no,
+                    source: error.into(),
+                })
+        })
+        .transpose()
 }
 
-impl<E> EntityRef<E>
+/// Recover the given entity by restoring the given or loaded snapshot and replaying the events
+/// after it. Only takes mutable references, as neither the entity, nor the event log or snapshot
+/// store are required to be `Sync`.
+#[allow(clippy::too_many_arguments)]
+async fn recover<
+    E,
+    L,
+    S,
+    EvtFromBytes,
+    EvtFromBytesError,
+    StateFromBytes,
+    StateFromBytesError,
+    P,
+    Id,
+>(
+    event_sourced: &mut E,
+    id: &Id,
+    evt_log: &mut L,
+    snapshot_store: &mut S,
+    evt_from_bytes: EvtFromBytes,
+    state_from_bytes: StateFromBytes,
+    replay_error_policy: ReplayErrorPolicy,
+    snapshot: Option<Snapshot<E::State>>,
+    progress: Option<(NonZeroU64, P)>,
+) -> Result<SpawnReport, SpawnError>
 where
-    E: EventSourced,
+    E: EventSourced<Id>,
+    L: EvtLog<Id>,
+    S: SnapshotStore<Id>,
+    EvtFromBytes: Fn(Bytes) -> Result<E::Evt, EvtFromBytesError> + Copy + Send + Sync + 'static,
+    EvtFromBytesError: StdError + Send + Sync + 'static,
+    StateFromBytes:
+        Fn(Bytes) -> Result<E::State, StateFromBytesError> + Copy + Send + Sync + 'static,
+    StateFromBytesError: StdError + Send + Sync + 'static,
+    P: Fn(SeqNo, SeqNo) + Send,
+    Id: EntityId,
 {
-    /// Get the ID of the proxied event sourced entity.
-    pub fn id(&self) -> Uuid {
-        self.id
+    // Check the plain text deleted marker before loading anything, as the snapshot and events of
+    // a deleted entity might not be convertible anymore, e.g. after crypto-shredding.
+    let deleted = evt_log
+        .is_deleted(id.clone())
+        .await
+        .map_err(|error| SpawnError::IsDeleted(error.into()))?;
+    if deleted {
+        debug!(%id, "entity marked as deleted");
+        return Err(SpawnError::Deleted);
     }
 
-    /// Invoke the command handler of the entity.
-    ///
-    /// The returned (outer) `Result` signals, whether the command could be sent to the entity and
-    /// the command handler result could be received, i.e. an `Err` signals a technical failure.
-    ///
-    /// The (outer) `Ok` variant contains another (inner) `Result`, which signals whether the
-    /// command was valid or rejected. If it was valid, the persisted event is returned, else the
-    /// rejection error.
-    pub async fn handle_cmd(&self, cmd: E::Cmd) -> Result<Result<(), E::Error>, EntityRefError> {
-        let (result_in, result_out) = oneshot::channel();
-        self.cmd_in
-            .send((cmd, result_in))
-            .await
-            .map_err(|error| EntityRefError::SendCmd(Box::new(error)))?;
-        result_out.await.map_err(EntityRefError::RcvHandlerResult)
-    }
-}
+    // Load the snapshot, unless provided, concurrently with the last sequence number, as these
+    // might hit independent resources, e.g. separate snapshot and event stores.
+    let (snapshot_store_ref, evt_log_ref) = (&mut *snapshot_store, &mut *evt_log);
+    let load_snapshot = async move {
+        match snapshot {
+            Some(snapshot) => Ok(Some(snapshot)),
+            None => {
+                load_snapshot(
+                    snapshot_store_ref,
+                    id.clone(),
+                    E::STATE_VERSION,
+                    state_from_bytes,
+                )
+                .await
+            }
+        }
+    };
+    let last_seq_no = async move {
+        evt_log_ref
+            .last_seq_no(id.clone())
+            .await
+            .map_err(|error| SpawnError::LastSeqNo(error.into()))
+    };
+    let (snapshot, to_seq_no) = try_join!(load_snapshot, last_seq_no)?;
+
+    // Restore the snapshot, which – whether provided or loaded – must not be ahead of the events.
+    let snapshot_seq_no = snapshot.as_ref().map(|snapshot| snapshot.seq_no);
+    if snapshot_seq_no > to_seq_no {
+        return Err(SpawnError::InvalidSnapshot {
+            snapshot_seq_no,
+            last_seq_no: to_seq_no,
+        });
+    }
+    if let Some(Snapshot { seq_no, state }) = snapshot {
+        debug!(%id, %seq_no, "restoring snapshot");
+        event_sourced.set_state(state);
+    }
+
+    // Replay latest events up to the current last one, if there are any after the snapshot.
+    let mut last_seq_no = snapshot_seq_no;
+    let mut replayed = 0;
+    let mut skipped = 0;
+    if to_seq_no > snapshot_seq_no {
+        let from_seq_no = snapshot_seq_no
+            .map(|seq_no| seq_no.succ())
+            .unwrap_or(SeqNo::MIN);
+        debug!(%id, %from_seq_no, "replaying evts");
+        // Load the raw bytes, as these might need to be upcasted before conversion.
+        let evts = evt_log
+            .evts_by_id_from::<Bytes, _, _>(id.clone(), from_seq_no, Ok::<_, Infallible>)
+            .await
+            .map_err(|error| SpawnError::EvtsById(error.into()))?;
+        pin!(evts);
+        while let Some(evt) = evts.next().await {
+            let EvtEnvelope {
+                seq_no,
+                version,
+                evt: bytes,
+                ..
+            } = evt.map_err(|error| SpawnError::NextEvt(error.into()))?;
+            let bytes = match event_sourced.upcaster() {
+                Some(upcaster) => upcaster.upcast(version, bytes),
+                None => bytes,
+            };
+            let evt = match evt_from_bytes(bytes) {
+                Ok(evt) => evt,
+
+                Err(error) if replay_error_policy == ReplayErrorPolicy::SkipAndLog => {
+                    warn!(%id, %seq_no, error = %error, "skipping undeserializable event");
+                    last_seq_no = Some(seq_no);
+                    skipped += 1;
+                    continue;
+                }
+
+                Err(error) => {
+                    return Err(SpawnError::DeserializeEvt {
+                        seq_no,
+                        source: error.into(),
+                    })
+                }
+            };
+            event_sourced
+                .apply_evt(seq_no, evt)
+                .map_err(|error| SpawnError::ApplyEvt(error.into()))?;
+            last_seq_no = Some(seq_no);
+            replayed += 1;
+            if let Some((progress_interval, on_progress)) = &progress {
+                if replayed % progress_interval.get() == 0 {
+                    on_progress(
+                        seq_no,
+                        to_seq_no.map_or(seq_no, |to_seq_no| to_seq_no.max(seq_no)),
+                    );
+                }
+            }
+        }
+    }
+
+    debug!(%id, ?last_seq_no, "recovery completed");
+    Ok(SpawnReport {
+        restored_from_snapshot: snapshot_seq_no.is_some(),
+        snapshot_seq_no,
+        replayed_evts: replayed,
+        skipped_evts: skipped,
+        last_seq_no,
+    })
+}
+
+/// Spawn the given entity, restoring the given snapshot or else loading one from the given
+/// snapshot store, see [EventSourcedExt::spawn], returning its [EntityRef] along with a
+/// [SpawnReport].
+#[allow(clippy::too_many_arguments)]
+async fn spawn_entity<
+    E,
+    L,
+    S,
+    EvtToBytes,
+    EvtToBytesError,
+    StateToBytes,
+    StateToBytesError,
+    EvtFromBytes,
+    EvtFromBytesError,
+    StateFromBytes,
+    StateFromBytesError,
+    P,
+    Id,
+>(
+    event_sourced: E,
+    id: Id,
+    config: SpawnConfig,
+    evt_log: L,
+    snapshot_store: S,
+    binarizer: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
+    snapshot: Option<Snapshot<E::State>>,
+    progress: Option<(NonZeroU64, P)>,
+) -> Result<(EntityRef<E, Id>, SpawnReport), SpawnError>
+where
+    E: EventSourced<Id> + Send,
+    E::Cmd: Send + Sync,
+    E::Evt: Send + Sync,
+    E::State: Send,
+    E::Effect: Send,
+    L: EvtLog<Id>,
+    S: SnapshotStore<Id>,
+    EvtToBytes: Fn(&E::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+    EvtToBytesError: StdError + Send + Sync + 'static,
+    StateToBytes: Fn(&E::State) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
+    StateToBytesError: StdError + Send + Sync + 'static,
+    EvtFromBytes: Fn(Bytes) -> Result<E::Evt, EvtFromBytesError> + Copy + Send + Sync + 'static,
+    EvtFromBytesError: StdError + Send + Sync + 'static,
+    StateFromBytes:
+        Fn(Bytes) -> Result<E::State, StateFromBytesError> + Copy + Send + Sync + 'static,
+    StateFromBytesError: StdError + Send + Sync + 'static,
+    P: Fn(SeqNo, SeqNo) + Send,
+    Id: EntityId,
+{
+    let (entity, report) = recover_entity(
+        event_sourced,
+        id,
+        evt_log,
+        snapshot_store,
+        binarizer,
+        &config,
+        snapshot,
+        progress,
+    )
+    .await?;
+    let (entity_ref, run) = run_entity(entity, config);
+    entity_ref.set_task(task::spawn(run));
+    Ok((entity_ref, report))
+}
+
+/// Recover an entity, see [recover], and create it.
+#[allow(clippy::too_many_arguments)]
+async fn recover_entity<
+    E,
+    L,
+    S,
+    EvtToBytes,
+    EvtToBytesError,
+    StateToBytes,
+    StateToBytesError,
+    EvtFromBytes,
+    EvtFromBytesError,
+    StateFromBytes,
+    StateFromBytesError,
+    P,
+    Id,
+>(
+    mut event_sourced: E,
+    id: Id,
+    mut evt_log: L,
+    mut snapshot_store: S,
+    binarizer: Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes>,
+    config: &SpawnConfig,
+    snapshot: Option<Snapshot<E::State>>,
+    progress: Option<(NonZeroU64, P)>,
+) -> Result<(Entity<E, L, S, EvtToBytes, StateToBytes, Id>, SpawnReport), SpawnError>
+where
+    E: EventSourced<Id>,
+    L: EvtLog<Id>,
+    S: SnapshotStore<Id>,
+    EvtToBytes: Fn(&E::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+    EvtToBytesError: StdError + Send + Sync + 'static,
+    StateToBytes: Fn(&E::State) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
+    StateToBytesError: StdError + Send + Sync + 'static,
+    EvtFromBytes: Fn(Bytes) -> Result<E::Evt, EvtFromBytesError> + Copy + Send + Sync + 'static,
+    EvtFromBytesError: StdError + Send + Sync + 'static,
+    StateFromBytes:
+        Fn(Bytes) -> Result<E::State, StateFromBytesError> + Copy + Send + Sync + 'static,
+    StateFromBytesError: StdError + Send + Sync + 'static,
+    P: Fn(SeqNo, SeqNo) + Send,
+    Id: EntityId,
+{
+    let Binarizer {
+        evt_to_bytes,
+        evt_from_bytes,
+        state_to_bytes,
+        state_from_bytes,
+    } = binarizer;
+
+    #[cfg(feature = "metrics")]
+    let recovery_start = Instant::now();
+
+    let span = span!(config.span_level, "spawn", %id);
+    let recovery = recover(
+        &mut event_sourced,
+        &id,
+        &mut evt_log,
+        &mut snapshot_store,
+        evt_from_bytes,
+        state_from_bytes,
+        config.replay_error_policy,
+        snapshot,
+        progress,
+    )
+    .instrument(span);
+    let report = match config.recovery_timeout {
+        Some(recovery_timeout) => timeout(recovery_timeout, recovery)
+            .await
+            .map_err(|_| SpawnError::RecoveryTimeout(recovery_timeout))??,
+        None => recovery.await?,
+    };
+    let last_seq_no = report.last_seq_no;
+    #[cfg(feature = "metrics")]
+    ::metrics::histogram!(metrics::REPLAY_DURATION, "entity_type" => E::ENTITY_TYPE.unwrap_or_default()).record(recovery_start.elapsed().as_secs_f64());
+    if event_sourced.is_terminal() {
+        debug!(%id, "entity deleted");
+        return Err(SpawnError::Deleted);
+    }
+    event_sourced.on_recovery_completed(last_seq_no);
+
+    // Create entity.
+    let entity = Entity {
+        event_sourced,
+        id: id.clone(),
+        last_seq_no,
+        evt_log,
+        snapshot_store,
+        evt_to_bytes,
+        state_to_bytes,
+        persist_retry_policy: config.persist_retry_policy,
+        prune_on_snapshot: config.prune_on_snapshot,
+        span_level: config.span_level,
+    };
+    debug!(%id, "entity created");
+
+    Ok((entity, report))
+}
+
+/// Create the [EntityRef] for the given entity and its handler loop, to be spawned by the caller.
+fn run_entity<E, L, S, EvtToBytes, EvtToBytesError, StateToBytes, StateToBytesError, Id>(
+    mut entity: Entity<E, L, S, EvtToBytes, StateToBytes, Id>,
+    config: SpawnConfig,
+) -> (EntityRef<E, Id>, impl Future<Output = ()>)
+where
+    E: EventSourced<Id>,
+    L: EvtLog<Id>,
+    S: SnapshotStore<Id>,
+    EvtToBytes: Fn(&E::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+    EvtToBytesError: StdError + Send + Sync + 'static,
+    StateToBytes: Fn(&E::State) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
+    StateToBytesError: StdError + Send + Sync + 'static,
+    Id: EntityId,
+{
+    let id = entity.id.clone();
+    let cmd_buffer_size = config.cmd_buffer.size.get();
+    let (cmd_in, mut cmd_out) = cmd_channel::<E::Cmd, E::Error>(config.cmd_buffer);
+
+    // Create handler loop, to be spawned by the caller.
+    let deleted = Arc::new(AtomicBool::new(false));
+    let entity_deleted = deleted.clone();
+    let panicked = Arc::new(AtomicBool::new(false));
+    let entity_panicked = panicked.clone();
+    let shutdown = Arc::new(Notify::new());
+    let entity_shutdown = shutdown.clone();
+    // Once the last EntityRef has been dropped, all already buffered commands are still handled
+    // before the loop ends; it only ends early if the entity terminates or panics or on shutdown.
+    let run = async move {
+        let id = entity.id.clone();
+        let mut cmd_buffer_full_count = 0;
+        loop {
+            let cmd_msg = select! {
+                biased;
+
+                _ = entity_shutdown.notified() => {
+                    debug!(%id, "shutting down entity");
+                    break;
+                }
+
+                cmd_msg = cmd_out.recv() => cmd_msg,
+            };
+            let Some(cmd_msg) = cmd_msg else {
+                break;
+            };
+
+            // Monitor the usage of the command buffer, including the received command.
+            let buffered = cmd_out.len() + 1;
+            #[cfg(feature = "metrics")]
+            ::metrics::histogram!(metrics::CMD_BUFFER_USAGE, "entity_type" => E::ENTITY_TYPE.unwrap_or_default())
+                .record(buffered as f64 / cmd_buffer_size as f64);
+            if let Some(threshold) = config.cmd_buffer_full_threshold {
+                if buffered >= cmd_buffer_size {
+                    cmd_buffer_full_count += 1;
+                    if cmd_buffer_full_count >= threshold.get() {
+                        warn!(
+                            %id,
+                            size = cmd_buffer_size,
+                            count = cmd_buffer_full_count,
+                            "command buffer persistently full"
+                        );
+                        cmd_buffer_full_count = 0;
+                    }
+                } else {
+                    cmd_buffer_full_count = 0;
+                }
+            }
+
+            let cmd_start = Instant::now();
+
+            // Catch panics, e.g. from `unwrap` in the command or event handler, such that
+            // callers can tell a crashed entity from a terminated one.
+            let proceed = match cmd_msg {
+                CmdMsg::Single {
+                    cmd,
+                    ctx,
+                    meta,
+                    expected_seq_no,
+                    result_sender,
+                } => {
+                    if let Some(expected) = expected_seq_no {
+                        let actual = entity.last_seq_no;
+                        if expected != actual {
+                            debug!(%id, ?expected, ?actual, "rejecting command with conflict");
+                            let conflict = EntityRefError::Conflict { expected, actual };
+                            if result_sender.send(Err(conflict)).is_err() {
+                                error!(%id, "cannot send command handler result");
+                            };
+                            continue;
+                        }
+                    }
+
+                    let result = AssertUnwindSafe(entity.handle_cmd(cmd, &ctx, &meta))
+                        .catch_unwind()
+                        .await;
+                    #[cfg(feature = "metrics")]
+                    ::metrics::counter!(metrics::CMDS_HANDLED, "entity_type" => E::ENTITY_TYPE.unwrap_or_default()).increment(1);
+                    let terminal = entity.event_sourced.is_terminal();
+                    complete(
+                        &id,
+                        result,
+                        terminal,
+                        result_sender,
+                        &entity_deleted,
+                        &entity_panicked,
+                    )
+                }
+
+                CmdMsg::Streaming {
+                    cmd,
+                    progress,
+                    result_sender,
+                } => {
+                    let result = AssertUnwindSafe(entity.handle_cmd_streaming(cmd, &progress))
+                        .catch_unwind()
+                        .await;
+                    #[cfg(feature = "metrics")]
+                    ::metrics::counter!(metrics::CMDS_HANDLED, "entity_type" => E::ENTITY_TYPE.unwrap_or_default()).increment(1);
+                    let terminal = entity.event_sourced.is_terminal();
+                    complete(
+                        &id,
+                        result,
+                        terminal,
+                        result_sender,
+                        &entity_deleted,
+                        &entity_panicked,
+                    )
+                }
+
+                CmdMsg::Batch {
+                    cmds,
+                    result_sender,
+                } => {
+                    #[cfg(feature = "metrics")]
+                    let n = cmds.len() as u64;
+                    let result = AssertUnwindSafe(entity.handle_cmds(cmds))
+                        .catch_unwind()
+                        .await;
+                    #[cfg(feature = "metrics")]
+                    ::metrics::counter!(metrics::CMDS_HANDLED, "entity_type" => E::ENTITY_TYPE.unwrap_or_default()).increment(n);
+                    let (result, failure) = match result {
+                        Ok(Ok((results, failure))) => (Ok(Ok(results)), failure),
+                        Ok(Err(error)) => (Ok(Err(error)), None),
+                        Err(panic) => (Err(panic), None),
+                    };
+                    let terminal = entity.event_sourced.is_terminal();
+                    let proceed = complete(
+                        &id,
+                        result,
+                        terminal,
+                        result_sender,
+                        &entity_deleted,
+                        &entity_panicked,
+                    );
+                    match failure {
+                        Some(error) => {
+                            error!(%id, %error, "cannot convert or apply event");
+                            false
+                        }
+
+                        None => proceed,
+                    }
+                }
+            };
+
+            let elapsed = cmd_start.elapsed();
+            if config
+                .slow_cmd_threshold
+                .is_some_and(|threshold| elapsed > threshold)
+            {
+                warn!(%id, ?elapsed, "slow command handling");
+            }
+            #[cfg(feature = "metrics")]
+            ::metrics::histogram!(metrics::CMD_DURATION, "entity_type" => E::ENTITY_TYPE.unwrap_or_default()).record(elapsed.as_secs_f64());
+
+            if !proceed {
+                break;
+            }
+        }
+        debug!(%id, "entity terminated");
+    };
+
+    let entity_ref = EntityRef {
+        id,
+        cmd_in,
+        deleted,
+        panicked,
+        shutdown,
+        task: Arc::default(),
+    };
+    (entity_ref, run)
+}
+
+/// Error from spawning an event sourced entity.
+#[derive(Debug, Error)]
+pub enum SpawnError {
+    /// A snapshot cannot be loaded from the snapshot store.
+    #[error("cannot load snapshot from snapshot store")]
+    LoadSnapshot(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// The last seqence number cannot be obtained from the event log.
+    #[error("cannot get last seqence number from event log")]
+    LastSeqNo(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// Whether the entity has been marked as deleted cannot be obtained from the event log.
+    #[error("cannot get deleted marker from event log")]
+    IsDeleted(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// Events by ID cannot be obtained from the event log.
+    #[error("cannot get events by ID from event log")]
+    EvtsById(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// The next event cannot be obtained from the event log.
+    #[error("cannot get next event from event log")]
+    NextEvt(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// The event with the given sequence number cannot be converted from bytes.
+    #[error("cannot convert event with sequence number {seq_no} from bytes")]
+    DeserializeEvt {
+        seq_no: SeqNo,
+        #[source]
+        source: Box<dyn StdError + Send + Sync>,
+    },
+
+    /// The state of the snapshot with the given sequence number cannot be converted from bytes.
+    #[error("cannot convert state of snapshot with sequence number {seq_no} from bytes")]
+    DeserializeState {
+        seq_no: SeqNo,
+        #[source]
+        source: Box<dyn StdError + Send + Sync>,
+    },
+
+    /// An event cannot be applied by the event handler.
+    #[error("cannot apply event")]
+    ApplyEvt(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// The sequence number of the snapshot given to
+    /// [spawn_with_snapshot](EventSourcedExt::spawn_with_snapshot) or loaded from the snapshot
+    /// store is greater than the last one of the event log.
+    #[error("snapshot sequence number {snapshot_seq_no:?} greater than last sequence number {last_seq_no:?}")]
+    InvalidSnapshot {
+        snapshot_seq_no: Option<SeqNo>,
+        last_seq_no: Option<SeqNo>,
+    },
+
+    /// Recovery has not completed within the given
+    /// [recovery_timeout](SpawnConfig::recovery_timeout).
+    #[error("recovery not completed within {0:?}")]
+    RecoveryTimeout(Duration),
+
+    /// The entity has been deleted, i.e. either marked as deleted, see [EvtLog::mark_deleted], or
+    /// in a terminal state after recovery, see [EventSourced::is_terminal].
+    #[error("entity has been deleted")]
+    Deleted,
+}
+
+/// How an entity has been recovered when spawned, see
+/// [spawn_with_report](EventSourcedExt::spawn_with_report).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpawnReport {
+    /// Whether a snapshot has been restored.
+    pub restored_from_snapshot: bool,
+
+    /// The sequence number of the restored snapshot, if any.
+    pub snapshot_seq_no: Option<SeqNo>,
+
+    /// The number of events replayed after the snapshot, if any.
+    pub replayed_evts: u64,
+
+    /// The number of events skipped, because they could not be deserialized, see
+    /// [ReplayErrorPolicy::SkipAndLog].
+    pub skipped_evts: u64,
+
+    /// The sequence number of the last applied or skipped event, if any.
+    pub last_seq_no: Option<SeqNo>,
+}
+
+/// What to do with events which cannot be deserialized when replaying them on recovery, see
+/// [SpawnConfig::replay_error_policy].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplayErrorPolicy {
+    /// Fail spawning the entity with [SpawnError::DeserializeEvt].
+    #[default]
+    Fail,
+
+    /// Log and skip the event and continue replaying, e.g. for best-effort read models over a
+    /// messy history. Skipped events are counted in [SpawnReport::skipped_evts].
+    SkipAndLog,
+}
+
+/// A handle for a spawned [EventSourced] entity which can be used to invoke its command handler.
+#[derive(Debug)]
+pub struct EntityRef<E, Id = Uuid>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    id: Id,
+    cmd_in: CmdSender<E::Cmd, E::Error>,
+    deleted: Arc<AtomicBool>,
+    panicked: Arc<AtomicBool>,
+    shutdown: Arc<Notify>,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl<E, Id> Clone for EntityRef<E, Id>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            cmd_in: self.cmd_in.clone(),
+            deleted: self.deleted.clone(),
+            panicked: self.panicked.clone(),
+            shutdown: self.shutdown.clone(),
+            task: self.task.clone(),
+        }
+    }
+}
+
+impl<E, Id> EntityRef<E, Id>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    fn set_task(&self, task: JoinHandle<()>) {
+        *self.task.lock().expect("lock task") = Some(task);
+    }
+
+    /// Create a [WeakEntityRef] which does not keep the entity alive, e.g. to be held by a
+    /// registry: once all [EntityRef]s have been dropped, the entity handles the buffered commands
+    /// and terminates, even if [WeakEntityRef]s remain.
+    pub fn downgrade(&self) -> WeakEntityRef<E, Id> {
+        WeakEntityRef {
+            id: self.id.clone(),
+            cmd_in: self.cmd_in.downgrade(),
+            deleted: self.deleted.clone(),
+            panicked: self.panicked.clone(),
+            shutdown: self.shutdown.clone(),
+            task: self.task.clone(),
+        }
+    }
+
+    /// Get the ID of the proxied event sourced entity.
+    pub fn id(&self) -> Id {
+        self.id.clone()
+    }
+
+    /// Invoke the command handler of the entity.
+    ///
+    /// The returned (outer) `Result` signals, whether the command could be sent to the entity and
+    /// the command handler result could be received, i.e. an `Err` signals a technical failure.
+    ///
+    /// The (outer) `Ok` variant contains another (inner) `Result`, which signals whether the
+    /// command was valid or rejected. If it was valid, the persisted event is returned, else the
+    /// rejection error.
+    pub async fn handle_cmd(&self, cmd: E::Cmd) -> Result<Result<(), E::Error>, EntityRefError> {
+        self.send_cmd(cmd, CmdContext::default(), EvtMeta::default(), None)
+            .await
+            .map(discard_seq_nos)
+    }
+
+    /// Like [handle_cmd](EntityRef::handle_cmd), but if the command was valid, return the global
+    /// sequence number assigned to the persisted event, if the [EvtLog] provides it, see
+    /// [EvtLog::persist_returning_global_seq_no]. It can be used for read-your-writes consistency,
+    /// e.g. by waiting until a projection has processed the event.
+    pub async fn handle_cmd_returning_global_seq_no(
+        &self,
+        cmd: E::Cmd,
+    ) -> Result<Result<Option<GlobalSeqNo>, E::Error>, EntityRefError> {
+        self.send_cmd(cmd, CmdContext::default(), EvtMeta::default(), None)
+            .await
+            .map(|result| {
+                result
+                    .map(|(_, global_seq_no)| global_seq_no)
+                    .map_err(|rejected| rejected.error)
+            })
+    }
+
+    /// Like [handle_cmd](EntityRef::handle_cmd), but if the command was valid, return the sequence
+    /// number of the persisted event, and if it was rejected, return the rejection error along with
+    /// the sequence number of the last persisted event of the entity at that time, see
+    /// [Rejected]. It can be used to decide whether to retry a rejected command, e.g. only after
+    /// re-reading a state which has changed since, possibly via
+    /// [handle_cmd_if](EntityRef::handle_cmd_if).
+    pub async fn handle_cmd_returning_seq_no(
+        &self,
+        cmd: E::Cmd,
+    ) -> Result<Result<SeqNo, Rejected<E::Error>>, EntityRefError> {
+        self.send_cmd(cmd, CmdContext::default(), EvtMeta::default(), None)
+            .await
+            .map(|result| result.map(|(seq_no, _)| seq_no))
+    }
+
+    /// Like [handle_cmd](EntityRef::handle_cmd), but with the given [EvtMeta] which is persisted
+    /// along with the resulting event.
+    pub async fn handle_cmd_with_meta(
+        &self,
+        cmd: E::Cmd,
+        meta: EvtMeta,
+    ) -> Result<Result<(), E::Error>, EntityRefError> {
+        self.send_cmd(cmd, CmdContext::default(), meta, None)
+            .await
+            .map(discard_seq_nos)
+    }
+
+    /// Like [handle_cmd](EntityRef::handle_cmd), but with the given [CmdContext] which is passed
+    /// to [EventSourced::enrich] for the resulting event.
+    pub async fn handle_cmd_with_ctx(
+        &self,
+        cmd: E::Cmd,
+        ctx: CmdContext,
+    ) -> Result<Result<(), E::Error>, EntityRefError> {
+        self.send_cmd(cmd, ctx, EvtMeta::default(), None)
+            .await
+            .map(discard_seq_nos)
+    }
+
+    /// Invoke the command handler of the entity, but only if the sequence number of the last
+    /// persisted event of the entity equals the given expected one (`None` meaning that no event
+    /// has been persisted yet); otherwise [EntityRefError::Conflict] is returned.
+    ///
+    /// As commands for a single entity are handled sequentially anyway, this only guards against
+    /// commands based on stale reads, e.g. from a read side projection, thereby providing
+    /// compare-and-swap semantics.
+    ///
+    /// See [handle_cmd](EntityRef::handle_cmd) for the meaning of the returned value.
+    pub async fn handle_cmd_if(
+        &self,
+        expected_seq_no: Option<SeqNo>,
+        cmd: E::Cmd,
+    ) -> Result<Result<(), E::Error>, EntityRefError> {
+        self.send_cmd(
+            cmd,
+            CmdContext::default(),
+            EvtMeta::default(),
+            Some(expected_seq_no),
+        )
+        .await
+        .map(discard_seq_nos)
+    }
+
+    /// Invoke the streaming command handler of the entity, see
+    /// [EventSourced::handle_cmd_streaming], sending the sequence number of each persisted and
+    /// applied event to the given progress channel, e.g. to report the progress of a bulk
+    /// operation. The progress should be received concurrently, as the entity waits for capacity
+    /// of the channel; if its receiver has been dropped, the progress is no longer sent, but the
+    /// command is still handled completely.
+    ///
+    /// See [handle_cmd](EntityRef::handle_cmd) for the meaning of the returned value, which is
+    /// only returned after all events have been persisted and applied.
+    pub async fn handle_cmd_streaming(
+        &self,
+        cmd: E::Cmd,
+        progress: mpsc::Sender<SeqNo>,
+    ) -> Result<Result<(), E::Error>, EntityRefError> {
+        if self.is_deleted() {
+            return Err(EntityRefError::Deleted);
+        }
+
+        let (result_sender, result_receiver) = oneshot::channel();
+        let cmd_msg = CmdMsg::Streaming {
+            cmd,
+            progress,
+            result_sender,
+        };
+        self.send_msg(cmd_msg, result_receiver).await
+    }
+
+    /// Invoke the command handler of the entity for the given commands, which are handled strictly
+    /// in the given order, thereby only using a single message and persisting the events of all
+    /// valid commands via a single [EvtLog::persist_batch] call. Only if the [EvtLog] persists
+    /// batches atomically, which the Postgres, MySQL and in-memory ones do, either all or none of
+    /// these events are persisted.
+    ///
+    /// The returned (outer) `Result` signals, whether the commands could be sent to the entity and
+    /// the results could be received. The (outer) `Ok` variant contains a result for each
+    /// command, each with the same meaning as for [handle_cmd](EntityRef::handle_cmd); if the
+    /// entity becomes deleted by one of the commands, the remaining ones are not handled but
+    /// result in [EntityRefError::Deleted]; if the event of one of the commands cannot be
+    /// converted or applied, the entity terminates and this and the remaining ones result in
+    /// [EntityRefError::Failed].
+    pub async fn handle_cmds(
+        &self,
+        cmds: Vec<E::Cmd>,
+    ) -> Result<Vec<Result<Result<(), E::Error>, EntityRefError>>, EntityRefError> {
+        if self.is_deleted() {
+            return Err(EntityRefError::Deleted);
+        }
+
+        let (result_sender, result_receiver) = oneshot::channel();
+        let cmd_msg = CmdMsg::Batch {
+            cmds,
+            result_sender,
+        };
+        self.send_msg(cmd_msg, result_receiver).await
+    }
+
+    /// Whether the entity has been deleted, i.e. is in a terminal state.
+    pub fn is_deleted(&self) -> bool {
+        self.deleted.load(Ordering::Acquire)
+    }
+
+    /// Whether the entity has panicked while handling a command and hence has terminated.
+    pub fn is_panicked(&self) -> bool {
+        self.panicked.load(Ordering::Acquire)
+    }
+
+    /// Shut down the entity, i.e. stop handling commands after the currently handled one, if any,
+    /// and wait for its task to finish, e.g. for a clean shutdown of an application. Buffered
+    /// commands are not handled; further commands fail to be sent. Only the first invocation for
+    /// any of the clones of this [EntityRef] waits for the task, the others return immediately.
+    /// A [JoinError] signals that the task has panicked or has been cancelled.
+    pub async fn shutdown(&self) -> Result<(), JoinError> {
+        self.shutdown.notify_one();
+
+        let task = self.task.lock().expect("lock task").take();
+        match task {
+            Some(task) => task.await,
+            None => Ok(()),
+        }
+    }
+
+    async fn send_cmd(
+        &self,
+        cmd: E::Cmd,
+        ctx: CmdContext,
+        meta: EvtMeta,
+        expected_seq_no: Option<Option<SeqNo>>,
+    ) -> Result<Result<(SeqNo, Option<GlobalSeqNo>), Rejected<E::Error>>, EntityRefError> {
+        if self.is_deleted() {
+            return Err(EntityRefError::Deleted);
+        }
+
+        let (result_sender, result_receiver) = oneshot::channel();
+        let cmd_msg = CmdMsg::Single {
+            cmd,
+            ctx,
+            meta,
+            expected_seq_no,
+            result_sender,
+        };
+        self.send_msg(cmd_msg, result_receiver).await
+    }
+
+    async fn send_msg<T>(
+        &self,
+        cmd_msg: CmdMsg<E::Cmd, E::Error>,
+        result_receiver: oneshot::Receiver<Result<T, EntityRefError>>,
+    ) -> Result<T, EntityRefError> {
+        self.cmd_in
+            .send(cmd_msg, |error| self.terminated_or(error))
+            .await?;
+        result_receiver
+            .await
+            .map_err(|error| self.terminated_or(EntityRefError::RcvHandlerResult(error)))?
+    }
+
+    fn terminated_or(&self, error: EntityRefError) -> EntityRefError {
+        if self.is_deleted() {
+            EntityRefError::Deleted
+        } else if self.is_panicked() {
+            EntityRefError::Panicked
+        } else {
+            error
+        }
+    }
+}
+
+/// A weak reference to a spawned [EventSourced] entity, created via [EntityRef::downgrade], which
+/// does not keep the entity alive.
+#[derive(Debug)]
+pub struct WeakEntityRef<E, Id = Uuid>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    id: Id,
+    cmd_in: WeakCmdSender<E::Cmd, E::Error>,
+    deleted: Arc<AtomicBool>,
+    panicked: Arc<AtomicBool>,
+    shutdown: Arc<Notify>,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl<E, Id> Clone for WeakEntityRef<E, Id>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            cmd_in: self.cmd_in.clone(),
+            deleted: self.deleted.clone(),
+            panicked: self.panicked.clone(),
+            shutdown: self.shutdown.clone(),
+            task: self.task.clone(),
+        }
+    }
+}
+
+impl<E, Id> WeakEntityRef<E, Id>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    /// Get the ID of the referenced event sourced entity.
+    pub fn id(&self) -> Id {
+        self.id.clone()
+    }
+
+    /// Try to create an [EntityRef], which fails, i.e. returns `None`, if all [EntityRef]s have
+    /// already been dropped and hence the entity has terminated or is about to terminate.
+    pub fn upgrade(&self) -> Option<EntityRef<E, Id>> {
+        self.cmd_in.upgrade().map(|cmd_in| EntityRef {
+            id: self.id.clone(),
+            cmd_in,
+            deleted: self.deleted.clone(),
+            panicked: self.panicked.clone(),
+            shutdown: self.shutdown.clone(),
+            task: self.task.clone(),
+        })
+    }
+}
 
 /// Error from an [EntityRef].
 #[derive(Debug, Error)]
@@ -287,267 +1765,2410 @@ pub enum EntityRefError {
     /// because its entity has terminated.
     #[error("cannot receive command handler result from Entity")]
     RcvHandlerResult(#[from] oneshot::error::RecvError),
+
+    /// The entity has been deleted, i.e. is in a terminal state, and does not accept commands.
+    #[error("Entity has been deleted")]
+    Deleted,
+
+    /// The entity has panicked while handling a command, e.g. because of an `unwrap` in the
+    /// command or event handler, and has terminated.
+    #[error("Entity has panicked")]
+    Panicked,
+
+    /// The entity has failed to convert or apply the event of a command handled via
+    /// [handle_cmds](EntityRef::handle_cmds), this or a former one of the batch, and has
+    /// terminated; the events of the former commands have been persisted.
+    #[error("Entity has failed")]
+    Failed,
+
+    /// The command has been dropped, because the command buffer of the entity was full, see
+    /// [Overflow].
+    #[error("command buffer of Entity overflowed")]
+    Overflow,
+
+    /// The sequence number of the last persisted event of the entity does not match the expected
+    /// one given to [handle_cmd_if](EntityRef::handle_cmd_if).
+    #[error("sequence number conflict: expected {expected:?}, actual {actual:?}")]
+    Conflict {
+        expected: Option<SeqNo>,
+        actual: Option<SeqNo>,
+    },
+}
+
+impl EntityRefError {
+    /// Whether this error is caused by the client rather than by a technical failure, e.g. to be
+    /// mapped to a 4xx rather than a 5xx HTTP status code: a command for a deleted entity or with
+    /// a sequence number conflict is a client error, whereas failing to send a command or receive
+    /// its result or a panicked entity are not. Invalid commands are not signaled by an
+    /// [EntityRefError] at all, but by the inner `Result` of [EntityRef::handle_cmd].
+    pub fn is_client_error(&self) -> bool {
+        matches!(self, Self::Deleted | Self::Conflict { .. })
+    }
+}
+
+/// A command rejected by the command handler of an entity, returned by
+/// [handle_cmd_returning_seq_no](EntityRef::handle_cmd_returning_seq_no).
+#[derive(Debug, Error)]
+#[error("command rejected at sequence number {seq_no:?}")]
+pub struct Rejected<Err> {
+    /// The error returned by the command handler.
+    #[source]
+    pub error: Err,
+
+    /// The sequence number of the last persisted event of the entity when the command was
+    /// rejected; `None` if no event has been persisted yet.
+    pub seq_no: Option<SeqNo>,
+}
+
+/// A command or a batch of commands sent from an [EntityRef] to its entity, generic over the
+/// command and error types of the entity.
+enum CmdMsg<C, Err> {
+    Single {
+        cmd: C,
+        ctx: CmdContext,
+        meta: EvtMeta,
+        /// Precondition for handling the command; `None` means unconditional.
+        expected_seq_no: Option<Option<SeqNo>>,
+        #[allow(clippy::type_complexity)]
+        result_sender: oneshot::Sender<
+            Result<Result<(SeqNo, Option<GlobalSeqNo>), Rejected<Err>>, EntityRefError>,
+        >,
+    },
+
+    Streaming {
+        cmd: C,
+        progress: mpsc::Sender<SeqNo>,
+        result_sender: oneshot::Sender<Result<Result<(), Err>, EntityRefError>>,
+    },
+
+    Batch {
+        cmds: Vec<C>,
+        #[allow(clippy::type_complexity)]
+        result_sender:
+            oneshot::Sender<Result<Vec<Result<Result<(), Err>, EntityRefError>>, EntityRefError>>,
+    },
+}
+
+impl<C, Err> CmdMsg<C, Err> {
+    /// Reject this [CmdMsg] without handling it by sending the given error.
+    fn reject(self, error: EntityRefError) {
+        let sent = match self {
+            CmdMsg::Single { result_sender, .. } => result_sender.send(Err(error)).is_ok(),
+            CmdMsg::Streaming { result_sender, .. } => result_sender.send(Err(error)).is_ok(),
+            CmdMsg::Batch { result_sender, .. } => result_sender.send(Err(error)).is_ok(),
+        };
+        if !sent {
+            error!("cannot send command handler result");
+        }
+    }
+}
+
+fn discard_seq_nos<Err>(
+    result: Result<(SeqNo, Option<GlobalSeqNo>), Rejected<Err>>,
+) -> Result<(), Err> {
+    result.map(|_| ()).map_err(|rejected| rejected.error)
 }
 
-/// Collection of conversion functions from and to [Bytes] for events and snapshots.
-pub struct Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes> {
-    pub evt_to_bytes: EvtToBytes,
-    pub evt_from_bytes: EvtFromBytes,
-    pub state_to_bytes: StateToBytes,
-    pub state_from_bytes: StateFromBytes,
-}
+/// Complete handling a [CmdMsg] by sending the result and return whether to proceed handling
+/// further ones, i.e. `false` if the entity has panicked, failed or is terminal.
+fn complete<T, Id>(
+    id: &Id,
+    result: Result<Result<T, Box<dyn StdError>>, Box<dyn Any + Send>>,
+    terminal: bool,
+    result_sender: oneshot::Sender<Result<T, EntityRefError>>,
+    deleted: &AtomicBool,
+    panicked: &AtomicBool,
+) -> bool
+where
+    Id: EntityId,
+{
+    match result {
+        Ok(Ok(result)) => {
+            // Mark as deleted before sending the result, such that subsequent commands are
+            // rejected.
+            if terminal {
+                deleted.store(true, Ordering::Release);
+            }
+            if result_sender.send(Ok(result)).is_err() {
+                error!(%id, "cannot send command handler result");
+            };
+            if terminal {
+                debug!(%id, "entity deleted");
+            }
+            !terminal
+        }
+
+        Ok(Err(error)) => {
+            error!(%id, %error, "cannot persist or apply event");
+            false
+        }
+
+        Err(panic) => {
+            let panic = panic_message(&panic);
+            error!(%id, panic, "entity panicked");
+            panicked.store(true, Ordering::Release);
+            false
+        }
+    }
+}
+
+fn panic_message(panic: &Box<dyn Any + Send>) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Collection of conversion functions from and to [Bytes] for events and snapshots.
+#[derive(Clone)]
+pub struct Binarizer<EvtToBytes, EvtFromBytes, StateToBytes, StateFromBytes> {
+    pub evt_to_bytes: EvtToBytes,
+    pub evt_from_bytes: EvtFromBytes,
+    pub state_to_bytes: StateToBytes,
+    pub state_from_bytes: StateFromBytes,
+}
+
+struct Entity<E, L, S, EvtToBytes, StateToBytes, Id> {
+    event_sourced: E,
+    id: Id,
+    last_seq_no: Option<SeqNo>,
+    evt_log: L,
+    snapshot_store: S,
+    evt_to_bytes: EvtToBytes,
+    state_to_bytes: StateToBytes,
+    persist_retry_policy: RetryPolicy,
+    prune_on_snapshot: bool,
+    span_level: Level,
+}
+
+impl<E, L, S, EvtToBytes, EvtToBytesError, StateToBytes, StateToBytesError, Id>
+    Entity<E, L, S, EvtToBytes, StateToBytes, Id>
+where
+    E: EventSourced<Id>,
+    L: EvtLog<Id>,
+    S: SnapshotStore<Id>,
+    EvtToBytes: Fn(&E::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
+    EvtToBytesError: StdError + Send + Sync + 'static,
+    StateToBytes: Fn(&E::State) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
+    StateToBytesError: StdError + Send + Sync + 'static,
+    Id: EntityId,
+{
+    async fn handle_cmd(
+        &mut self,
+        cmd: E::Cmd,
+        ctx: &CmdContext,
+        meta: &EvtMeta,
+    ) -> Result<Result<(SeqNo, Option<GlobalSeqNo>), Rejected<E::Error>>, Box<dyn StdError>> {
+        let span = span!(
+            self.span_level,
+            "handle_cmd",
+            id = %self.id,
+            seq_no = field::Empty
+        );
+        self.handle_cmd_in_span(cmd, ctx, meta)
+            .instrument(span)
+            .await
+    }
+
+    async fn handle_cmd_in_span(
+        &mut self,
+        cmd: E::Cmd,
+        ctx: &CmdContext,
+        meta: &EvtMeta,
+    ) -> Result<Result<(SeqNo, Option<GlobalSeqNo>), Rejected<E::Error>>, Box<dyn StdError>> {
+        // Convert into a tagged event right away, as the value returned by the command handler need
+        // not be `Send`.
+        let tagged_evt = match self.event_sourced.handle_cmd(self.id.clone(), cmd) {
+            Ok(tagged_evt) => tagged_evt.into_tagged_evt(),
+
+            Err(error) => {
+                #[cfg(feature = "metrics")]
+                ::metrics::counter!(metrics::CMDS_REJECTED, "entity_type" => E::ENTITY_TYPE.unwrap_or_default()).increment(1);
+                let seq_no = self.last_seq_no;
+                return Ok(Err(Rejected { error, seq_no }));
+            }
+        };
+
+        self.persist_and_apply(tagged_evt, ctx, meta).await.map(Ok)
+    }
+
+    async fn handle_cmd_streaming(
+        &mut self,
+        cmd: E::Cmd,
+        progress: &mpsc::Sender<SeqNo>,
+    ) -> Result<Result<(), E::Error>, Box<dyn StdError>> {
+        let span = span!(
+            self.span_level,
+            "handle_cmd_streaming",
+            id = %self.id,
+            seq_no = field::Empty
+        );
+        self.handle_cmd_streaming_in_span(cmd, progress)
+            .instrument(span)
+            .await
+    }
+
+    async fn handle_cmd_streaming_in_span(
+        &mut self,
+        cmd: E::Cmd,
+        progress: &mpsc::Sender<SeqNo>,
+    ) -> Result<Result<(), E::Error>, Box<dyn StdError>> {
+        let tagged_evts = match self
+            .event_sourced
+            .handle_cmd_streaming(self.id.clone(), cmd)
+        {
+            Ok(tagged_evts) => tagged_evts,
+
+            Err(error) => {
+                #[cfg(feature = "metrics")]
+                ::metrics::counter!(metrics::CMDS_REJECTED, "entity_type" => E::ENTITY_TYPE.unwrap_or_default()).increment(1);
+                return Ok(Err(error));
+            }
+        };
+
+        // As applying an event consumes it, the events are also kept as bytes for persisting.
+        let ctx = CmdContext::default();
+        let mut evts = Vec::with_capacity(tagged_evts.len());
+        let mut pending = Vec::with_capacity(tagged_evts.len());
+        let mut effects = vec![];
+        for tagged_evt in tagged_evts {
+            let TaggedEvt {
+                evt,
+                tags,
+                effects: tagged_effects,
+            } = tagged_evt;
+            let evt = self.event_sourced.enrich(evt, &ctx);
+            let version = self.event_sourced.evt_version(&evt);
+            let bytes = (self.evt_to_bytes)(&evt)?;
+            pending.push((
+                version,
+                TaggedEvt {
+                    evt: bytes,
+                    tags,
+                    effects: vec![],
+                },
+            ));
+            effects.extend(tagged_effects);
+            evts.push(evt);
+        }
+        if evts.is_empty() {
+            return Ok(Ok(()));
+        }
+
+        // Persist all events via a single call, see [EvtLog::persist_batch], before applying any
+        // of them.
+        let seq_nos = self.persist_evts(&pending).await?;
+        self.execute_effects(effects);
+        self.event_sourced
+            .on_evts_persisted(seq_nos[0]..=seq_nos[seq_nos.len() - 1], &evts);
+
+        for (evt, seq_no) in evts.into_iter().zip(seq_nos) {
+            if let Some(state) = self.handle_evt(evt)? {
+                self.save_snapshot(seq_no, state).await?;
+            }
+            if progress.send(seq_no).await.is_err() {
+                debug!(id = %self.id, %seq_no, "cannot send progress");
+            }
+        }
+
+        Ok(Ok(()))
+    }
+
+    /// Persist the given tagged event, execute its effects, apply it and possibly save a snapshot,
+    /// returning its sequence number and global sequence number, if any.
+    async fn persist_and_apply(
+        &mut self,
+        tagged_evt: TaggedEvt<E::Evt, E::Effect>,
+        ctx: &CmdContext,
+        meta: &EvtMeta,
+    ) -> Result<(SeqNo, Option<GlobalSeqNo>), Box<dyn StdError>> {
+        let TaggedEvt {
+            evt,
+            mut tags,
+            effects,
+        } = tagged_evt;
+        tags.extend(meta.to_tags());
+        let evt = self.event_sourced.enrich(evt, ctx);
+        let version = self.event_sourced.evt_version(&evt);
+        let bytes = (self.evt_to_bytes)(&evt)?;
+        let retry_policy = self.persist_retry_policy;
+        let mut attempt = 0;
+        let (seq_no, global_seq_no) = loop {
+            let seq_nos = self
+                .evt_log
+                .persist_returning_global_seq_no(
+                    &bytes,
+                    version,
+                    &tags,
+                    self.id.clone(),
+                    E::ENTITY_TYPE,
+                    self.last_seq_no,
+                    &clone_bytes,
+                )
+                .await;
+            match seq_nos {
+                Ok(seq_nos) => break seq_nos,
+
+                Err(error) => {
+                    let delay = L::is_retryable(&error)
+                        .then(|| retry_policy.delay(attempt))
+                        .flatten();
+                    let Some(delay) = delay else {
+                        return Err(error.into());
+                    };
+                    warn!(id = %self.id, %error, attempt, ?delay, "retrying to persist event");
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        };
+        self.last_seq_no = Some(seq_no);
+        Span::current().record("seq_no", seq_no.as_u64());
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!(metrics::EVTS_PERSISTED, "entity_type" => E::ENTITY_TYPE.unwrap_or_default()).increment(1);
+        self.execute_effects(effects);
+        self.event_sourced
+            .on_evts_persisted(seq_no..=seq_no, slice::from_ref(&evt));
+
+        if let Some(state) = self.handle_evt(evt)? {
+            self.save_snapshot(seq_no, state).await?;
+        }
+
+        Ok((seq_no, global_seq_no))
+    }
+
+    async fn handle_cmds(&mut self, cmds: Vec<E::Cmd>) -> BatchResult<E::Error> {
+        let span = span!(
+            self.span_level,
+            "handle_cmds",
+            id = %self.id,
+            seq_no = field::Empty
+        );
+        self.handle_cmds_in_span(cmds).instrument(span).await
+    }
+
+    /// Handle the given commands, returning their results along with an error for the event which
+    /// could not be converted into bytes or applied, if any, terminating the entity.
+    async fn handle_cmds_in_span(&mut self, cmds: Vec<E::Cmd>) -> BatchResult<E::Error> {
+        let mut results = Vec::with_capacity(cmds.len());
+
+        // As the command handler must see the effect of the former events, these are applied right
+        // away, but only persisted at the end via a single call, see [EvtLog::persist_batch]. Only
+        // then the effects are executed and the latest snapshot, if any, is saved. If an event
+        // cannot be converted into bytes or applied, only the former events are persisted and the
+        // remaining commands fail.
+        let mut pending = vec![];
+        let mut evts = vec![];
+        let mut effects = vec![];
+        let mut snapshot = None;
+        let mut failure = None;
+        let ctx = CmdContext::default();
+
+        let mut cmds = cmds.into_iter();
+        for cmd in cmds.by_ref() {
+            if self.event_sourced.is_terminal() {
+                results.push(Err(EntityRefError::Deleted));
+                continue;
+            }
+
+            let tagged_evt = match self.event_sourced.handle_cmd(self.id.clone(), cmd) {
+                Ok(tagged_evt) => tagged_evt.into_tagged_evt(),
+
+                Err(error) => {
+                    #[cfg(feature = "metrics")]
+                    ::metrics::counter!(metrics::CMDS_REJECTED, "entity_type" => E::ENTITY_TYPE.unwrap_or_default()).increment(1);
+                    results.push(Ok(Err(error)));
+                    continue;
+                }
+            };
+
+            let TaggedEvt {
+                evt,
+                tags,
+                effects: tagged_effects,
+            } = tagged_evt;
+            let evt = self.event_sourced.enrich(evt, &ctx);
+            let version = self.event_sourced.evt_version(&evt);
+            let bytes = match (self.evt_to_bytes)(&evt) {
+                Ok(bytes) => bytes,
+
+                Err(error) => {
+                    failure = Some(error.into());
+                    results.push(Err(EntityRefError::Failed));
+                    break;
+                }
+            };
+            let state = match self.handle_evt(evt.clone()) {
+                Ok(state) => state,
+
+                Err(error) => {
+                    failure = Some(error.into());
+                    results.push(Err(EntityRefError::Failed));
+                    break;
+                }
+            };
+
+            pending.push((
+                version,
+                TaggedEvt {
+                    evt: bytes,
+                    tags,
+                    effects: vec![],
+                },
+            ));
+            evts.push(evt);
+            effects.extend(tagged_effects);
+            results.push(Ok(Ok(())));
+            if let Some(state) = state {
+                snapshot = Some((pending.len() - 1, state));
+            }
+        }
+        results.extend(cmds.map(|_| Err(EntityRefError::Failed)));
+
+        if !pending.is_empty() {
+            let seq_nos = self.persist_evts(&pending).await?;
+            self.execute_effects(effects);
+            self.event_sourced
+                .on_evts_persisted(seq_nos[0]..=seq_nos[seq_nos.len() - 1], &evts);
+
+            if let Some((n, state)) = snapshot {
+                self.save_snapshot(seq_nos[n], state).await?;
+            }
+        }
+
+        Ok((results, failure))
+    }
+
+    /// Apply the given event and return the snapshot state to be saved, if any: the one returned by
+    /// the event handler or else the final one if the event has made the entity terminal.
+    fn handle_evt(&mut self, evt: E::Evt) -> Result<Option<E::State>, E::Error> {
+        let terminal = self.event_sourced.is_terminal();
+        let state = self.event_sourced.handle_evt(evt)?;
+        if state.is_none() && !terminal && self.event_sourced.is_terminal() {
+            debug!(id = %self.id, "entity has become terminal");
+            return Ok(self.event_sourced.terminal_state());
+        }
+        Ok(state)
+    }
+
+    /// Execute the given deferred effects of persisted events, if any.
+    fn execute_effects(&self, effects: Vec<E::Effect>) {
+        if effects.is_empty() {
+            return;
+        }
+
+        match self.event_sourced.effect_executor() {
+            Some(executor) => {
+                debug!(id = %self.id, count = effects.len(), "executing effects");
+                effects
+                    .into_iter()
+                    .for_each(|effect| executor.execute(effect));
+            }
+
+            None => warn!(
+                id = %self.id,
+                count = effects.len(),
+                "dropping effects without effect executor"
+            ),
+        }
+    }
+
+    /// Persist the given versioned events at once, each as its own entry of the batch such that
+    /// its sequence number is known, and return their sequence numbers.
+    async fn persist_evts(
+        &mut self,
+        evts: &[(u32, TaggedEvt<Bytes>)],
+    ) -> Result<Vec<SeqNo>, Box<dyn StdError>> {
+        let batch = evts
+            .iter()
+            .map(|(version, evt)| EntityEvts {
+                id: self.id.clone(),
+                entity_type: E::ENTITY_TYPE,
+                last_seq_no: self.last_seq_no,
+                version: *version,
+                evts: slice::from_ref(evt),
+            })
+            .collect::<Vec<_>>();
+        let retry_policy = self.persist_retry_policy;
+        let mut attempt = 0;
+        let last_seq_nos = loop {
+            let last_seq_nos = self.evt_log.persist_batch(&batch, &clone_bytes).await;
+            match last_seq_nos {
+                Ok(last_seq_nos) => break last_seq_nos,
+
+                Err(error) => {
+                    let delay = L::is_retryable(&error)
+                        .then(|| retry_policy.delay(attempt))
+                        .flatten();
+                    let Some(delay) = delay else {
+                        return Err(error.into());
+                    };
+                    warn!(id = %self.id, %error, attempt, ?delay, "retrying to persist events");
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        };
+        let seq_nos = last_seq_nos
+            .into_iter()
+            .map(|seq_no| seq_no.expect("last_seq_no is some after persisting an event"))
+            .collect::<Vec<_>>();
+
+        if let Some(&seq_no) = seq_nos.last() {
+            self.last_seq_no = Some(seq_no);
+            Span::current().record("seq_no", seq_no.as_u64());
+        }
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!(metrics::EVTS_PERSISTED, "entity_type" => E::ENTITY_TYPE.unwrap_or_default()).increment(seq_nos.len() as u64);
+
+        Ok(seq_nos)
+    }
+
+    /// Save the given snapshot state at the given sequence number and possibly delete the events
+    /// up to it.
+    async fn save_snapshot(
+        &mut self,
+        seq_no: SeqNo,
+        state: E::State,
+    ) -> Result<(), Box<dyn StdError>> {
+        debug!(id = %self.id, %seq_no, "saving snapshot");
+        let bytes = (self.state_to_bytes)(&state)?;
+        self.snapshot_store
+            .save(
+                self.id.clone(),
+                seq_no,
+                E::STATE_VERSION,
+                bytes,
+                &clone_bytes,
+            )
+            .await?;
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!(metrics::SNAPSHOTS_SAVED, "entity_type" => E::ENTITY_TYPE.unwrap_or_default()).increment(1);
+
+        if self.prune_on_snapshot {
+            debug!(id = %self.id, %seq_no, "deleting events");
+            self.evt_log.delete_to(self.id.clone(), seq_no).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Results of handling a batch of commands, see [Entity::handle_cmds_in_span].
+#[allow(clippy::type_complexity)]
+type BatchResult<Err> = Result<
+    (
+        Vec<Result<Result<(), Err>, EntityRefError>>,
+        Option<Box<dyn StdError + Send + Sync>>,
+    ),
+    Box<dyn StdError>,
+>;
+
+/// Identity conversion for events and snapshot states already converted to bytes by the entity,
+/// such that these need neither be `Send` nor `Sync`, see [EventSourcedExt::spawn_local].
+fn clone_bytes(bytes: &Bytes) -> Result<Bytes, Infallible> {
+    Ok(bytes.clone())
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use async_stream::stream;
+    use bytes::BytesMut;
+    use chrono::Utc;
+    use futures::{stream, Stream, TryStreamExt};
+    use prost::Message;
+    use std::{cell::Cell, convert::Infallible, io, num::NonZeroUsize, rc::Rc};
+    use tokio::task::LocalSet;
+
+    #[derive(Debug)]
+    struct Simple(u64);
+
+    impl EventSourced for Simple {
+        type Cmd = ();
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = Infallible;
+
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            _cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            Ok(((1 << 32) + self.0).with_tag("tag"))
+        }
+
+        fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            self.0 += evt >> 32;
+            Ok(None)
+        }
+
+        fn set_state(&mut self, state: Self::State) {
+            self.0 = state;
+        }
+    }
+
+    /// Neither `Send` nor `Sync`, see [EventSourcedExt::spawn_local].
+    #[derive(Debug)]
+    struct Local(Rc<Cell<u64>>);
+
+    impl EventSourced for Local {
+        type Cmd = Rc<u64>;
+
+        type Evt = Rc<u64>;
+
+        type State = Rc<u64>;
+
+        type Error = Infallible;
+
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            Ok(cmd)
+        }
+
+        fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            self.0.set(self.0.get() + *evt);
+            Ok(Some(Rc::new(self.0.get())))
+        }
+
+        fn set_state(&mut self, state: Self::State) {
+            self.0.set(*state);
+        }
+    }
+
+    #[derive(Debug)]
+    struct Enriched;
+
+    impl EventSourced for Enriched {
+        type Cmd = ();
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = Infallible;
+
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            _cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            Ok(1)
+        }
+
+        fn handle_evt(&mut self, _evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            Ok(None)
+        }
+
+        fn set_state(&mut self, _state: Self::State) {}
+
+        fn enrich(&self, evt: Self::Evt, ctx: &CmdContext) -> Self::Evt {
+            let bonus = ctx.get("bonus").map_or(0, |bonus| bonus.parse().unwrap());
+            evt + bonus
+        }
+    }
+
+    #[derive(Debug)]
+    struct Deletable(bool);
+
+    impl EventSourced for Deletable {
+        type Cmd = ();
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = Infallible;
+
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            _cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            Ok(0)
+        }
+
+        fn handle_evt(&mut self, _evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            self.0 = true;
+            Ok(None)
+        }
+
+        fn set_state(&mut self, state: Self::State) {
+            self.0 = state != 0;
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.0
+        }
+
+        fn terminal_state(&self) -> Option<Self::State> {
+            Some(self.0 as u64)
+        }
+    }
+
+    #[derive(Debug)]
+    /// Fails to apply the event `0`.
+    struct Faulty;
+
+    impl EventSourced for Faulty {
+        type Cmd = u64;
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = FaultyError;
+
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            Ok(cmd)
+        }
+
+        fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            if evt == 0 {
+                Err(FaultyError)
+            } else {
+                Ok(None)
+            }
+        }
+
+        fn set_state(&mut self, _state: Self::State) {}
+    }
+
+    #[derive(Debug, Error)]
+    #[error("FaultyError")]
+    struct FaultyError;
+
+    /// Counts up to two, rejecting further commands.
+    #[derive(Debug, Default)]
+    struct Capped(u64);
+
+    impl EventSourced for Capped {
+        type Cmd = ();
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = CapReached;
+
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            _cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            if self.0 < 2 {
+                Ok(1)
+            } else {
+                Err(CapReached)
+            }
+        }
+
+        fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            self.0 += evt;
+            Ok(None)
+        }
+
+        fn set_state(&mut self, state: Self::State) {
+            self.0 = state;
+        }
+    }
+
+    #[derive(Debug, Error)]
+    #[error("CapReached")]
+    struct CapReached;
+
+    #[derive(Debug)]
+    struct Panicky;
+
+    impl EventSourced for Panicky {
+        type Cmd = ();
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = Infallible;
+
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            _cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            panic!("Panicky panics");
+            #[allow(unreachable_code)]
+            Ok(0)
+        }
+
+        fn handle_evt(&mut self, _evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            Ok(None)
+        }
+
+        fn set_state(&mut self, _state: Self::State) {}
+    }
+
+    #[derive(Debug)]
+    struct Versioned(u64, VersionRecorder);
+
+    impl EventSourced for Versioned {
+        type Cmd = ();
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = Infallible;
+
+        type Effect = ();
+
+        const ENTITY_TYPE: Option<&'static str> = Some("versioned");
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            _cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            Ok(1)
+        }
+
+        fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            self.0 += evt;
+            Ok(None)
+        }
+
+        fn set_state(&mut self, state: Self::State) {
+            self.0 = state;
+        }
+
+        fn evt_version(&self, _evt: &Self::Evt) -> u32 {
+            2
+        }
+
+        fn upcaster(&self) -> Option<&dyn Upcaster> {
+            Some(&self.1)
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct VersionRecorder(Arc<std::sync::Mutex<Vec<u32>>>);
+
+    impl Upcaster for VersionRecorder {
+        fn upcast(&self, version: u32, bytes: Bytes) -> Bytes {
+            self.0.lock().unwrap().push(version);
+            bytes
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct TestEvtLog;
+
+    impl EvtLog for TestEvtLog {
+        type Error = TestEvtLogError;
+
+        async fn persist<E, ToBytes, ToBytesError>(
+            &self,
+            _evt: &E,
+            _version: u32,
+            _tags: &[String],
+            _id: Uuid,
+            _entity_type: Option<&str>,
+            _last_seq_no: Option<SeqNo>,
+            _to_bytes: &ToBytes,
+        ) -> Result<SeqNo, Self::Error>
+        where
+            E: Sync,
+            ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+            ToBytesError: StdError + Send + Sync + 'static,
+        {
+            Ok(SeqNo(43.try_into().unwrap()))
+        }
+
+        async fn delete_to(&self, _id: Uuid, _to_seq_no: SeqNo) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn mark_deleted(&self, _id: Uuid) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn is_deleted(&self, _id: Uuid) -> Result<bool, Self::Error> {
+            Ok(false)
+        }
+
+        async fn last_seq_no(&self, _entity_id: Uuid) -> Result<Option<SeqNo>, Self::Error> {
+            Ok(Some(SeqNo(42.try_into().unwrap())))
+        }
+
+        async fn evts_by_id<E, FromBytes, FromBytesError>(
+            &self,
+            _id: Uuid,
+            from_seq_no: SeqNo,
+            evt_from_bytes: FromBytes,
+        ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+        where
+            E: Send,
+            FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send,
+            FromBytesError: StdError + Send + Sync + 'static,
+        {
+            let evts = stream! {
+                for n in 0..666 {
+                    for evt in 1..=3 {
+                        let seq_no = (n * 3 + evt).try_into().unwrap();
+                        if from_seq_no <= seq_no  {
+                            let mut bytes = BytesMut::new();
+                            evt.encode(&mut bytes).map_err(|error| TestEvtLogError(error.into()))?;
+                            let evt = evt_from_bytes(bytes.into()).map_err(|error| TestEvtLogError(error.into()))?;
+                            yield Ok(evt_envelope(seq_no, evt));
+                        }
+                    }
+
+                }
+            };
+            Ok(evts)
+        }
+
+        async fn evts_by_ids<E, FromBytes, FromBytesError>(
+            &self,
+            _ids: Vec<Uuid>,
+            _from_global_seq_no: GlobalSeqNo,
+            _evt_from_bytes: FromBytes,
+        ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+        where
+            E: Send,
+            FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+            FromBytesError: StdError + Send + Sync + 'static,
+        {
+            Ok(stream::empty())
+        }
+
+        async fn evts<E, FromBytes, FromBytesError>(
+            &self,
+            _from_global_seq_no: GlobalSeqNo,
+            _evt_from_bytes: FromBytes,
+        ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+        where
+            E: Send,
+            FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+            FromBytesError: StdError + Send + Sync + 'static,
+        {
+            Ok(stream::empty())
+        }
+
+        async fn evts_by_type<E, FromBytes, FromBytesError>(
+            &self,
+            _entity_type: String,
+            _from_global_seq_no: GlobalSeqNo,
+            _evt_from_bytes: FromBytes,
+        ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+        where
+            E: Send,
+            FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+            FromBytesError: StdError + Send + Sync + 'static,
+        {
+            Ok(stream::empty())
+        }
+
+        async fn evts_by_tag<E, FromBytes, FromBytesError>(
+            &self,
+            _tag: String,
+            from_seq_no: SeqNo,
+            evt_from_bytes: FromBytes,
+        ) -> Result<impl Stream<Item = Result<EvtEnvelope<E>, Self::Error>> + Send, Self::Error>
+        where
+            E: Send,
+            FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+            FromBytesError: StdError + Send + Sync + 'static,
+        {
+            let evts = stream! {
+                for n in 0..666 {
+                    for evt in 1..=3 {
+                        let seq_no = (n * 3 + evt).try_into().unwrap();
+                        if from_seq_no <= seq_no  {
+                            let mut bytes = BytesMut::new();
+                            evt.encode(&mut bytes).map_err(|error| TestEvtLogError(error.into()))?;
+                            let evt = evt_from_bytes(bytes.into()).map_err(|error| TestEvtLogError(error.into()))?;
+                            yield Ok(evt_envelope(seq_no, evt));
+                        }
+                    }
+
+                }
+            };
+            Ok(evts)
+        }
+    }
+
+    fn evt_envelope<E>(seq_no: SeqNo, evt: E) -> EvtEnvelope<E> {
+        EvtEnvelope {
+            id: Uuid::nil(),
+            entity_type: None,
+            seq_no,
+            global_seq_no: GlobalSeqNo::new(seq_no.0),
+            version: 1,
+            timestamp: Utc::now(),
+            tags: vec![],
+            evt,
+        }
+    }
+
+    #[derive(Debug, Error)]
+    #[error("TestEvtLogError")]
+    struct TestEvtLogError(#[source] Box<dyn StdError + Send + Sync>);
+
+    #[derive(Debug, Clone)]
+    struct TestSnapshotStore;
+
+    impl SnapshotStore for TestSnapshotStore {
+        type Error = TestSnapshotStoreError;
+
+        async fn save<S, ToBytes, ToBytesError>(
+            &self,
+            _id: Uuid,
+            _seq_no: SeqNo,
+            _state_version: u32,
+            _state: S,
+            _state_to_bytes: &ToBytes,
+        ) -> Result<(), Self::Error>
+        where
+            S: Send,
+            ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
+            ToBytesError: StdError,
+        {
+            Ok(())
+        }
+
+        async fn load<S, FromBytes, FromBytesError>(
+            &self,
+            _id: Uuid,
+            _state_version: u32,
+            state_from_bytes: FromBytes,
+        ) -> Result<Option<Snapshot<S>>, Self::Error>
+        where
+            FromBytes: Fn(Bytes) -> Result<S, FromBytesError>,
+            FromBytesError: StdError,
+        {
+            let mut bytes = BytesMut::new();
+            42.encode(&mut bytes).unwrap();
+            let state = state_from_bytes(bytes.into()).unwrap();
+            Ok(Some(Snapshot {
+                seq_no: 42.try_into().unwrap(),
+                state,
+            }))
+        }
+
+        async fn delete_before(&self, _id: Uuid, _seq_no: SeqNo) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Error)]
+    #[error("TestSnapshotStoreError")]
+    struct TestSnapshotStoreError;
+
+    /// Returns its count as deferred effect and executes effects by recording them.
+    #[derive(Debug, Default)]
+    struct Effectful {
+        count: u64,
+        executed: Arc<Mutex<Vec<u64>>>,
+    }
+
+    impl EventSourced for Effectful {
+        type Cmd = ();
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = Infallible;
+
+        type Effect = u64;
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            _cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt, Self::Effect>, Self::Error> {
+            Ok(1.with_effect(self.count + 1))
+        }
+
+        fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            self.count += evt;
+            Ok(None)
+        }
+
+        fn set_state(&mut self, state: Self::State) {
+            self.count = state;
+        }
+
+        fn effect_executor(&self) -> Option<&dyn EffectExecutor<Self::Effect>> {
+            Some(self)
+        }
+    }
+
+    impl EffectExecutor<u64> for Effectful {
+        fn execute(&self, effect: u64) {
+            self.executed.lock().unwrap().push(effect);
+        }
+    }
+
+    /// Records the sequence numbers of replayed events and wants a snapshot for every handled one.
+    #[derive(Debug, Default)]
+    struct Replaying {
+        applied: Arc<Mutex<Vec<u64>>>,
+    }
+
+    impl EventSourced for Replaying {
+        type Cmd = ();
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = Infallible;
+
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            _cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            Ok(1)
+        }
+
+        fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            Ok(Some(evt))
+        }
+
+        fn apply_evt(&mut self, seq_no: SeqNo, _evt: Self::Evt) -> Result<(), Self::Error> {
+            self.applied.lock().unwrap().push(seq_no.as_u64());
+            Ok(())
+        }
+
+        fn set_state(&mut self, _state: Self::State) {}
+    }
+
+    /// Skips events which cannot be deserialized.
+    #[derive(Debug)]
+    struct Lenient;
+
+    impl EventSourced for Lenient {
+        type Cmd = u64;
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = Infallible;
+
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            Ok(cmd)
+        }
+
+        fn handle_evt(&mut self, _evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            Ok(None)
+        }
+
+        fn set_state(&mut self, _state: Self::State) {}
+    }
+
+    /// Sums up the events, the given number of which a streaming command results in, recording
+    /// the ranges of sequence numbers and the events it has been notified about having been
+    /// persisted.
+    #[derive(Debug, Default)]
+    struct Bulk {
+        sum: u64,
+        #[allow(clippy::type_complexity)]
+        persisted: Arc<Mutex<Vec<((u64, u64), Vec<u64>)>>>,
+    }
+
+    impl EventSourced for Bulk {
+        type Cmd = u64;
+
+        type Evt = u64;
+
+        type State = u64;
+
+        type Error = Infallible;
+
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            _cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            Ok(1)
+        }
+
+        fn handle_cmd_streaming(
+            &self,
+            _id: Uuid,
+            cmd: Self::Cmd,
+        ) -> Result<Vec<TaggedEvt<Self::Evt>>, Self::Error> {
+            Ok((1..=cmd).map(IntoTaggedEvt::into_tagged_evt).collect())
+        }
+
+        fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            self.sum += evt;
+            Ok(None)
+        }
+
+        fn set_state(&mut self, state: Self::State) {
+            self.sum = state;
+        }
+
+        fn on_evts_persisted(&self, seq_nos: RangeInclusive<SeqNo>, evts: &[Self::Evt]) {
+            let seq_nos = (seq_nos.start().as_u64(), seq_nos.end().as_u64());
+            self.persisted
+                .lock()
+                .unwrap()
+                .push((seq_nos, evts.to_vec()));
+        }
+    }
+
+    /// Takes a second to load no snapshot.
+    #[derive(Debug, Clone)]
+    struct SlowSnapshotStore;
+
+    impl SnapshotStore for SlowSnapshotStore {
+        type Error = Infallible;
+
+        async fn save<S, ToBytes, ToBytesError>(
+            &self,
+            _id: Uuid,
+            _seq_no: SeqNo,
+            _state_version: u32,
+            _state: S,
+            _state_to_bytes: &ToBytes,
+        ) -> Result<(), Self::Error>
+        where
+            S: Send,
+            ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
+            ToBytesError: StdError,
+        {
+            Ok(())
+        }
+
+        async fn load<S, FromBytes, FromBytesError>(
+            &self,
+            _id: Uuid,
+            _state_version: u32,
+            _state_from_bytes: FromBytes,
+        ) -> Result<Option<Snapshot<S>>, Self::Error>
+        where
+            FromBytes: Fn(Bytes) -> Result<S, FromBytesError>,
+            FromBytesError: StdError,
+        {
+            sleep(Duration::from_secs(1)).await;
+            Ok(None)
+        }
+
+        async fn delete_before(&self, _id: Uuid, _seq_no: SeqNo) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmd() -> Result<(), Box<dyn StdError>> {
+        let evt_log = TestEvtLog;
+        let snapshot_store = TestSnapshotStore;
+
+        let entity = spawn(Simple(0), evt_log, snapshot_store).await?;
+        entity.handle_cmd(()).await??;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmd_effects() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+
+        let effectful = Effectful::default();
+        let executed = effectful.executed.clone();
+        let entity = spawn(effectful, evt_log, snapshot_store).await?;
+        entity.handle_cmd(()).await??;
+        assert_eq!(*executed.lock().unwrap(), vec![1]);
+
+        let results = entity.handle_cmds(vec![(), ()]).await?;
+        assert!(results.iter().all(|result| matches!(result, Ok(Ok(())))));
+        assert_eq!(*executed.lock().unwrap(), vec![1, 2, 3]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmd_streaming() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let id = Uuid::now_v7();
+
+        let bulk = Bulk::default();
+        let persisted = bulk.persisted.clone();
+        let entity = spawn_with_id(id, bulk, evt_log.clone(), NoopSnapshotStore).await?;
+        let (progress_in, mut progress_out) = mpsc::channel(1);
+        let (result, seq_nos) = tokio::join!(entity.handle_cmd_streaming(3, progress_in), async {
+            let mut seq_nos = vec![];
+            while let Some(seq_no) = progress_out.recv().await {
+                seq_nos.push(seq_no.as_u64());
+            }
+            seq_nos
+        });
+        result??;
+        assert_eq!(seq_nos, vec![1, 2, 3]);
+
+        // A dropped progress receiver does not prevent handling the command.
+        let (progress_in, progress_out) = mpsc::channel(1);
+        drop(progress_out);
+        entity.handle_cmd_streaming(2, progress_in).await??;
+        entity.handle_cmd(0).await??;
+        assert_eq!(
+            evt_log.last_seq_no(id).await?.map(|seq_no| seq_no.as_u64()),
+            Some(6)
+        );
+        assert_eq!(
+            *persisted.lock().unwrap(),
+            vec![
+                ((1, 3), vec![1, 2, 3]),
+                ((4, 5), vec![1, 2]),
+                ((6, 6), vec![1])
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_apply_evt() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let id = Uuid::now_v7();
+
+        let replaying = Replaying::default();
+        let applied = replaying.applied.clone();
+        let entity = spawn_with_id(id, replaying, evt_log.clone(), NoopSnapshotStore).await?;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
+        assert!(applied.lock().unwrap().is_empty());
+
+        let replaying = Replaying::default();
+        let applied = replaying.applied.clone();
+        let _entity = spawn_with_id(id, replaying, evt_log, NoopSnapshotStore).await?;
+        assert_eq!(*applied.lock().unwrap(), vec![1, 2]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_downgrade() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+
+        let entity = spawn(Simple(0), evt_log, snapshot_store).await?;
+        let weak_entity = entity.downgrade();
+        assert_eq!(weak_entity.id(), entity.id());
+
+        let upgraded = weak_entity.upgrade().expect("entity ref not yet dropped");
+        upgraded.handle_cmd(()).await??;
+        drop(upgraded);
+
+        let task = entity.task.lock().unwrap().take().expect("task");
+        drop(entity);
+        assert!(weak_entity.upgrade().is_none());
+        // The weak reference does not keep the entity alive.
+        timeout(Duration::from_secs(1), task).await??;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_shutdown() -> Result<(), Box<dyn StdError>> {
+        let evt_log = TestEvtLog;
+        let snapshot_store = TestSnapshotStore;
+
+        let entity = spawn(Simple(0), evt_log, snapshot_store).await?;
+        entity.handle_cmd(()).await??;
+        entity.clone().shutdown().await?;
+        entity.shutdown().await?;
+        let result = entity.handle_cmd(()).await;
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmd_deleted() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
+
+        let entity = spawn_with_id(
+            id,
+            Deletable(false),
+            evt_log.clone(),
+            snapshot_store.clone(),
+        )
+        .await?;
+        assert!(!entity.is_deleted());
+        entity.handle_cmd(()).await??;
+        assert!(entity.is_deleted());
+        let result = entity.handle_cmd(()).await;
+        assert!(matches!(result, Err(ref error) if error.is_client_error()));
+        assert!(matches!(result, Err(EntityRefError::Deleted)));
+
+        // A final snapshot is saved, such that the deleted entity is recovered from it.
+        let snapshot = snapshot_store
+            .load::<u64, _, _>(id, Deletable::STATE_VERSION, convert::prost::from_bytes)
+            .await?
+            .map(|snapshot| (snapshot.seq_no.as_u64(), snapshot.state));
+        assert_eq!(snapshot, Some((1, 1)));
+
+        // The deleted entity cannot be spawned again.
+        let result = spawn_with_id(id, Deletable(false), evt_log, snapshot_store).await;
+        assert!(matches!(
+            result
+                .as_ref()
+                .map_err(|error| error.downcast_ref::<SpawnError>()),
+            Err(Some(SpawnError::Deleted))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_marked_deleted() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
+
+        let entity = spawn_with_id(id, Simple(0), evt_log.clone(), snapshot_store.clone()).await?;
+        entity.handle_cmd(()).await??;
+        evt_log.mark_deleted(id).await?;
+
+        // Neither the snapshot nor the events are loaded, hence they need not be convertible.
+        let shredded = |_: Bytes| Err::<u64, _>(std::io::Error::other("key discarded"));
+        let binarizer = Binarizer {
+            evt_to_bytes: convert::prost::to_bytes,
+            evt_from_bytes: shredded,
+            state_to_bytes: convert::prost::to_bytes,
+            state_from_bytes: shredded,
+        };
+        let result = Simple(0)
+            .spawn(
+                id,
+                NonZeroUsize::MIN,
+                evt_log.clone(),
+                snapshot_store.clone(),
+                binarizer.clone(),
+            )
+            .await;
+        assert!(matches!(result, Err(SpawnError::Deleted)));
+
+        let result = replay_state(
+            Simple(0),
+            id,
+            evt_log,
+            snapshot_store,
+            binarizer,
+            ReplayErrorPolicy::Fail,
+            Level::INFO,
+        )
+        .await;
+        assert!(matches!(result, Err(SpawnError::Deleted)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmd_panicked() -> Result<(), Box<dyn StdError>> {
+        let evt_log = TestEvtLog;
+        let snapshot_store = TestSnapshotStore;
+
+        let entity = spawn(Panicky, evt_log, snapshot_store).await?;
+        assert!(!entity.is_panicked());
+        let result = entity.handle_cmd(()).await;
+        assert!(matches!(result, Err(EntityRefError::Panicked)));
+        assert!(matches!(result, Err(ref error) if !error.is_client_error()));
+        assert!(entity.is_panicked());
+        let result = entity.handle_cmd(()).await;
+        assert!(matches!(result, Err(EntityRefError::Panicked)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmds() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
+
+        let entity = spawn_with_id(id, Simple(0), evt_log.clone(), snapshot_store.clone()).await?;
+        let results = entity.handle_cmds(vec![(), (), ()]).await?;
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|result| matches!(result, Ok(Ok(())))));
+        entity.handle_cmd_if(Some(3.try_into()?), ()).await??;
+
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, Some(4.try_into()?));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmds_persisted() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let id = Uuid::now_v7();
+
+        let bulk = Bulk::default();
+        let persisted = bulk.persisted.clone();
+        let entity = spawn_with_id(id, bulk, evt_log.clone(), NoopSnapshotStore).await?;
+        entity.handle_cmd(0).await??;
+        let results = entity.handle_cmds(vec![0, 0, 0]).await?;
+        assert!(results.iter().all(|result| matches!(result, Ok(Ok(())))));
+        assert_eq!(
+            *persisted.lock().unwrap(),
+            vec![((1, 1), vec![1]), ((2, 4), vec![1, 1, 1])]
+        );
+        assert_eq!(
+            evt_log.last_seq_no(id).await?.map(|seq_no| seq_no.as_u64()),
+            Some(4)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmds_deleted() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+
+        let entity = spawn(Deletable(false), evt_log, snapshot_store).await?;
+        let results = entity.handle_cmds(vec![(), ()]).await?;
+        assert!(matches!(
+            results[..],
+            [Ok(Ok(())), Err(EntityRefError::Deleted)]
+        ));
+        assert!(entity.is_deleted());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmd_if() -> Result<(), Box<dyn StdError>> {
+        let evt_log = TestEvtLog;
+        let snapshot_store = TestSnapshotStore;
+
+        let entity = spawn(Simple(0), evt_log, snapshot_store).await?;
+        entity.handle_cmd_if(Some(42.try_into()?), ()).await??;
+        let result = entity.handle_cmd_if(Some(42.try_into()?), ()).await;
+        assert!(matches!(
+            result,
+            Err(EntityRefError::Conflict { expected, actual })
+                if expected == Some(42.try_into()?) && actual == Some(43.try_into()?)
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmd_returning_seq_no() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+
+        let entity = spawn(Capped::default(), evt_log, NoopSnapshotStore).await?;
+        let seq_no = entity.handle_cmd_returning_seq_no(()).await??;
+        assert_eq!(seq_no.as_u64(), 1);
+        let seq_no = entity.handle_cmd_returning_seq_no(()).await??;
+        assert_eq!(seq_no.as_u64(), 2);
+
+        let result = entity.handle_cmd_returning_seq_no(()).await?;
+        assert!(matches!(
+            result,
+            Err(Rejected { error: CapReached, seq_no }) if seq_no == Some(2.try_into()?)
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_persist_retry() -> Result<(), Box<dyn StdError>> {
+        let id = Uuid::now_v7();
+        let evt_log =
+            ThrottledEvtLog::new(MemoryEvtLog::default(), NonZeroUsize::MIN).with_max_queued(0);
+
+        let backoff =
+            ExponentialBackoff::new(Duration::from_millis(10), Duration::from_millis(10), 1);
+        let config = SpawnConfig::new(NonZeroUsize::MIN)
+            .with_persist_retry_policy(RetryPolicy::new(100, backoff));
+        let entity = Simple(0)
+            .spawn(
+                id,
+                config,
+                evt_log.clone(),
+                NoopSnapshotStore,
+                convert::prost::binarizer(),
+            )
+            .await?;
+
+        // While the stream holds the only permit, persisting is rejected, which is transient.
+        let evts = evt_log
+            .evts_by_id_from::<Bytes, _, _>(id, SeqNo::MIN, Ok::<_, Infallible>)
+            .await?;
+        let cmd = {
+            let entity = entity.clone();
+            task::spawn(async move { entity.handle_cmd(()).await })
+        };
+        sleep(Duration::from_millis(50)).await;
+        drop(evts);
+        cmd.await???;
+        assert!(evt_log.last_seq_no(id).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_persist_conflict_not_retried() -> Result<(), Box<dyn StdError>> {
+        let id = Uuid::now_v7();
+        let evt_log = MemoryEvtLog::default();
+
+        let backoff = ExponentialBackoff::new(Duration::from_secs(3600), Duration::MAX, 2);
+        let config = SpawnConfig::new(NonZeroUsize::MIN)
+            .with_persist_retry_policy(RetryPolicy::new(3, backoff));
+        let entity = Simple(0)
+            .spawn(
+                id,
+                config,
+                evt_log.clone(),
+                NoopSnapshotStore,
+                convert::prost::binarizer(),
+            )
+            .await?;
+
+        // A concurrent writer causes a sequence number conflict, which must not be retried.
+        evt_log
+            .persist(&1, 1, &[], id, None, None, &convert::prost::to_bytes)
+            .await?;
+        let result = timeout(Duration::from_secs(1), entity.handle_cmd(())).await?;
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_prune_on_snapshot() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let id = Uuid::now_v7();
+
+        let config = SpawnConfig::new(NonZeroUsize::MIN).with_prune_on_snapshot(true);
+        let entity = Replaying::default()
+            .spawn(
+                id,
+                config,
+                evt_log.clone(),
+                MemorySnapshotStore::default(),
+                convert::prost::binarizer(),
+            )
+            .await?;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
+
+        // Only the last event is kept.
+        let seq_nos = evt_log
+            .evts_by_id_from::<u64, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .map_ok(|evt| evt.seq_no.as_u64())
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(seq_nos, vec![3]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmd_apply_evt_error() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
+
+        let entity = spawn_with_id(id, Faulty, evt_log.clone(), snapshot_store.clone()).await?;
+        let result = entity.handle_cmd(0).await;
+        assert!(result.is_err());
+
+        let result = spawn_with_id(id, Faulty, evt_log, snapshot_store).await;
+        assert!(matches!(
+            result,
+            Err(error) if matches!(error.downcast_ref(), Some(SpawnError::ApplyEvt(_)))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmds_apply_evt_error() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
+
+        let entity = spawn_with_id(id, Faulty, evt_log.clone(), snapshot_store.clone()).await?;
+        let results = entity.handle_cmds(vec![1, 2, 0, 3]).await?;
+        assert!(matches!(
+            results[..],
+            [
+                Ok(Ok(())),
+                Ok(Ok(())),
+                Err(EntityRefError::Failed),
+                Err(EntityRefError::Failed)
+            ]
+        ));
+
+        // Only the events applied cleanly have been persisted, hence the entity can be recovered.
+        let last_seq_no = evt_log.last_seq_no(id).await?;
+        assert_eq!(last_seq_no, Some(2.try_into()?));
+        spawn_with_id(id, Faulty, evt_log, snapshot_store).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_evt_version() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
+
+        let version_recorder = VersionRecorder::default();
+        let entity = spawn_with_id(
+            id,
+            Versioned(0, version_recorder.clone()),
+            evt_log.clone(),
+            snapshot_store.clone(),
+        )
+        .await?;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
+        assert!(version_recorder.0.lock().unwrap().is_empty());
+
+        let evts = evt_log
+            .evts_by_id::<u64, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?;
+        let versions = evts
+            .take(2)
+            .map_ok(|evt| (evt.version, evt.entity_type))
+            .try_collect::<Vec<_>>()
+            .await?;
+        let entity_type = Some("versioned".to_string());
+        assert_eq!(versions, vec![(2, entity_type.clone()), (2, entity_type)]);
+
+        spawn_with_id(
+            id,
+            Versioned(0, version_recorder.clone()),
+            evt_log,
+            snapshot_store,
+        )
+        .await?;
+        assert_eq!(*version_recorder.0.lock().unwrap(), vec![2, 2]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_without_snapshots() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let id = Uuid::now_v7();
+
+        let entity = Simple(0)
+            .spawn_without_snapshots(
+                id,
+                unsafe { NonZeroUsize::new_unchecked(1) },
+                evt_log.clone(),
+                convert::prost::to_bytes,
+                convert::prost::from_bytes,
+            )
+            .await?;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
+
+        let entity = Simple(0)
+            .spawn_without_snapshots(
+                id,
+                unsafe { NonZeroUsize::new_unchecked(1) },
+                evt_log,
+                convert::prost::to_bytes,
+                convert::prost::from_bytes,
+            )
+            .await?;
+        entity.handle_cmd_if(Some(2.try_into()?), ()).await??;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_with_progress() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
+
+        let entity = spawn_with_id(id, Simple(0), evt_log.clone(), snapshot_store.clone()).await?;
+        for _ in 0..5 {
+            entity.handle_cmd(()).await??;
+        }
+
+        let progress = Arc::new(std::sync::Mutex::new(vec![]));
+        let on_progress = {
+            let progress = progress.clone();
+            move |seq_no: SeqNo, last_seq_no: SeqNo| {
+                progress
+                    .lock()
+                    .unwrap()
+                    .push((seq_no.as_u64(), last_seq_no.as_u64()))
+            }
+        };
+        Simple(0)
+            .spawn_with_progress(
+                id,
+                unsafe { NonZeroUsize::new_unchecked(1) },
+                evt_log,
+                snapshot_store,
+                convert::prost::binarizer(),
+                unsafe { NonZeroU64::new_unchecked(2) },
+                on_progress,
+            )
+            .await?;
+        assert_eq!(*progress.lock().unwrap(), vec![(2, 5), (4, 5)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_with_snapshot() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
+
+        let version_recorder = VersionRecorder::default();
+        let entity = spawn_with_id(
+            id,
+            Versioned(0, version_recorder.clone()),
+            evt_log.clone(),
+            snapshot_store.clone(),
+        )
+        .await?;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
+
+        let snapshot = Snapshot {
+            seq_no: 1.try_into()?,
+            state: 1,
+        };
+        let entity = Versioned(0, version_recorder.clone())
+            .spawn_with_snapshot(
+                id,
+                unsafe { NonZeroUsize::new_unchecked(1) },
+                evt_log.clone(),
+                snapshot_store.clone(),
+                convert::prost::binarizer(),
+                snapshot,
+            )
+            .await?;
+        assert_eq!(*version_recorder.0.lock().unwrap(), vec![2]);
+        entity.handle_cmd_if(Some(2.try_into()?), ()).await??;
+
+        let snapshot = Snapshot {
+            seq_no: 42.try_into()?,
+            state: 42,
+        };
+        let result = Versioned(0, version_recorder)
+            .spawn_with_snapshot(
+                id,
+                unsafe { NonZeroUsize::new_unchecked(1) },
+                evt_log,
+                snapshot_store,
+                convert::prost::binarizer(),
+                snapshot,
+            )
+            .await;
+        assert!(matches!(result, Err(SpawnError::InvalidSnapshot { .. })));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_with_report() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
+
+        let spawn_with_report = |snapshot_store| {
+            Simple(0).spawn_with_report(
+                id,
+                unsafe { NonZeroUsize::new_unchecked(1) },
+                evt_log.clone(),
+                snapshot_store,
+                convert::prost::binarizer(),
+            )
+        };
 
-struct Entity<E, L, S, EvtToBytes, StateToBytes> {
-    event_sourced: E,
-    id: Uuid,
-    last_seq_no: Option<SeqNo>,
-    evt_log: L,
-    snapshot_store: S,
-    evt_to_bytes: EvtToBytes,
-    state_to_bytes: StateToBytes,
-}
+        let (entity, report) = spawn_with_report(snapshot_store.clone()).await?;
+        assert_eq!(
+            report,
+            SpawnReport {
+                restored_from_snapshot: false,
+                snapshot_seq_no: None,
+                replayed_evts: 0,
+                skipped_evts: 0,
+                last_seq_no: None
+            }
+        );
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
 
-impl<E, L, S, EvtToBytes, EvtToBytesError, StateToBytes, StateToBytesError>
-    Entity<E, L, S, EvtToBytes, StateToBytes>
-where
-    E: EventSourced,
-    L: EvtLog,
-    S: SnapshotStore,
-    EvtToBytes: Fn(&E::Evt) -> Result<Bytes, EvtToBytesError> + Send + Sync + 'static,
-    EvtToBytesError: StdError + Send + Sync + 'static,
-    StateToBytes: Fn(&E::State) -> Result<Bytes, StateToBytesError> + Send + Sync + 'static,
-    StateToBytesError: StdError + Send + Sync + 'static,
-{
-    async fn handle_cmd(&mut self, cmd: E::Cmd) -> Result<Result<(), E::Error>, Box<dyn StdError>> {
-        let (seq_no, evt) = match self.event_sourced.handle_cmd(self.id, cmd) {
-            Ok(tagged_evt) => {
-                let TaggedEvt { evt, tag } = tagged_evt.into_tagged_evt();
-                let seq_no = self
-                    .evt_log
-                    .persist(
-                        &evt,
-                        tag.as_deref(),
-                        self.id,
-                        self.last_seq_no,
-                        &self.evt_to_bytes,
-                    )
-                    .await?;
-                self.last_seq_no = Some(seq_no);
-                (seq_no, evt)
+        let (_, report) = spawn_with_report(snapshot_store.clone()).await?;
+        assert!(!report.restored_from_snapshot);
+        assert_eq!(report.replayed_evts, 3);
+        assert_eq!(report.last_seq_no, Some(3.try_into()?));
+
+        snapshot_store
+            .save(id, 2.try_into()?, 1, 2, &convert::prost::to_bytes)
+            .await?;
+        let (_, report) = spawn_with_report(snapshot_store.clone()).await?;
+        assert_eq!(
+            report,
+            SpawnReport {
+                restored_from_snapshot: true,
+                snapshot_seq_no: Some(2.try_into()?),
+                replayed_evts: 1,
+                skipped_evts: 0,
+                last_seq_no: Some(3.try_into()?)
             }
+        );
 
-            Err(error) => return Ok(Err(error)),
-        };
+        // A loaded snapshot ahead of the events is rejected like a given one.
+        snapshot_store
+            .save(id, 42.try_into()?, 1, 42, &convert::prost::to_bytes)
+            .await?;
+        let result = spawn_with_report(snapshot_store).await;
+        assert!(matches!(result, Err(SpawnError::InvalidSnapshot { .. })));
 
-        let state = self.event_sourced.handle_evt(evt);
+        Ok(())
+    }
 
-        // Persist latest snapshot if any.
-        if let Some(state) = state {
-            debug!(id = %self.id, %seq_no, "saving snapshot");
-            self.snapshot_store
-                .save(self.id, seq_no, state, &self.state_to_bytes)
-                .await?;
-        }
+    #[tokio::test]
+    async fn test_spawn_incompatible_snapshot() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
 
-        Ok(Ok(()))
+        let entity = spawn_with_id(id, Simple(0), evt_log.clone(), NoopSnapshotStore).await?;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
+
+        // A snapshot with another state version is ignored, i.e. all events are replayed.
+        snapshot_store
+            .save(
+                id,
+                2.try_into()?,
+                Simple::STATE_VERSION + 1,
+                666,
+                &convert::prost::to_bytes,
+            )
+            .await?;
+        let (_, report) = Simple(0)
+            .spawn_with_report(
+                id,
+                unsafe { NonZeroUsize::new_unchecked(1) },
+                evt_log,
+                snapshot_store,
+                convert::prost::binarizer(),
+            )
+            .await?;
+        assert!(!report.restored_from_snapshot);
+        assert_eq!(report.replayed_evts, 3);
+
+        Ok(())
     }
-}
 
-#[cfg(all(test, feature = "prost"))]
-mod tests {
-    use super::*;
-    use async_stream::stream;
-    use bytes::BytesMut;
-    use futures::Stream;
-    use prost::Message;
-    use std::convert::Infallible;
+    #[tokio::test]
+    async fn test_spawn_recovery_timeout() -> Result<(), Box<dyn StdError>> {
+        let config =
+            SpawnConfig::new(NonZeroUsize::MIN).with_recovery_timeout(Duration::from_millis(10));
 
-    #[derive(Debug)]
-    struct Simple(u64);
+        let result = task::spawn(async move {
+            Simple(0)
+                .spawn(
+                    Uuid::now_v7(),
+                    config,
+                    MemoryEvtLog::default(),
+                    SlowSnapshotStore,
+                    convert::prost::binarizer(),
+                )
+                .await
+        })
+        .await?;
+        assert!(matches!(result, Err(SpawnError::RecoveryTimeout(_))));
 
-    impl EventSourced for Simple {
-        type Cmd = ();
+        // Fast enough.
+        let entity = Simple(0)
+            .spawn(
+                Uuid::now_v7(),
+                config,
+                MemoryEvtLog::default(),
+                NoopSnapshotStore,
+                convert::prost::binarizer(),
+            )
+            .await?;
+        entity.handle_cmd(()).await??;
 
-        type Evt = u64;
+        Ok(())
+    }
 
-        type State = u64;
+    #[tokio::test]
+    async fn test_spawn_deserialize_error() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
 
-        type Error = Infallible;
+        let entity = spawn_with_id(id, Simple(0), evt_log.clone(), snapshot_store.clone()).await?;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
 
-        fn handle_cmd(
-            &self,
-            _id: Uuid,
-            _cmd: Self::Cmd,
-        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
-            Ok(((1 << 32) + self.0).with_tag("tag"))
-        }
+        let corrupt = |_| Err::<u64, _>(io::Error::other("corrupt"));
 
-        fn handle_evt(&mut self, evt: Self::Evt) -> Option<Self::State> {
-            self.0 += evt >> 32;
-            None
-        }
+        let binarizer = Binarizer {
+            evt_to_bytes: convert::prost::to_bytes,
+            evt_from_bytes: corrupt,
+            state_to_bytes: convert::prost::to_bytes,
+            state_from_bytes: convert::prost::from_bytes,
+        };
+        let result = Simple(0)
+            .spawn(
+                id,
+                unsafe { NonZeroUsize::new_unchecked(1) },
+                evt_log.clone(),
+                snapshot_store.clone(),
+                binarizer,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(SpawnError::DeserializeEvt { seq_no, .. }) if seq_no == SeqNo::MIN
+        ));
 
-        fn set_state(&mut self, state: Self::State) {
-            self.0 = state;
-        }
+        snapshot_store
+            .save(id, 1.try_into()?, 1, 1, &convert::prost::to_bytes)
+            .await?;
+        let binarizer = Binarizer {
+            evt_to_bytes: convert::prost::to_bytes,
+            evt_from_bytes: convert::prost::from_bytes,
+            state_to_bytes: convert::prost::to_bytes,
+            state_from_bytes: corrupt,
+        };
+        let result = Simple(0)
+            .spawn(
+                id,
+                unsafe { NonZeroUsize::new_unchecked(1) },
+                evt_log,
+                snapshot_store,
+                binarizer,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(SpawnError::DeserializeState { seq_no, .. }) if seq_no == SeqNo::MIN
+        ));
+
+        Ok(())
     }
 
-    #[derive(Debug, Clone)]
-    struct TestEvtLog;
+    #[tokio::test]
+    async fn test_spawn_skip_deserialize_error() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let id = Uuid::now_v7();
 
-    impl EvtLog for TestEvtLog {
-        type Error = TestEvtLogError;
+        let entity = spawn_with_id(id, Lenient, evt_log.clone(), NoopSnapshotStore).await?;
+        entity.handle_cmd(1).await??;
+        entity.handle_cmd(2).await??;
+        entity.handle_cmd(3).await??;
 
-        async fn persist<E, ToBytes, ToBytesError>(
-            &mut self,
-            _evt: &E,
-            _tag: Option<&str>,
-            _id: Uuid,
-            _last_seq_no: Option<SeqNo>,
-            _to_bytes: &ToBytes,
-        ) -> Result<SeqNo, Self::Error>
-        where
-            E: Sync,
-            ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
-            ToBytesError: StdError + Send + Sync + 'static,
-        {
-            Ok(SeqNo(43.try_into().unwrap()))
-        }
+        let corrupt_2 = |bytes| {
+            let evt = convert::prost::from_bytes::<u64>(bytes).map_err(io::Error::other)?;
+            if evt == 2 {
+                Err(io::Error::other("corrupt"))
+            } else {
+                Ok(evt)
+            }
+        };
+        let binarizer = Binarizer {
+            evt_to_bytes: convert::prost::to_bytes,
+            evt_from_bytes: corrupt_2,
+            state_to_bytes: convert::prost::to_bytes,
+            state_from_bytes: convert::prost::from_bytes,
+        };
+        let config = SpawnConfig::new(NonZeroUsize::MIN)
+            .with_replay_error_policy(ReplayErrorPolicy::SkipAndLog);
+        let (entity, report) = Lenient
+            .spawn_with_report(id, config, evt_log, NoopSnapshotStore, binarizer)
+            .await?;
+        assert_eq!(report.replayed_evts, 2);
+        assert_eq!(report.skipped_evts, 1);
+        assert_eq!(report.last_seq_no, Some(3.try_into()?));
+        entity.handle_cmd_if(Some(3.try_into()?), 4).await??;
 
-        async fn last_seq_no(&self, _entity_id: Uuid) -> Result<Option<SeqNo>, Self::Error> {
-            Ok(Some(SeqNo(42.try_into().unwrap())))
-        }
+        Ok(())
+    }
 
-        async fn evts_by_id<E, FromBytes, FromBytesError>(
-            &self,
-            _id: Uuid,
-            from_seq_no: SeqNo,
-            evt_from_bytes: FromBytes,
-        ) -> Result<impl Stream<Item = Result<(SeqNo, E), Self::Error>> + Send, Self::Error>
-        where
-            E: Send,
-            FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send,
-            FromBytesError: StdError + Send + Sync + 'static,
-        {
-            let evts = stream! {
-                for n in 0..666 {
-                    for evt in 1..=3 {
-                        let seq_no = (n * 3 + evt).try_into().unwrap();
-                        if from_seq_no <= seq_no  {
-                            let mut bytes = BytesMut::new();
-                            evt.encode(&mut bytes).map_err(|error| TestEvtLogError(error.into()))?;
-                            let evt = evt_from_bytes(bytes.into()).map_err(|error| TestEvtLogError(error.into()))?;
-                            yield Ok((seq_no, evt));
-                        }
-                    }
+    #[tokio::test]
+    async fn test_replay_state() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
 
-                }
-            };
-            Ok(evts)
-        }
+        let (seq_no, simple) = replay_state(
+            Simple(0),
+            id,
+            evt_log.clone(),
+            snapshot_store.clone(),
+            convert::prost::binarizer(),
+            ReplayErrorPolicy::Fail,
+            Level::INFO,
+        )
+        .await?;
+        assert_eq!(seq_no, None);
+        assert_eq!(simple.0, 0);
 
-        async fn evts_by_tag<E, FromBytes, FromBytesError>(
-            &self,
-            _tag: String,
-            from_seq_no: SeqNo,
-            evt_from_bytes: FromBytes,
-        ) -> Result<impl Stream<Item = Result<(SeqNo, E), Self::Error>> + Send, Self::Error>
-        where
-            E: Send,
-            FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
-            FromBytesError: StdError + Send + Sync + 'static,
-        {
-            let evts = stream! {
-                for n in 0..666 {
-                    for evt in 1..=3 {
-                        let seq_no = (n * 3 + evt).try_into().unwrap();
-                        if from_seq_no <= seq_no  {
-                            let mut bytes = BytesMut::new();
-                            evt.encode(&mut bytes).map_err(|error| TestEvtLogError(error.into()))?;
-                            let evt = evt_from_bytes(bytes.into()).map_err(|error| TestEvtLogError(error.into()))?;
-                            yield Ok((seq_no, evt));
-                        }
-                    }
+        let entity = spawn_with_id(id, Simple(0), evt_log.clone(), snapshot_store.clone()).await?;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
+        entity.handle_cmd(()).await??;
+        snapshot_store
+            .save(id, 2.try_into()?, 1, 2, &convert::prost::to_bytes)
+            .await?;
 
-                }
-            };
-            Ok(evts)
-        }
+        let (seq_no, simple) = task::spawn(replay_state(
+            Simple(0),
+            id,
+            evt_log,
+            snapshot_store,
+            convert::prost::binarizer(),
+            ReplayErrorPolicy::Fail,
+            Level::INFO,
+        ))
+        .await??;
+        assert_eq!(seq_no, Some(3.try_into()?));
+        assert_eq!(simple.0, 3);
+
+        Ok(())
     }
 
-    #[derive(Debug, Error)]
-    #[error("TestEvtLogError")]
-    struct TestEvtLogError(#[source] Box<dyn StdError + Send + Sync>);
+    #[tokio::test]
+    async fn test_spawn_local() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
 
-    #[derive(Debug, Clone)]
-    struct TestSnapshotStore;
+        let binarizer = Binarizer {
+            evt_to_bytes: |evt: &Rc<u64>| convert::prost::to_bytes(&**evt),
+            evt_from_bytes: |bytes| convert::prost::from_bytes(bytes).map(Rc::new),
+            state_to_bytes: |state: &Rc<u64>| convert::prost::to_bytes(&**state),
+            state_from_bytes: |bytes| convert::prost::from_bytes(bytes).map(Rc::new),
+        };
 
-    impl SnapshotStore for TestSnapshotStore {
-        type Error = TestSnapshotStoreError;
+        LocalSet::new()
+            .run_until(async move {
+                let count = Rc::new(Cell::new(0));
+                let entity = Local(count.clone())
+                    .spawn_local(
+                        id,
+                        unsafe { NonZeroUsize::new_unchecked(1) },
+                        evt_log,
+                        snapshot_store.clone(),
+                        binarizer,
+                    )
+                    .await?;
+                entity.handle_cmd(Rc::new(1)).await??;
+                entity.handle_cmd(Rc::new(2)).await??;
+                assert_eq!(count.get(), 3);
 
-        async fn save<S, ToBytes, ToBytesError>(
-            &mut self,
-            _id: Uuid,
-            _seq_no: SeqNo,
-            _state: S,
-            _state_to_bytes: &ToBytes,
-        ) -> Result<(), Self::Error>
-        where
-            S: Send,
-            ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
-            ToBytesError: StdError,
-        {
-            Ok(())
-        }
+                let snapshot = snapshot_store
+                    .load::<u64, _, _>(id, 1, convert::prost::from_bytes)
+                    .await?;
+                assert_eq!(snapshot.map(|snapshot| snapshot.state), Some(3));
 
-        async fn load<S, FromBytes, FromBytesError>(
-            &self,
-            _id: Uuid,
-            state_from_bytes: FromBytes,
-        ) -> Result<Option<Snapshot<S>>, Self::Error>
-        where
-            FromBytes: Fn(Bytes) -> Result<S, FromBytesError>,
-            FromBytesError: StdError,
-        {
-            let mut bytes = BytesMut::new();
-            42.encode(&mut bytes).unwrap();
-            let state = state_from_bytes(bytes.into()).unwrap();
-            Ok(Some(Snapshot {
-                seq_no: 42.try_into().unwrap(),
-                state,
-            }))
-        }
+                Ok(())
+            })
+            .await
     }
 
-    #[derive(Debug, Error)]
-    #[error("TestSnapshotStoreError")]
-    struct TestSnapshotStoreError;
+    #[tokio::test]
+    async fn test_spawn_handle_cmd_with_ctx() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let id = Uuid::now_v7();
+
+        let entity = spawn_with_id(id, Enriched, evt_log.clone(), NoopSnapshotStore).await?;
+        entity.handle_cmd(()).await??;
+        entity
+            .handle_cmd_with_ctx((), CmdContext::default().with("bonus", 41))
+            .await??;
+
+        let evts = evt_log
+            .evts_by_id_from::<u64, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .map_ok(|evt| evt.evt)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(evts, vec![1, 42]);
+
+        Ok(())
+    }
 
     #[tokio::test]
-    async fn test_spawn_handle_cmd() -> Result<(), Box<dyn StdError>> {
-        let evt_log = TestEvtLog;
-        let snapshot_store = TestSnapshotStore;
+    async fn test_spawn_handle_cmd_with_meta() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+        let id = Uuid::now_v7();
 
-        let entity = spawn(evt_log, snapshot_store).await?;
+        let entity = spawn_with_id(id, Simple(0), evt_log.clone(), NoopSnapshotStore).await?;
+        let meta = EvtMeta::default()
+            .with_correlation_id("c1")
+            .with_causation_id("c0")
+            .with_value("user", "joe");
+        entity.handle_cmd_with_meta((), meta.clone()).await??;
         entity.handle_cmd(()).await??;
 
+        let metas = evt_log
+            .evts_by_id_from::<u64, _, _>(id, SeqNo::MIN, convert::prost::from_bytes)
+            .await?
+            .map_ok(|evt| evt.meta())
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(metas, vec![meta, EvtMeta::default()]);
+
+        let evts = evt_log
+            .evts_by_tag::<u64, _, _>(
+                EvtMeta::correlation_tag("c1"),
+                SeqNo::MIN,
+                convert::prost::from_bytes,
+            )
+            .await?;
+        let evt = evts.take(1).try_collect::<Vec<_>>().await?;
+        assert_eq!(evt[0].seq_no, SeqNo::MIN);
+        assert_eq!(evt[0].tags[0], "tag");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_handle_cmd_returning_global_seq_no() -> Result<(), Box<dyn StdError>> {
+        let evt_log = MemoryEvtLog::default();
+
+        let entity_1 = spawn_with_id(
+            Uuid::now_v7(),
+            Simple(0),
+            evt_log.clone(),
+            NoopSnapshotStore,
+        )
+        .await?;
+        let entity_2 = spawn_with_id(Uuid::now_v7(), Simple(0), evt_log, NoopSnapshotStore).await?;
+
+        let global_seq_no = entity_1.handle_cmd_returning_global_seq_no(()).await??;
+        assert_eq!(global_seq_no, Some(GlobalSeqNo::MIN));
+        let global_seq_no = entity_2.handle_cmd_returning_global_seq_no(()).await??;
+        assert_eq!(global_seq_no, Some(GlobalSeqNo::MIN.succ()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_read_only() -> Result<(), Box<dyn StdError>> {
+        let snapshot_store = MemorySnapshotStore::default();
+        let id = Uuid::now_v7();
+
+        let entity = task::spawn({
+            let snapshot_store = snapshot_store.clone();
+            async move {
+                Simple(0)
+                    .spawn_read_only(id, snapshot_store, convert::prost::from_bytes)
+                    .await
+            }
+        })
+        .await??;
+        assert_eq!(entity.id(), id);
+        assert_eq!(entity.seq_no(), None);
+        assert_eq!(entity.state().0, 0);
+
+        snapshot_store
+            .save(id, 42.try_into()?, 1, 666, &convert::prost::to_bytes)
+            .await?;
+        let entity = Simple(0)
+            .spawn_read_only(id, snapshot_store, convert::prost::from_bytes)
+            .await?;
+        assert_eq!(entity.seq_no(), Some(42.try_into()?));
+        assert_eq!(entity.into_inner().0, 666);
+
         Ok(())
     }
 
     // We go through these hoops to ensure oddities in "async fn in trait" and other unstable
     // features are handle appropriately, e.g. by asserting futures are send.
-    async fn spawn<E, S>(
-        evt_log: E,
+    async fn spawn<E, L, S>(
+        event_sourced: E,
+        evt_log: L,
+        snapshot_store: S,
+    ) -> Result<EntityRef<E>, Box<dyn StdError>>
+    where
+        E: EventSourced<Evt = u64, State = u64> + Send,
+        E::Cmd: Send + Sync,
+        E::Effect: Send,
+        L: EvtLog,
+        S: SnapshotStore,
+    {
+        spawn_with_id(Uuid::now_v7(), event_sourced, evt_log, snapshot_store).await
+    }
+
+    async fn spawn_with_id<E, L, S>(
+        id: Uuid,
+        event_sourced: E,
+        evt_log: L,
         snapshot_store: S,
-    ) -> Result<EntityRef<Simple>, Box<dyn StdError>>
+    ) -> Result<EntityRef<E>, Box<dyn StdError>>
     where
-        E: EvtLog,
+        E: EventSourced<Evt = u64, State = u64> + Send,
+        E::Cmd: Send + Sync,
+        E::Effect: Send,
+        L: EvtLog,
         S: SnapshotStore,
     {
         let entity = task::spawn(async move {
-            Simple(0)
+            event_sourced
                 .spawn(
-                    Uuid::now_v7(),
+                    id,
                     unsafe { NonZeroUsize::new_unchecked(1) },
                     evt_log,
                     snapshot_store,
@@ -0,0 +1,28 @@
+//! Names of the metrics recorded via the [metrics](https://docs.rs/metrics) crate, e.g. to be
+//! exported to Prometheus by installing a respective recorder like `metrics-exporter-prometheus`.
+//! All metrics are labeled with `entity_type`, i.e. the
+//! [ENTITY_TYPE](crate::EventSourced::ENTITY_TYPE) of the entity or empty if it has none.
+
+/// Counter for handled commands, i.e. valid ones as well as rejected ones.
+pub const CMDS_HANDLED: &str = "eventsourced_cmds_handled_total";
+
+/// Counter for commands rejected by the command handler.
+pub const CMDS_REJECTED: &str = "eventsourced_cmds_rejected_total";
+
+/// Counter for persisted events.
+pub const EVTS_PERSISTED: &str = "eventsourced_evts_persisted_total";
+
+/// Counter for saved snapshots.
+pub const SNAPSHOTS_SAVED: &str = "eventsourced_snapshots_saved_total";
+
+/// Histogram for the duration of command handling in seconds, including persisting the event,
+/// applying it and possibly saving a snapshot.
+pub const CMD_DURATION: &str = "eventsourced_cmd_duration_seconds";
+
+/// Histogram for the usage of the command buffer of an entity when receiving a command, i.e. the
+/// ratio of buffered commands, including the received one, to the size of the buffer.
+pub const CMD_BUFFER_USAGE: &str = "eventsourced_cmd_buffer_usage_ratio";
+
+/// Histogram for the duration of the recovery when spawning an entity in seconds, i.e. restoring
+/// the snapshot and replaying the events.
+pub const REPLAY_DURATION: &str = "eventsourced_replay_duration_seconds";
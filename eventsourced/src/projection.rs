@@ -0,0 +1,222 @@
+use crate::{EntityId, EvtEnvelope, EvtLog, GlobalSeqNo, ProjectionOffsetStore};
+use bytes::Bytes;
+use futures::StreamExt;
+use std::{error::Error as StdError, future::Future, num::NonZeroUsize, pin::pin};
+use thiserror::Error;
+use tracing::debug;
+use uuid::Uuid;
+
+/// A read side projection, e.g. building a read model, driven by [run_projection] which feeds it
+/// all events in the order they were persisted.
+pub trait Projection<Id = Uuid>: Send + 'static
+where
+    Id: EntityId,
+{
+    /// Event type.
+    type Evt: Send;
+
+    /// Error type for handling events.
+    type Error: StdError + Send + Sync + 'static;
+
+    /// The name under which the offset of this projection is saved in the
+    /// [ProjectionOffsetStore].
+    fn name(&self) -> &str;
+
+    /// Handle the given event. Events are delivered at least once: those handled after the last
+    /// saved offset are handled again after a restart, hence handling should be idempotent, e.g. by
+    /// storing the global sequence number of the last handled event together with the read model.
+    fn handle(
+        &mut self,
+        envelope: EvtEnvelope<Self::Evt, Id>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// The maximum number of events handled before saving the offset; fewer are handled if no
+    /// more events are immediately available. By default 100.
+    fn batch_size(&self) -> NonZeroUsize {
+        unsafe { NonZeroUsize::new_unchecked(100) }
+    }
+}
+
+/// Run the given [Projection]: resume from the successor of its offset loaded from the given
+/// [ProjectionOffsetStore] or from the start, tail all events from the given [EvtLog] calling
+/// [handle](Projection::handle) for each and save the offset after each batch, see
+/// [batch_size](Projection::batch_size).
+///
+/// As the event stream is live, this only returns on error, e.g. when an event cannot be handled.
+/// Events handled after the last saved offset are delivered again when running the projection
+/// anew, i.e. delivery is at-least-once.
+pub async fn run_projection<P, L, O, Id, FromBytes, FromBytesError>(
+    evt_log: &L,
+    projection: &mut P,
+    offset_store: &mut O,
+    evt_from_bytes: FromBytes,
+) -> Result<(), ProjectionError>
+where
+    P: Projection<Id>,
+    L: EvtLog<Id>,
+    O: ProjectionOffsetStore,
+    Id: EntityId,
+    FromBytes: Fn(Bytes) -> Result<P::Evt, FromBytesError> + Copy + Send + Sync + 'static,
+    FromBytesError: StdError + Send + Sync + 'static,
+{
+    let name = projection.name().to_string();
+    let offset = offset_store
+        .load_offset(&name)
+        .await
+        .map_err(|error| ProjectionError::LoadOffset(error.into()))?;
+    debug!(name, ?offset, "running projection");
+
+    let from_global_seq_no = offset
+        .map(|offset| offset.succ())
+        .unwrap_or(GlobalSeqNo::MIN);
+    let evts = evt_log
+        .evts(from_global_seq_no, evt_from_bytes)
+        .await
+        .map_err(|error| ProjectionError::Evts(error.into()))?;
+    let mut batches = pin!(evts.ready_chunks(projection.batch_size().get()));
+
+    while let Some(batch) = batches.next().await {
+        let mut offset = None;
+
+        for envelope in batch {
+            let envelope = envelope.map_err(|error| ProjectionError::NextEvt(error.into()))?;
+            let global_seq_no = envelope.global_seq_no;
+            projection
+                .handle(envelope)
+                .await
+                .map_err(|error| ProjectionError::Handle {
+                    global_seq_no,
+                    source: error.into(),
+                })?;
+            offset = Some(global_seq_no);
+        }
+
+        if let Some(offset) = offset {
+            offset_store
+                .save_offset(&name, offset)
+                .await
+                .map_err(|error| ProjectionError::SaveOffset(error.into()))?;
+            debug!(name, %offset, "saved projection offset");
+        }
+    }
+
+    Ok(())
+}
+
+/// Error from running a [Projection] via [run_projection].
+#[derive(Debug, Error)]
+pub enum ProjectionError {
+    /// The offset cannot be loaded from the projection offset store.
+    #[error("cannot load offset from projection offset store")]
+    LoadOffset(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// Events cannot be obtained from the event log.
+    #[error("cannot get events from event log")]
+    Evts(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// The next event cannot be obtained from the event log.
+    #[error("cannot get next event from event log")]
+    NextEvt(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// The event with the given global sequence number cannot be handled.
+    #[error("cannot handle event with global sequence number {global_seq_no}")]
+    Handle {
+        global_seq_no: GlobalSeqNo,
+        #[source]
+        source: Box<dyn StdError + Send + Sync>,
+    },
+
+    /// The offset cannot be saved to the projection offset store.
+    #[error("cannot save offset to projection offset store")]
+    SaveOffset(#[source] Box<dyn StdError + Send + Sync>),
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use crate::{convert, MemoryEvtLog, MemoryProjectionOffsetStore};
+    use std::{convert::Infallible, time::Duration};
+    use tokio::{sync::mpsc, task, time::sleep};
+
+    struct Forward(mpsc::UnboundedSender<i32>);
+
+    impl Projection for Forward {
+        type Evt = i32;
+        type Error = Infallible;
+
+        fn name(&self) -> &str {
+            "forward"
+        }
+
+        async fn handle(&mut self, envelope: EvtEnvelope<i32>) -> Result<(), Self::Error> {
+            let _ = self.0.send(envelope.evt);
+            Ok(())
+        }
+    }
+
+    async fn run(
+        evt_log: MemoryEvtLog,
+        offset_store: MemoryProjectionOffsetStore,
+    ) -> (task::JoinHandle<()>, mpsc::UnboundedReceiver<i32>) {
+        let (evt_sender, evt_receiver) = mpsc::unbounded_channel();
+        let task = task::spawn(async move {
+            let mut offset_store = offset_store;
+            let _ = run_projection(
+                &evt_log,
+                &mut Forward(evt_sender),
+                &mut offset_store,
+                convert::prost::from_bytes,
+            )
+            .await;
+        });
+        (task, evt_receiver)
+    }
+
+    async fn await_offset(offset_store: &MemoryProjectionOffsetStore, offset: u64) {
+        while offset_store.load_offset("forward").await.unwrap() != Some(offset.try_into().unwrap())
+        {
+            sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_projection() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let evt_log = MemoryEvtLog::default();
+        let offset_store = MemoryProjectionOffsetStore::default();
+
+        let id = Uuid::now_v7();
+        let mut last_seq_no = None;
+        for evt in 1..=3 {
+            let seq_no = evt_log
+                .persist(
+                    &evt,
+                    1,
+                    &[],
+                    id,
+                    None,
+                    last_seq_no,
+                    &convert::prost::to_bytes,
+                )
+                .await?;
+            last_seq_no = Some(seq_no);
+        }
+
+        let (task, mut evts) = run(evt_log.clone(), offset_store.clone()).await;
+        for evt in 1..=3 {
+            assert_eq!(evts.recv().await, Some(evt));
+        }
+        await_offset(&offset_store, 3).await;
+        task.abort();
+
+        // Restart from the saved offset.
+        evt_log
+            .persist(&4, 1, &[], id, None, last_seq_no, &convert::prost::to_bytes)
+            .await?;
+        let (task, mut evts) = run(evt_log, offset_store.clone()).await;
+        assert_eq!(evts.recv().await, Some(4));
+        await_offset(&offset_store, 4).await;
+        task.abort();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+//! An in-memory [ProjectionOffsetStore] implementation, e.g. for testing.
+
+use crate::{GlobalSeqNo, ProjectionOffsetStore};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+/// An in-memory [ProjectionOffsetStore] implementation, e.g. for testing. Clones share the same
+/// offsets.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryProjectionOffsetStore {
+    offsets: Arc<Mutex<HashMap<String, GlobalSeqNo>>>,
+}
+
+impl ProjectionOffsetStore for MemoryProjectionOffsetStore {
+    type Error = Infallible;
+
+    async fn save_offset(&mut self, name: &str, offset: GlobalSeqNo) -> Result<(), Self::Error> {
+        self.offsets
+            .lock()
+            .expect("lock not poisoned")
+            .insert(name.to_string(), offset);
+        Ok(())
+    }
+
+    async fn load_offset(&self, name: &str) -> Result<Option<GlobalSeqNo>, Self::Error> {
+        let offset = self
+            .offsets
+            .lock()
+            .expect("lock not poisoned")
+            .get(name)
+            .copied();
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as StdError;
+
+    #[tokio::test]
+    async fn test_projection_offset_store() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let mut offset_store = MemoryProjectionOffsetStore::default();
+
+        let offset = offset_store.load_offset("foo").await?;
+        assert_eq!(offset, None);
+
+        offset_store.save_offset("foo", 42.try_into()?).await?;
+        offset_store
+            .clone()
+            .save_offset("foo", 43.try_into()?)
+            .await?;
+        offset_store.save_offset("bar", 1.try_into()?).await?;
+
+        let offset = offset_store.load_offset("foo").await?;
+        assert_eq!(offset, Some(43.try_into()?));
+        let offset = offset_store.load_offset("bar").await?;
+        assert_eq!(offset, Some(1.try_into()?));
+
+        Ok(())
+    }
+}
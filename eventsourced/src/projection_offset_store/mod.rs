@@ -0,0 +1,31 @@
+//! Persistence for the offsets of projections.
+
+mod memory;
+
+pub use memory::*;
+
+use crate::GlobalSeqNo;
+use std::{error::Error as StdError, future::Future};
+
+/// Persistence for the offsets of read side projections, i.e. the global sequence number of the
+/// last event processed by a projection with a given name, such that the projection can resume
+/// from the successor of its offset after a restart or crash, e.g. by passing it to
+/// [EvtLog::evts](crate::EvtLog::evts).
+pub trait ProjectionOffsetStore: Clone + Send + 'static {
+    type Error: StdError + Send + Sync + 'static;
+
+    /// Save the given offset for the projection with the given name, replacing a previously saved
+    /// one.
+    fn save_offset(
+        &mut self,
+        name: &str,
+        offset: GlobalSeqNo,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Load the offset for the projection with the given name or `None` if no offset has been
+    /// saved for it yet.
+    fn load_offset(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = Result<Option<GlobalSeqNo>, Self::Error>> + Send;
+}
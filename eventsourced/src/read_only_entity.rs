@@ -0,0 +1,45 @@
+use crate::{EntityId, EventSourced, SeqNo};
+use uuid::Uuid;
+
+/// A read-only [EventSourced] entity created via
+/// [spawn_read_only](crate::EventSourcedExt::spawn_read_only) from its latest snapshot only.
+///
+/// It does not accept commands at all and it is not updated; as the events persisted after the
+/// snapshot are not replayed, it is stale by up to the interval in which the entity saves
+/// snapshots.
+#[derive(Debug, Clone)]
+pub struct ReadOnlyEntity<E, Id = Uuid>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    pub(crate) id: Id,
+    pub(crate) event_sourced: E,
+    pub(crate) seq_no: Option<SeqNo>,
+}
+
+impl<E, Id> ReadOnlyEntity<E, Id>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    /// Get the ID of the entity.
+    pub fn id(&self) -> Id {
+        self.id.clone()
+    }
+
+    /// Get the sequence number of the loaded snapshot or `None` if there has been no snapshot.
+    pub fn seq_no(&self) -> Option<SeqNo> {
+        self.seq_no
+    }
+
+    /// Get the [EventSourced] value with the state of the loaded snapshot, if any, restored.
+    pub fn state(&self) -> &E {
+        &self.event_sourced
+    }
+
+    /// Consume this [ReadOnlyEntity], returning the [EventSourced] value.
+    pub fn into_inner(self) -> E {
+        self.event_sourced
+    }
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Policy for retrying to persist events after transient errors of the [EvtLog](crate::EvtLog),
+/// e.g. during an outage of the database, see [EvtLog::is_retryable](crate::EvtLog::is_retryable),
+/// before terminating the entity, see
+/// [SpawnConfig::persist_retry_policy](crate::SpawnConfig::persist_retry_policy). Rejected
+/// commands are never retried. By default there are no retries.
+///
+/// Backends can also use it for retrying transient errors, e.g. when connecting; hence it can be
+/// part of their configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub backoff: ExponentialBackoff,
+}
+
+impl RetryPolicy {
+    #[allow(missing_docs)]
+    pub fn new(max_retries: u32, backoff: ExponentialBackoff) -> Self {
+        Self {
+            max_retries,
+            backoff,
+        }
+    }
+
+    /// The delay before the retry after the given failed attempt, starting at zero, or `None` if
+    /// the retries are exhausted.
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        (attempt < self.max_retries).then(|| self.backoff.delay(attempt))
+    }
+}
+
+/// Delays growing by the given factor from the given initial one up to the given maximum one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ExponentialBackoff {
+    #[serde(with = "humantime_serde")]
+    pub initial: Duration,
+
+    #[serde(with = "humantime_serde")]
+    pub max: Duration,
+
+    pub factor: u32,
+}
+
+impl ExponentialBackoff {
+    #[allow(missing_docs)]
+    pub fn new(initial: Duration, max: Duration, factor: u32) -> Self {
+        Self {
+            initial,
+            max,
+            factor,
+        }
+    }
+
+    /// The delay for the given attempt, starting at zero.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial
+            .saturating_mul(self.factor.saturating_pow(attempt))
+            .min(self.max)
+    }
+}
+
+impl Default for ExponentialBackoff {
+    /// Starting at 100ms, doubling up to 10s.
+    fn default() -> Self {
+        Self::new(Duration::from_millis(100), Duration::from_secs(10), 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(0), None);
+
+        let backoff = ExponentialBackoff::new(Duration::from_secs(1), Duration::from_secs(5), 2);
+        let policy = RetryPolicy::new(4, backoff);
+        let delays = (0..5)
+            .map(|attempt| policy.delay(attempt))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            vec![
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(2)),
+                Some(Duration::from_secs(4)),
+                Some(Duration::from_secs(5)),
+                None
+            ]
+        );
+
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(5));
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_deserialize() {
+        let policy =
+            serde_json::from_str::<RetryPolicy>(r#"{"max-retries":3,"backoff":{"max":"1s"}}"#)
+                .unwrap();
+        assert_eq!(
+            policy,
+            RetryPolicy::new(
+                3,
+                ExponentialBackoff::new(Duration::from_millis(100), Duration::from_secs(1), 2)
+            )
+        );
+    }
+}
@@ -50,3 +50,46 @@ impl Display for SeqNo {
         Display::fmt(&self.0, f)
     }
 }
+
+/// Non-zero 64-bit global sequence number, i.e. the position of an event in the whole event log
+/// across all entities. Used for queries spanning multiple entities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GlobalSeqNo(NonZeroU64);
+
+impl GlobalSeqNo {
+    /// The number one (`1`).
+    pub const MIN: GlobalSeqNo = Self(unsafe { NonZeroU64::new_unchecked(1) });
+
+    #[allow(missing_docs)]
+    pub const fn new(value: NonZeroU64) -> Self {
+        Self(value)
+    }
+
+    #[allow(missing_docs)]
+    pub const fn as_u64(&self) -> u64 {
+        self.0.get()
+    }
+
+    /// Get the successor of this global sequence number.
+    ///
+    /// # Panics
+    /// Panics on overflow, which is highly unlikely in practice (64-bit).
+    pub fn succ(&self) -> Self {
+        let seq_no = self.0.checked_add(1).expect("overflow");
+        Self(seq_no)
+    }
+}
+
+impl TryFrom<u64> for GlobalSeqNo {
+    type Error = ZeroSeqNoError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        NonZeroU64::new(value).ok_or(ZeroSeqNoError).map(Self::new)
+    }
+}
+
+impl Display for GlobalSeqNo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
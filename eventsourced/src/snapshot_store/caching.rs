@@ -0,0 +1,466 @@
+//! A [SnapshotStore] decorator caching recently loaded snapshots.
+
+use crate::{EntityId, SeqNo, Snapshot, SnapshotStore};
+use bytes::Bytes;
+use futures::{stream, Stream, TryStreamExt};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    error::Error as StdError,
+    fmt::Debug,
+    future::Future,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// A [SnapshotStore] decorator caching the bytes of recently loaded snapshots by entity ID in a
+/// least recently used (LRU) cache with the given capacity, e.g. to speed up frequently re-spawning
+/// passivated entities. On a miss, the snapshot is loaded from the wrapped [SnapshotStore]. Saving
+/// a snapshot or deleting snapshots for an entity ID invalidates its cached one. Clones share the
+/// same cache.
+///
+/// Snapshots saved to the wrapped [SnapshotStore] other than via this one, e.g. by another
+/// instance of the application, are not noticed; hence each entity should only be spawned by a
+/// single instance. [load_at](SnapshotStore::load_at) is not cached.
+#[derive(Debug, Clone)]
+pub struct CachingSnapshotStore<S, Id = Uuid> {
+    snapshot_store: S,
+    cache: Arc<Mutex<Lru<Id>>>,
+}
+
+impl<S, Id> CachingSnapshotStore<S, Id>
+where
+    Id: EntityId,
+{
+    /// Wrap the given [SnapshotStore], caching at most the given number of snapshots.
+    pub fn new(snapshot_store: S, capacity: NonZeroUsize) -> Self {
+        Self {
+            snapshot_store,
+            cache: Arc::new(Mutex::new(Lru::new(capacity))),
+        }
+    }
+
+    fn get(&self, id: &Id, state_version: u32) -> Option<Cached> {
+        self.cache
+            .lock()
+            .expect("lock not poisoned")
+            .get(id)
+            .filter(|cached| cached.state_version == state_version)
+    }
+
+    fn invalidate(&self, id: &Id) {
+        self.cache.lock().expect("lock not poisoned").remove(id);
+    }
+
+    fn generation(&self) -> u64 {
+        self.cache.lock().expect("lock not poisoned").generation
+    }
+
+    /// Insert the given snapshot unless any snapshot has been invalidated since the given
+    /// generation, as it might be stale.
+    fn insert(&self, id: Id, generation: u64, cached: Cached) {
+        let mut cache = self.cache.lock().expect("lock not poisoned");
+        if cache.generation == generation {
+            cache.insert(id, cached);
+        }
+    }
+}
+
+impl<T, Id> SnapshotStore<Id> for CachingSnapshotStore<T, Id>
+where
+    T: SnapshotStore<Id>,
+    Id: EntityId,
+{
+    type Error = CachingSnapshotStoreError<T::Error>;
+
+    fn save<S, ToBytes, ToBytesError>(
+        &self,
+        id: Id,
+        seq_no: SeqNo,
+        state_version: u32,
+        state: S,
+        to_bytes: &ToBytes,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send
+    where
+        S: Send,
+        ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        // Invalidate before and after saving, as a concurrent load might otherwise cache the former
+        // snapshot loaded while saving.
+        self.invalidate(&id);
+        let saved = self
+            .snapshot_store
+            .save(id.clone(), seq_no, state_version, state, to_bytes);
+        let cache = self.cache.clone();
+
+        async move {
+            saved.await.map_err(CachingSnapshotStoreError::Inner)?;
+            cache.lock().expect("lock not poisoned").remove(&id);
+            Ok(())
+        }
+    }
+
+    fn load<S, FromBytes, FromBytesError>(
+        &self,
+        id: Id,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> impl Future<Output = Result<Option<Snapshot<S>>, Self::Error>> + Send
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        // Clone to not require `Self: Sync`.
+        let snapshot_store = self.clone();
+
+        async move {
+            let cached = match snapshot_store.get(&id, state_version) {
+                Some(cached) => Some(cached),
+
+                None => {
+                    let generation = snapshot_store.generation();
+                    let snapshot = snapshot_store
+                        .snapshot_store
+                        .load(id.clone(), state_version, Ok::<_, Infallible>)
+                        .await
+                        .map_err(CachingSnapshotStoreError::Inner)?;
+                    snapshot.map(|Snapshot { seq_no, state }| {
+                        let cached = Cached {
+                            seq_no,
+                            state_version,
+                            bytes: state,
+                        };
+                        snapshot_store.insert(id, generation, cached.clone());
+                        cached
+                    })
+                }
+            };
+
+            cached
+                .map(|cached| cached.into_snapshot(&from_bytes))
+                .transpose()
+        }
+    }
+
+    fn load_many<S, FromBytes, FromBytesError>(
+        &self,
+        ids: &[Id],
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<(Id, Snapshot<S>), Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        S: Send,
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send + Sync,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        // Clone to not require `Self: Sync`.
+        let snapshot_store = self.clone();
+
+        async move {
+            let mut snapshots = Vec::with_capacity(ids.len());
+            let mut missed = vec![];
+            for id in ids {
+                match snapshot_store.get(id, state_version) {
+                    Some(cached) => snapshots.push((id.clone(), cached)),
+                    None => missed.push(id.clone()),
+                }
+            }
+
+            if !missed.is_empty() {
+                let generation = snapshot_store.generation();
+                let loaded = snapshot_store
+                    .snapshot_store
+                    .load_many(&missed, state_version, Ok::<_, Infallible>)
+                    .await
+                    .map_err(CachingSnapshotStoreError::Inner)?
+                    .map_err(CachingSnapshotStoreError::Inner)
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                for (id, Snapshot { seq_no, state }) in loaded {
+                    let cached = Cached {
+                        seq_no,
+                        state_version,
+                        bytes: state,
+                    };
+                    snapshot_store.insert(id.clone(), generation, cached.clone());
+                    snapshots.push((id, cached));
+                }
+            }
+
+            let snapshots = snapshots
+                .into_iter()
+                .map(|(id, cached)| cached.into_snapshot(&from_bytes).map(|s| (id, s)))
+                .collect::<Vec<_>>();
+            Ok(stream::iter(snapshots))
+        }
+    }
+
+    fn load_at<S, FromBytes, FromBytesError>(
+        &self,
+        id: Id,
+        max_seq_no: SeqNo,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> impl Future<Output = Result<Option<Snapshot<S>>, Self::Error>> + Send
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let snapshot = self
+            .snapshot_store
+            .load_at(id, max_seq_no, state_version, from_bytes);
+        async move { snapshot.await.map_err(CachingSnapshotStoreError::Inner) }
+    }
+
+    fn delete_before(
+        &self,
+        id: Id,
+        seq_no: SeqNo,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        // Invalidate before and after deleting, see save.
+        self.invalidate(&id);
+        let deleted = self.snapshot_store.delete_before(id.clone(), seq_no);
+        let cache = self.cache.clone();
+
+        async move {
+            deleted.await.map_err(CachingSnapshotStoreError::Inner)?;
+            cache.lock().expect("lock not poisoned").remove(&id);
+            Ok(())
+        }
+    }
+
+    fn ping(&self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let ping = self.snapshot_store.ping();
+        async move { ping.await.map_err(CachingSnapshotStoreError::Inner) }
+    }
+}
+
+/// Error from the [CachingSnapshotStore].
+#[derive(Debug, Error)]
+pub enum CachingSnapshotStoreError<E> {
+    /// Error from the wrapped [SnapshotStore].
+    #[error(transparent)]
+    Inner(E),
+
+    /// A snapshot state cannot be converted from bytes.
+    #[error("cannot convert snapshot state from bytes")]
+    FromBytes(#[source] Box<dyn StdError + Send + Sync>),
+}
+
+/// The bytes of a cached snapshot along with its sequence number and state version.
+#[derive(Debug, Clone)]
+struct Cached {
+    seq_no: SeqNo,
+    state_version: u32,
+    bytes: Bytes,
+}
+
+impl Cached {
+    fn into_snapshot<S, FromBytes, FromBytesError, E>(
+        self,
+        from_bytes: FromBytes,
+    ) -> Result<Snapshot<S>, CachingSnapshotStoreError<E>>
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError>,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        from_bytes(self.bytes)
+            .map_err(|error| CachingSnapshotStoreError::FromBytes(error.into()))
+            .map(|state| Snapshot::new(self.seq_no, state))
+    }
+}
+
+/// Least recently used cache: each access is stamped with an increasing tick, hence the entry
+/// with the smallest one is the least recently used.
+#[derive(Debug)]
+struct Lru<Id> {
+    capacity: NonZeroUsize,
+    tick: u64,
+    entries: HashMap<Id, (u64, Cached)>,
+    ticks: BTreeMap<u64, Id>,
+    /// Increased on each invalidation, such that loads running concurrently do not insert stale
+    /// snapshots.
+    generation: u64,
+}
+
+impl<Id> Lru<Id>
+where
+    Id: EntityId,
+{
+    fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            ticks: BTreeMap::new(),
+            generation: 0,
+        }
+    }
+
+    fn get(&mut self, id: &Id) -> Option<Cached> {
+        self.tick += 1;
+        let (tick, cached) = self.entries.get_mut(id)?;
+        self.ticks.remove(tick);
+        self.ticks.insert(self.tick, id.clone());
+        *tick = self.tick;
+        Some(cached.clone())
+    }
+
+    fn insert(&mut self, id: Id, cached: Cached) {
+        self.tick += 1;
+        if let Some((tick, _)) = self.entries.insert(id.clone(), (self.tick, cached)) {
+            self.ticks.remove(&tick);
+        }
+        self.ticks.insert(self.tick, id);
+
+        if self.entries.len() > self.capacity.get() {
+            if let Some((_, id)) = self.ticks.pop_first() {
+                self.entries.remove(&id);
+            }
+        }
+    }
+
+    fn remove(&mut self, id: &Id) {
+        self.generation += 1;
+        if let Some((tick, _)) = self.entries.remove(id) {
+            self.ticks.remove(&tick);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemorySnapshotStore;
+    use tokio::sync::Notify;
+
+    fn to_bytes(state: &u64) -> Result<Bytes, Infallible> {
+        Ok(Bytes::copy_from_slice(&state.to_be_bytes()))
+    }
+
+    fn from_bytes(bytes: Bytes) -> Result<u64, Infallible> {
+        Ok(u64::from_be_bytes(bytes.as_ref().try_into().unwrap()))
+    }
+
+    async fn state<T>(
+        snapshot_store: &CachingSnapshotStore<T, u64>,
+        id: u64,
+    ) -> Result<Option<u64>, Box<dyn StdError>>
+    where
+        T: SnapshotStore<u64>,
+    {
+        let snapshot = snapshot_store.load(id, 1, from_bytes).await?;
+        Ok(snapshot.map(|snapshot| snapshot.state))
+    }
+
+    #[tokio::test]
+    async fn test_caching_snapshot_store() -> Result<(), Box<dyn StdError>> {
+        let inner = MemorySnapshotStore::<u64>::default();
+        let snapshot_store = CachingSnapshotStore::new(inner.clone(), NonZeroUsize::MIN);
+
+        assert_eq!(state(&snapshot_store, 1).await?, None);
+        snapshot_store.save(1, SeqNo::MIN, 1, 1, &to_bytes).await?;
+        assert_eq!(state(&snapshot_store, 1).await?, Some(1));
+
+        // Snapshots saved to the wrapped store directly are not noticed while cached.
+        inner.save(1, SeqNo::MIN, 1, 2, &to_bytes).await?;
+        assert_eq!(state(&snapshot_store, 1).await?, Some(1));
+
+        // Saving invalidates the cached snapshot.
+        snapshot_store.save(1, SeqNo::MIN, 1, 3, &to_bytes).await?;
+        assert_eq!(state(&snapshot_store, 1).await?, Some(3));
+
+        // Cached snapshots with another state version are not considered.
+        let snapshot = snapshot_store.load(1, 2, from_bytes).await?;
+        assert!(snapshot.is_none());
+
+        // Loading another snapshot evicts the least recently used one.
+        inner.save(2, SeqNo::MIN, 1, 4, &to_bytes).await?;
+        let snapshots = snapshot_store
+            .load_many(&[1, 2], 1, from_bytes)
+            .await?
+            .map_ok(|(id, snapshot)| (id, snapshot.state))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(snapshots.len(), 2);
+        inner.save(1, SeqNo::MIN, 1, 5, &to_bytes).await?;
+        assert_eq!(state(&snapshot_store, 1).await?, Some(5));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_caching_snapshot_store_load_while_saving() -> Result<(), Box<dyn StdError>> {
+        let inner = GatedSnapshotStore::default();
+        let snapshot_store = CachingSnapshotStore::new(inner.clone(), NonZeroUsize::MIN);
+        inner.inner.save(1, SeqNo::MIN, 1, 1, &to_bytes).await?;
+
+        // A load while saving gets the former snapshot, which must not stay cached.
+        let saved = snapshot_store.save(1, SeqNo::MIN, 1, 2, &to_bytes);
+        let (saved, loaded) = tokio::join!(saved, async {
+            let snapshot = snapshot_store.load(1, 1, from_bytes).await;
+            inner.gate.notify_one();
+            snapshot
+        });
+        saved?;
+        assert_eq!(loaded?.map(|snapshot| snapshot.state), Some(1));
+        assert_eq!(state(&snapshot_store, 1).await?, Some(2));
+
+        Ok(())
+    }
+
+    /// Delegates to a [MemorySnapshotStore], but saving waits for the gate to be opened.
+    #[derive(Debug, Clone, Default)]
+    struct GatedSnapshotStore {
+        inner: MemorySnapshotStore<u64>,
+        gate: Arc<Notify>,
+    }
+
+    impl SnapshotStore<u64> for GatedSnapshotStore {
+        type Error = <MemorySnapshotStore<u64> as SnapshotStore<u64>>::Error;
+
+        async fn save<S, ToBytes, ToBytesError>(
+            &self,
+            id: u64,
+            seq_no: SeqNo,
+            state_version: u32,
+            state: S,
+            to_bytes: &ToBytes,
+        ) -> Result<(), Self::Error>
+        where
+            S: Send,
+            ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
+            ToBytesError: StdError + Send + Sync + 'static,
+        {
+            self.gate.notified().await;
+            self.inner
+                .save(id, seq_no, state_version, state, to_bytes)
+                .await
+        }
+
+        async fn load<S, FromBytes, FromBytesError>(
+            &self,
+            id: u64,
+            state_version: u32,
+            from_bytes: FromBytes,
+        ) -> Result<Option<Snapshot<S>>, Self::Error>
+        where
+            FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+            FromBytesError: StdError + Send + Sync + 'static,
+        {
+            self.inner.load(id, state_version, from_bytes).await
+        }
+
+        async fn delete_before(&self, id: u64, seq_no: SeqNo) -> Result<(), Self::Error> {
+            self.inner.delete_before(id, seq_no).await
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! An in-memory [SnapshotStore] implementation, e.g. for testing.
+
+use crate::{EntityId, SeqNo, Snapshot, SnapshotStore};
+use bytes::Bytes;
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    sync::{Arc, Mutex},
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// An in-memory [SnapshotStore] implementation, e.g. for testing, for any [EntityId] type. Only the
+/// last saved snapshot per entity ID is kept. Clones share the same snapshots.
+#[derive(Debug, Clone)]
+pub struct MemorySnapshotStore<Id = Uuid> {
+    #[allow(clippy::type_complexity)]
+    snapshots: Arc<Mutex<HashMap<Id, (SeqNo, u32, Bytes)>>>,
+}
+
+impl<Id> Default for MemorySnapshotStore<Id> {
+    fn default() -> Self {
+        Self {
+            snapshots: Default::default(),
+        }
+    }
+}
+
+impl<Id> SnapshotStore<Id> for MemorySnapshotStore<Id>
+where
+    Id: EntityId,
+{
+    type Error = MemorySnapshotStoreError;
+
+    async fn save<S, ToBytes, ToBytesError>(
+        &self,
+        id: Id,
+        seq_no: SeqNo,
+        state_version: u32,
+        state: S,
+        to_bytes: &ToBytes,
+    ) -> Result<(), Self::Error>
+    where
+        S: Send,
+        ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        let bytes =
+            to_bytes(&state).map_err(|error| MemorySnapshotStoreError::ToBytes(error.into()))?;
+        self.snapshots
+            .lock()
+            .expect("lock not poisoned")
+            .insert(id, (seq_no, state_version, bytes));
+        Ok(())
+    }
+
+    async fn load<S, FromBytes, FromBytesError>(
+        &self,
+        id: Id,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> Result<Option<Snapshot<S>>, Self::Error>
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let snapshot = self
+            .snapshots
+            .lock()
+            .expect("lock not poisoned")
+            .get(&id)
+            .filter(|(_, version, _)| *version == state_version)
+            .cloned();
+
+        snapshot
+            .map(|(seq_no, _, bytes)| {
+                from_bytes(bytes)
+                    .map_err(|error| MemorySnapshotStoreError::FromBytes(error.into()))
+                    .map(|state| Snapshot::new(seq_no, state))
+            })
+            .transpose()
+    }
+
+    async fn delete_before(&self, id: Id, seq_no: SeqNo) -> Result<(), Self::Error> {
+        let mut snapshots = self.snapshots.lock().expect("lock not poisoned");
+        if snapshots
+            .get(&id)
+            .is_some_and(|(snapshot_seq_no, _, _)| *snapshot_seq_no < seq_no)
+        {
+            snapshots.remove(&id);
+        }
+        Ok(())
+    }
+}
+
+/// Errors from the [MemorySnapshotStore].
+#[derive(Debug, Error)]
+pub enum MemorySnapshotStoreError {
+    /// A snapshot state cannot be converted into bytes.
+    #[error("cannot convert a snapshot state to bytes")]
+    ToBytes(#[source] Box<dyn StdError + Send + Sync>),
+
+    /// Bytes cannot be converted to a snapshot state.
+    #[error("cannot convert bytes to a snapshot state")]
+    FromBytes(#[source] Box<dyn StdError + Send + Sync>),
+}
+
+#[cfg(all(test, feature = "prost"))]
+mod tests {
+    use super::*;
+    use crate::convert;
+    use futures::{future::try_join, TryStreamExt};
+
+    #[tokio::test]
+    async fn test_snapshot_store() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let snapshot_store = MemorySnapshotStore::default();
+
+        let id = Uuid::now_v7();
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        snapshot_store
+            .save(id, 42.try_into()?, 1, 666, &convert::prost::to_bytes)
+            .await?;
+        snapshot_store
+            .clone()
+            .save(id, 43.try_into()?, 1, 777, &convert::prost::to_bytes)
+            .await?;
+
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_some());
+        let snapshot = snapshot.unwrap();
+        assert_eq!(snapshot.seq_no.as_u64(), 43);
+        assert_eq!(snapshot.state, 777);
+
+        // Snapshots with another state version are incompatible.
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 2, convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        snapshot_store.delete_before(id, 43.try_into()?).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_some());
+
+        snapshot_store.delete_before(id, 44.try_into()?).await?;
+        let snapshot = snapshot_store
+            .load::<i32, _, _>(id, 1, convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_at() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let snapshot_store = MemorySnapshotStore::default();
+
+        let id = Uuid::now_v7();
+        snapshot_store
+            .save(id, 42.try_into()?, 1, 666, &convert::prost::to_bytes)
+            .await?;
+
+        let snapshot = snapshot_store
+            .load_at::<i32, _, _>(id, 43.try_into()?, 1, convert::prost::from_bytes)
+            .await?
+            .map(|snapshot| (snapshot.seq_no.as_u64(), snapshot.state));
+        assert_eq!(snapshot, Some((42, 666)));
+
+        let snapshot = snapshot_store
+            .load_at::<i32, _, _>(id, 41.try_into()?, 1, convert::prost::from_bytes)
+            .await?;
+        assert!(snapshot.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_many() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let snapshot_store = MemorySnapshotStore::default();
+
+        let id_1 = Uuid::now_v7();
+        let id_2 = Uuid::now_v7();
+        let id_3 = Uuid::now_v7();
+
+        snapshot_store
+            .save(id_1, 42.try_into()?, 1, 666, &convert::prost::to_bytes)
+            .await?;
+        snapshot_store
+            .save(id_3, 43.try_into()?, 1, 777, &convert::prost::to_bytes)
+            .await?;
+
+        let snapshots = snapshot_store
+            .load_many::<i32, _, _>(&[id_1, id_2, id_3], 1, convert::prost::from_bytes)
+            .await?
+            .map_ok(|(id, snapshot)| (id, snapshot.seq_no.as_u64(), snapshot.state))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(snapshots, vec![(id_1, 42, 666), (id_3, 43, 777)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_save_shared() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let snapshot_store = MemorySnapshotStore::default();
+        let shared = &snapshot_store;
+
+        // Saving does not require exclusive access, hence can happen concurrently via references.
+        let id_1 = Uuid::now_v7();
+        let id_2 = Uuid::now_v7();
+        try_join(
+            shared.save(id_1, 42.try_into()?, 1, 666, &convert::prost::to_bytes),
+            shared.save(id_2, 43.try_into()?, 1, 777, &convert::prost::to_bytes),
+        )
+        .await?;
+
+        let snapshots = snapshot_store
+            .load_many::<i32, _, _>(&[id_1, id_2], 1, convert::prost::from_bytes)
+            .await?
+            .map_ok(|(id, snapshot)| (id, snapshot.state))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(snapshots, vec![(id_1, 666), (id_2, 777)]);
+
+        Ok(())
+    }
+}
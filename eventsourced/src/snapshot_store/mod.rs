@@ -1,23 +1,38 @@
 //! Persistence for snapshots.
 
+mod caching;
+mod memory;
 mod noop;
 
+pub use caching::*;
+pub use memory::*;
 pub use noop::*;
 
-use crate::SeqNo;
+use crate::{EntityId, SeqNo};
 use bytes::Bytes;
+use futures::{stream, Stream};
 use std::{error::Error as StdError, future::Future};
 use uuid::Uuid;
 
-/// Persistence for snapshots.
-pub trait SnapshotStore: Clone + Send + 'static {
+/// Persistence for snapshots of entities with IDs of the given type, see [EntityId].
+///
+/// Implementations must be cheap to clone and clones must share the underlying backend, e.g. a
+/// connection pool, such that a clone can be kept for administrative operations like
+/// [delete_before](SnapshotStore::delete_before) after another one has been passed to
+/// [spawn](crate::EventSourcedExt::spawn). Hence all methods take `&self`.
+pub trait SnapshotStore<Id = Uuid>: Clone + Send + 'static
+where
+    Id: EntityId,
+{
     type Error: StdError + Send + Sync + 'static;
 
-    /// Save the given snapshot state for the given entity ID and sequence number.
+    /// Save the given snapshot state for the given entity ID and sequence number along with the
+    /// given state version, see [EventSourced::STATE_VERSION](crate::EventSourced::STATE_VERSION).
     fn save<S, ToBytes, ToBytesError>(
-        &mut self,
-        id: Uuid,
+        &self,
+        id: Id,
         seq_no: SeqNo,
+        state_version: u32,
         state: S,
         to_bytes: &ToBytes,
     ) -> impl Future<Output = Result<(), Self::Error>> + Send
@@ -26,15 +41,96 @@ pub trait SnapshotStore: Clone + Send + 'static {
         ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
         ToBytesError: StdError + Send + Sync + 'static;
 
-    /// Find and possibly load the [Snapshot] for the given entity ID.
+    /// Find and possibly load the [Snapshot] for the given entity ID. Snapshots saved with another
+    /// than the given state version are incompatible and hence not considered, i.e. without a
+    /// compatible one `None` is returned – without converting any bytes – such that the entity is
+    /// recovered from its events.
     fn load<S, FromBytes, FromBytesError>(
         &self,
-        id: Uuid,
+        id: Id,
+        state_version: u32,
         from_bytes: FromBytes,
     ) -> impl Future<Output = Result<Option<Snapshot<S>>, Self::Error>> + Send
     where
         FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
         FromBytesError: StdError + Send + Sync + 'static;
+
+    /// Find and possibly load the [Snapshot]s for the given entity IDs, e.g. to warm many entities
+    /// at once, yielding them along with their entity ID; IDs without a snapshot with the given
+    /// state version are skipped. Implementations should load all snapshots at once, e.g. with a
+    /// single query. The default implementation loads one snapshot after the other via
+    /// [load](SnapshotStore::load).
+    fn load_many<S, FromBytes, FromBytesError>(
+        &self,
+        ids: &[Id],
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<(Id, Snapshot<S>), Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        S: Send,
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send + Sync,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        // Clone to not require `Self: Sync`.
+        let snapshot_store = self.clone();
+
+        async move {
+            let mut snapshots = Vec::with_capacity(ids.len());
+            for id in ids {
+                if let Some(snapshot) = snapshot_store
+                    .load(id.clone(), state_version, &from_bytes)
+                    .await?
+                {
+                    snapshots.push(Ok((id.clone(), snapshot)));
+                }
+            }
+            Ok(stream::iter(snapshots))
+        }
+    }
+
+    /// Find and possibly load the newest [Snapshot] for the given entity ID with a sequence number
+    /// less than or equal to the given one, e.g. to rehydrate an entity at a point in time for
+    /// debugging or auditing. This requires implementations to keep former snapshots. The default
+    /// implementation only considers the latest snapshot via [load](SnapshotStore::load) and hence
+    /// is only appropriate for implementations keeping just that one.
+    fn load_at<S, FromBytes, FromBytesError>(
+        &self,
+        id: Id,
+        max_seq_no: SeqNo,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> impl Future<Output = Result<Option<Snapshot<S>>, Self::Error>> + Send
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        // Clone to not require `Self: Sync`.
+        let snapshot_store = self.clone();
+
+        async move {
+            let snapshot = snapshot_store.load(id, state_version, from_bytes).await?;
+            Ok(snapshot.filter(|snapshot| snapshot.seq_no <= max_seq_no))
+        }
+    }
+
+    /// Delete the snapshots for the given entity ID with a sequence number less than the given one.
+    fn delete_before(
+        &self,
+        id: Id,
+        seq_no: SeqNo,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Check whether this snapshot store is reachable, e.g. for a readiness probe before serving
+    /// traffic. The default implementation always succeeds and hence is only appropriate for
+    /// implementations without a remote backend.
+    fn ping(&self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async { Ok(()) }
+    }
 }
 
 /// Snapshot state along with its sequence number.
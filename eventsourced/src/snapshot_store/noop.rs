@@ -1,21 +1,24 @@
 //! A [SnapshotStore] implementation that does nothing.
 
-use crate::{SeqNo, Snapshot, SnapshotStore};
+use crate::{EntityId, SeqNo, Snapshot, SnapshotStore};
 use bytes::Bytes;
 use std::{convert::Infallible, error::Error as StdError, fmt::Debug};
-use uuid::Uuid;
 
-/// A [SnapshotStore] implementation that does nothing.
+/// A [SnapshotStore] implementation that does nothing, for any [EntityId] type.
 #[derive(Debug, Clone, Copy)]
 pub struct NoopSnapshotStore;
 
-impl SnapshotStore for NoopSnapshotStore {
+impl<Id> SnapshotStore<Id> for NoopSnapshotStore
+where
+    Id: EntityId,
+{
     type Error = Infallible;
 
     async fn save<S, ToBytes, ToBytesError>(
-        &mut self,
-        _id: Uuid,
+        &self,
+        _id: Id,
         _seq_no: SeqNo,
+        _state_version: u32,
         _state: S,
         _to_bytes: &ToBytes,
     ) -> Result<(), Self::Error>
@@ -29,7 +32,8 @@ impl SnapshotStore for NoopSnapshotStore {
 
     async fn load<S, FromBytes, FromBytesError>(
         &self,
-        _id: Uuid,
+        _id: Id,
+        _state_version: u32,
         _from_bytes: FromBytes,
     ) -> Result<Option<Snapshot<S>>, Self::Error>
     where
@@ -38,4 +42,8 @@ impl SnapshotStore for NoopSnapshotStore {
     {
         Ok(None)
     }
+
+    async fn delete_before(&self, _id: Id, _seq_no: SeqNo) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
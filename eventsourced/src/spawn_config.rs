@@ -0,0 +1,132 @@
+use crate::{CmdBuffer, ReplayErrorPolicy, RetryPolicy};
+use std::{
+    num::{NonZeroU64, NonZeroUsize},
+    time::Duration,
+};
+use tracing::Level;
+
+/// Options for spawning an entity, see [spawn](crate::EventSourcedExt::spawn). Can be created from
+/// a [CmdBuffer] or a [NonZeroUsize] buffer size, using the defaults for all other options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpawnConfig {
+    /// The buffer for commands sent to the spawned entity.
+    pub cmd_buffer: CmdBuffer,
+
+    /// The duration after which handling a command – including persisting and applying the event
+    /// and saving a snapshot – is considered slow and logged as a warning, e.g. to spot
+    /// pathological entities. `None` by default, i.e. disabled.
+    pub slow_cmd_threshold: Option<Duration>,
+
+    /// The maximum duration of recovery – loading the snapshot and replaying the events – after
+    /// which spawning fails with
+    /// [SpawnError::RecoveryTimeout](crate::SpawnError::RecoveryTimeout), e.g. to keep callers
+    /// spawning on demand from hanging on an entity with a huge history. `None` by default,
+    /// i.e. unbounded.
+    pub recovery_timeout: Option<Duration>,
+
+    /// The [ReplayErrorPolicy] for events which cannot be deserialized when replaying them on
+    /// recovery. [ReplayErrorPolicy::Fail] by default.
+    pub replay_error_policy: ReplayErrorPolicy,
+
+    /// The [RetryPolicy] for persisting events: on a transient error of the
+    /// [EvtLog](crate::EvtLog), see [is_retryable](crate::EvtLog::is_retryable), the entity does
+    /// not terminate right away, but only after the retries have been exhausted; other errors,
+    /// e.g. sequence number conflicts, are not retried. No retries by default.
+    pub persist_retry_policy: RetryPolicy,
+
+    /// Whether to delete the events up to and including the sequence number of a saved snapshot
+    /// from the event log, see [EvtLog::delete_to](crate::EvtLog::delete_to). `false` by default,
+    /// i.e. all events are kept.
+    pub prune_on_snapshot: bool,
+
+    /// The level of the `spawn` span covering recovery and of the `handle_cmd` span covering
+    /// command handling, persisting and applying the event and saving a snapshot. [Level::INFO]
+    /// by default.
+    pub span_level: Level,
+
+    /// The number of consecutively received commands with a full command buffer after which a
+    /// warning is logged, hinting that the buffer should be larger or the entity faster, see
+    /// [CmdBuffer::recommended_size]. `None` by default, i.e. disabled.
+    pub cmd_buffer_full_threshold: Option<NonZeroU64>,
+}
+
+impl SpawnConfig {
+    #[allow(missing_docs)]
+    pub fn new(cmd_buffer: impl Into<CmdBuffer>) -> Self {
+        Self {
+            cmd_buffer: cmd_buffer.into(),
+            slow_cmd_threshold: None,
+            recovery_timeout: None,
+            replay_error_policy: ReplayErrorPolicy::default(),
+            persist_retry_policy: RetryPolicy::default(),
+            prune_on_snapshot: false,
+            span_level: Level::INFO,
+            cmd_buffer_full_threshold: None,
+        }
+    }
+
+    /// Change the `slow_cmd_threshold`.
+    pub fn with_slow_cmd_threshold(self, slow_cmd_threshold: Duration) -> Self {
+        Self {
+            slow_cmd_threshold: Some(slow_cmd_threshold),
+            ..self
+        }
+    }
+
+    /// Change the `recovery_timeout`.
+    pub fn with_recovery_timeout(self, recovery_timeout: Duration) -> Self {
+        Self {
+            recovery_timeout: Some(recovery_timeout),
+            ..self
+        }
+    }
+
+    /// Change the `replay_error_policy`.
+    pub fn with_replay_error_policy(self, replay_error_policy: ReplayErrorPolicy) -> Self {
+        Self {
+            replay_error_policy,
+            ..self
+        }
+    }
+
+    /// Change the `persist_retry_policy`.
+    pub fn with_persist_retry_policy(self, persist_retry_policy: RetryPolicy) -> Self {
+        Self {
+            persist_retry_policy,
+            ..self
+        }
+    }
+
+    /// Change `prune_on_snapshot`.
+    pub fn with_prune_on_snapshot(self, prune_on_snapshot: bool) -> Self {
+        Self {
+            prune_on_snapshot,
+            ..self
+        }
+    }
+
+    /// Change the `span_level`.
+    pub fn with_span_level(self, span_level: Level) -> Self {
+        Self { span_level, ..self }
+    }
+
+    /// Change the `cmd_buffer_full_threshold`.
+    pub fn with_cmd_buffer_full_threshold(self, cmd_buffer_full_threshold: NonZeroU64) -> Self {
+        Self {
+            cmd_buffer_full_threshold: Some(cmd_buffer_full_threshold),
+            ..self
+        }
+    }
+}
+
+impl From<CmdBuffer> for SpawnConfig {
+    fn from(cmd_buffer: CmdBuffer) -> Self {
+        Self::new(cmd_buffer)
+    }
+}
+
+impl From<NonZeroUsize> for SpawnConfig {
+    fn from(size: NonZeroUsize) -> Self {
+        Self::new(size)
+    }
+}
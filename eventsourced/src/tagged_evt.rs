@@ -1,44 +1,85 @@
-/// An event and an optional tag. Typically not used direcly, but via [IntoTaggedEvt] or [EvtExt].
-pub struct TaggedEvt<E> {
+/// An event, its tags and its deferred effects, see
+/// [EventSourced::Effect](super::EventSourced::Effect). Typically not used direcly, but via
+/// [IntoTaggedEvt] or [EvtExt].
+#[derive(Debug)]
+pub struct TaggedEvt<E, Eff = ()> {
     pub(crate) evt: E,
-    pub(crate) tag: Option<String>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) effects: Vec<Eff>,
+}
+
+impl<E, Eff> TaggedEvt<E, Eff> {
+    /// The event.
+    pub fn evt(&self) -> &E {
+        &self.evt
+    }
+
+    /// The tags.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Add the given tag, which allows for chaining calls to `with_tag`.
+    pub fn with_tag<T>(mut self, tag: T) -> Self
+    where
+        T: ToString,
+    {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// The deferred effects.
+    pub fn effects(&self) -> &[Eff] {
+        &self.effects
+    }
+
+    /// Add the given deferred effect, executed only after the event has been persisted, which
+    /// allows for chaining calls to `with_effect`.
+    pub fn with_effect(mut self, effect: Eff) -> Self {
+        self.effects.push(effect);
+        self
+    }
 }
 
 /// Used in an [EventSourced](super::EventSourced) command handler as impl trait in return position.
 /// Together with its blanket implementation for any event allows for returning plain events without
 /// boilerplate.
-pub trait IntoTaggedEvt<E>: Send {
-    fn into_tagged_evt(self) -> TaggedEvt<E>;
+pub trait IntoTaggedEvt<E, Eff = ()> {
+    fn into_tagged_evt(self) -> TaggedEvt<E, Eff>;
 }
 
-impl<E> IntoTaggedEvt<E> for E
-where
-    E: Send,
-{
-    fn into_tagged_evt(self) -> TaggedEvt<E> {
+impl<E, Eff> IntoTaggedEvt<E, Eff> for E {
+    fn into_tagged_evt(self) -> TaggedEvt<E, Eff> {
         TaggedEvt {
             evt: self,
-            tag: None,
+            tags: vec![],
+            effects: vec![],
         }
     }
 }
 
-impl<E> IntoTaggedEvt<E> for TaggedEvt<E>
-where
-    E: Send,
-{
-    fn into_tagged_evt(self) -> TaggedEvt<E> {
+impl<E, Eff> IntoTaggedEvt<E, Eff> for TaggedEvt<E, Eff> {
+    fn into_tagged_evt(self) -> TaggedEvt<E, Eff> {
         self
     }
 }
 
-/// Provide a `with_tag` extension method for events. Together with its blanket implementation for
-/// any event allows for calling `with_tag` on any event type.
+/// Provide `with_tag`, `with_tags` and `with_effect` extension methods for events. Together with
+/// its blanket implementation for any event allows for calling these on any event type.
 pub trait EvtExt: Sized {
     /// Create a [TaggedEvt] with the given tag.
     fn with_tag<T>(self, tag: T) -> TaggedEvt<Self>
     where
         T: ToString;
+
+    /// Create a [TaggedEvt] with the given tags.
+    fn with_tags<I, T>(self, tags: I) -> TaggedEvt<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: ToString;
+
+    /// Create a [TaggedEvt] with the given deferred effect; tags can be added afterwards.
+    fn with_effect<Eff>(self, effect: Eff) -> TaggedEvt<Self, Eff>;
 }
 
 impl<E> EvtExt for E {
@@ -48,7 +89,54 @@ impl<E> EvtExt for E {
     {
         TaggedEvt {
             evt: self,
-            tag: Some(tag.to_string()),
+            tags: vec![tag.to_string()],
+            effects: vec![],
         }
     }
+
+    fn with_tags<I, T>(self, tags: I) -> TaggedEvt<E>
+    where
+        I: IntoIterator<Item = T>,
+        T: ToString,
+    {
+        TaggedEvt {
+            evt: self,
+            tags: tags.into_iter().map(|tag| tag.to_string()).collect(),
+            effects: vec![],
+        }
+    }
+
+    fn with_effect<Eff>(self, effect: Eff) -> TaggedEvt<E, Eff> {
+        TaggedEvt {
+            evt: self,
+            tags: vec![],
+            effects: vec![effect],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tagged_evt() {
+        let TaggedEvt { evt, tags, effects } = IntoTaggedEvt::<_, ()>::into_tagged_evt(42);
+        assert_eq!(evt, 42);
+        assert!(tags.is_empty());
+        assert!(effects.is_empty());
+
+        let TaggedEvt { evt, tags, .. } = 42.with_tag("a").with_tag("b");
+        assert_eq!(evt, 42);
+        assert_eq!(tags, vec!["a", "b"]);
+
+        let TaggedEvt { evt, tags, .. } = 42.with_tags(["a", "b"]);
+        assert_eq!(evt, 42);
+        assert_eq!(tags, vec!["a", "b"]);
+
+        let TaggedEvt { evt, tags, effects } = 42.with_effect("x").with_effect("y").with_tag("a");
+        assert_eq!(evt, 42);
+        assert_eq!(tags, vec!["a"]);
+        assert_eq!(effects, vec!["x", "y"]);
+    }
 }
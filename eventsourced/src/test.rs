@@ -0,0 +1,323 @@
+//! Deterministic, in-memory testing of the command and event handlers of an [EventSourced]
+//! implementation in the style of given-when-then, i.e. without spawning it and without an
+//! [EvtLog](crate::EvtLog) or [SnapshotStore](crate::SnapshotStore).
+//!
+//! ```ignore
+//! let counter = given(Counter::default(), [Evt::Increased(1)])
+//!     .when(Cmd::Increase(2))
+//!     .then([Evt::Increased(2)]);
+//! assert_eq!(counter.value(), 3);
+//! ```
+
+use crate::{CmdContext, EntityId, EventSourced, IntoTaggedEvt, SeqNo, TaggedEvt};
+use std::fmt::Debug;
+use uuid::Uuid;
+
+/// Apply the given prior events to the given [EventSourced] value like on recovery, i.e. via
+/// [EventSourced::apply_evt] with sequence numbers starting at [SeqNo::MIN], using a random [Uuid]
+/// as ID.
+///
+/// # Panics
+///
+/// Panics if the event handler returns an error for any of the given events.
+#[track_caller]
+pub fn given<E, I>(event_sourced: E, evts: I) -> Given<E>
+where
+    E: EventSourced,
+    I: IntoIterator<Item = E::Evt>,
+{
+    given_with_id(event_sourced, Uuid::now_v7(), evts)
+}
+
+/// Like [given], but using the given ID, e.g. for entities with other IDs than [Uuid]s.
+///
+/// # Panics
+///
+/// Panics if the event handler returns an error for any of the given events.
+#[track_caller]
+pub fn given_with_id<E, Id, I>(mut event_sourced: E, id: Id, evts: I) -> Given<E, Id>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+    I: IntoIterator<Item = E::Evt>,
+{
+    let mut seq_no = SeqNo::MIN;
+    for evt in evts {
+        if let Err(error) = event_sourced.apply_evt(seq_no, evt) {
+            panic!("cannot apply given event: {error}");
+        }
+        seq_no = seq_no.succ();
+    }
+
+    Given { event_sourced, id }
+}
+
+/// [EventSourced] value with applied prior events, created via [given].
+#[derive(Debug)]
+pub struct Given<E, Id = Uuid> {
+    event_sourced: E,
+    id: Id,
+}
+
+impl<E, Id> Given<E, Id>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    /// Change the ID given to the command handler, by default a random one.
+    pub fn with_id(self, id: Id) -> Self {
+        Self { id, ..self }
+    }
+
+    /// Invoke the command handler with the given command and, if the command is valid, apply the
+    /// resulting event via the event handler.
+    #[track_caller]
+    pub fn when(self, cmd: E::Cmd) -> When<E, Id>
+    where
+        E::Evt: Clone,
+    {
+        When {
+            event_sourced: self.event_sourced,
+            id: self.id,
+            result: Ok(vec![]),
+        }
+        .when(cmd)
+    }
+}
+
+/// Result of invoking the command handler via [Given::when], possibly for multiple commands.
+#[derive(Debug)]
+pub struct When<E, Id = Uuid>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    event_sourced: E,
+    id: Id,
+    result: Result<Vec<TaggedEvt<E::Evt>>, E::Error>,
+}
+
+impl<E, Id> When<E, Id>
+where
+    E: EventSourced<Id>,
+    Id: EntityId,
+{
+    /// Invoke the command handler with a further command, unless a previous one was rejected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the event handler returns an error for the resulting event.
+    #[track_caller]
+    pub fn when(mut self, cmd: E::Cmd) -> Self
+    where
+        E::Evt: Clone,
+    {
+        if let Ok(evts) = &mut self.result {
+            let evt = self
+                .event_sourced
+                .handle_cmd(self.id.clone(), cmd)
+                .map(IntoTaggedEvt::into_tagged_evt);
+            match evt {
+                Ok(TaggedEvt { evt, tags, .. }) => {
+                    let evt = self.event_sourced.enrich(evt, &CmdContext::default());
+                    let applied = self.event_sourced.handle_evt(evt.clone());
+                    if let Err(error) = applied {
+                        panic!("cannot apply event: {error}");
+                    }
+                    evts.push(TaggedEvt {
+                        evt,
+                        tags,
+                        effects: vec![],
+                    });
+                }
+
+                Err(error) => self.result = Err(error),
+            }
+        }
+
+        self
+    }
+
+    /// Assert that all commands were valid and resulted in the given events, returning the
+    /// [EventSourced] value, e.g. to assert its state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a command was rejected or the events do not equal the given ones.
+    #[track_caller]
+    pub fn then<I>(self, expected: I) -> E
+    where
+        E::Evt: PartialEq + Debug,
+        I: IntoIterator<Item = E::Evt>,
+    {
+        match self.result {
+            Ok(evts) => {
+                let evts = evts.into_iter().map(|evt| evt.evt).collect::<Vec<_>>();
+                let expected = expected.into_iter().collect::<Vec<_>>();
+                assert_eq!(evts, expected, "unexpected events");
+                self.event_sourced
+            }
+
+            Err(error) => panic!("expected events, but command was rejected: {error}"),
+        }
+    }
+
+    /// Assert that all commands were valid and return the resulting events along with their tags.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a command was rejected.
+    #[track_caller]
+    pub fn then_tagged_evts(self) -> Vec<TaggedEvt<E::Evt>> {
+        match self.result {
+            Ok(evts) => evts,
+            Err(error) => panic!("expected events, but command was rejected: {error}"),
+        }
+    }
+
+    /// Assert that a command was rejected and return the error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if all commands were valid.
+    #[track_caller]
+    pub fn then_rejected(self) -> E::Error
+    where
+        E::Evt: Debug,
+    {
+        match self.result {
+            Ok(evts) => {
+                let evts = evts.into_iter().map(|evt| evt.evt).collect::<Vec<_>>();
+                panic!("expected rejection, but got events: {evts:?}")
+            }
+
+            Err(error) => error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EvtExt;
+    use thiserror::Error;
+
+    #[derive(Debug)]
+    struct Counter(u64);
+
+    #[derive(Debug)]
+    enum Cmd {
+        Increase(u64),
+        Decrease(u64),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Evt {
+        Increased(u64),
+        Decreased(u64),
+    }
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    #[error("underflow")]
+    struct Underflow;
+
+    impl EventSourced for Counter {
+        type Cmd = Cmd;
+
+        type Evt = Evt;
+
+        type State = u64;
+
+        type Error = Underflow;
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            _id: Uuid,
+            cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            match cmd {
+                Cmd::Increase(n) => Ok(Evt::Increased(n).with_tag("increased")),
+                Cmd::Decrease(n) if n <= self.0 => Ok(Evt::Decreased(n).with_tag("decreased")),
+                Cmd::Decrease(_) => Err(Underflow),
+            }
+        }
+
+        fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            match evt {
+                Evt::Increased(n) => self.0 += n,
+                Evt::Decreased(n) => self.0 -= n,
+            }
+            Ok(None)
+        }
+
+        fn set_state(&mut self, state: Self::State) {
+            self.0 = state;
+        }
+    }
+
+    #[test]
+    fn test_given_when_then() {
+        let counter = given(Counter(0), [Evt::Increased(3)])
+            .when(Cmd::Decrease(1))
+            .when(Cmd::Increase(2))
+            .then([Evt::Decreased(1), Evt::Increased(2)]);
+        assert_eq!(counter.0, 4);
+
+        let evts = given(Counter(0), [])
+            .when(Cmd::Increase(1))
+            .then_tagged_evts();
+        assert_eq!(evts.len(), 1);
+        assert_eq!(evts[0].evt(), &Evt::Increased(1));
+        assert_eq!(evts[0].tags().len(), 1);
+
+        let error = given(Counter(0), [Evt::Increased(1)])
+            .when(Cmd::Decrease(2))
+            .when(Cmd::Increase(1))
+            .then_rejected();
+        assert_eq!(error, Underflow);
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected events")]
+    fn test_then_unexpected() {
+        given(Counter(0), [])
+            .when(Cmd::Increase(1))
+            .then([Evt::Increased(2)]);
+    }
+
+    #[derive(Debug)]
+    struct Greeter;
+
+    impl EventSourced<String> for Greeter {
+        type Cmd = ();
+
+        type Evt = String;
+
+        type State = ();
+
+        type Error = Underflow;
+        type Effect = ();
+
+        fn handle_cmd(
+            &self,
+            id: String,
+            _cmd: Self::Cmd,
+        ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
+            Ok(format!("hello {id}"))
+        }
+
+        fn handle_evt(&mut self, _evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
+            Ok(None)
+        }
+
+        fn set_state(&mut self, _state: Self::State) {}
+    }
+
+    #[test]
+    fn test_given_with_id() {
+        given_with_id(Greeter, "world".to_string(), [])
+            .when(())
+            .then(["hello world".to_string()]);
+    }
+}
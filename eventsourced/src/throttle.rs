@@ -0,0 +1,601 @@
+//! Decorators for [EvtLog] and [SnapshotStore] implementations limiting the number of concurrent
+//! operations.
+
+use crate::{
+    EntityEvts, EntityId, EvtEnvelope, EvtLog, GlobalSeqNo, SeqNo, Snapshot, SnapshotStore,
+    VerifyReport,
+};
+use bytes::Bytes;
+use futures::{Stream, StreamExt, TryStreamExt};
+use std::{error::Error as StdError, future::Future, num::NonZeroUsize, sync::Arc};
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::debug;
+
+/// Error from a [ThrottledEvtLog] or [ThrottledSnapshotStore].
+#[derive(Debug, Error)]
+pub enum ThrottleError<E> {
+    /// Error from the wrapped [EvtLog] or [SnapshotStore].
+    #[error(transparent)]
+    Inner(E),
+
+    /// The operation has been rejected, because the maximum numbers of concurrent and queued
+    /// operations have been reached.
+    #[error("operation rejected because of too many concurrent operations")]
+    Rejected,
+}
+
+/// An [EvtLog] decorator limiting the number of concurrent operations of the wrapped one via a
+/// semaphore, e.g. to not overwhelm the database beyond its connection pool when thousands of
+/// entities are spawned at once. Operations beyond the limit are queued, by default without bound,
+/// see [with_max_queued](ThrottledEvtLog::with_max_queued).
+///
+/// For the query methods, the limit only covers obtaining the streams, as these are live and hence
+/// do not terminate – except for [evts_by_id_from](EvtLog::evts_by_id_from), the stream of which
+/// is covered until it gets dropped. [ping](EvtLog::ping) is not limited.
+#[derive(Debug, Clone)]
+pub struct ThrottledEvtLog<L> {
+    evt_log: L,
+    throttle: Throttle,
+}
+
+impl<L> ThrottledEvtLog<L> {
+    /// Wrap the given [EvtLog], allowing for at most the given number of concurrent operations.
+    pub fn new(evt_log: L, max_concurrent: NonZeroUsize) -> Self {
+        Self {
+            evt_log,
+            throttle: Throttle::new(max_concurrent),
+        }
+    }
+
+    /// Change the maximum number of operations waiting for one of the concurrent ones to complete,
+    /// beyond which operations are rejected with [ThrottleError::Rejected]; `0` rejects operations
+    /// right away.
+    pub fn with_max_queued(self, max_queued: usize) -> Self {
+        Self {
+            throttle: self.throttle.with_max_queued(max_queued),
+            ..self
+        }
+    }
+}
+
+impl<L, Id> EvtLog<Id> for ThrottledEvtLog<L>
+where
+    L: EvtLog<Id>,
+    Id: EntityId,
+{
+    type Error = ThrottleError<L::Error>;
+
+    const MAX_SEQ_NO: SeqNo = L::MAX_SEQ_NO;
+
+    fn persist<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Id,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> impl Future<Output = Result<SeqNo, Self::Error>> + Send
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        let throttle = self.throttle.clone();
+        let seq_no =
+            self.evt_log
+                .persist(evt, version, tags, id, entity_type, last_seq_no, to_bytes);
+        throttle.run(seq_no)
+    }
+
+    fn persist_returning_global_seq_no<E, ToBytes, ToBytesError>(
+        &self,
+        evt: &E,
+        version: u32,
+        tags: &[String],
+        id: Id,
+        entity_type: Option<&str>,
+        last_seq_no: Option<SeqNo>,
+        to_bytes: &ToBytes,
+    ) -> impl Future<Output = Result<(SeqNo, Option<GlobalSeqNo>), Self::Error>> + Send
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        let throttle = self.throttle.clone();
+        let seq_nos = self.evt_log.persist_returning_global_seq_no(
+            evt,
+            version,
+            tags,
+            id,
+            entity_type,
+            last_seq_no,
+            to_bytes,
+        );
+        throttle.run(seq_nos)
+    }
+
+    fn persist_batch<E, ToBytes, ToBytesError>(
+        &self,
+        batch: &[EntityEvts<'_, E, Id>],
+        to_bytes: &ToBytes,
+    ) -> impl Future<Output = Result<Vec<Option<SeqNo>>, Self::Error>> + Send
+    where
+        E: Sync,
+        ToBytes: Fn(&E) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        let throttle = self.throttle.clone();
+        let last_seq_nos = self.evt_log.persist_batch(batch, to_bytes);
+        throttle.run(last_seq_nos)
+    }
+
+    fn delete_to(
+        &self,
+        id: Id,
+        to_seq_no: SeqNo,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let throttle = self.throttle.clone();
+        let deleted = self.evt_log.delete_to(id, to_seq_no);
+        throttle.run(deleted)
+    }
+
+    fn mark_deleted(&self, id: Id) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let throttle = self.throttle.clone();
+        let marked = self.evt_log.mark_deleted(id);
+        throttle.run(marked)
+    }
+
+    fn is_deleted(&self, id: Id) -> impl Future<Output = Result<bool, Self::Error>> + Send {
+        let throttle = self.throttle.clone();
+        let deleted = self.evt_log.is_deleted(id);
+        throttle.run(deleted)
+    }
+
+    fn last_seq_no(
+        &self,
+        id: Id,
+    ) -> impl Future<Output = Result<Option<SeqNo>, Self::Error>> + Send {
+        self.throttle.clone().run(self.evt_log.last_seq_no(id))
+    }
+
+    fn evts_by_id<E, FromBytes, FromBytesError>(
+        &self,
+        id: Id,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let evts = self.evt_log.evts_by_id(id, from_seq_no, from_bytes);
+        self.throttle.clone().run_stream(evts)
+    }
+
+    fn evts_by_id_from<E, FromBytes, FromBytesError>(
+        &self,
+        id: Id,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let throttle = self.throttle.clone();
+        let evts = self.evt_log.evts_by_id_from(id, from_seq_no, from_bytes);
+
+        async move {
+            let permit = throttle.acquire().await?;
+            let evts = evts.await.map_err(ThrottleError::Inner)?;
+
+            // The stream terminates, hence keep the permit until it gets dropped.
+            let evts = evts.map(move |evt| {
+                let _permit = &permit;
+                evt.map_err(ThrottleError::Inner)
+            });
+            Ok(evts)
+        }
+    }
+
+    fn evts_by_ids<E, FromBytes, FromBytesError>(
+        &self,
+        ids: Vec<Id>,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let evts = self
+            .evt_log
+            .evts_by_ids(ids, from_global_seq_no, from_bytes);
+        self.throttle.clone().run_stream(evts)
+    }
+
+    fn evts<E, FromBytes, FromBytesError>(
+        &self,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let evts = self.evt_log.evts(from_global_seq_no, from_bytes);
+        self.throttle.clone().run_stream(evts)
+    }
+
+    fn evts_by_type<E, FromBytes, FromBytesError>(
+        &self,
+        entity_type: String,
+        from_global_seq_no: GlobalSeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let evts = self
+            .evt_log
+            .evts_by_type(entity_type, from_global_seq_no, from_bytes);
+        self.throttle.clone().run_stream(evts)
+    }
+
+    fn evts_by_tag<E, FromBytes, FromBytesError>(
+        &self,
+        tag: String,
+        from_seq_no: SeqNo,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<EvtEnvelope<E, Id>, Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        E: Send,
+        FromBytes: Fn(Bytes) -> Result<E, FromBytesError> + Copy + Send + Sync + 'static,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let evts = self.evt_log.evts_by_tag(tag, from_seq_no, from_bytes);
+        self.throttle.clone().run_stream(evts)
+    }
+
+    fn verify(&self, id: Id) -> impl Future<Output = Result<VerifyReport, Self::Error>> + Send {
+        self.throttle.clone().run(self.evt_log.verify(id))
+    }
+
+    fn ping(&self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let ping = self.evt_log.ping();
+        async move { ping.await.map_err(ThrottleError::Inner) }
+    }
+
+    fn is_retryable(error: &Self::Error) -> bool {
+        match error {
+            ThrottleError::Inner(error) => L::is_retryable(error),
+            ThrottleError::Rejected => true,
+        }
+    }
+}
+
+/// A [SnapshotStore] decorator limiting the number of concurrent operations of the wrapped one via
+/// a semaphore, like [ThrottledEvtLog]. [ping](SnapshotStore::ping) is not limited.
+#[derive(Debug, Clone)]
+pub struct ThrottledSnapshotStore<S> {
+    snapshot_store: S,
+    throttle: Throttle,
+}
+
+impl<S> ThrottledSnapshotStore<S> {
+    /// Wrap the given [SnapshotStore], allowing for at most the given number of concurrent
+    /// operations.
+    pub fn new(snapshot_store: S, max_concurrent: NonZeroUsize) -> Self {
+        Self {
+            snapshot_store,
+            throttle: Throttle::new(max_concurrent),
+        }
+    }
+
+    /// Change the maximum number of operations waiting for one of the concurrent ones to complete,
+    /// beyond which operations are rejected with [ThrottleError::Rejected]; `0` rejects operations
+    /// right away.
+    pub fn with_max_queued(self, max_queued: usize) -> Self {
+        Self {
+            throttle: self.throttle.with_max_queued(max_queued),
+            ..self
+        }
+    }
+}
+
+impl<T, Id> SnapshotStore<Id> for ThrottledSnapshotStore<T>
+where
+    T: SnapshotStore<Id>,
+    Id: EntityId,
+{
+    type Error = ThrottleError<T::Error>;
+
+    fn save<S, ToBytes, ToBytesError>(
+        &self,
+        id: Id,
+        seq_no: SeqNo,
+        state_version: u32,
+        state: S,
+        to_bytes: &ToBytes,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send
+    where
+        S: Send,
+        ToBytes: Fn(&S) -> Result<Bytes, ToBytesError> + Sync,
+        ToBytesError: StdError + Send + Sync + 'static,
+    {
+        let throttle = self.throttle.clone();
+        let saved = self
+            .snapshot_store
+            .save(id, seq_no, state_version, state, to_bytes);
+        throttle.run(saved)
+    }
+
+    fn load<S, FromBytes, FromBytesError>(
+        &self,
+        id: Id,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> impl Future<Output = Result<Option<Snapshot<S>>, Self::Error>> + Send
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let snapshot = self.snapshot_store.load(id, state_version, from_bytes);
+        self.throttle.clone().run(snapshot)
+    }
+
+    fn load_many<S, FromBytes, FromBytesError>(
+        &self,
+        ids: &[Id],
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<(Id, Snapshot<S>), Self::Error>> + Send,
+            Self::Error,
+        >,
+    > + Send
+    where
+        S: Send,
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send + Sync,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let snapshots = self
+            .snapshot_store
+            .load_many(ids, state_version, from_bytes);
+        self.throttle.clone().run_stream(snapshots)
+    }
+
+    fn load_at<S, FromBytes, FromBytesError>(
+        &self,
+        id: Id,
+        max_seq_no: SeqNo,
+        state_version: u32,
+        from_bytes: FromBytes,
+    ) -> impl Future<Output = Result<Option<Snapshot<S>>, Self::Error>> + Send
+    where
+        FromBytes: Fn(Bytes) -> Result<S, FromBytesError> + Send,
+        FromBytesError: StdError + Send + Sync + 'static,
+    {
+        let snapshot = self
+            .snapshot_store
+            .load_at(id, max_seq_no, state_version, from_bytes);
+        self.throttle.clone().run(snapshot)
+    }
+
+    fn delete_before(
+        &self,
+        id: Id,
+        seq_no: SeqNo,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let throttle = self.throttle.clone();
+        let deleted = self.snapshot_store.delete_before(id, seq_no);
+        throttle.run(deleted)
+    }
+
+    fn ping(&self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let ping = self.snapshot_store.ping();
+        async move { ping.await.map_err(ThrottleError::Inner) }
+    }
+}
+
+/// Semaphores for the concurrent operations and, if bounded, for the concurrent and queued ones.
+#[derive(Debug, Clone)]
+struct Throttle {
+    max_concurrent: NonZeroUsize,
+    concurrent: Arc<Semaphore>,
+    admitted: Option<Arc<Semaphore>>,
+}
+
+impl Throttle {
+    fn new(max_concurrent: NonZeroUsize) -> Self {
+        Self {
+            max_concurrent,
+            concurrent: Arc::new(Semaphore::new(max_concurrent.get())),
+            admitted: None,
+        }
+    }
+
+    fn with_max_queued(self, max_queued: usize) -> Self {
+        let admitted = Semaphore::new(self.max_concurrent.get() + max_queued);
+        Self {
+            admitted: Some(Arc::new(admitted)),
+            ..self
+        }
+    }
+
+    /// Acquire a permit for an operation, waiting if the maximum number of concurrent operations
+    /// has been reached or failing if also the maximum number of queued ones has been reached.
+    async fn acquire<E>(self) -> Result<Permit, ThrottleError<E>> {
+        let admitted = self
+            .admitted
+            .map(|admitted| {
+                admitted.try_acquire_owned().map_err(|_| {
+                    debug!("rejecting operation");
+                    ThrottleError::Rejected
+                })
+            })
+            .transpose()?;
+        let concurrent = self
+            .concurrent
+            .acquire_owned()
+            .await
+            .expect("semaphore is not closed");
+
+        Ok(Permit {
+            _admitted: admitted,
+            _concurrent: concurrent,
+        })
+    }
+
+    /// Run the given operation once a permit has been acquired.
+    async fn run<T, E, F>(self, f: F) -> Result<T, ThrottleError<E>>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let _permit = self.acquire().await?;
+        f.await.map_err(ThrottleError::Inner)
+    }
+
+    /// Run the given operation returning a stream once a permit has been acquired, which is
+    /// released once the stream has been obtained.
+    async fn run_stream<S, T, E, F>(
+        self,
+        f: F,
+    ) -> Result<impl Stream<Item = Result<T, ThrottleError<E>>>, ThrottleError<E>>
+    where
+        F: Future<Output = Result<S, E>>,
+        S: Stream<Item = Result<T, E>>,
+    {
+        let stream = self.run(f).await?;
+        Ok(stream.map_err(ThrottleError::Inner))
+    }
+}
+
+/// Permit for an operation, released when dropped.
+struct Permit {
+    _admitted: Option<OwnedSemaphorePermit>,
+    _concurrent: OwnedSemaphorePermit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryEvtLog, MemorySnapshotStore};
+    use std::{convert::Infallible, time::Duration};
+    use tokio::time::timeout;
+    use uuid::Uuid;
+
+    fn clone_bytes(bytes: &Bytes) -> Result<Bytes, Infallible> {
+        Ok(bytes.clone())
+    }
+
+    #[tokio::test]
+    async fn test_throttled_evt_log() -> Result<(), Box<dyn StdError>> {
+        let id = Uuid::now_v7();
+        let evt_log =
+            ThrottledEvtLog::new(MemoryEvtLog::default(), NonZeroUsize::MIN).with_max_queued(1);
+
+        let seq_no = evt_log
+            .persist(
+                &Bytes::from_static(b"evt"),
+                1,
+                &[],
+                id,
+                None,
+                None,
+                &clone_bytes,
+            )
+            .await?;
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(seq_no));
+
+        // The permit is kept until the terminating stream gets dropped.
+        let evts = evt_log
+            .evts_by_id_from::<Bytes, _, _>(id, SeqNo::MIN, Ok::<_, Infallible>)
+            .await?;
+
+        // With one operation in progress, one is queued and further ones are rejected.
+        let queued = evt_log.last_seq_no(id);
+        let mut queued = Box::pin(queued);
+        assert!(timeout(Duration::from_millis(100), &mut queued)
+            .await
+            .is_err());
+        assert!(matches!(
+            evt_log.last_seq_no(id).await,
+            Err(ThrottleError::Rejected)
+        ));
+
+        drop(evts);
+        assert_eq!(queued.await?, Some(seq_no));
+        assert_eq!(evt_log.last_seq_no(id).await?, Some(seq_no));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_throttled_snapshot_store() -> Result<(), Box<dyn StdError>> {
+        let id = Uuid::now_v7();
+        let snapshot_store =
+            ThrottledSnapshotStore::new(MemorySnapshotStore::default(), NonZeroUsize::MIN)
+                .with_max_queued(0);
+
+        snapshot_store
+            .save(
+                id,
+                SeqNo::MIN,
+                1,
+                Bytes::from_static(b"state"),
+                &clone_bytes,
+            )
+            .await?;
+        let snapshot = snapshot_store
+            .load::<Bytes, _, _>(id, 1, Ok::<_, Infallible>)
+            .await?;
+        assert_eq!(
+            snapshot.map(|snapshot| snapshot.state),
+            Some(Bytes::from_static(b"state"))
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use bytes::Bytes;
+
+/// Transformation of the bytes of events persisted with an older version into bytes which can be
+/// converted into the current event type, i.e. schema migration for events. Typically used via
+/// [EventSourced::upcaster](crate::EventSourced::upcaster).
+pub trait Upcaster: Send + Sync {
+    /// Upcast the given bytes of an event persisted with the given version.
+    fn upcast(&self, version: u32, bytes: Bytes) -> Bytes;
+}
+
+impl<F> Upcaster for F
+where
+    F: Fn(u32, Bytes) -> Bytes + Send + Sync,
+{
+    fn upcast(&self, version: u32, bytes: Bytes) -> Bytes {
+        self(version, bytes)
+    }
+}
+
+/// A chain of upcasters, applied in order, e.g. one for each breaking change of the event type.
+/// Each upcaster is given the persisted version and hence must leave the bytes of versions it is
+/// not responsible for untouched.
+impl<U> Upcaster for Vec<U>
+where
+    U: Upcaster,
+{
+    fn upcast(&self, version: u32, bytes: Bytes) -> Bytes {
+        self.iter()
+            .fold(bytes, |bytes, upcaster| upcaster.upcast(version, bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upcaster() {
+        let v1_to_v2 = |version, bytes: Bytes| {
+            if version < 2 {
+                [bytes.as_ref(), b"-v2"].concat().into()
+            } else {
+                bytes
+            }
+        };
+        let v2_to_v3 = |version, bytes: Bytes| {
+            if version < 3 {
+                [bytes.as_ref(), b"-v3"].concat().into()
+            } else {
+                bytes
+            }
+        };
+        let upcasters: Vec<Box<dyn Fn(u32, Bytes) -> Bytes + Send + Sync>> =
+            vec![Box::new(v1_to_v2), Box::new(v2_to_v3)];
+
+        assert_eq!(upcasters.upcast(1, Bytes::from("evt")), "evt-v2-v3");
+        assert_eq!(upcasters.upcast(2, Bytes::from("evt")), "evt-v3");
+        assert_eq!(upcasters.upcast(3, Bytes::from("evt")), "evt");
+    }
+}
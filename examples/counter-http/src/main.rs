@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use bytes::Bytes;
+use configured::Configured;
+use counter::counter::{self as counter_entity, Cmd, Counter, Evt};
+use eventsourced::{convert, EntityManager, EntityRefError, SpawnError};
+use eventsourced_postgres::{
+    PostgresEvtLog, PostgresEvtLogConfig, PostgresSnapshotStore, PostgresSnapshotStoreConfig,
+};
+use serde::Deserialize;
+use std::{net::IpAddr, num::NonZeroUsize, sync::Arc};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use uuid::Uuid;
+
+/// Spawns the counters on first access and caches their `EntityRef`s, such that subsequent
+/// requests for the same ID reuse the running entity instead of recovering it again.
+type Counters = EntityManager<
+    Counter,
+    PostgresEvtLog,
+    PostgresSnapshotStore,
+    fn(Uuid) -> Counter,
+    for<'a> fn(&'a Evt) -> Result<Bytes, serde_json::Error>,
+    fn(Bytes) -> Result<Evt, serde_json::Error>,
+    for<'a> fn(&'a u64) -> Result<Bytes, serde_json::Error>,
+    fn(Bytes) -> Result<u64, serde_json::Error>,
+>;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(tracing_subscriber::fmt::layer())
+        .try_init()
+        .context("initialize tracing")?;
+
+    let config = Config::load().context("load configuration")?;
+    println!("Starting with configuration: {config:?}");
+
+    let evt_log = PostgresEvtLog::new(config.evt_log)
+        .await
+        .context("create event log")?;
+
+    let snapshot_store = PostgresSnapshotStore::new(config.snapshot_store)
+        .await
+        .context("create snapshot store")?;
+
+    let counters: Counters = EntityManager::new(
+        |_| Counter::default(),
+        NonZeroUsize::new(42).expect("42 is not zero"),
+        evt_log,
+        snapshot_store,
+        convert::serde_json::binarizer(),
+    );
+
+    let app = Router::new()
+        .route("/counters/:id/inc/:n", post(inc))
+        .route("/counters/:id/dec/:n", post(dec))
+        .with_state(Arc::new(counters));
+
+    let listener = TcpListener::bind((config.http.address, config.http.port))
+        .await
+        .context("bind TcpListener")?;
+    info!(address = %config.http.address, port = config.http.port, "listening");
+    axum::serve(listener, app).await.context("run server")
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Config {
+    http: HttpConfig,
+    evt_log: PostgresEvtLogConfig,
+    snapshot_store: PostgresSnapshotStoreConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct HttpConfig {
+    address: IpAddr,
+    port: u16,
+}
+
+async fn inc(
+    State(counters): State<Arc<Counters>>,
+    Path((id, n)): Path<(Uuid, u64)>,
+) -> Result<StatusCode, ApiError> {
+    handle_cmd(&counters, id, Cmd::Inc(n)).await
+}
+
+async fn dec(
+    State(counters): State<Arc<Counters>>,
+    Path((id, n)): Path<(Uuid, u64)>,
+) -> Result<StatusCode, ApiError> {
+    handle_cmd(&counters, id, Cmd::Dec(n)).await
+}
+
+async fn handle_cmd(counters: &Counters, id: Uuid, cmd: Cmd) -> Result<StatusCode, ApiError> {
+    let counter = counters.get_or_spawn(id).await?;
+    counter.handle_cmd(cmd).await??;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Errors of the HTTP API: invalid commands and client errors of the [EntityRef] are mapped to
+/// 400 Bad Request, all other, i.e. technical, errors to 500 Internal Server Error.
+///
+/// [EntityRef]: eventsourced::EntityRef
+#[derive(Debug)]
+enum ApiError {
+    InvalidCmd(counter_entity::Error),
+    EntityRef(EntityRefError),
+    Spawn(SpawnError),
+}
+
+impl From<counter_entity::Error> for ApiError {
+    fn from(error: counter_entity::Error) -> Self {
+        Self::InvalidCmd(error)
+    }
+}
+
+impl From<EntityRefError> for ApiError {
+    fn from(error: EntityRefError) -> Self {
+        Self::EntityRef(error)
+    }
+}
+
+impl From<SpawnError> for ApiError {
+    fn from(error: SpawnError) -> Self {
+        Self::Spawn(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::InvalidCmd(error) => (StatusCode::BAD_REQUEST, error.to_string()).into_response(),
+
+            Self::EntityRef(error) if error.is_client_error() => {
+                (StatusCode::BAD_REQUEST, error.to_string()).into_response()
+            }
+
+            Self::EntityRef(error) => {
+                error!(error = %error, "cannot handle command");
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+
+            Self::Spawn(error) => {
+                error!(error = %error, "cannot spawn counter");
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use eventsourced::{EventSourced, IntoTaggedEvt};
+use eventsourced::{CmdDispatch, EventSourced, IntoTaggedEvt};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
@@ -9,7 +9,7 @@ pub struct Counter {
     value: u64,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CmdDispatch)]
 pub enum Cmd {
     Inc(u64),
     Dec(u64),
@@ -35,37 +35,52 @@ impl EventSourced for Counter {
     type Evt = Evt;
     type State = u64;
     type Error = Error;
+    type Effect = ();
 
     /// Command handler, returning the to be persisted event or an error.
     fn handle_cmd(
         &self,
-        _id: Uuid,
+        id: Uuid,
         cmd: Self::Cmd,
     ) -> Result<impl IntoTaggedEvt<Self::Evt>, Self::Error> {
-        let value = self.value;
-
-        match cmd {
-            Cmd::Inc(inc) if inc > u64::MAX - value => Err(Error::Overflow { value, inc }),
-            Cmd::Inc(inc) => Ok(Evt::Increased(inc)),
-
-            Cmd::Dec(dec) if dec > value => Err(Error::Underflow { value, dec }),
-            Cmd::Dec(dec) => Ok(Evt::Decreased(dec)),
-        }
+        cmd.dispatch(self, id)
     }
 
     /// Event handler, also returning whether to take a snapshot or not.
-    fn handle_evt(&mut self, evt: Self::Evt) -> Option<Self::State> {
+    fn handle_evt(&mut self, evt: Self::Evt) -> Result<Option<Self::State>, Self::Error> {
         match evt {
             Evt::Increased(inc) => self.value += inc,
             Evt::Decreased(dec) => self.value -= dec,
         }
 
         // No snapshots.
-        None
+        Ok(None)
     }
 
     fn set_state(&mut self, _state: Self::State) {
-        // This method cannot be called as long as `handle_evt` always returns `None`.
+        // This method cannot be called as long as `handle_evt` always returns `Ok(None)`.
         panic!("impossible: no snapshots");
     }
 }
+
+impl CmdHandler<Uuid> for Counter {
+    type Output = Result<Evt, Error>;
+
+    fn handle_inc(&self, _id: Uuid, inc: u64) -> Self::Output {
+        let value = self.value;
+        if inc > u64::MAX - value {
+            Err(Error::Overflow { value, inc })
+        } else {
+            Ok(Evt::Increased(inc))
+        }
+    }
+
+    fn handle_dec(&self, _id: Uuid, dec: u64) -> Self::Output {
+        let value = self.value;
+        if dec > value {
+            Err(Error::Underflow { value, dec })
+        } else {
+            Ok(Evt::Decreased(dec))
+        }
+    }
+}